@@ -0,0 +1,430 @@
+//! Conversion between JSON and CBOR (RFC 8949).
+//!
+//! Each top-level JSON record becomes one CBOR data item, and a
+//! sequence of CBOR data items becomes newline-delimited JSON.
+//!
+//! Integers are encoded as CBOR integers when they fit in 64 bits, and
+//! other numbers as the smallest float which holds them exactly.  When
+//! decoding, byte strings become base64 strings, bignums become
+//! integers, `undefined` becomes null, and other tags are dropped in
+//! favor of the values they enclose.
+
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use value::{self, Value};
+
+const UINT: u8 = 0;
+const NEGINT: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+/// Converts a string of JSON-encoded data to CBOR, one data item per
+/// top-level record.
+///
+/// # Example:
+///
+/// ```
+/// let cbor = jsonxf::to_cbor("{\"a\": [1, -2, 1.5, null]}").unwrap();
+/// assert_eq!(
+///     cbor,
+///     vec![0xa1, 0x61, b'a', 0x84, 0x01, 0x21, 0xfa, 0x3f, 0xc0, 0x00, 0x00, 0xf6]
+/// );
+/// assert_eq!(jsonxf::from_cbor(&cbor).unwrap(), "{\"a\":[1,-2,1.5,null]}\n");
+/// ```
+pub fn to_cbor(json_string: &str) -> Result<Vec<u8>, String> {
+    let mut output: Vec<u8> = vec![];
+    to_cbor_stream(&mut json_string.as_bytes(), &mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+/// Converts a stream of JSON-encoded data to CBOR, one data item per
+/// top-level record.
+///
+/// Only one record is held in memory at a time.
+pub fn to_cbor_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut buf: Vec<u8> = vec![];
+    value::read_records(input, &mut |record| {
+        buf.clear();
+        encode_value(&mut buf, &record)?;
+        writer.write_all(&buf)
+    })?;
+    writer.flush()
+}
+
+/// Converts a sequence of CBOR data items to minimized JSON, one
+/// record per line, each followed by a newline.
+///
+/// # Example:
+///
+/// ```
+/// // {"id": h'01ff'}, as a sensor might send it
+/// let cbor = [0xa1, 0x62, b'i', b'd', 0x42, 0x01, 0xff];
+/// assert_eq!(jsonxf::from_cbor(&cbor).unwrap(), "{\"id\":\"Af8=\"}\n");
+/// ```
+pub fn from_cbor(bytes: &[u8]) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    from_cbor_stream(&mut &bytes[..], &mut output).map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Converts a stream of CBOR data items to minimized JSON, one record
+/// per line, each followed by a newline.
+///
+/// Values are written as they are decoded, so memory use depends on
+/// the size of the largest string, not of the largest record.
+pub fn from_cbor_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    loop {
+        let mut byte = [0u8];
+        if input.read(&mut byte)? == 0 {
+            break;
+        }
+        decode_value(input, &mut writer, byte[0])?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+fn encode_value(buf: &mut Vec<u8>, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Null => buf.push(0xf6),
+        Value::Bool(false) => buf.push(0xf4),
+        Value::Bool(true) => buf.push(0xf5),
+        Value::Number(n) => encode_number(buf, n)?,
+        Value::String(s) => {
+            write_head(buf, TEXT, s.len() as u64);
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_head(buf, ARRAY, items.len() as u64);
+            for item in items {
+                encode_value(buf, item)?;
+            }
+        }
+        Value::Object(members) => {
+            write_head(buf, MAP, members.len() as u64);
+            for (key, value) in members {
+                write_head(buf, TEXT, key.len() as u64);
+                buf.extend_from_slice(key.as_bytes());
+                encode_value(buf, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_number(buf: &mut Vec<u8>, n: &str) -> Result<(), Error> {
+    if let Ok(u) = n.parse::<u64>() {
+        write_head(buf, UINT, u);
+    } else if let Some(u) = n.strip_prefix('-').and_then(|m| m.parse::<u128>().ok()) {
+        if u == 0 {
+            // -0 has no integer encoding
+            buf.extend_from_slice(&[0xf9, 0x80, 0x00]);
+        } else if u <= 1 << 64 {
+            write_head(buf, NEGINT, (u - 1) as u64);
+        } else {
+            encode_float(buf, n)?;
+        }
+    } else {
+        encode_float(buf, n)?;
+    }
+    Ok(())
+}
+
+fn encode_float(buf: &mut Vec<u8>, n: &str) -> Result<(), Error> {
+    let f = match n.parse::<f64>() {
+        Ok(f) if f.is_finite() => f,
+        _ => return Err(invalid(format!("invalid number `{}`", n))),
+    };
+    if f as f32 as f64 == f {
+        buf.push(0xfa);
+        buf.extend_from_slice(&(f as f32).to_bits().to_be_bytes());
+    } else {
+        buf.push(0xfb);
+        buf.extend_from_slice(&f.to_bits().to_be_bytes());
+    }
+    Ok(())
+}
+
+// Writes an initial byte and argument, in the shortest form.
+fn write_head(buf: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    if n < 24 {
+        buf.push(major | n as u8);
+    } else if n <= u8::MAX as u64 {
+        buf.extend_from_slice(&[major | 24, n as u8]);
+    } else if n <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn decode_value(input: &mut dyn Read, output: &mut dyn Write, initial: u8) -> Result<(), Error> {
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+    match major {
+        UINT => write!(output, "{}", read_argument(input, info)?),
+        NEGINT => write!(output, "{}", -1 - read_argument(input, info)? as i128),
+        BYTES => {
+            let bytes = read_string(input, major, info)?;
+            output.write_all(value::escape(&value::base64(&bytes)).as_bytes())
+        }
+        TEXT => {
+            let text = read_text(input, info)?;
+            output.write_all(value::escape(&text).as_bytes())
+        }
+        ARRAY => {
+            output.write_all(b"[")?;
+            let mut remaining = read_length(input, info)?;
+            let mut i = 0;
+            while let Some(initial) = next_item(input, &mut remaining)? {
+                if i > 0 {
+                    output.write_all(b",")?;
+                }
+                decode_value(input, output, initial)?;
+                i += 1;
+            }
+            output.write_all(b"]")
+        }
+        MAP => {
+            output.write_all(b"{")?;
+            let mut remaining = read_length(input, info)?;
+            let mut i = 0;
+            while let Some(initial) = next_item(input, &mut remaining)? {
+                if i > 0 {
+                    output.write_all(b",")?;
+                }
+                let key = read_key(input, initial)?;
+                output.write_all(value::escape(&key).as_bytes())?;
+                output.write_all(b":")?;
+                let initial = read_byte(input)?;
+                decode_value(input, output, initial)?;
+                i += 1;
+            }
+            output.write_all(b"}")
+        }
+        TAG => {
+            let tag = read_argument(input, info)?;
+            let initial = read_byte(input)?;
+            match tag {
+                2 | 3 => write_bignum(input, output, tag == 3, initial),
+                _ => decode_value(input, output, initial),
+            }
+        }
+        _ => decode_simple(input, output, info),
+    }
+}
+
+fn decode_simple(input: &mut dyn Read, output: &mut dyn Write, info: u8) -> Result<(), Error> {
+    let text = match info {
+        20 => String::from("false"),
+        21 => String::from("true"),
+        22 | 23 => String::from("null"),
+        25 => format!("{:?}", half(read_uint(input, 2)? as u16)),
+        26 => format!("{:?}", f32::from_bits(read_uint(input, 4)? as u32)),
+        27 => format!("{:?}", f64::from_bits(read_uint(input, 8)?)),
+        INDEFINITE => return Err(invalid(String::from("unexpected CBOR break"))),
+        _ => {
+            return Err(invalid(String::from(
+                "CBOR simple values cannot be represented in JSON",
+            )))
+        }
+    };
+    if (25..=27).contains(&info) && !value::is_number(&text) {
+        return Err(invalid(format!("{} cannot be represented in JSON", text)));
+    }
+    output.write_all(text.as_bytes())
+}
+
+// Converts a half-precision float to single precision, which holds
+// every half-precision value exactly.
+fn half(bits: u16) -> f32 {
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f32;
+    let magnitude = match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (mantissa + 1024.0) * 2f32.powi(exponent as i32 - 25),
+    };
+    if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+// Writes a tagged bignum as a JSON integer.
+fn write_bignum(
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    negative: bool,
+    initial: u8,
+) -> Result<(), Error> {
+    if initial >> 5 != BYTES {
+        return Err(invalid(String::from("CBOR bignums must be byte strings")));
+    }
+    let bytes = read_string(input, BYTES, initial & 0x1f)?;
+
+    // base 10^9 digits, least significant first
+    let mut digits: Vec<u64> = vec![0];
+    for byte in bytes {
+        let mut carry = byte as u64;
+        for digit in digits.iter_mut() {
+            let n = *digit * 256 + carry;
+            *digit = n % 1_000_000_000;
+            carry = n / 1_000_000_000;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+    if negative {
+        // the value is -1 - n
+        let mut carry = 1;
+        for digit in digits.iter_mut() {
+            let n = *digit + carry;
+            *digit = n % 1_000_000_000;
+            carry = n / 1_000_000_000;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+        output.write_all(b"-")?;
+    }
+    while digits.len() > 1 && digits[digits.len() - 1] == 0 {
+        digits.pop();
+    }
+    let mut digits = digits.iter().rev();
+    write!(output, "{}", digits.next().unwrap())?;
+    for digit in digits {
+        write!(output, "{:09}", digit)?;
+    }
+    Ok(())
+}
+
+// Object keys must be strings in JSON; other scalar keys are
+// converted to their JSON text.
+fn read_key(input: &mut dyn Read, initial: u8) -> Result<String, Error> {
+    match initial >> 5 {
+        TEXT => read_text(input, initial & 0x1f),
+        ARRAY | MAP => Err(invalid(String::from(
+            "only scalars can be used as object keys",
+        ))),
+        _ => {
+            let mut key: Vec<u8> = vec![];
+            decode_value(input, &mut key, initial)?;
+            let key = String::from_utf8(key).map_err(|e| invalid(e.to_string()))?;
+            Ok(value::unescape(key.trim_matches('"')))
+        }
+    }
+}
+
+// Reads the length of an array or map, or None for an
+// indefinite-length one.
+fn read_length(input: &mut dyn Read, info: u8) -> Result<Option<u64>, Error> {
+    match info {
+        INDEFINITE => Ok(None),
+        _ => read_argument(input, info).map(Some),
+    }
+}
+
+// Returns the initial byte of the next item in an array or map, or
+// None at its end.
+fn next_item(input: &mut dyn Read, remaining: &mut Option<u64>) -> Result<Option<u8>, Error> {
+    match remaining {
+        Some(0) => Ok(None),
+        Some(n) => {
+            *n -= 1;
+            read_byte(input).map(Some)
+        }
+        None => match read_byte(input)? {
+            BREAK => Ok(None),
+            initial => Ok(Some(initial)),
+        },
+    }
+}
+
+fn read_text(input: &mut dyn Read, info: u8) -> Result<String, Error> {
+    let bytes = read_string(input, TEXT, info)?;
+    String::from_utf8(bytes).map_err(|e| invalid(e.to_string()))
+}
+
+// Reads a byte or text string, joining the chunks of an
+// indefinite-length string.
+fn read_string(input: &mut dyn Read, major: u8, info: u8) -> Result<Vec<u8>, Error> {
+    if info != INDEFINITE {
+        let len = read_argument(input, info)?;
+        return read_bytes(input, len);
+    }
+    let mut bytes: Vec<u8> = vec![];
+    loop {
+        let initial = read_byte(input)?;
+        if initial == BREAK {
+            return Ok(bytes);
+        }
+        if initial >> 5 != major || initial & 0x1f == INDEFINITE {
+            return Err(invalid(String::from("invalid chunk in CBOR string")));
+        }
+        let len = read_argument(input, initial & 0x1f)?;
+        bytes.extend(read_bytes(input, len)?);
+    }
+}
+
+// Reads the argument which follows an initial byte.
+fn read_argument(input: &mut dyn Read, info: u8) -> Result<u64, Error> {
+    match info {
+        0..=23 => Ok(info as u64),
+        24 => read_uint(input, 1),
+        25 => read_uint(input, 2),
+        26 => read_uint(input, 4),
+        27 => read_uint(input, 8),
+        _ => Err(invalid(format!(
+            "invalid CBOR additional information {}",
+            info
+        ))),
+    }
+}
+
+fn read_byte(input: &mut dyn Read) -> Result<u8, Error> {
+    Ok(read_uint(input, 1)? as u8)
+}
+
+// Reads a big-endian unsigned integer of `size` bytes.
+fn read_uint(input: &mut dyn Read, size: usize) -> Result<u64, Error> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf[8 - size..])?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_bytes(input: &mut dyn Read, len: u64) -> Result<Vec<u8>, Error> {
+    let mut bytes: Vec<u8> = vec![];
+    input.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "unexpected end of input",
+        ));
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
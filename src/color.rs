@@ -0,0 +1,271 @@
+//! Syntax highlighting with ANSI color codes, for terminals.
+//!
+//! `ColorWriter` colors formatted JSON as it is written, using the
+//! colors in `Palette`.  It can also tint whole records by their log
+//! level, so that errors and warnings stand out in a stream of logs.
+
+use std::io::prelude::*;
+use std::io::Error;
+
+use highlight::{Class, Highlighter, Markup};
+use scanner::{Scanner, Token};
+use value::{self, Value};
+use Formatter;
+
+const RESET: &[u8] = b"\x1b[0m";
+
+/// The ANSI SGR parameters used for each class of token, e.g. `"32"`
+/// for green.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub key: String,
+    pub string: String,
+    pub number: String,
+    pub boolean: String,
+    pub null: String,
+    pub punct: String,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            key: String::from("1;34"),
+            string: String::from("32"),
+            number: String::from("36"),
+            boolean: String::from("33"),
+            null: String::from("35"),
+            punct: String::from("1"),
+        }
+    }
+}
+
+impl Palette {
+    fn color(&self, class: Class) -> &str {
+        match class {
+            Class::Key => &self.key,
+            Class::String => &self.string,
+            Class::Number => &self.number,
+            Class::Boolean => &self.boolean,
+            Class::Null => &self.null,
+            Class::Punct => &self.punct,
+        }
+    }
+}
+
+/// Returns the color for a log level, if it deserves one: red for
+/// errors and worse, yellow for warnings.  Numeric levels are read as
+/// in Bunyan and Pino, where 40 is a warning and 50 an error.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::color::level_color;
+/// use jsonxf::value::Value;
+///
+/// assert_eq!(level_color(&Value::String(String::from("ERROR"))), Some("31"));
+/// assert_eq!(level_color(&Value::Number(String::from("40"))), Some("33"));
+/// assert_eq!(level_color(&Value::String(String::from("info"))), None);
+/// ```
+pub fn level_color(level: &Value) -> Option<&'static str> {
+    match level {
+        Value::String(s) => match s.to_lowercase().as_str() {
+            "emerg" | "emergency" | "alert" | "crit" | "critical" | "fatal" | "panic" | "err"
+            | "error" => Some("31"),
+            "warn" | "warning" => Some("33"),
+            _ => None,
+        },
+        Value::Number(n) => match n.parse::<f64>() {
+            Ok(n) if n >= 50.0 => Some("31"),
+            Ok(n) if n >= 40.0 => Some("33"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Formats a string of JSON-encoded data with `xf`, and returns it
+/// with ANSI color codes for the default palette.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// assert_eq!(
+///     jsonxf::color::colorize("{\"a\":null}", &mut xf).unwrap(),
+///     "\x1b[1m{\x1b[0m\x1b[1;34m\"a\"\x1b[0m\x1b[1m:\x1b[0m\
+///      \x1b[35mnull\x1b[0m\x1b[1m}\x1b[0m"
+/// );
+/// ```
+pub fn colorize(json_string: &str, xf: &mut Formatter) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    let mut writer = ColorWriter::new(&mut output);
+    xf.format_stream(&mut json_string.as_bytes(), &mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|e| e.to_string())?;
+    drop(writer);
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Colors formatted JSON while writing it to an underlying writer.
+///
+/// Tokens may be split across writes.  Flushing resets the terminal's
+/// color, resuming it on the next write if a token was left open, so
+/// flush after the last write.
+pub struct ColorWriter<W: Write> {
+    output: W,
+    palette: Palette,
+    highlighter: Highlighter,
+    paused: bool, // whether a token's color was reset by `flush`
+    level_pointer: Option<String>,
+    tint: Option<&'static str>, // the level color of the current record
+    scanner: Scanner,           // finds the ends of records to tint
+    depth: usize,
+    pending: Vec<u8>,   // the part of the current record written so far
+    pending_start: u64, // the offset of `pending` in the stream
+}
+
+impl<W: Write> ColorWriter<W> {
+    pub fn new(output: W) -> ColorWriter<W> {
+        ColorWriter::with_palette(output, Palette::default())
+    }
+
+    pub fn with_palette(output: W, palette: Palette) -> ColorWriter<W> {
+        ColorWriter {
+            output,
+            palette,
+            highlighter: Highlighter::default(),
+            paused: false,
+            level_pointer: None,
+            tint: None,
+            scanner: Scanner::new(),
+            depth: 0,
+            pending: vec![],
+            pending_start: 0,
+        }
+    }
+
+    /// Colors each record entirely in the color of the log level found
+    /// at the given JSON Pointer (see `level_color`), instead of by
+    /// token.  Records without a notable level are colored as usual.
+    ///
+    /// Each record is held until it is complete, even across flushes.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::color::ColorWriter;
+    /// use std::io::Write;
+    ///
+    /// let mut output: Vec<u8> = vec![];
+    /// {
+    ///     let mut writer = ColorWriter::new(&mut output);
+    ///     writer.tint_by_level("/level");
+    ///     writer.write_all(b"{\"level\":\"warn\"}\n").unwrap();
+    ///     writer.flush().unwrap();
+    /// }
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "\x1b[33m{\x1b[0m\x1b[33m\"level\"\x1b[0m\x1b[33m:\x1b[0m\
+    ///      \x1b[33m\"warn\"\x1b[0m\x1b[33m}\x1b[0m\n"
+    /// );
+    /// ```
+    pub fn tint_by_level(&mut self, pointer: &str) {
+        self.level_pointer = Some(String::from(pointer));
+    }
+
+    fn highlight(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut ansi = Ansi {
+            output: &mut self.output,
+            palette: &self.palette,
+            tint: self.tint,
+        };
+        if self.paused {
+            if let Some(class) = self.highlighter.open_token() {
+                ansi.begin(class)?;
+            }
+            self.paused = false;
+        }
+        self.highlighter.feed(buf, &mut ansi)
+    }
+
+    // Passes along each record completed by `buf`, in its level color.
+    fn tint_records(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut ends: Vec<u64> = vec![];
+        let depth = &mut self.depth;
+        self.scanner.feed_spanned(buf, &mut |token, span| {
+            match token {
+                Token::BeginObject | Token::BeginArray => *depth += 1,
+                Token::EndObject | Token::EndArray => *depth = depth.saturating_sub(1),
+                Token::Key(_) => return Ok(()),
+                _ => {}
+            }
+            if *depth == 0 {
+                ends.push(span.end);
+            }
+            Ok(())
+        })?;
+        self.pending.extend_from_slice(buf);
+
+        for end in ends {
+            let len = (end - self.pending_start) as usize;
+            let record: Vec<u8> = self.pending.drain(..len).collect();
+            self.pending_start = end;
+            self.tint = self.level_of(&record).as_ref().and_then(level_color);
+            self.highlight(&record)?;
+            self.tint = None;
+        }
+        Ok(())
+    }
+
+    fn level_of(&self, record: &[u8]) -> Option<Value> {
+        let pointer = self.level_pointer.as_ref()?;
+        let record = value::parse(std::str::from_utf8(record).ok()?).ok()?;
+        record.pointer(pointer).cloned()
+    }
+}
+
+impl<W: Write> Write for ColorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if self.level_pointer.is_some() {
+            self.tint_records(buf)?;
+        } else {
+            self.highlight(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if !self.pending.is_empty() && self.depth == 0 {
+            // Whitespace and scalars between records go out untinted
+            let rest: Vec<u8> = self.pending.drain(..).collect();
+            self.pending_start += rest.len() as u64;
+            self.highlight(&rest)?;
+        }
+        if !self.paused && self.highlighter.open_token().is_some() {
+            self.output.write_all(RESET)?;
+            self.paused = true;
+        }
+        self.output.flush()
+    }
+}
+
+struct Ansi<'a> {
+    output: &'a mut dyn Write,
+    palette: &'a Palette,
+    tint: Option<&'a str>,
+}
+
+impl<'a> Markup for Ansi<'a> {
+    fn begin(&mut self, class: Class) -> Result<(), Error> {
+        let color = self.tint.unwrap_or_else(|| self.palette.color(class));
+        write!(self.output, "\x1b[{}m", color)
+    }
+
+    fn end(&mut self, _class: Class) -> Result<(), Error> {
+        self.output.write_all(RESET)
+    }
+
+    fn text(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.output.write_all(buf)
+    }
+}
@@ -0,0 +1,176 @@
+//! Compressed input and output, as log archives are usually stored.
+//!
+//! Compression is recognized by the magic bytes at the start of the
+//! input, or by a filename's extension.  Decompressing (`decoder`) and
+//! compressing (`Encoder`) require the `compression` feature;
+//! recognizing does not.
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io::Error;
+
+/// The supported compression formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Recognizes a format by the first bytes of its data.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::compress::Compression;
+    ///
+    /// assert_eq!(Compression::from_magic(b"\x1f\x8b\x08\x00"), Some(Compression::Gzip));
+    /// assert_eq!(Compression::from_magic(b"BZh91AY"), Some(Compression::Bzip2));
+    /// assert_eq!(Compression::from_magic(b"{\"a\":1}"), None);
+    /// ```
+    pub fn from_magic(prefix: &[u8]) -> Option<Compression> {
+        if prefix.starts_with(b"\x1f\x8b") {
+            Some(Compression::Gzip)
+        } else if prefix.starts_with(b"\x28\xb5\x2f\xfd") {
+            Some(Compression::Zstd)
+        } else if prefix.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    /// Recognizes a format by a filename's extension.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::compress::Compression;
+    ///
+    /// assert_eq!(Compression::from_filename("app.log.zst"), Some(Compression::Zstd));
+    /// assert_eq!(Compression::from_filename("app.json"), None);
+    /// ```
+    pub fn from_filename(filename: &str) -> Option<Compression> {
+        let extension = filename.rsplit('.').next()?;
+        match extension {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Recognizes the compression of `input` by its first bytes, without
+/// consuming them.
+pub fn detect<R: BufRead>(input: &mut R) -> Result<Option<Compression>, Error> {
+    Ok(Compression::from_magic(input.fill_buf()?))
+}
+
+/// Returns a reader of the decompressed contents of `input`.
+/// Concatenated gzip members and bzip2 streams are read as one, as
+/// `zcat` and `bzcat` do.
+#[cfg(feature = "compression")]
+pub fn decoder<'a, R: BufRead + 'a>(
+    input: R,
+    compression: Compression,
+) -> Result<Box<dyn Read + 'a>, Error> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(::flate2::bufread::MultiGzDecoder::new(input)),
+        Compression::Zstd => Box::new(::zstd::Decoder::with_buffer(input)?),
+        Compression::Bzip2 => Box::new(::bzip2::bufread::MultiBzDecoder::new(input)),
+    })
+}
+
+/// Compresses data as it is written to an underlying writer.  Call
+/// `finish` after the last write to complete the compressed stream.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::compress::{decoder, Compression, Encoder};
+/// use std::io::{Read, Write};
+///
+/// let mut encoder = Encoder::new(vec![], Compression::Gzip).unwrap();
+/// encoder.write_all(b"{\"a\":1}\n").unwrap();
+/// let compressed = encoder.finish().unwrap();
+/// assert_eq!(Compression::from_magic(&compressed), Some(Compression::Gzip));
+///
+/// let mut json = String::new();
+/// decoder(&compressed[..], Compression::Gzip)
+///     .unwrap()
+///     .read_to_string(&mut json)
+///     .unwrap();
+/// assert_eq!(json, "{\"a\":1}\n");
+/// ```
+#[cfg(feature = "compression")]
+pub struct Encoder<W: Write> {
+    kind: EncoderKind<W>,
+}
+
+#[cfg(feature = "compression")]
+enum EncoderKind<W: Write> {
+    Gzip(::flate2::write::GzEncoder<W>),
+    Zstd(::zstd::Encoder<'static, W>),
+    Bzip2(::bzip2::write::BzEncoder<W>),
+}
+
+#[cfg(feature = "compression")]
+impl<W: Write> Encoder<W> {
+    /// Returns an encoder with each format's default compression level.
+    pub fn new(output: W, compression: Compression) -> Result<Encoder<W>, Error> {
+        let kind = match compression {
+            Compression::Gzip => EncoderKind::Gzip(::flate2::write::GzEncoder::new(
+                output,
+                ::flate2::Compression::default(),
+            )),
+            Compression::Zstd => EncoderKind::Zstd(::zstd::Encoder::new(output, 0)?),
+            Compression::Bzip2 => EncoderKind::Bzip2(::bzip2::write::BzEncoder::new(
+                output,
+                ::bzip2::Compression::default(),
+            )),
+        };
+        Ok(Encoder { kind })
+    }
+
+    /// Writes the end of the compressed stream, and returns the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        match self.kind {
+            EncoderKind::Gzip(encoder) => encoder.finish(),
+            EncoderKind::Zstd(encoder) => encoder.finish(),
+            EncoderKind::Bzip2(encoder) => encoder.finish(),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match &mut self.kind {
+            EncoderKind::Gzip(encoder) => encoder.write(buf),
+            EncoderKind::Zstd(encoder) => encoder.write(buf),
+            EncoderKind::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        match &mut self.kind {
+            EncoderKind::Gzip(encoder) => encoder.flush(),
+            EncoderKind::Zstd(encoder) => encoder.flush(),
+            EncoderKind::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
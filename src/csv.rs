@@ -0,0 +1,201 @@
+//! Conversion of CSV and TSV data to JSON.
+//!
+//! The first row of input names the fields, and each following row
+//! becomes an object.  Quoted fields may contain delimiters, doubled
+//! quotes, and line breaks, as in RFC 4180.
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use value::{self, Value};
+
+/// Options for `csv_to_json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The field delimiter, usually `b','` or `b'\t'`.
+    pub delimiter: u8,
+
+    /// Convert fields which look like numbers, `true`, `false`, or
+    /// `null` to those types, and empty fields to `null`.  Otherwise
+    /// every field is a string.
+    pub infer_types: bool,
+
+    /// Write a single array of objects, rather than a stream of
+    /// objects separated by newlines.
+    pub array: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            infer_types: false,
+            array: false,
+        }
+    }
+}
+
+/// Converts a string of CSV data to minimized JSON objects.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::csv::CsvOptions;
+///
+/// let csv = "name,age\nana,30\n\"bo, jr.\",\n";
+/// let options = CsvOptions { infer_types: true, ..CsvOptions::default() };
+/// assert_eq!(
+///     jsonxf::csv_to_json(csv, options).unwrap(),
+///     "{\"name\":\"ana\",\"age\":30}\n{\"name\":\"bo, jr.\",\"age\":null}\n"
+/// );
+/// ```
+pub fn csv_to_json(csv_string: &str, options: CsvOptions) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    csv_to_json_stream(&mut csv_string.as_bytes(), &mut output, options)
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Converts a stream of CSV data to minimized JSON objects.
+///
+/// Only one row is held in memory at a time.
+pub fn csv_to_json_stream(
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    options: CsvOptions,
+) -> Result<(), Error> {
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::new(output);
+    let mut line_number = 0;
+    let mut header: Option<Vec<String>> = None;
+    let mut first = true;
+
+    if options.array {
+        writer.write_all(b"[")?;
+    }
+    loop {
+        let start_line = line_number + 1;
+        let fields = match read_row(&mut reader, options.delimiter, &mut line_number)? {
+            None => break,
+            Some(fields) => fields,
+        };
+        let names = match &header {
+            None => {
+                header = Some(fields);
+                continue;
+            }
+            Some(names) => names,
+        };
+        if fields.len() > names.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "line {}: {} fields, but the header has only {}",
+                    start_line,
+                    fields.len(),
+                    names.len()
+                ),
+            ));
+        }
+
+        let mut fields = fields.into_iter();
+        let members = names
+            .iter()
+            .map(|name| {
+                let value = match fields.next() {
+                    None => Value::Null,
+                    Some(field) if options.infer_types => infer(field),
+                    Some(field) => Value::String(field),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+
+        if options.array && !first {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "{}", Value::Object(members))?;
+        if !options.array {
+            writer.write_all(b"\n")?;
+        }
+        first = false;
+    }
+    if options.array {
+        writer.write_all(b"]")?;
+    }
+    writer.flush()
+}
+
+// Reads the fields of the next non-blank row, counting lines read.
+fn read_row(
+    reader: &mut dyn BufRead,
+    delimiter: u8,
+    line_number: &mut usize,
+) -> Result<Option<Vec<String>>, Error> {
+    let mut row = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            if row.is_empty() {
+                return Ok(None);
+            }
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "unterminated quoted field at end of input",
+            ));
+        }
+        *line_number += 1;
+        row.push_str(&line);
+        if row.bytes().filter(|&b| b == b'"').count() % 2 == 1 {
+            continue; // a quoted field continues on the next line
+        }
+        if row.trim_end_matches(['\r', '\n']).is_empty() {
+            row.clear();
+            continue;
+        }
+        return Ok(Some(split_row(&row, delimiter as char)));
+    }
+}
+
+fn split_row(row: &str, delimiter: char) -> Vec<String> {
+    let row = row.strip_suffix('\n').unwrap_or(row);
+    let row = row.strip_suffix('\r').unwrap_or(row);
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c != '"' {
+                field.push(c);
+            } else if chars.peek() == Some(&'"') {
+                field.push('"');
+                chars.next();
+            } else {
+                in_quotes = false;
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(field);
+            field = String::new();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn infer(field: String) -> Value {
+    match field.as_str() {
+        "" | "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        s if value::is_number(s) => Value::Number(field),
+        _ => Value::String(field),
+    }
+}
@@ -0,0 +1,320 @@
+//! Structural comparison of JSON documents.
+
+use std::fmt;
+
+use path::{PathSegment, Pointer};
+use value::{self, Value};
+
+/// One difference between two JSON documents.  Paths are JSON
+/// Pointers (RFC 6901), with `""` meaning the whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A value present only in the second document.
+    Added { path: String, value: Value },
+
+    /// A value present only in the first document.
+    Removed { path: String, value: Value },
+
+    /// A value which differs between the documents.
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    /// Returns the JSON Pointer of the changed value.
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } => path,
+            Change::Removed { path, .. } => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Changes are displayed one per line, as `+ path: value`,
+/// `- path: value`, or `~ path: old -> new`.
+///
+/// # Example:
+///
+/// ```
+/// let changes = jsonxf::diff("{\"a\":1,\"b\":2}", "{\"a\":3,\"c\":4}").unwrap();
+/// let lines: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+/// assert_eq!(lines, vec!["~ /a: 1 -> 3", "- /b: 2", "+ /c: 4"]);
+/// ```
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Change::Changed { path, old, new } => write!(f, "~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+/// `Differ` compares JSON documents structurally, ignoring whitespace
+/// and the formatting of numbers.
+///
+/// # Example:
+///
+/// ```
+/// let mut differ = jsonxf::diff::Differ::new();
+/// differ.ignore_key_order = false;
+/// let changes = differ.diff("{\"a\":1,\"b\":2}", "{\"b\":2,\"a\":1}").unwrap();
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].path(), "");
+/// ```
+pub struct Differ {
+    /// Treat objects with the same members in a different order as
+    /// equal.  When false, an object whose shared keys are in a
+    /// different order is reported as changed, after any differences in
+    /// its members.
+    pub ignore_key_order: bool,
+
+    /// Treat arrays with the same elements in a different order as
+    /// equal.  Elements of one array with no equal element left in the
+    /// other are reported as removed or added, at their own indices.
+    pub ignore_array_order: bool,
+}
+
+impl Default for Differ {
+    fn default() -> Differ {
+        Differ {
+            ignore_key_order: true,
+            ignore_array_order: false,
+        }
+    }
+}
+
+impl Differ {
+    /// Returns a `Differ` which ignores key order, but not array order.
+    pub fn new() -> Differ {
+        Differ::default()
+    }
+
+    /// Compares two strings, each containing one JSON value.
+    pub fn diff(&self, a: &str, b: &str) -> Result<Vec<Change>, String> {
+        let a = value::parse(a)?;
+        let b = value::parse(b)?;
+        Ok(self.diff_values(&a, &b))
+    }
+
+    /// Compares two parsed JSON values.
+    pub fn diff_values(&self, a: &Value, b: &Value) -> Vec<Change> {
+        let mut changes = vec![];
+        self.diff_at(&mut vec![], a, b, &mut changes);
+        changes
+    }
+
+    /// Returns true if the two values are structurally equal.
+    pub fn equal(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Number(x), Value::Number(y)) => numbers_equal(x, y),
+            (Value::Array(xs), Value::Array(ys)) if self.ignore_array_order => {
+                let (unmatched_xs, unmatched_ys) = self.match_elements(xs, ys);
+                unmatched_xs.is_empty() && unmatched_ys.is_empty()
+            }
+            (Value::Array(xs), Value::Array(ys)) => {
+                xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| self.equal(x, y))
+            }
+            (Value::Object(xs), Value::Object(ys)) => {
+                xs.len() == ys.len()
+                    && (self.ignore_key_order || same_key_order(xs, ys))
+                    && xs.iter().all(|(k, x)| match b.get(k) {
+                        Some(y) => self.equal(x, y),
+                        None => false,
+                    })
+            }
+            _ => a == b,
+        }
+    }
+
+    fn diff_at(
+        &self,
+        path: &mut Vec<PathSegment>,
+        a: &Value,
+        b: &Value,
+        changes: &mut Vec<Change>,
+    ) {
+        match (a, b) {
+            (Value::Object(xs), Value::Object(ys)) => {
+                for (key, x) in xs {
+                    path.push(PathSegment::Key(key.clone()));
+                    match b.get(key) {
+                        Some(y) => self.diff_at(path, x, y, changes),
+                        None => changes.push(Change::Removed {
+                            path: Pointer(path).to_string(),
+                            value: x.clone(),
+                        }),
+                    }
+                    path.pop();
+                }
+                for (key, y) in ys {
+                    if a.get(key).is_none() {
+                        path.push(PathSegment::Key(key.clone()));
+                        changes.push(Change::Added {
+                            path: Pointer(path).to_string(),
+                            value: y.clone(),
+                        });
+                        path.pop();
+                    }
+                }
+                if !(self.ignore_key_order || same_key_order(xs, ys)) {
+                    changes.push(Change::Changed {
+                        path: Pointer(path).to_string(),
+                        old: a.clone(),
+                        new: b.clone(),
+                    });
+                }
+            }
+            (Value::Array(xs), Value::Array(ys)) if self.ignore_array_order => {
+                let (unmatched_xs, unmatched_ys) = self.match_elements(xs, ys);
+                for i in unmatched_xs {
+                    path.push(PathSegment::Index(i));
+                    changes.push(Change::Removed {
+                        path: Pointer(path).to_string(),
+                        value: xs[i].clone(),
+                    });
+                    path.pop();
+                }
+                for i in unmatched_ys {
+                    path.push(PathSegment::Index(i));
+                    changes.push(Change::Added {
+                        path: Pointer(path).to_string(),
+                        value: ys[i].clone(),
+                    });
+                    path.pop();
+                }
+            }
+            (Value::Array(xs), Value::Array(ys)) => {
+                for i in 0..xs.len().max(ys.len()) {
+                    path.push(PathSegment::Index(i));
+                    match (xs.get(i), ys.get(i)) {
+                        (Some(x), Some(y)) => self.diff_at(path, x, y, changes),
+                        (Some(x), None) => changes.push(Change::Removed {
+                            path: Pointer(path).to_string(),
+                            value: x.clone(),
+                        }),
+                        (None, Some(y)) => changes.push(Change::Added {
+                            path: Pointer(path).to_string(),
+                            value: y.clone(),
+                        }),
+                        (None, None) => {}
+                    }
+                    path.pop();
+                }
+            }
+            _ => {
+                if !self.equal(a, b) {
+                    changes.push(Change::Changed {
+                        path: Pointer(path).to_string(),
+                        old: a.clone(),
+                        new: b.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Pairs each element of `xs` with an equal element of `ys`, if one
+    // is left, and returns the indices of those left unpaired in each.
+    fn match_elements(&self, xs: &[Value], ys: &[Value]) -> (Vec<usize>, Vec<usize>) {
+        let mut paired = vec![false; ys.len()];
+        let mut unmatched_xs = vec![];
+        for (i, x) in xs.iter().enumerate() {
+            let found = ys
+                .iter()
+                .enumerate()
+                .position(|(j, y)| !paired[j] && self.equal(x, y));
+            match found {
+                Some(j) => paired[j] = true,
+                None => unmatched_xs.push(i),
+            }
+        }
+        let unmatched_ys = (0..ys.len()).filter(|&j| !paired[j]).collect();
+        (unmatched_xs, unmatched_ys)
+    }
+}
+
+/// Compares two strings, each containing one JSON value, and returns
+/// their differences.  Whitespace, key order, and the formatting of
+/// numbers are ignored.
+///
+/// To consider key order, use a `jsonxf::diff::Differ` directly.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::diff::Change;
+/// use jsonxf::value::Value;
+///
+/// assert_eq!(
+///     jsonxf::diff("{\"a\": [1, 2.0]}", "{\"a\":[1,2e0,3]}").unwrap(),
+///     vec![Change::Added {
+///         path: String::from("/a/2"),
+///         value: Value::Number(String::from("3")),
+///     }]
+/// );
+/// ```
+pub fn diff(a: &str, b: &str) -> Result<Vec<Change>, String> {
+    Differ::new().diff(a, b)
+}
+
+// Are the keys which two objects share in the same order in each?
+// Keys only one of them has don't matter.
+fn same_key_order(xs: &[(String, Value)], ys: &[(String, Value)]) -> bool {
+    let shared = |members: &[(String, Value)], others: &[(String, Value)]| -> Vec<String> {
+        members
+            .iter()
+            .filter(|(key, _)| others.iter().any(|(other, _)| other == key))
+            .map(|(key, _)| key.clone())
+            .collect()
+    };
+    shared(xs, ys) == shared(ys, xs)
+}
+
+/// Returns true if two JSON number literals have the same decimal value,
+/// e.g. `1`, `1.0`, and `10e-1`.
+pub fn numbers_equal(a: &str, b: &str) -> bool {
+    a == b
+        || match (normalize_number(a), normalize_number(b)) {
+            (Some(x), Some(y)) => x == y,
+            _ => false,
+        }
+}
+
+// Returns (negative, significant digits, exponent) such that the number
+// equals 0.DIGITS * 10^exponent, or None if `s` is not a number.
+fn normalize_number(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int_part, frac_part) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    if int_part.is_empty()
+        || !int_part
+            .bytes()
+            .chain(frac_part.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let digits = format!("{}{}", int_part, frac_part);
+    let leading_zeros = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_matches('0');
+    if digits.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    let exponent = exponent + int_part.len() as i64 - leading_zeros as i64;
+    Some((negative, String::from(digits), exponent))
+}
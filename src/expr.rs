@@ -0,0 +1,723 @@
+//! A small, jq-inspired expression language for the most common edits
+//! to each record of a stream.
+//!
+//! An expression is one or more filters joined by `|`, each of which
+//! turns one value into any number of values:
+//!
+//! * `.`, `.a.b`, `."a b"`, `.items[0]`, `.["a b"]` select a value,
+//!   or `null` where there is none.
+//! * `.items[]` selects each element (or member value) in turn.
+//! * `del(.a.b)` removes a member or element.
+//! * `set(.a.b, 3)` replaces a value, or adds it along with any
+//!   objects on the way to it.  The new value is a JSON literal.
+//!
+//! Expressions are applied to the stream of tokens from
+//! `jsonxf::scanner::Scanner`, each filter passing its output tokens
+//! to the next, so records are never held in memory whole.
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use scanner::{Scanner, Token};
+use validate::Validator;
+use value::{self, Value};
+use BUF_SIZE;
+
+/// One step of a path expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Each,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Select(Vec<Step>),
+    Delete(Vec<Step>),
+    Set(Vec<Step>, Value),
+}
+
+/// A parsed expression.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::expr::Expr;
+///
+/// let expr: Expr = "del(.debug) | .items[]".parse().unwrap();
+/// let record = jsonxf::value::parse("{\"debug\": 1, \"items\": [{\"a\": 1}, 2]}").unwrap();
+/// let results: Vec<String> = expr
+///     .apply(record)
+///     .unwrap()
+///     .iter()
+///     .map(|v| v.to_string())
+///     .collect();
+/// assert_eq!(results, vec!["{\"a\":1}", "2"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    source: String,
+    filters: Vec<Filter>,
+}
+
+impl Expr {
+    /// Applies this expression to a value, and returns the values it
+    /// produces, in order.
+    pub fn apply(&self, value: Value) -> Result<Vec<Value>, String> {
+        let mut output = vec![];
+        self.apply_stream(&mut value.to_string().as_bytes(), &mut output)
+            .map_err(|e| e.to_string())?;
+        value::parse_records(&String::from_utf8_lossy(&output))
+    }
+
+    /// Applies this expression to each record of a stream of
+    /// JSON-encoded data, writing the values it produces to `output`
+    /// minimized, one per line.
+    ///
+    /// Each value is written as it goes by, so an error part way
+    /// through a record leaves that record's output unfinished.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::expr::Expr;
+    ///
+    /// let expr: Expr = ".items[] | del(.tmp)".parse().unwrap();
+    /// let mut input = &b"{\"items\": [{\"id\": 1, \"tmp\": 0}]} {\"items\": []}"[..];
+    /// let mut output = vec![];
+    /// expr.apply_stream(&mut input, &mut output).unwrap();
+    /// assert_eq!(output, b"{\"id\":1}\n");
+    /// ```
+    pub fn apply_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+        let mut stages: Vec<Stage> = self.filters.iter().map(Stage::new).collect();
+        let mut writer = BufWriter::new(output);
+        let mut sink = TokenWriter::new(&mut writer);
+        let mut scanner = Scanner::new();
+        // The scanner doesn't check for commas, colons, or escapes, so
+        // each chunk is validated before its tokens are used
+        let mut validator = Validator::new();
+        let mut buf = [0_u8; BUF_SIZE];
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            validator.feed(&buf[..n])?;
+            scanner.feed(&buf[..n], &mut |token| {
+                run(&mut stages, token, &mut |token| sink.write(token))
+            })?;
+        }
+        validator.finish()?;
+        scanner.finish(&mut |token| run(&mut stages, token, &mut |token| sink.write(token)))?;
+        writer.flush()
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            source,
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let mut filters = vec![parser.filter()?];
+        while parser.eat('|') {
+            filters.push(parser.filter()?);
+        }
+        if parser.peek().is_some() {
+            return Err(parser.error("expected `|` or end of expression"));
+        }
+        Ok(Expr {
+            source: String::from(source),
+            filters,
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Applies an expression to a string of JSON-encoded data, containing
+/// any number of records, and returns the results minimized, one per
+/// line.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::expr::transform("set(.seen, true) | .user", "{\"user\": \"pat\"} {}").unwrap(),
+///     "\"pat\"\nnull"
+/// );
+/// ```
+pub fn transform(expr: &str, json_string: &str) -> Result<String, String> {
+    let expr: Expr = expr.parse()?;
+    let mut output = vec![];
+    expr.apply_stream(&mut json_string.as_bytes(), &mut output)
+        .map_err(|e| e.to_string())?;
+    let output = String::from_utf8(output).map_err(|e| e.to_string())?;
+    Ok(String::from(output.trim_end_matches('\n')))
+}
+
+// Passes a token through each stage in turn, and what comes out of the
+// last to `sink`.
+fn run(
+    stages: &mut [Stage],
+    token: Token,
+    sink: &mut dyn FnMut(Token) -> Result<(), Error>,
+) -> Result<(), Error> {
+    match stages.split_first_mut() {
+        None => sink(token),
+        Some((stage, rest)) => stage.push(token, &mut |token| run(&mut *rest, token, &mut *sink)),
+    }
+}
+
+// An open container in a stage's input.
+#[derive(Debug, Default)]
+struct Frame {
+    array: bool,          // is this an array, rather than an object?
+    on_path: bool,        // is this container on the filter's path?
+    found: bool,          // has the member or element the path names been seen?
+    count: usize,         // the number of elements seen, in an array
+    member_on_path: bool, // is the current member on the path?
+}
+
+// One filter, applied to a stream of tokens.
+struct Stage<'a> {
+    filter: &'a Filter,
+    frames: Vec<Frame>, // open containers, innermost last
+    nested: usize,      // the depth within a value being copied or dropped whole
+    copying: bool,      // is that value being copied, rather than dropped?
+}
+
+impl<'a> Stage<'a> {
+    fn new(filter: &'a Filter) -> Stage<'a> {
+        Stage {
+            filter,
+            frames: vec![],
+            nested: 0,
+            copying: false,
+        }
+    }
+
+    fn path(&self) -> &'a [Step] {
+        match self.filter {
+            Filter::Select(path) | Filter::Delete(path) | Filter::Set(path, _) => path,
+        }
+    }
+
+    // Whether this stage passes along the tokens it isn't changing;
+    // a selection passes along only what it selects.
+    fn passes(&self) -> bool {
+        !matches!(self.filter, Filter::Select(_))
+    }
+
+    // Takes the next input token, passing output tokens to `out`.
+    fn push(
+        &mut self,
+        token: Token,
+        out: &mut dyn FnMut(Token) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if self.nested > 0 {
+            match token {
+                Token::BeginObject | Token::BeginArray => self.nested += 1,
+                Token::EndObject | Token::EndArray => self.nested -= 1,
+                _ => {}
+            }
+            return if self.copying { out(token) } else { Ok(()) };
+        }
+        let path = self.path();
+        let level = self.frames.len();
+        match token {
+            Token::Key(raw) => {
+                let every = matches!(self.filter, Filter::Delete(_));
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.member_on_path = frame.on_path
+                        && match &path[level - 1] {
+                            Step::Key(key) if every || !frame.found => {
+                                frame.found = value::unescape(raw) == *key;
+                                frame.found
+                            }
+                            Step::Each => true,
+                            _ => false,
+                        };
+                    // A deleted member's key goes along with its value
+                    if every && frame.member_on_path && level == path.len() {
+                        return Ok(());
+                    }
+                }
+                if self.passes() {
+                    out(token)?;
+                }
+                Ok(())
+            }
+            Token::EndObject | Token::EndArray => {
+                let frame = self.frames.pop().unwrap_or_default();
+                if frame.on_path && !frame.found {
+                    self.missing(&frame, out)?;
+                }
+                if self.passes() {
+                    out(token)?;
+                }
+                Ok(())
+            }
+            _ => {
+                let on_path = match self.frames.last_mut() {
+                    None => true,
+                    Some(frame) if !frame.array => frame.on_path && frame.member_on_path,
+                    Some(frame) => {
+                        frame.count += 1;
+                        frame.on_path
+                            && match path[level - 1] {
+                                Step::Index(index) if index == frame.count - 1 => {
+                                    frame.found = true;
+                                    true
+                                }
+                                Step::Each => true,
+                                _ => false,
+                            }
+                    }
+                };
+                if on_path && level == path.len() {
+                    self.target(token, out)
+                } else if on_path {
+                    self.on_the_way(token, out)
+                } else {
+                    self.enter(token, false);
+                    if self.passes() {
+                        out(token)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    // Notes the start of a value; containers are tracked until they end.
+    fn enter(&mut self, token: Token, on_path: bool) {
+        match token {
+            Token::BeginObject | Token::BeginArray => self.frames.push(Frame {
+                array: token == Token::BeginArray,
+                on_path,
+                ..Frame::default()
+            }),
+            _ => {}
+        }
+    }
+
+    // Handles a value at the end of the path.
+    fn target(
+        &mut self,
+        token: Token,
+        out: &mut dyn FnMut(Token) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        match self.filter {
+            Filter::Select(_) => {
+                self.copying = true;
+                out(token)?;
+            }
+            Filter::Delete(_) if self.frames.is_empty() => {
+                return Err(invalid(String::from("cannot delete the whole record")));
+            }
+            Filter::Delete(_) => self.copying = false,
+            Filter::Set(_, new) => {
+                self.copying = false;
+                write_value(new, out)?;
+            }
+        }
+        if matches!(token, Token::BeginObject | Token::BeginArray) {
+            self.nested = 1;
+        }
+        Ok(())
+    }
+
+    // Handles a value part way along the path.
+    fn on_the_way(
+        &mut self,
+        token: Token,
+        out: &mut dyn FnMut(Token) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let path = &self.path()[self.frames.len()..];
+        match (&path[0], token) {
+            (Step::Key(_), Token::BeginObject)
+            | (Step::Index(_), Token::BeginArray)
+            | (Step::Each, Token::BeginObject)
+            | (Step::Each, Token::BeginArray) => {
+                self.enter(token, true);
+                if self.passes() {
+                    out(token)?;
+                }
+                Ok(())
+            }
+            (_, Token::Null) => match self.filter {
+                Filter::Select(_) => select_null(path, out),
+                Filter::Delete(_) => out(token),
+                Filter::Set(_, new) => write_value(&build(path, new), out),
+            },
+            (step, token) => Err(invalid(mismatch(step, token.type_name().unwrap_or("")))),
+        }
+    }
+
+    // Handles the end of a container on the path which lacked the
+    // member or element the path names.
+    fn missing(
+        &self,
+        frame: &Frame,
+        out: &mut dyn FnMut(Token) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let level = self.frames.len();
+        let rest = &self.path()[level + 1..];
+        match (self.filter, &self.path()[level]) {
+            (Filter::Select(_), Step::Key(_)) | (Filter::Select(_), Step::Index(_)) => {
+                select_null(rest, out)
+            }
+            (Filter::Set(_, new), Step::Key(key)) => {
+                out(Token::Key(&value::escape(key)))?;
+                write_value(&build(rest, new), out)
+            }
+            (Filter::Set(_, new), Step::Index(index)) => {
+                for _ in frame.count..*index {
+                    out(Token::Null)?;
+                }
+                write_value(&build(rest, new), out)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Selects the rest of a path from a `null`, which gives `null` unless
+// the path iterates.
+fn select_null(
+    path: &[Step],
+    out: &mut dyn FnMut(Token) -> Result<(), Error>,
+) -> Result<(), Error> {
+    match path.iter().find(|step| **step == Step::Each) {
+        Some(step) => Err(invalid(mismatch(step, "null"))),
+        None => out(Token::Null),
+    }
+}
+
+// Returns what setting the rest of a path to `new` makes of a `null`:
+// the objects and arrays on the way to it, or `null` if the path
+// iterates.
+fn build(path: &[Step], new: &Value) -> Value {
+    match path.split_first() {
+        None => new.clone(),
+        Some((Step::Key(key), rest)) => Value::Object(vec![(key.clone(), build(rest, new))]),
+        Some((Step::Index(index), rest)) => {
+            let mut items = vec![Value::Null; *index];
+            items.push(build(rest, new));
+            Value::Array(items)
+        }
+        Some((Step::Each, _)) => Value::Null,
+    }
+}
+
+// Passes a value along as tokens.
+fn write_value(
+    value: &Value,
+    out: &mut dyn FnMut(Token) -> Result<(), Error>,
+) -> Result<(), Error> {
+    match value {
+        Value::Null => out(Token::Null),
+        Value::Bool(b) => out(Token::Bool(*b)),
+        Value::Number(n) => out(Token::Number(n)),
+        Value::String(s) => out(Token::String(&value::escape(s))),
+        Value::Array(items) => {
+            out(Token::BeginArray)?;
+            for item in items {
+                write_value(item, out)?;
+            }
+            out(Token::EndArray)
+        }
+        Value::Object(members) => {
+            out(Token::BeginObject)?;
+            for (key, member) in members {
+                out(Token::Key(&value::escape(key)))?;
+                write_value(member, out)?;
+            }
+            out(Token::EndObject)
+        }
+    }
+}
+
+// Writes a stream of tokens as minimized JSON, one value to a line.
+struct TokenWriter<'a> {
+    output: &'a mut dyn Write,
+    commas: Vec<bool>, // whether each open container needs a comma next
+    after_key: bool,
+}
+
+impl<'a> TokenWriter<'a> {
+    fn new(output: &'a mut dyn Write) -> TokenWriter<'a> {
+        TokenWriter {
+            output,
+            commas: vec![],
+            after_key: false,
+        }
+    }
+
+    fn write(&mut self, token: Token) -> Result<(), Error> {
+        match token {
+            Token::EndObject | Token::EndArray => {
+                self.commas.pop();
+            }
+            _ if self.after_key => {}
+            _ => {
+                if let Some(comma) = self.commas.last_mut() {
+                    if *comma {
+                        self.output.write_all(b",")?;
+                    }
+                    *comma = true;
+                }
+            }
+        }
+        self.after_key = false;
+        match token {
+            Token::BeginObject => self.output.write_all(b"{")?,
+            Token::EndObject => self.output.write_all(b"}")?,
+            Token::BeginArray => self.output.write_all(b"[")?,
+            Token::EndArray => self.output.write_all(b"]")?,
+            Token::Key(raw) => {
+                self.output.write_all(raw.as_bytes())?;
+                self.output.write_all(b":")?;
+                self.after_key = true;
+            }
+            Token::String(raw) | Token::Number(raw) => self.output.write_all(raw.as_bytes())?,
+            Token::Bool(true) => self.output.write_all(b"true")?,
+            Token::Bool(false) => self.output.write_all(b"false")?,
+            Token::Null => self.output.write_all(b"null")?,
+        }
+        match token {
+            Token::BeginObject | Token::BeginArray => self.commas.push(false),
+            Token::Key(_) => {}
+            _ if self.commas.is_empty() => self.output.write_all(b"\n")?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// Whether a parsed literal is strictly JSON, since the parser passes
+// bare words through as numbers.
+fn is_json(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => value::is_number(n),
+        Value::Array(items) => items.iter().all(is_json),
+        Value::Object(members) => members.iter().all(|(_, v)| is_json(v)),
+        _ => true,
+    }
+}
+
+fn mismatch(step: &Step, type_name: &str) -> String {
+    match step {
+        Step::Key(key) => format!("cannot get key `{}` of {}", key, type_name),
+        Step::Index(index) => format!("cannot get index {} of {}", index, type_name),
+        Step::Each => format!("cannot iterate over {}", type_name),
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> String {
+        format!(
+            "{} at column {} of expression `{}`",
+            message,
+            self.pos + 1,
+            self.source
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter, String> {
+        let name = self.identifier();
+        match name.as_str() {
+            "" => Ok(Filter::Select(self.path()?)),
+            "del" => {
+                self.expect('(')?;
+                let path = self.path()?;
+                self.expect(')')?;
+                Ok(Filter::Delete(path))
+            }
+            "set" => {
+                self.expect('(')?;
+                let path = self.path()?;
+                self.expect(',')?;
+                let new = self.literal()?;
+                self.expect(')')?;
+                Ok(Filter::Set(path, new))
+            }
+            _ => Err(self.error(&format!("unknown function `{}`", name))),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|&c| c == '_' || c.is_alphanumeric())
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn path(&mut self) -> Result<Vec<Step>, String> {
+        self.expect('.')?;
+        let mut steps = vec![];
+        // A key may follow the leading dot directly, as in `.a`
+        match self.chars.get(self.pos) {
+            Some('"') => steps.push(Step::Key(self.string()?)),
+            Some(&c) if c == '_' || c.is_alphabetic() => steps.push(Step::Key(self.identifier())),
+            _ => {}
+        }
+        loop {
+            match self.chars.get(self.pos) {
+                Some('.') => {
+                    self.pos += 1;
+                    match self.chars.get(self.pos) {
+                        Some('"') => steps.push(Step::Key(self.string()?)),
+                        _ => {
+                            let key = self.identifier();
+                            if key.is_empty() {
+                                return Err(self.error("expected a key"));
+                            }
+                            steps.push(Step::Key(key));
+                        }
+                    }
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    if self.eat(']') {
+                        steps.push(Step::Each);
+                        continue;
+                    }
+                    if self.peek() == Some('"') {
+                        steps.push(Step::Key(self.string()?));
+                    } else {
+                        let digits = self.identifier();
+                        match digits.parse::<usize>() {
+                            Ok(index) => steps.push(Step::Index(index)),
+                            Err(_) => return Err(self.error("expected an index, a key, or `]`")),
+                        }
+                    }
+                    self.expect(']')?;
+                }
+                _ => return Ok(steps),
+            }
+        }
+    }
+
+    // Reads a JSON string, returning its unescaped contents.
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        self.pos += 1; // the opening quote
+        let mut in_backslash = false;
+        while let Some(&c) = self.chars.get(self.pos) {
+            self.pos += 1;
+            if in_backslash {
+                in_backslash = false;
+            } else if c == '\\' {
+                in_backslash = true;
+            } else if c == '"' {
+                let text: String = self.chars[start..self.pos].iter().collect();
+                return match value::parse(&text) {
+                    Ok(Value::String(s)) => Ok(s),
+                    _ => Err(self.error("invalid string")),
+                };
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    // Reads a JSON literal, up to the `)` which ends the call.
+    fn literal(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut in_backslash = false;
+        while let Some(&c) = self.chars.get(self.pos) {
+            if in_string {
+                if in_backslash {
+                    in_backslash = false;
+                } else if c == '\\' {
+                    in_backslash = true;
+                } else if c == '"' {
+                    in_string = false;
+                }
+            } else {
+                match c {
+                    '"' => in_string = true,
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => depth -= 1,
+                    ')' if depth == 0 => break,
+                    _ => {}
+                }
+            }
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match value::parse(text.trim()) {
+            Ok(value) if is_json(&value) => Ok(value),
+            Ok(_) => {
+                self.pos = start;
+                Err(self.error("invalid JSON value"))
+            }
+            Err(e) => {
+                self.pos = start;
+                Err(self.error(&format!("invalid JSON value ({})", e)))
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Formatting with caller-provided buffers, without allocating.
+//!
+//! `FixedFormatter` formats from one byte slice into another.  When the
+//! output slice fills up it stops and says how far it got, and picks up
+//! where it left off on the next call, even partway through a line's
+//! indentation.  It keeps no buffers of its own, takes its settings as
+//! borrowed strings, and uses nothing from `std` but what is also in
+//! `core`, for constrained targets and for contexts like signal handlers
+//! where allocating isn't safe.
+//!
+//! It formats as `Formatter` does, but without escaping non-ASCII
+//! characters, folding deep arrays and objects, inlining short ones, or
+//! sorting keys, and it passes along a closing bracket with nothing to
+//! close instead of failing.
+
+use is_bare_byte;
+use {C_BACKSLASH, C_COLON, C_COMMA, C_CR, C_LEFT_BRACE, C_LEFT_BRACKET, C_LF, C_QUOTE};
+use {C_RIGHT_BRACE, C_RIGHT_BRACKET, C_SPACE, C_TAB};
+
+// The most pieces of output owed for one byte of input.
+const MAX_OWED: usize = 6;
+
+/// How far a call to `FixedFormatter::format` or `finish` got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of input consumed.
+    pub read: usize,
+    /// Bytes of output written.
+    pub written: usize,
+    /// Whether the output slice filled up before the input was used up.
+    /// Call again with more room to continue.
+    pub output_full: bool,
+}
+
+/// Formats JSON-encoded data from one byte slice into another, without
+/// allocating.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::fixed::FixedFormatter;
+///
+/// let input = b"{\"a\": [1, 2]}";
+/// let mut output = [0_u8; 8];
+/// let mut formatted: Vec<u8> = vec![];
+/// let mut xf = FixedFormatter::pretty_printer();
+/// let mut rest = &input[..];
+/// loop {
+///     let progress = xf.format(rest, &mut output);
+///     formatted.extend_from_slice(&output[..progress.written]);
+///     rest = &rest[progress.read..];
+///     if !progress.output_full {
+///         break;
+///     }
+/// }
+/// loop {
+///     let progress = xf.finish(&mut output);
+///     formatted.extend_from_slice(&output[..progress.written]);
+///     if !progress.output_full {
+///         break;
+///     }
+/// }
+/// assert_eq!(formatted, b"{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedFormatter<'a> {
+    /// Used for beginning-of-line indentation in arrays and objects.
+    pub indent: &'a str,
+
+    /// Used inside arrays and objects.
+    pub line_separator: &'a str,
+
+    /// Used between root-level values.
+    pub record_separator: &'a str,
+
+    /// Used after a colon inside objects.
+    pub after_colon: &'a str,
+
+    /// Used at very end of output.
+    pub trailing_output: &'a str,
+
+    /// Add a record_separator as soon as a record ends, before seeing a
+    /// subsequent record.
+    pub eager_record_separators: bool,
+
+    // private mutable state, as in `Formatter`
+    depth: usize,
+    in_string: bool,
+    in_backslash: bool,
+    empty: bool,
+    first: bool,
+    in_bare: bool,
+    finished: bool,              // has `finish` queued the end of the output?
+    owed: [Piece<'a>; MAX_OWED], // output not yet written
+    owed_len: usize,
+    owed_start: usize, // the first piece not yet written
+    offset: usize,     // bytes of that piece already written
+}
+
+// Part of the output owed for a byte of input.
+#[derive(Debug, Clone, Copy)]
+enum Piece<'a> {
+    Text(&'a [u8]),
+    Indent(usize), // this many indents
+    Byte(u8),
+}
+
+impl<'a> FixedFormatter<'a> {
+    /// Returns a FixedFormatter set up for pretty-printing, as
+    /// `Formatter::pretty_printer` is.
+    pub fn pretty_printer() -> FixedFormatter<'a> {
+        FixedFormatter {
+            indent: "  ",
+            line_separator: "\n",
+            record_separator: "\n",
+            after_colon: " ",
+            trailing_output: "",
+            eager_record_separators: false,
+            depth: 0,
+            in_string: false,
+            in_backslash: false,
+            empty: false,
+            first: true,
+            in_bare: false,
+            finished: false,
+            owed: [Piece::Byte(0); MAX_OWED],
+            owed_len: 0,
+            owed_start: 0,
+            offset: 0,
+        }
+    }
+
+    /// Returns a FixedFormatter set up for minimizing, as
+    /// `Formatter::minimizer` is.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut output = [0_u8; 64];
+    /// let mut xf = jsonxf::fixed::FixedFormatter::minimizer();
+    /// let progress = xf.format(b"{ \"a\" : [ 1 ] }", &mut output);
+    /// assert_eq!(&output[..progress.written], b"{\"a\":[1]}");
+    /// ```
+    pub fn minimizer() -> FixedFormatter<'a> {
+        FixedFormatter {
+            indent: "",
+            line_separator: "",
+            after_colon: "",
+            ..FixedFormatter::pretty_printer()
+        }
+    }
+
+    /// Returns to the start-of-input state, keeping the settings and
+    /// dropping any output owed.
+    pub fn reset(&mut self) {
+        self.depth = 0;
+        self.in_string = false;
+        self.in_backslash = false;
+        self.empty = false;
+        self.first = true;
+        self.in_bare = false;
+        self.finished = false;
+        self.owed_len = 0;
+        self.owed_start = 0;
+        self.offset = 0;
+    }
+
+    /// Formats as much of `input` into `output` as fits.  Any input
+    /// that wasn't read should be passed to the next call, along with
+    /// whatever follows it.
+    pub fn format(&mut self, input: &[u8], output: &mut [u8]) -> Progress {
+        let mut progress = Progress {
+            read: 0,
+            written: 0,
+            output_full: false,
+        };
+        loop {
+            if !self.pay(output, &mut progress.written) {
+                progress.output_full = true;
+                return progress;
+            }
+            let rest = &input[progress.read..];
+            if rest.is_empty() {
+                return progress;
+            }
+            if self.in_string && !self.in_backslash {
+                // Copy the string up to its next quote or backslash
+                let len = rest
+                    .iter()
+                    .position(|&b| b == C_QUOTE || b == C_BACKSLASH)
+                    .unwrap_or(rest.len())
+                    .min(output.len() - progress.written);
+                if len > 0 {
+                    output[progress.written..progress.written + len].copy_from_slice(&rest[..len]);
+                    progress.read += len;
+                    progress.written += len;
+                    continue;
+                }
+            }
+            self.owe_for(rest[0]);
+            progress.read += 1;
+        }
+    }
+
+    /// Writes the end of the output, as at the end of input.  Call
+    /// again while `output_full` is true.
+    pub fn finish(&mut self, output: &mut [u8]) -> Progress {
+        if !self.finished {
+            self.finished = true;
+            self.end_bare();
+            self.owe(Piece::Text(self.trailing_output.as_bytes()));
+        }
+        let mut written = 0;
+        let output_full = !self.pay(output, &mut written);
+        Progress {
+            read: 0,
+            written,
+            output_full,
+        }
+    }
+
+    // Writes as much owed output as fits, and returns whether all of it
+    // did.
+    fn pay(&mut self, output: &mut [u8], written: &mut usize) -> bool {
+        while self.owed_start < self.owed_len {
+            let piece = self.owed[self.owed_start];
+            let len = match piece {
+                Piece::Text(text) => text.len(),
+                Piece::Indent(n) => n * self.indent.len(),
+                Piece::Byte(_) => 1,
+            };
+            while self.offset < len {
+                let room = &mut output[*written..];
+                if room.is_empty() {
+                    return false;
+                }
+                let copied = match piece {
+                    Piece::Text(text) => copy(&text[self.offset..], room),
+                    Piece::Indent(_) => {
+                        let indent = self.indent.as_bytes();
+                        copy(&indent[self.offset % indent.len()..], room)
+                    }
+                    Piece::Byte(b) => copy(&[b], room),
+                };
+                self.offset += copied;
+                *written += copied;
+            }
+            self.owed_start += 1;
+            self.offset = 0;
+        }
+        self.owed_len = 0;
+        self.owed_start = 0;
+        true
+    }
+
+    fn owe(&mut self, piece: Piece<'a>) {
+        self.owed[self.owed_len] = piece;
+        self.owed_len += 1;
+    }
+
+    // Owes a line separator and indentation, as at the start of a line
+    // inside an array or object.
+    fn owe_newline(&mut self) {
+        self.owe(Piece::Text(self.line_separator.as_bytes()));
+        self.owe(Piece::Indent(self.depth));
+    }
+
+    // Decides the output for one byte of input, as `Formatter` does.
+    fn owe_for(&mut self, b: u8) {
+        if self.in_string {
+            self.owe(Piece::Byte(b));
+            if self.in_backslash {
+                self.in_backslash = false;
+            } else if b == C_BACKSLASH {
+                self.in_backslash = true;
+            } else if b == C_QUOTE {
+                self.in_string = false;
+                if self.eager_record_separators && self.depth == 0 {
+                    self.owe(Piece::Text(self.record_separator.as_bytes()));
+                }
+            }
+            return;
+        }
+        if self.in_bare && !is_bare_byte(b) {
+            self.end_bare();
+        }
+        match b {
+            C_SPACE | C_LF | C_CR | C_TAB => {}
+
+            C_LEFT_BRACKET | C_LEFT_BRACE => {
+                if self.first {
+                    self.first = false;
+                } else if self.empty {
+                    self.owe_newline();
+                } else if !self.eager_record_separators && self.depth == 0 {
+                    self.owe(Piece::Text(self.record_separator.as_bytes()));
+                }
+                self.owe(Piece::Byte(b));
+                self.depth += 1;
+                self.empty = true;
+            }
+
+            C_RIGHT_BRACKET | C_RIGHT_BRACE => {
+                self.depth = self.depth.saturating_sub(1);
+                if self.empty {
+                    self.empty = false;
+                } else {
+                    self.owe_newline();
+                }
+                self.owe(Piece::Byte(b));
+                if self.eager_record_separators && self.depth == 0 {
+                    self.owe(Piece::Text(self.record_separator.as_bytes()));
+                }
+            }
+
+            C_COMMA => {
+                self.owe(Piece::Byte(b));
+                self.owe_newline();
+            }
+
+            C_COLON => {
+                self.owe(Piece::Byte(b));
+                self.owe(Piece::Text(self.after_colon.as_bytes()));
+            }
+
+            _ if self.depth == 0 => {
+                // A scalar record
+                if !self.in_bare {
+                    if self.first {
+                        self.first = false;
+                    } else if !self.eager_record_separators {
+                        self.owe(Piece::Text(self.record_separator.as_bytes()));
+                    }
+                    if b == C_QUOTE {
+                        self.in_string = true;
+                    } else {
+                        self.in_bare = true;
+                    }
+                }
+                self.owe(Piece::Byte(b));
+            }
+
+            _ => {
+                if self.empty {
+                    self.owe_newline();
+                    self.empty = false;
+                }
+                if b == C_QUOTE {
+                    self.in_string = true;
+                }
+                self.owe(Piece::Byte(b));
+            }
+        }
+    }
+
+    // Ends a root-level number or literal.
+    fn end_bare(&mut self) {
+        if self.in_bare {
+            self.in_bare = false;
+            if self.eager_record_separators {
+                self.owe(Piece::Text(self.record_separator.as_bytes()));
+            }
+        }
+    }
+}
+
+// Copies as much of `from` as fits into `to`, and returns how much.
+fn copy(from: &[u8], to: &mut [u8]) -> usize {
+    let len = from.len().min(to.len());
+    to[..len].copy_from_slice(&from[..len]);
+    len
+}
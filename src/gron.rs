@@ -0,0 +1,225 @@
+//! Conversion between JSON and greppable assignment statements, in the
+//! style of <a href="https://github.com/tomnomnom/gron" target="_blank">gron</a>.
+//!
+//! Each value in the input becomes one line, giving its full path:
+//!
+//! ```text
+//! json = {};
+//! json.users = [];
+//! json.users[0] = {};
+//! json.users[0].name = "ana";
+//! ```
+//!
+//! Every top-level record starts again with a `json = ...;` line, and
+//! `ungron` likewise starts a new record whenever the root is assigned.
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use path::{Dotted, PathSegment, PathTracker};
+use scanner::{Scanner, Token};
+use value::{self, Value};
+
+const ROOT: &str = "json";
+
+/// Converts a string of JSON-encoded data into gron statements.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::gron("{\"users\":[{\"name\":\"ana\"}]}").unwrap(),
+///     "json = {};\njson.users = [];\njson.users[0] = {};\njson.users[0].name = \"ana\";\n"
+/// );
+/// ```
+pub fn gron(json_string: &str) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    gron_stream(&mut json_string.as_bytes(), &mut output).map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Converts a stream of JSON-encoded data into gron statements.
+///
+/// Values are written as soon as they are scanned, so memory use
+/// depends only on nesting depth.
+pub fn gron_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut tracker = PathTracker::new();
+    Scanner::new().scan_stream(&mut BufReader::new(input), &mut |token| {
+        tracker.update(&token);
+        let value = match token {
+            Token::Key(_) | Token::EndObject | Token::EndArray => return Ok(()),
+            Token::BeginObject => "{}",
+            Token::BeginArray => "[]",
+            Token::String(raw) | Token::Number(raw) => raw,
+            Token::Bool(true) => "true",
+            Token::Bool(false) => "false",
+            Token::Null => "null",
+        };
+        writeln!(writer, "{} = {};", Dotted(ROOT, tracker.path()), value)
+    })?;
+    writer.flush()
+}
+
+/// Reassembles gron statements into minimized JSON, one record per
+/// line.  Blank lines are ignored.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::ungron("json.users[0].name = \"ana\";\njson.ok = true;\n").unwrap(),
+///     "{\"users\":[{\"name\":\"ana\"}],\"ok\":true}"
+/// );
+/// ```
+pub fn ungron(gron_string: &str) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    ungron_stream(&mut gron_string.as_bytes(), &mut output).map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Reassembles a stream of gron statements into minimized JSON,
+/// one record per line.
+///
+/// Each record is written once the next record begins, or at the end
+/// of input.
+pub fn ungron_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut record: Option<Value> = None;
+    let mut first = true;
+
+    for (n, line) in BufReader::new(input).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (path, value) = parse_statement(&line)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("line {}: {}", n + 1, e)))?;
+
+        if path.is_empty() {
+            if let Some(done) = record.take() {
+                write_record(&mut writer, &done, &mut first)?;
+            }
+        }
+        assign(record.get_or_insert(Value::Null), &path, value);
+    }
+
+    if let Some(done) = record {
+        write_record(&mut writer, &done, &mut first)?;
+    }
+    writer.flush()
+}
+
+fn write_record(writer: &mut dyn Write, record: &Value, first: &mut bool) -> Result<(), Error> {
+    if !*first {
+        writer.write_all(b"\n")?;
+    }
+    *first = false;
+    write!(writer, "{}", record)
+}
+
+// Sets the value at `path`, creating or replacing containers as needed.
+fn assign(target: &mut Value, path: &[PathSegment], value: Value) {
+    let (segment, rest) = match path.split_first() {
+        None => {
+            *target = value;
+            return;
+        }
+        Some(split) => split,
+    };
+
+    let next = match segment {
+        PathSegment::Key(key) => {
+            if !matches!(target, Value::Object(_)) {
+                *target = Value::Object(vec![]);
+            }
+            let members = match target {
+                Value::Object(members) => members,
+                _ => unreachable!(),
+            };
+            let index = match members.iter().position(|(k, _)| k == key) {
+                Some(index) => index,
+                None => {
+                    members.push((key.clone(), Value::Null));
+                    members.len() - 1
+                }
+            };
+            &mut members[index].1
+        }
+        PathSegment::Index(index) => {
+            if !matches!(target, Value::Array(_)) {
+                *target = Value::Array(vec![]);
+            }
+            let items = match target {
+                Value::Array(items) => items,
+                _ => unreachable!(),
+            };
+            if items.len() <= *index {
+                items.resize(index + 1, Value::Null);
+            }
+            &mut items[*index]
+        }
+    };
+    assign(next, rest, value);
+}
+
+// Parses `json.a["b"][0] = value;` into a path and a value.
+fn parse_statement(line: &str) -> Result<(Vec<PathSegment>, Value), String> {
+    let mut rest = line.trim();
+    if !rest.starts_with(ROOT) {
+        return Err(format!("statement must begin with `{}`", ROOT));
+    }
+    rest = &rest[ROOT.len()..];
+
+    let mut path = vec![];
+    loop {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(after.len());
+            if end == 0 {
+                return Err(String::from("expected a key after `.`"));
+            }
+            path.push(PathSegment::Key(String::from(&after[..end])));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix("[\"") {
+            let end = closing_quote(after).ok_or("unterminated key")?;
+            path.push(PathSegment::Key(value::unescape(&after[..end])));
+            rest = after[end + 1..]
+                .strip_prefix(']')
+                .ok_or("expected `]` after key")?;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or("expected `]` after index")?;
+            let index = after[..end]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid array index `{}`", &after[..end]))?;
+            path.push(PathSegment::Index(index));
+            rest = &after[end + 1..];
+        } else {
+            break;
+        }
+    }
+
+    let rest = rest.trim_start().strip_prefix('=').ok_or("expected `=`")?;
+    let rest = rest.trim_end();
+    let rest = rest.strip_suffix(';').unwrap_or(rest);
+    let value = value::parse(rest)?;
+    Ok((path, value))
+}
+
+// Returns the index of the first unescaped double quote in `s`.
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut backslash = false;
+    for (i, b) in s.bytes().enumerate() {
+        match b {
+            b'\\' => backslash = !backslash,
+            b'"' if !backslash => return Some(i),
+            _ => backslash = false,
+        }
+    }
+    None
+}
@@ -0,0 +1,195 @@
+//! Content hashing of JSON data, independent of formatting.
+//!
+//! Each record is hashed in its canonical form, as RFC 8785 (JCS)
+//! defines it and `jsonxf::jcs` writes it: minimized, with object
+//! members sorted by key, strings escaped minimally, and numbers
+//! written as ECMAScript writes them, so that `1.0` and `1` hash the
+//! same.
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256, Sha512};
+use xxhash_rust::xxh3::Xxh3Default;
+
+use jcs;
+use value::{self, Value};
+
+/// A hash function for `canonical_hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// SHA-256, with a 64-character hex digest.
+    #[default]
+    Sha256,
+
+    /// SHA-512, with a 128-character hex digest.
+    Sha512,
+
+    /// XXH3, 64-bit, with a 16-character hex digest.  Much faster, but
+    /// not for security.
+    Xxh3,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    /// Parses an algorithm name: `sha256`, `sha512`, or `xxh3`.
+    fn from_str(s: &str) -> Result<Algorithm, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(Algorithm::Sha256),
+            "sha512" | "sha-512" => Ok(Algorithm::Sha512),
+            "xxh3" => Ok(Algorithm::Xxh3),
+            _ => Err(format!("unknown hash algorithm `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Algorithm::Sha256 => f.write_str("sha256"),
+            Algorithm::Sha512 => f.write_str("sha512"),
+            Algorithm::Xxh3 => f.write_str("xxh3"),
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Xxh3(Box<Xxh3Default>),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            Algorithm::Xxh3 => Hasher::Xxh3(Box::new(Xxh3Default::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Xxh3(h) => h.update(data),
+        }
+    }
+
+    fn hex_digest(self) -> String {
+        let digest = match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Xxh3(h) => h.digest().to_be_bytes().to_vec(),
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Returns the canonical form of a value, as hashed by
+/// `canonical_hash`.  This is `jsonxf::jcs::to_canonical`, and fails as
+/// it does, on numbers out of range for a double.
+///
+/// # Example:
+///
+/// ```
+/// let value = jsonxf::value::parse("{ \"b\": \"\\u00e9\", \"a\": [1.0, 2e0] }").unwrap();
+/// assert_eq!(
+///     jsonxf::hash::canonical_string(&value).unwrap(),
+///     "{\"a\":[1,2],\"b\":\"\u{e9}\"}"
+/// );
+/// ```
+pub fn canonical_string(value: &Value) -> Result<String, String> {
+    jcs::to_canonical(value)
+}
+
+// The canonical form of a record being read.
+fn canonical_record(value: &Value) -> Result<String, Error> {
+    canonical_string(value).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Hashes the canonical form of a string of JSON-encoded data, and
+/// returns the hex digest.  Multiple records are hashed as their
+/// canonical forms joined by newlines.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::hash::Algorithm;
+///
+/// let a = jsonxf::canonical_hash("{\"a\": 1, \"b\": [true]}", Algorithm::Sha256).unwrap();
+/// let b = jsonxf::canonical_hash("{\n  \"b\": [\n    true\n  ],\n  \"a\": 1\n}", Algorithm::Sha256).unwrap();
+/// assert_eq!(a, b);
+/// assert_eq!(a.len(), 64);
+/// ```
+pub fn canonical_hash(json_string: &str, algorithm: Algorithm) -> Result<String, String> {
+    canonical_hash_stream(&mut json_string.as_bytes(), algorithm).map_err(|e| e.to_string())
+}
+
+/// Hashes the canonical form of a stream of JSON-encoded data, and
+/// returns the hex digest.
+///
+/// Only one record is held in memory at a time.
+pub fn canonical_hash_stream(input: &mut dyn Read, algorithm: Algorithm) -> Result<String, Error> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut first = true;
+    value::read_records(&mut BufReader::new(input), &mut |record| {
+        if !first {
+            hasher.update(b"\n");
+        }
+        first = false;
+        hasher.update(canonical_record(&record)?.as_bytes());
+        Ok(())
+    })?;
+    Ok(hasher.hex_digest())
+}
+
+/// Hashes the canonical form of each record in a string of
+/// JSON-encoded data, and returns the hex digests, in order.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::hash::Algorithm;
+///
+/// let digests = jsonxf::hash_records("{\"a\": 1, \"b\": 2}\n[]\n{\"b\":2,\"a\":1}", Algorithm::Xxh3).unwrap();
+/// assert_eq!(digests.len(), 3);
+/// assert_eq!(digests[0], digests[2]);
+/// assert_ne!(digests[0], digests[1]);
+/// assert_eq!(digests[0].len(), 16);
+/// ```
+pub fn hash_records(json_string: &str, algorithm: Algorithm) -> Result<Vec<String>, String> {
+    let mut digests = vec![];
+    hash_records_stream(&mut json_string.as_bytes(), algorithm, &mut |digest, _| {
+        digests.push(String::from(digest));
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(digests)
+}
+
+/// Hashes the canonical form of each record in a stream of
+/// JSON-encoded data, passing `f` the hex digest and the canonical form
+/// of each.
+///
+/// Only one record is held in memory at a time.
+pub fn hash_records_stream<F>(
+    input: &mut dyn Read,
+    algorithm: Algorithm,
+    f: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(&str, &str) -> Result<(), Error>,
+{
+    value::read_records(&mut BufReader::new(input), &mut |record| {
+        let canonical = canonical_record(&record)?;
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(canonical.as_bytes());
+        f(&hasher.hex_digest(), &canonical)
+    })
+}
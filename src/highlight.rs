@@ -0,0 +1,142 @@
+//! Classifying the tokens of formatted JSON as it streams past, for
+//! syntax highlighting.
+//!
+//! `Highlighter` tracks just enough state to tell keys from string
+//! values, and passes the input through to a `Markup` unchanged except
+//! for the markers placed around each token.
+
+use std::io::Error;
+
+/// The kinds of token which highlighters distinguish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    /// Brackets, braces, commas, and colons.
+    Punct,
+}
+
+/// Receives highlighted input from a `Highlighter`.
+pub(crate) trait Markup {
+    /// Called before the first byte of a token.
+    fn begin(&mut self, class: Class) -> Result<(), Error>;
+
+    /// Called after the last byte of a token.
+    fn end(&mut self, class: Class) -> Result<(), Error>;
+
+    /// Called with input bytes, both within and between tokens.
+    fn text(&mut self, buf: &[u8]) -> Result<(), Error>;
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Highlighter {
+    containers: Vec<u8>, // b'{' or b'[' for each open container
+    expect_key: bool,
+    token: Option<Class>, // the string or literal in progress
+    in_backslash: bool,
+}
+
+impl Highlighter {
+    /// Returns the class of the string or literal in progress at the
+    /// end of the input so far.
+    pub fn open_token(&self) -> Option<Class> {
+        self.token
+    }
+
+    /// Ends any token in progress, as at the end of input.
+    pub fn finish(&mut self, markup: &mut dyn Markup) -> Result<(), Error> {
+        match self.token.take() {
+            Some(class) => markup.end(class),
+            None => Ok(()),
+        }
+    }
+
+    pub fn feed(&mut self, buf: &[u8], markup: &mut dyn Markup) -> Result<(), Error> {
+        let mut copied = 0; // bytes of `buf` already passed along
+        for (i, &b) in buf.iter().enumerate() {
+            match self.token {
+                Some(class @ Class::Key) | Some(class @ Class::String) => {
+                    if self.in_backslash {
+                        self.in_backslash = false;
+                    } else if b == b'\\' {
+                        self.in_backslash = true;
+                    } else if b == b'"' {
+                        markup.text(&buf[copied..=i])?;
+                        markup.end(class)?;
+                        copied = i + 1;
+                        self.token = None;
+                    }
+                    continue;
+                }
+                Some(class) => {
+                    let delimiter = matches!(
+                        b,
+                        b'{' | b'}'
+                            | b'['
+                            | b']'
+                            | b','
+                            | b':'
+                            | b'"'
+                            | b' '
+                            | b'\t'
+                            | b'\r'
+                            | b'\n'
+                    );
+                    if !delimiter {
+                        continue;
+                    }
+                    markup.text(&buf[copied..i])?;
+                    markup.end(class)?;
+                    copied = i;
+                    self.token = None;
+                }
+                None => {}
+            }
+
+            markup.text(&buf[copied..i])?;
+            copied = i;
+            match b {
+                b' ' | b'\t' | b'\r' | b'\n' => continue,
+                b'"' => {
+                    let class = if self.expect_key {
+                        Class::Key
+                    } else {
+                        Class::String
+                    };
+                    markup.begin(class)?;
+                    self.token = Some(class);
+                    continue;
+                }
+                b'{' | b'[' => {
+                    self.containers.push(b);
+                    self.expect_key = b == b'{';
+                }
+                b'}' | b']' => {
+                    self.containers.pop();
+                    self.expect_key = false;
+                }
+                b',' => self.expect_key = self.containers.last() == Some(&b'{'),
+                b':' => self.expect_key = false,
+                _ => {
+                    let class = match b {
+                        b't' | b'f' => Class::Boolean,
+                        b'n' => Class::Null,
+                        b'.' => Class::Punct, // a folded container's ellipsis
+                        _ => Class::Number,
+                    };
+                    markup.begin(class)?;
+                    self.token = Some(class);
+                    continue;
+                }
+            }
+            markup.begin(Class::Punct)?;
+            markup.text(&buf[i..=i])?;
+            markup.end(Class::Punct)?;
+            copied = i + 1;
+        }
+        markup.text(&buf[copied..])
+    }
+}
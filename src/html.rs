@@ -0,0 +1,149 @@
+//! Syntax-highlighted HTML output.
+//!
+//! `HtmlWriter` marks up formatted JSON as it is written, wrapping each
+//! token in a `<span>` with one of these classes:
+//!
+//! * `json-key`
+//! * `json-string`
+//! * `json-number`
+//! * `json-boolean`
+//! * `json-null`
+//! * `json-punct` (brackets, braces, commas, and colons)
+//!
+//! The whole document is wrapped in `<pre class="json">`, and `STYLESHEET`
+//! holds a default set of colors for these classes.
+
+use std::io::prelude::*;
+use std::io::Error;
+
+use highlight::{Class, Highlighter, Markup};
+use Formatter;
+
+/// A default stylesheet for highlighted output.
+pub const STYLESHEET: &str = "<style>
+pre.json { background: #f8f8f8; color: #333; padding: 1em; }
+pre.json .json-key { color: #0451a5; }
+pre.json .json-string { color: #a31515; }
+pre.json .json-number { color: #098658; }
+pre.json .json-boolean { color: #0000ff; }
+pre.json .json-null { color: #795e26; }
+pre.json .json-punct { color: #777; }
+</style>
+";
+
+/// Formats a string of JSON-encoded data with `xf`, and returns it as
+/// highlighted HTML.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// assert_eq!(
+///     jsonxf::to_html("{\"a<b\": [1, true]}", &mut xf).unwrap(),
+///     "<pre class=\"json\"><span class=\"json-punct\">{</span>\
+///      <span class=\"json-key\">\"a&lt;b\"</span><span class=\"json-punct\">:</span>\
+///      <span class=\"json-punct\">[</span><span class=\"json-number\">1</span>\
+///      <span class=\"json-punct\">,</span><span class=\"json-boolean\">true</span>\
+///      <span class=\"json-punct\">]</span><span class=\"json-punct\">}</span></pre>\n"
+/// );
+/// ```
+pub fn to_html(json_string: &str, xf: &mut Formatter) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    let mut writer = HtmlWriter::new(&mut output);
+    xf.format_stream(&mut json_string.as_bytes(), &mut writer)
+        .and_then(|_| writer.finish())
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Highlights formatted JSON as HTML while writing it to an
+/// underlying writer.
+///
+/// Tokens may be split across writes.  Call `finish` after the last
+/// write to close the document.
+pub struct HtmlWriter<W: Write> {
+    output: W,
+    started: bool,
+    highlighter: Highlighter,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    pub fn new(output: W) -> HtmlWriter<W> {
+        HtmlWriter {
+            output,
+            started: false,
+            highlighter: Highlighter::default(),
+        }
+    }
+
+    /// Closes any open token and the document, and flushes the
+    /// underlying writer.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        self.start()?;
+        self.highlighter.finish(&mut Html(&mut self.output))?;
+        self.output.write_all(b"</pre>\n")?;
+        self.started = false;
+        self.output.flush()
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        if !self.started {
+            self.output.write_all(b"<pre class=\"json\">")?;
+            self.started = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HtmlWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.start()?;
+        self.highlighter.feed(buf, &mut Html(&mut self.output))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.output.flush()
+    }
+}
+
+struct Html<'a>(&'a mut dyn Write);
+
+impl<'a> Markup for Html<'a> {
+    fn begin(&mut self, class: Class) -> Result<(), Error> {
+        let class = match class {
+            Class::Key => "json-key",
+            Class::String => "json-string",
+            Class::Number => "json-number",
+            Class::Boolean => "json-boolean",
+            Class::Null => "json-null",
+            Class::Punct => "json-punct",
+        };
+        write!(self.0, "<span class=\"{}\">", class)
+    }
+
+    fn end(&mut self, _class: Class) -> Result<(), Error> {
+        self.0.write_all(b"</span>")
+    }
+
+    fn text(&mut self, buf: &[u8]) -> Result<(), Error> {
+        write_escaped(self.0, buf)
+    }
+}
+
+// Writes `buf` with HTML special characters escaped.
+fn write_escaped(output: &mut dyn Write, buf: &[u8]) -> Result<(), Error> {
+    let mut start = 0;
+    for (i, &b) in buf.iter().enumerate() {
+        let entity: &[u8] = match b {
+            b'&' => b"&amp;",
+            b'<' => b"&lt;",
+            b'>' => b"&gt;",
+            _ => continue,
+        };
+        output.write_all(&buf[start..i])?;
+        output.write_all(entity)?;
+        start = i + 1;
+    }
+    output.write_all(&buf[start..])
+}
@@ -0,0 +1,493 @@
+//! Byte-offset indexes of the records in a stream of JSON data, so that
+//! record N of a large file can be found by seeking instead of scanning.
+//!
+//! An index has one line per top-level record, giving the record's
+//! start offset and length in bytes, separated by a tab.  An index of
+//! both input and output has four columns: input start and length,
+//! then output start and length.
+//!
+//! A path index goes further, listing the location of every value
+//! down to a given depth, so that a single value deep within a large
+//! document can be read without scanning the rest (`extract_indexed()`).
+//! Each line gives a value's record number, start offset, length, and
+//! JSON Pointer.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::SeekFrom;
+use std::str::FromStr;
+
+use path::{PathTracker, Pointer};
+use scanner::{Scanner, Span, Token};
+use value::{self, Value};
+use Formatter;
+use BUF_SIZE;
+
+/// Which stream `format_indexed` should index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexOf {
+    Input,
+    #[default]
+    Output,
+    Both,
+}
+
+impl FromStr for IndexOf {
+    type Err = String;
+
+    /// Parses `input`, `output`, or `both`.
+    fn from_str(s: &str) -> Result<IndexOf, String> {
+        match s {
+            "input" => Ok(IndexOf::Input),
+            "output" => Ok(IndexOf::Output),
+            "both" => Ok(IndexOf::Both),
+            _ => Err(format!(
+                "expected `input`, `output`, or `both`, not `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// `RecordIndexer` finds the location of each top-level record in
+/// JSON-encoded data fed to it in chunks.  The whitespace between
+/// records is not part of any record.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::index::RecordIndexer;
+/// use jsonxf::scanner::Span;
+///
+/// let mut spans = vec![];
+/// let mut indexer = RecordIndexer::new();
+/// let mut f = |span: Span| {
+///     spans.push((span.start, span.len()));
+///     Ok(())
+/// };
+/// indexer.feed(b"{\"a\": [1]}\n  tr", &mut f).unwrap();
+/// indexer.feed(b"ue\n\"x\"", &mut f).unwrap();
+/// indexer.finish(&mut f).unwrap();
+/// assert_eq!(spans, vec![(0, 10), (13, 4), (18, 3)]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordIndexer {
+    scanner: Scanner,
+    depth: usize,
+    start: u64,
+}
+
+impl RecordIndexer {
+    /// Returns a new `RecordIndexer`, ready for the start of input.
+    pub fn new() -> RecordIndexer {
+        RecordIndexer::default()
+    }
+
+    /// Adds a chunk of input, passing the span of each record it
+    /// completes to `f`.
+    pub fn feed<F>(&mut self, buf: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Span) -> Result<(), Error>,
+    {
+        let RecordIndexer {
+            scanner,
+            depth,
+            start,
+        } = self;
+        scanner.feed_spanned(buf, &mut |token, span| {
+            observe(depth, start, token, span, f)
+        })
+    }
+
+    /// Signals the end of input, passing the span of a final record
+    /// to `f` if the input ended with one.
+    pub fn finish<F>(&mut self, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Span) -> Result<(), Error>,
+    {
+        let RecordIndexer {
+            scanner,
+            depth,
+            start,
+        } = self;
+        scanner.finish_spanned(&mut |token, span| observe(depth, start, token, span, f))
+    }
+}
+
+fn observe<F>(
+    depth: &mut usize,
+    start: &mut u64,
+    token: Token,
+    span: Span,
+    f: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(Span) -> Result<(), Error>,
+{
+    if *depth == 0 && token.is_value_start() {
+        *start = span.start;
+    }
+    match token {
+        Token::BeginObject | Token::BeginArray => *depth += 1,
+        Token::EndObject | Token::EndArray => *depth = depth.saturating_sub(1),
+        _ => {}
+    }
+    if *depth == 0 && !matches!(token, Token::Key(_)) {
+        f(Span {
+            start: *start,
+            end: span.end,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Lists the span of each top-level record in a string of
+/// JSON-encoded data.
+///
+/// # Example:
+///
+/// ```
+/// let spans = jsonxf::index_records("[1, 2]\n{}\n").unwrap();
+/// let offsets: Vec<(u64, u64)> = spans.iter().map(|s| (s.start, s.len())).collect();
+/// assert_eq!(offsets, vec![(0, 6), (7, 2)]);
+/// ```
+pub fn index_records(json_string: &str) -> Result<Vec<Span>, String> {
+    index_records_stream(&mut json_string.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Lists the span of each top-level record in a stream of
+/// JSON-encoded data.
+pub fn index_records_stream(input: &mut dyn Read) -> Result<Vec<Span>, Error> {
+    let mut spans = vec![];
+    let mut indexer = RecordIndexer::new();
+    let mut buf = [0_u8; BUF_SIZE];
+    let mut f = |span| {
+        spans.push(span);
+        Ok(())
+    };
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => indexer.feed(&buf[..n], &mut f)?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    indexer.finish(&mut f)?;
+    Ok(spans)
+}
+
+/// Formats a stream of JSON-encoded data, writing an index of the
+/// records in the input, the output, or both to `index`.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::index::IndexOf;
+///
+/// let mut output: Vec<u8> = vec![];
+/// let mut index: Vec<u8> = vec![];
+/// jsonxf::index::format_indexed(
+///     &mut jsonxf::Formatter::minimizer(),
+///     &mut "{ \"a\": 1 }\n[ 2 ]".as_bytes(),
+///     &mut output,
+///     &mut index,
+///     IndexOf::Both,
+/// )
+/// .unwrap();
+/// assert_eq!(output, b"{\"a\":1}\n[2]");
+/// assert_eq!(index, b"0\t10\t0\t7\n11\t5\t8\t3\n");
+/// ```
+pub fn format_indexed(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    index: &mut dyn Write,
+    of: IndexOf,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut index_writer = BufWriter::new(index);
+    let mut input_indexer = RecordIndexer::new();
+    let mut output_indexer = RecordIndexer::new();
+    let mut inputs: VecDeque<Span> = VecDeque::new();
+    let mut outputs: VecDeque<Span> = VecDeque::new();
+    let mut buf = [0_u8; BUF_SIZE];
+    let mut formatted: Vec<u8> = vec![];
+
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        formatted.clear();
+        xf.format_buf(&buf[..n], &mut formatted)?;
+        writer.write_all(&formatted)?;
+        if of != IndexOf::Output {
+            input_indexer.feed(&buf[..n], &mut |span| {
+                inputs.push_back(span);
+                Ok(())
+            })?;
+        }
+        if of != IndexOf::Input {
+            output_indexer.feed(&formatted, &mut |span| {
+                outputs.push_back(span);
+                Ok(())
+            })?;
+        }
+        write_entries(&mut index_writer, of, &mut inputs, &mut outputs)?;
+    }
+
+    writer.write_all(xf.trailing_output.as_bytes())?;
+    input_indexer.finish(&mut |span| {
+        inputs.push_back(span);
+        Ok(())
+    })?;
+    output_indexer.finish(&mut |span| {
+        outputs.push_back(span);
+        Ok(())
+    })?;
+    write_entries(&mut index_writer, of, &mut inputs, &mut outputs)?;
+    writer.flush()?;
+    index_writer.flush()
+}
+
+// Writes an index line for each record found so far on every indexed side.
+fn write_entries(
+    index: &mut dyn Write,
+    of: IndexOf,
+    inputs: &mut VecDeque<Span>,
+    outputs: &mut VecDeque<Span>,
+) -> Result<(), Error> {
+    loop {
+        match of {
+            IndexOf::Input | IndexOf::Output => {
+                let queue = if of == IndexOf::Input {
+                    &mut *inputs
+                } else {
+                    &mut *outputs
+                };
+                match queue.pop_front() {
+                    Some(span) => writeln!(index, "{}\t{}", span.start, span.len())?,
+                    None => return Ok(()),
+                }
+            }
+            IndexOf::Both => {
+                if inputs.is_empty() || outputs.is_empty() {
+                    return Ok(());
+                }
+                let i = inputs.pop_front().unwrap();
+                let o = outputs.pop_front().unwrap();
+                writeln!(index, "{}\t{}\t{}\t{}", i.start, i.len(), o.start, o.len())?;
+            }
+        }
+    }
+}
+
+/// The location of one value, as listed in a path index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathEntry {
+    /// The number of the top-level record containing the value,
+    /// counting from zero.
+    pub record: u64,
+
+    /// The value's location within the input.
+    pub span: Span,
+
+    /// The value's JSON Pointer within its record.
+    pub pointer: String,
+}
+
+/// Path entries are displayed as index lines, without a trailing
+/// newline.
+impl fmt::Display for PathEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.record,
+            self.span.start,
+            self.span.len(),
+            self.pointer
+        )
+    }
+}
+
+impl FromStr for PathEntry {
+    type Err = String;
+
+    /// Parses an index line, as written by `Display`.
+    fn from_str(s: &str) -> Result<PathEntry, String> {
+        let mut fields = s.splitn(4, '\t');
+        let mut number = || -> Result<u64, String> {
+            fields
+                .next()
+                .and_then(|f| f.parse::<u64>().ok())
+                .ok_or_else(|| format!("invalid path index line: {}", s))
+        };
+        let record = number()?;
+        let start = number()?;
+        let length = number()?;
+        let pointer = match fields.next() {
+            Some(pointer) => String::from(pointer),
+            None => return Err(format!("invalid path index line: {}", s)),
+        };
+        Ok(PathEntry {
+            record,
+            span: Span {
+                start,
+                end: start + length,
+            },
+            pointer,
+        })
+    }
+}
+
+/// Lists the location of every value in a string of JSON-encoded
+/// data, down to `max_depth` levels below each record.
+///
+/// Containers are listed after the values inside them.
+///
+/// # Example:
+///
+/// ```
+/// let index = jsonxf::index_paths("{\"a\": [10, 20]}", 1).unwrap();
+/// let lines: Vec<String> = index.iter().map(|e| e.to_string()).collect();
+/// assert_eq!(lines, vec!["0\t6\t8\t/a", "0\t0\t15\t"]);
+/// ```
+pub fn index_paths(json_string: &str, max_depth: usize) -> Result<Vec<PathEntry>, String> {
+    let mut entries = vec![];
+    index_paths_stream(&mut json_string.as_bytes(), max_depth, &mut |entry| {
+        entries.push(entry);
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(entries)
+}
+
+/// Passes the location of every value in a stream of JSON-encoded
+/// data, down to `max_depth` levels below each record, to `f`.
+///
+/// Memory use depends only on nesting depth.
+pub fn index_paths_stream<F>(input: &mut dyn Read, max_depth: usize, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(PathEntry) -> Result<(), Error>,
+{
+    let mut tracker = PathTracker::new();
+    let mut open: Vec<Option<(String, u64)>> = vec![]; // indexed containers' pointers and starts
+    let mut record = 0;
+    Scanner::new().scan_stream_spanned(&mut BufReader::new(input), &mut |token, span| {
+        tracker.update(&token);
+        match token {
+            Token::Key(_) => return Ok(()),
+            Token::BeginObject | Token::BeginArray => {
+                let path = tracker.path();
+                open.push(if path.len() <= max_depth {
+                    Some((Pointer(path).to_string(), span.start))
+                } else {
+                    None
+                });
+                return Ok(());
+            }
+            Token::EndObject | Token::EndArray => {
+                if let Some(Some((pointer, start))) = open.pop() {
+                    f(PathEntry {
+                        record,
+                        span: Span {
+                            start,
+                            end: span.end,
+                        },
+                        pointer,
+                    })?;
+                }
+            }
+            _ => {
+                let path = tracker.path();
+                if path.len() <= max_depth {
+                    f(PathEntry {
+                        record,
+                        span,
+                        pointer: Pointer(path).to_string(),
+                    })?;
+                }
+            }
+        }
+        if open.is_empty() {
+            record += 1;
+        }
+        Ok(())
+    })
+}
+
+/// Reads the value at `pointer` in the given record of `input`,
+/// using a path index to seek past everything else.
+///
+/// If the value itself is not in the index, the nearest indexed
+/// value containing it is read instead, and the value found within.
+/// Returns `None` if there is no such value.
+///
+/// # Example:
+///
+/// ```
+/// use std::io::{Cursor, Write};
+///
+/// let data = "{\"results\": [{\"id\": 1}, {\"id\": 2, \"tags\": [\"x\"]}]}";
+/// let mut index: Vec<u8> = vec![];
+/// jsonxf::index::index_paths_stream(&mut data.as_bytes(), 2, &mut |entry| {
+///     writeln!(index, "{}", entry)
+/// })
+/// .unwrap();
+///
+/// let value = jsonxf::index::extract_indexed(
+///     &mut Cursor::new(data),
+///     &mut index.as_slice(),
+///     0,
+///     "/results/1/tags/0",
+/// )
+/// .unwrap();
+/// assert_eq!(value.unwrap().to_string(), "\"x\"");
+/// ```
+pub fn extract_indexed<R: Read + Seek>(
+    input: &mut R,
+    index: &mut dyn BufRead,
+    record: u64,
+    pointer: &str,
+) -> Result<Option<Value>, Error> {
+    // Find the deepest indexed value which contains `pointer`.
+    let mut best: Option<PathEntry> = None;
+    for line in index.lines() {
+        let entry = line?
+            .parse::<PathEntry>()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if entry.record != record {
+            continue;
+        }
+        let contains = entry.pointer == pointer
+            || (pointer.starts_with(&entry.pointer)
+                && pointer[entry.pointer.len()..].starts_with('/'));
+        let deeper = match &best {
+            None => true,
+            Some(best) => entry.pointer.len() > best.pointer.len(),
+        };
+        if contains && deeper {
+            best = Some(entry);
+        }
+    }
+    let entry = match best {
+        None => return Ok(None),
+        Some(entry) => entry,
+    };
+
+    input.seek(SeekFrom::Start(entry.span.start))?;
+    let mut raw = String::new();
+    input.take(entry.span.len()).read_to_string(&mut raw)?;
+    let found = value::parse(&raw).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    Ok(found.pointer(&pointer[entry.pointer.len()..]).cloned())
+}
@@ -0,0 +1,122 @@
+//! Canonical JSON, as defined by RFC 8785, the JSON Canonicalization
+//! Scheme (JCS), for signing and hashing documents byte for byte.
+//!
+//! The canonical form is minimized, with object members sorted by the
+//! UTF-16 code units of their keys, strings escaped minimally, and
+//! numbers written as ECMAScript writes them: `1e3` becomes `1000`,
+//! `0.10` becomes `0.1`, and `1E+30` becomes `1e+30`.  Numbers are read
+//! as IEEE 754 doubles, so integers beyond 2^53 may lose precision, and
+//! numbers too large for a double are an error.  So is an object with
+//! two members of the same name, which RFC 8785 doesn't allow.
+//!
+//! `Formatter::canonicalizer()` writes canonical JSON from a stream.
+
+use value::{self, Value};
+
+/// Returns the canonical form of a string containing one JSON value.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::jcs::canonicalize("{\"b\": [1.50, 2e2], \"a\": \"\\u00e9\\u000A\"}").unwrap(),
+///     "{\"a\":\"é\\n\",\"b\":[1.5,200]}"
+/// );
+/// ```
+pub fn canonicalize(json_string: &str) -> Result<String, String> {
+    to_canonical(&value::parse(json_string)?)
+}
+
+/// Returns the canonical form of a parsed JSON value.
+pub fn to_canonical(value: &Value) -> Result<String, String> {
+    let mut value = value.clone();
+    value.sort_keys();
+    canonicalize_members(&mut value)?;
+    Ok(value.to_string())
+}
+
+/// Returns a JSON number as ECMAScript's `Number.prototype.toString`
+/// writes it, as RFC 8785 requires.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::jcs::canonical_number;
+///
+/// assert_eq!(canonical_number("-0.0").unwrap(), "0");
+/// assert_eq!(canonical_number("123e-2").unwrap(), "1.23");
+/// assert_eq!(canonical_number("1e21").unwrap(), "1e+21");
+/// assert_eq!(canonical_number("0.0000001").unwrap(), "1e-7");
+/// assert!(canonical_number("1e400").is_err());
+/// ```
+pub fn canonical_number(text: &str) -> Result<String, String> {
+    let n: f64 = match text.parse() {
+        Ok(n) if value::is_number(text) => n,
+        _ => return Err(format!("invalid number `{}`", text)),
+    };
+    if !n.is_finite() {
+        return Err(format!(
+            "number `{}` is out of range for canonical JSON",
+            text
+        ));
+    }
+    if n == 0.0 {
+        return Ok(String::from("0"));
+    }
+    // Rust writes the shortest digits which read back as `n`, as
+    // ECMAScript does, but lays them out differently
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n_exp = exponent[1..].parse::<i32>().unwrap() + 1; // ECMAScript's n
+    let mut s = String::new();
+    if n < 0.0 {
+        s.push('-');
+    }
+    if k <= n_exp && n_exp <= 21 {
+        s.push_str(&digits);
+        s.push_str(&"0".repeat((n_exp - k) as usize));
+    } else if 0 < n_exp && n_exp <= 21 {
+        s.push_str(&digits[..n_exp as usize]);
+        s.push('.');
+        s.push_str(&digits[n_exp as usize..]);
+    } else if -6 < n_exp && n_exp <= 0 {
+        s.push_str("0.");
+        s.push_str(&"0".repeat(-n_exp as usize));
+        s.push_str(&digits);
+    } else {
+        s.push_str(&digits[..1]);
+        if k > 1 {
+            s.push('.');
+            s.push_str(&digits[1..]);
+        }
+        let e = n_exp - 1;
+        s.push_str(&format!("e{}{}", if e < 0 { '-' } else { '+' }, e.abs()));
+    }
+    Ok(s)
+}
+
+// Canonicalizes the numbers in a value whose keys are sorted, checking
+// that no object has two members of the same name.
+fn canonicalize_members(value: &mut Value) -> Result<(), String> {
+    match value {
+        Value::Number(n) => *n = canonical_number(n)?,
+        Value::Array(items) => {
+            for item in items {
+                canonicalize_members(item)?;
+            }
+        }
+        Value::Object(members) => {
+            // Sorted, so members of the same name are together
+            if let Some(pair) = members.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(format!("duplicate member name {:?}", pair[0].0));
+            }
+            for (_, member) in members {
+                canonicalize_members(member)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
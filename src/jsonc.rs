@@ -0,0 +1,314 @@
+//! Reading JSONC, the JSON with comments used by VS Code settings,
+//! tsconfig.json, and similar configuration files.
+//!
+//! `CommentStripper` drops `//` line comments and `/* */` block
+//! comments outside of strings from a stream fed to it in chunks, for
+//! `Formatter::strip_comments`.  Each comment becomes a space, so that
+//! it still separates whatever was on either side of it.
+//!
+//! `Formatter::keep_comments` instead writes each comment on its own
+//! line, indented as the element after it is.
+//!
+//! `TrailingCommaStripper` drops the commas which hand-edited files,
+//! and JSONC, often leave after the last element of an array or object,
+//! for `Formatter::strip_trailing_commas`.
+
+use {C_BACKSLASH, C_COMMA, C_CR, C_LF, C_QUOTE, C_RIGHT_BRACE, C_RIGHT_BRACKET, C_SPACE, C_TAB};
+
+const C_SLASH: u8 = b'/';
+const C_STAR: u8 = b'*';
+
+/// Returns a string of JSONC with its comments removed.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::jsonc::strip_comments("{\n  // Tabs\n  \"editor.tabSize\": /* not 2 */ 4\n}"),
+///     "{\n  \n  \"editor.tabSize\":   4\n}"
+/// );
+/// ```
+pub fn strip_comments(jsonc_string: &str) -> String {
+    let mut stripped: Vec<u8> = vec![];
+    let mut stripper = CommentStripper::new();
+    stripper.strip(jsonc_string.as_bytes(), &mut stripped);
+    stripper.finish(&mut stripped);
+    // Only whole comments, which end at ASCII bytes, are removed
+    String::from_utf8(stripped).unwrap()
+}
+
+/// Returns a string of JSON with its trailing commas removed.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(
+///     jsonxf::jsonc::strip_trailing_commas("{\"a\": [1, 2,], \"b,\": 3,\n}"),
+///     "{\"a\": [1, 2], \"b,\": 3\n}"
+/// );
+/// ```
+pub fn strip_trailing_commas(json_string: &str) -> String {
+    let mut stripped: Vec<u8> = vec![];
+    let mut stripper = TrailingCommaStripper::new();
+    stripper.strip(json_string.as_bytes(), &mut stripped);
+    stripper.finish(&mut stripped);
+    // Only commas are removed
+    String::from_utf8(stripped).unwrap()
+}
+
+/// `CommentStripper` removes comments from JSONC fed to it in chunks.
+///
+/// A `/` at the end of a chunk is held until the next byte shows
+/// whether it begins a comment.
+#[derive(Debug, Clone, Default)]
+pub struct CommentStripper {
+    state: State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Json,
+    String,
+    Backslash, // in a string, after a backslash
+    Slash,     // after a `/` which may begin a comment
+    Line,      // in a `//` comment
+    Block,     // in a `/*` comment
+    BlockStar, // in a `/*` comment, after a `*`
+}
+
+impl CommentStripper {
+    /// Returns a new `CommentStripper`, ready for the start of input.
+    pub fn new() -> CommentStripper {
+        CommentStripper::default()
+    }
+
+    /// Appends the next chunk of input to `stripped`, without its
+    /// comments.
+    pub fn strip(&mut self, buf: &[u8], stripped: &mut Vec<u8>) {
+        let mut i = 0;
+        while i < buf.len() {
+            let b = buf[i];
+            self.state = match (self.state, b) {
+                (State::Json, C_SLASH) => State::Slash,
+                (State::Json, C_QUOTE) | (State::Backslash, _) => {
+                    stripped.push(b);
+                    State::String
+                }
+                (State::Json, _) => {
+                    stripped.push(b);
+                    State::Json
+                }
+                (State::String, _) => {
+                    stripped.push(b);
+                    match b {
+                        C_BACKSLASH => State::Backslash,
+                        C_QUOTE => State::Json,
+                        _ => State::String,
+                    }
+                }
+                (State::Slash, C_SLASH) => State::Line,
+                (State::Slash, C_STAR) => State::Block,
+                (State::Slash, _) => {
+                    // Not a comment after all; look at `b` again
+                    stripped.push(C_SLASH);
+                    self.state = State::Json;
+                    continue;
+                }
+                (State::Line, C_LF) => {
+                    stripped.push(b);
+                    State::Json
+                }
+                (State::Line, _) => State::Line,
+                (State::Block, C_STAR) | (State::BlockStar, C_STAR) => State::BlockStar,
+                (State::BlockStar, C_SLASH) => {
+                    stripped.push(b' ');
+                    State::Json
+                }
+                (State::Block, _) | (State::BlockStar, _) => State::Block,
+            };
+            i += 1;
+        }
+    }
+
+    /// Signals the end of input, appending a held `/` to `stripped`,
+    /// and readies this stripper for new input.
+    pub fn finish(&mut self, stripped: &mut Vec<u8>) {
+        if self.state == State::Slash {
+            stripped.push(C_SLASH);
+        }
+        self.state = State::Json;
+    }
+
+    /// Returns whether the input so far ends inside a block comment.
+    pub fn in_block_comment(&self) -> bool {
+        matches!(self.state, State::Block | State::BlockStar)
+    }
+
+    // Is this stripper outside of any string or comment, so that a new
+    // one would carry on the same?
+    pub(crate) fn at_rest(&self) -> bool {
+        self.state == State::Json
+    }
+}
+
+// Reads the comments in JSONC a byte at a time, outside of strings,
+// holding each whole comment for `Formatter::keep_comments`.
+#[derive(Debug, Default)]
+pub(crate) struct CommentKeeper {
+    state: State,
+    comment: Vec<u8>,              // the comment being read
+    pub(crate) held: Vec<Vec<u8>>, // whole comments, not yet written
+}
+
+// What a byte read by `CommentKeeper` turned out to be.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Kept {
+    Json,    // not part of a comment
+    Comment, // part of a comment
+    Slash,   // not part of a comment, after a `/` which wasn't either
+}
+
+impl CommentKeeper {
+    // Reads a byte from outside of any string.
+    pub(crate) fn read(&mut self, b: u8) -> Kept {
+        self.state = match (self.state, b) {
+            (State::Json, C_SLASH) => State::Slash,
+            (State::Slash, C_SLASH) => {
+                self.comment.extend_from_slice(b"//");
+                State::Line
+            }
+            (State::Slash, C_STAR) => {
+                self.comment.extend_from_slice(b"/*");
+                State::Block
+            }
+            (State::Slash, _) => {
+                self.state = State::Json;
+                return Kept::Slash;
+            }
+            (State::Line, C_LF) => {
+                self.end();
+                State::Json
+            }
+            (State::Line, _) => {
+                self.comment.push(b);
+                State::Line
+            }
+            (State::Block, C_STAR) | (State::BlockStar, C_STAR) => {
+                self.comment.push(b);
+                State::BlockStar
+            }
+            (State::BlockStar, C_SLASH) => {
+                self.comment.push(b);
+                self.end();
+                State::Json
+            }
+            (State::Block, _) | (State::BlockStar, _) => {
+                self.comment.push(b);
+                State::Block
+            }
+            (State::Json, _) | (State::String, _) | (State::Backslash, _) => return Kept::Json,
+        };
+        Kept::Comment
+    }
+
+    // Holds a comment cut off by the end of input as it is, and returns
+    // whether a `/` was left over.
+    pub(crate) fn finish(&mut self) -> bool {
+        let slash = self.state == State::Slash;
+        if !self.comment.is_empty() {
+            self.end();
+        }
+        self.state = State::Json;
+        slash
+    }
+
+    pub(crate) fn in_block_comment(&self) -> bool {
+        matches!(self.state, State::Block | State::BlockStar)
+    }
+
+    // Is no comment being read or held?
+    pub(crate) fn at_rest(&self) -> bool {
+        self.state == State::Json && self.held.is_empty()
+    }
+
+    // Holds the comment just read, without any whitespace at its end.
+    fn end(&mut self) {
+        let len = self.comment.len()
+            - self
+                .comment
+                .iter()
+                .rev()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+        self.comment.truncate(len);
+        self.held.push(std::mem::take(&mut self.comment));
+    }
+}
+
+/// `TrailingCommaStripper` removes commas just before a `]` or `}` from
+/// JSON fed to it in chunks.
+///
+/// A comma outside of a string is held, with any whitespace after it,
+/// until the next byte shows whether it is a trailing comma.
+#[derive(Debug, Clone, Default)]
+pub struct TrailingCommaStripper {
+    in_string: bool,    // is the next byte part of a string?
+    in_backslash: bool, // does the next byte follow a backslash in a string?
+    held: Vec<u8>,      // a comma, and the whitespace after it
+}
+
+impl TrailingCommaStripper {
+    /// Returns a new `TrailingCommaStripper`, ready for the start of
+    /// input.
+    pub fn new() -> TrailingCommaStripper {
+        TrailingCommaStripper::default()
+    }
+
+    /// Appends the next chunk of input to `stripped`, without its
+    /// trailing commas.
+    pub fn strip(&mut self, buf: &[u8], stripped: &mut Vec<u8>) {
+        for &b in buf {
+            if !self.held.is_empty() {
+                match b {
+                    C_SPACE | C_LF | C_CR | C_TAB => {
+                        self.held.push(b);
+                        continue;
+                    }
+                    C_RIGHT_BRACKET | C_RIGHT_BRACE => stripped.extend_from_slice(&self.held[1..]),
+                    _ => stripped.extend_from_slice(&self.held),
+                }
+                self.held.clear();
+            }
+            if self.in_string {
+                if self.in_backslash {
+                    self.in_backslash = false;
+                } else if b == C_BACKSLASH {
+                    self.in_backslash = true;
+                } else if b == C_QUOTE {
+                    self.in_string = false;
+                }
+            } else if b == C_QUOTE {
+                self.in_string = true;
+            } else if b == C_COMMA {
+                self.held.push(b);
+                continue;
+            }
+            stripped.push(b);
+        }
+    }
+
+    // Is no comma held, outside of any string, so that a new stripper
+    // would carry on the same?
+    pub(crate) fn at_rest(&self) -> bool {
+        self.held.is_empty() && !self.in_string
+    }
+
+    /// Signals the end of input, appending a held comma to `stripped`,
+    /// and readies this stripper for new input.
+    pub fn finish(&mut self, stripped: &mut Vec<u8>) {
+        stripped.append(&mut self.held);
+        self.in_string = false;
+        self.in_backslash = false;
+    }
+}
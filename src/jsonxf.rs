@@ -6,24 +6,166 @@
 //! It provides default pretty-printers (`pretty_print()`,
 //! `pretty_print_stream()`) and minimizers (`minimize()`,
 //! `minimize_stream()`), `Formatter` for customizable JSON
-//! formatting, and the `jsonxf` command-line tool (see
-//! `jsonxf -h` for options).
+//! formatting, `fixed::FixedFormatter` for formatting between
+//! caller-provided buffers without allocating, and the `jsonxf`
+//! command-line tool (see `jsonxf -h` for options).
 //!
-//! Jsonxf is built for speed, and does not attempt to perform any
-//! input validation whatsoever.  Valid input produces valid output,
+//! It also converts JSON to and from greppable assignment statements
+//! (`gron()`, `ungron()`) and CBOR (`to_cbor()`, `from_cbor()`), to TOML
+//! (`to_toml()`), Rust source (`to_json_macro()`, `to_rust_string()`),
+//! syntax-highlighted HTML (`to_html()`) or ANSI-colored text
+//! (`color::colorize()`), and Markdown (`to_markdown()`), and from CSV
+//! (`csv_to_json()`).  With the `yaml` feature it reads YAML
+//! (`yaml::yaml_to_json()`), and with the `msgpack` feature it converts
+//! to and from MessagePack (`msgpack::to_msgpack()`,
+//! `msgpack::from_msgpack()`).  With the `compression` feature it reads
+//! and writes gzip, zstd, and bzip2-compressed data (`compress::decoder()`,
+//! `compress::Encoder`).  JSONC files, such as VS Code settings, can be
+//! formatted with their comments and trailing commas dropped
+//! (`Formatter::strip_comments`, `Formatter::strip_trailing_commas`), or
+//! pretty-printed with their comments kept (`Formatter::keep_comments`).
+//!
+//! For exploring data, it lists the distinct paths in a stream
+//! (`list_paths()`), infers a schema for them (`infer_schema()`), and
+//! profiles the values found there (`key_stats()`).  Summary
+//! statistics (`stream_stats()`) can be gathered alongside any of these,
+//! and the locations of records and values indexed for later seeking
+//! (`index_records()`, `index_paths()`).  Individual records can be
+//! picked out of long streams without formatting the rest
+//! (`split::select_records()`), and streams or huge arrays divided into
+//! size-limited files (`split::split_records()`, `split::rechunk_array()`).
+//! Long-running formatting can report its progress, for metrics
+//! (`metrics::format_stream_with_metrics()`), and live streams can be
+//! written as NDJSON, flushed a record at a time (`Formatter::ndjson()`).
+//!
+//! Whole documents can be compared structurally (`diff()`),
+//! deep-merged (`merge()`), patched (`apply_patch()`,
+//! `apply_merge_patch()`, `generate_patch()`), and hashed independently
+//! of their formatting (`canonical_hash()`).  For signing, documents
+//! can be written in the canonical form of RFC 8785
+//! (`Formatter::canonicalizer()`, `jcs::canonicalize()`).
+//!
+//! Jsonxf is built for speed, and by default does not attempt to
+//! perform any input validation whatsoever.  Valid input produces valid output,
 //! but no guarantees are offered around the detection and rejection
-//! of invalid input.
+//! of invalid input.  Where that matters, `verify::format_verified()`
+//! checks that formatting changed nothing but whitespace
+//! (`verify::format_audited()` reports where in the input it didn't),
+//! and with the
+//! `verify` feature, `verify::format_cross_checked()` checks that
+//! serde_json parses the output to the same values as the input.
+//! Strict checking of the input's syntax, with the line and column of
+//! any mistake, is opt-in (`Formatter::validate`,
+//! `validate::Validator`).
+//!
+//! With the `log` feature, non-fatal findings are reported through the
+//! `log` crate, with structured fields like `offset`, so that embedders
+//! see them in their existing logging: duplicate object keys found while
+//! parsing (`value::read_records()`) as warnings, and text in mixed
+//! streams (`mixed::format_mixed()`) which looked like JSON but was
+//! copied through as text.
+//!
+//! Code which formats JSON with Jsonxf can test that its formatting is
+//! stable and changes only whitespace with the helpers in `testkit`,
+//! which also generate random documents for property testing
+//! (`testkit::Generator`, `testkit::check_generated()`).
 //!
 //! GitHub:
 //! <a href="https://github.com/gamache/jsonxf" target="_blank">gamache/jsonxf</a>
 //!
 
+#[cfg(feature = "compression")]
+extern crate bzip2;
+#[cfg(feature = "compression")]
+extern crate flate2;
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(feature = "log")]
+extern crate log;
+extern crate regex;
+#[cfg(feature = "msgpack")]
+extern crate rmp;
+#[cfg(feature = "verify")]
+extern crate serde_json;
+extern crate sha2;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen_futures;
+#[cfg(feature = "wasm")]
+extern crate web_sys;
+extern crate xxhash_rust;
+#[cfg(feature = "yaml")]
+extern crate yaml_rust;
+#[cfg(feature = "compression")]
+extern crate zstd;
+
+use std::convert::{TryFrom, TryInto};
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::Error;
 use std::io::ErrorKind;
 
+use jsonc::{CommentKeeper, CommentStripper, Kept, TrailingCommaStripper};
+use validate::Validator;
+
+pub mod cbor;
+pub mod color;
+pub mod compress;
+pub mod csv;
+pub mod diff;
+pub mod expr;
+pub mod fixed;
+pub mod gron;
+pub mod hash;
+pub mod highlight;
+pub mod html;
+pub mod index;
+pub mod jcs;
+pub mod jsonc;
+pub mod logs;
+pub mod markdown;
+pub mod merge;
+pub mod metrics;
+pub mod mixed;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+pub mod patch;
+pub mod path;
+pub mod paths;
+pub mod redact;
+pub mod rust;
+pub mod scanner;
+pub mod schema;
+pub mod split;
+pub mod stats;
+pub mod testkit;
+pub mod toml;
+pub mod validate;
+pub mod value;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+pub use cbor::{from_cbor, from_cbor_stream, to_cbor, to_cbor_stream};
+pub use csv::{csv_to_json, csv_to_json_stream};
+pub use diff::diff;
+pub use gron::{gron, gron_stream, ungron, ungron_stream};
+pub use hash::{canonical_hash, canonical_hash_stream, hash_records, hash_records_stream};
+pub use html::to_html;
+pub use index::{index_paths, index_records, index_records_stream};
+pub use markdown::to_markdown;
+pub use merge::merge;
+pub use patch::{apply_merge_patch, apply_patch, generate_patch};
+pub use paths::{list_paths, list_paths_stream};
+pub use rust::{to_json_macro, to_rust_string};
+pub use schema::{infer_schema, infer_schema_stream};
+pub use stats::{key_stats, key_stats_stream, stream_stats, stream_stats_stream};
+pub use toml::to_toml;
+
 const BUF_SIZE: usize = 1024 * 16;
 
 const C_CR: u8 = b'\r';
@@ -62,7 +204,7 @@ pub struct Formatter {
     /// Used inside arrays and objects.
     pub line_separator: String,
 
-    /// Used between root-level arrays and objects.
+    /// Used between root-level values.
     pub record_separator: String,
 
     /// Used after a colon inside objects.
@@ -75,12 +217,193 @@ pub struct Formatter {
     /// subsequent record. Useful when there's a long time between records.
     pub eager_record_separators: bool,
 
+    /// Flush the output as soon as each record ends, so that it reaches
+    /// a reader right away, even through a buffered writer.  Useful for
+    /// live streams such as logs.
+    pub flush_records: bool,
+
+    /// Escape non-ASCII characters in strings as `\uXXXX`, so that the
+    /// output is pure ASCII.
+    pub escape_non_ascii: bool,
+
+    /// Arrays and objects nested deeper than this are folded according
+    /// to `fold_style`.  A depth of 0 folds each record.  Defaults to
+    /// `None`, folding nothing.
+    pub max_depth: Option<usize>,
+
+    /// How arrays and objects deeper than `max_depth` are folded.
+    pub fold_style: FoldStyle,
+
+    /// Arrays of numbers, strings, and literals are written on one line,
+    /// like `[1, 2, 3]`, when that line is no more than this many
+    /// characters wide, from `[` to `]`.  Defaults to `None`, writing
+    /// each element on its own line.
+    ///
+    /// An array is held in memory until it ends or grows too wide.
+    /// `state()` is `None` while an array is held.
+    pub max_inline_array_width: Option<usize>,
+
+    /// Objects whose values are numbers, strings, and literals are
+    /// written on one line, like `{"x": 1, "y": 2}`, when that line is no
+    /// more than this many characters wide, from `{` to `}`.  Defaults to
+    /// `None`, writing each member on its own line.
+    ///
+    /// Objects are held in memory as arrays are for
+    /// `max_inline_array_width`.
+    pub max_inline_object_width: Option<usize>,
+
+    /// Write the members of each object in order of their keys, as
+    /// `value::Value::sort_keys` does, for deterministic output.
+    ///
+    /// Each array or object record is held in memory until it ends,
+    /// then parsed, sorted, and formatted, so its strings come out
+    /// escaped minimally.  A record which doesn't parse is formatted
+    /// as it was.  `state()` is `None` while a record is held.
+    pub sort_keys: bool,
+
+    /// Write each record in the canonical form of RFC 8785, as
+    /// `jcs::to_canonical` does, holding every record in memory until it
+    /// ends, as `sort_keys` does.  Records which are not valid JSON, or
+    /// whose numbers are out of range, are an error.  Set up by
+    /// `Formatter::canonicalizer`.
+    pub canonical: bool,
+
+    /// Check that the input is well-formed JSON as it is formatted, as
+    /// `validate::Validator` does.  At the first mistake, formatting
+    /// returns an error holding a `scanner::SyntaxError` with its byte
+    /// offset, line, and column, and nothing more from that chunk of
+    /// input is written.  Defaults to `false`, formatting any input.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut xf = jsonxf::Formatter::pretty_printer();
+    /// xf.validate = true;
+    /// assert_eq!(
+    ///     xf.format("{\"a\": 1,\n \"b\": nul}").unwrap_err(),
+    ///     "invalid number or literal `nul` at line 2, column 7"
+    /// );
+    /// ```
+    pub validate: bool,
+
+    /// Return an error of kind `UnexpectedEof` from `finish_buf` when the
+    /// input ends inside a string, array, or object, as a truncated file
+    /// does, after writing what was formatted.  Defaults to `false`.
+    /// This is much cheaper than `validate`, which also reports it.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut xf = jsonxf::Formatter::minimizer();
+    /// xf.require_complete = true;
+    /// assert_eq!(
+    ///     xf.format("{\"a\": [1, 2").unwrap_err(),
+    ///     "input ended inside an array or object"
+    /// );
+    /// ```
+    pub require_complete: bool,
+
+    /// Drop `//` and `/* */` comments outside of strings from the input,
+    /// as `jsonc::CommentStripper` does, to format JSONC files like VS
+    /// Code settings.  Defaults to `false`, treating comments as any
+    /// other text.  Comments are dropped before `validate` sees the
+    /// input, and `require_complete` fails on an unterminated block
+    /// comment.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut xf = jsonxf::Formatter::minimizer();
+    /// xf.strip_comments = true;
+    /// assert_eq!(
+    ///     xf.format("{\n  // the default\n  \"url\": \"http://localhost\" /* for now */\n}")
+    ///         .unwrap(),
+    ///     "{\"url\":\"http://localhost\"}"
+    /// );
+    /// ```
+    pub strip_comments: bool,
+
+    /// Drop commas just before a `]` or `}` from the input, as
+    /// `jsonc::TrailingCommaStripper` does, so that `[1, 2,]` is written
+    /// as `[1, 2]`.  Defaults to `false`.  Commas are dropped after
+    /// comments, and before `validate` sees the input.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut xf = jsonxf::Formatter::minimizer();
+    /// xf.strip_trailing_commas = true;
+    /// assert_eq!(
+    ///     xf.format("{\"a\": [1, 2,],\n \"b\": \"c,\",\n}").unwrap(),
+    ///     "{\"a\":[1,2],\"b\":\"c,\"}"
+    /// );
+    /// ```
+    pub strip_trailing_commas: bool,
+
+    /// Keep `//` and `/* */` comments from the input in pretty-printed
+    /// output, each on its own line, indented as the element after it
+    /// is.  A comment between a key and its value stays on the key's
+    /// line.  Defaults to `false`, treating comments as any other text.
+    ///
+    /// Comments are dropped when minimizing, and inside folded arrays
+    /// and objects.  They are not kept with `strip_comments`, nor in
+    /// records reordered by `sort_keys`, and `validate` rejects them.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut xf = jsonxf::Formatter::pretty_printer();
+    /// xf.keep_comments = true;
+    /// assert_eq!(
+    ///     xf.format("{\"tabSize\": 4, // spaces\n\"rulers\": [/* none */]}").unwrap(),
+    ///     "{\n  \"tabSize\": 4,\n  // spaces\n  \"rulers\": [\n    /* none */\n  ]\n}"
+    /// );
+    /// ```
+    pub keep_comments: bool,
+
     // private mutable state
-    depth: usize,       // current nesting depth
-    in_string: bool,    // is the next byte part of a string?
-    in_backslash: bool, // does the next byte follow a backslash in a string?
-    empty: bool,        // is the next byte in an empty object or array?
-    first: bool,        // is this the first byte of input?
+    depth: usize,                  // current nesting depth
+    in_string: bool,               // is the next byte part of a string?
+    in_backslash: bool,            // does the next byte follow a backslash in a string?
+    empty: bool,                   // is the next byte in an empty object or array?
+    first: bool,                   // is this the first byte of input?
+    in_bare: bool,                 // is the next byte part of a root-level number or literal?
+    utf8: [u8; 4],                 // a character being escaped, which may span chunks
+    utf8_len: usize,               // how many bytes of `utf8` are filled
+    records: u64,                  // how many records have begun
+    sorting: Sorting,              // a record held for sorting its keys
+    inline: Option<Inline>,        // an array or object held to see whether it fits on a line
+    validator: Validator,          // checks the input, if `validate` is set
+    comments: CommentStripper,     // drops comments, if `strip_comments` is set
+    commas: TrailingCommaStripper, // drops trailing commas, if `strip_trailing_commas` is set
+    uncommented: Vec<u8>,          // the latest chunk, with comments dropped
+    cleaned: Vec<u8>,              // and with trailing commas dropped, as asked
+    kept: CommentKeeper,           // holds comments, if `keep_comments` is set
+    value_next: bool,              // is the next token an object member's value?
+}
+
+// An array or object being held, from just after its `[` or `{`, for
+// `Formatter::max_inline_array_width` or `max_inline_object_width`.
+struct Inline {
+    close: u8,         // the `]` or `}` which ends it
+    max_width: usize,  // how wide it may be on one line
+    held: Vec<u8>,     // the input
+    rendered: Vec<u8>, // its contents, as written on one line
+    width: usize,      // of it on one line so far, in characters
+    in_string: bool,
+    in_backslash: bool,
+}
+
+// Finds the array and object records in the input, and holds each
+// until it ends, for `Formatter::sort_keys`.
+#[derive(Default)]
+struct Sorting {
+    holding: bool,   // has a record begun?
+    record: Vec<u8>, // the record so far
+    depth: usize,
+    in_string: bool,
+    in_backslash: bool,
+    in_bare: bool, // in a number or literal record?
 }
 
 impl Formatter {
@@ -92,11 +415,37 @@ impl Formatter {
             after_colon: String::from(" "),
             trailing_output: String::from(""),
             eager_record_separators: false,
+            flush_records: false,
+            escape_non_ascii: false,
+            max_depth: None,
+            fold_style: FoldStyle::Minify,
+            max_inline_array_width: None,
+            max_inline_object_width: None,
+            sort_keys: false,
+            canonical: false,
+            validate: false,
+            require_complete: false,
+            strip_comments: false,
+            strip_trailing_commas: false,
+            keep_comments: false,
             depth: 0,
             in_string: false,
             in_backslash: false,
             empty: false,
             first: true,
+            in_bare: false,
+            utf8: [0; 4],
+            utf8_len: 0,
+            records: 0,
+            sorting: Sorting::default(),
+            inline: None,
+            validator: Validator::new(),
+            comments: CommentStripper::new(),
+            commas: TrailingCommaStripper::new(),
+            uncommented: vec![],
+            cleaned: vec![],
+            kept: CommentKeeper::default(),
+            value_next: false,
         }
     }
 
@@ -137,6 +486,167 @@ impl Formatter {
         xf
     }
 
+    /// Returns a Formatter set up for NDJSON, also known as JSON Lines:
+    /// minimized, with each record on a line of its own, ended as soon
+    /// as the record is, and flushed right away.
+    ///
+    /// Strings holding raw line breaks aren't valid JSON, and are passed
+    /// along as they are; set `validate` to reject them instead.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// assert_eq!(
+    ///     jsonxf::Formatter::ndjson().format("{\n  \"a\": 1\n} [\n  2\n]").unwrap(),
+    ///     "{\"a\":1}\n[2]\n"
+    /// );
+    /// ```
+    pub fn ndjson() -> Formatter {
+        let mut xf = Formatter::minimizer();
+        xf.eager_record_separators = true;
+        xf.flush_records = true;
+        xf
+    }
+
+    /// Ends lines and records with `eol`, such as `"\r\n"`.  Lines
+    /// inside arrays and objects are left unbroken if they already were,
+    /// as when minimizing.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut fmt = jsonxf::Formatter::pretty_printer();
+    /// fmt.set_line_ending("\r\n");
+    /// assert_eq!(fmt.format("[1] [2]").unwrap(), "[\r\n  1\r\n]\r\n[\r\n  2\r\n]");
+    /// ```
+    pub fn set_line_ending(&mut self, eol: &str) {
+        if !self.line_separator.is_empty() {
+            self.line_separator = String::from(eol);
+        }
+        self.record_separator = String::from(eol);
+    }
+
+    /// Returns a Formatter which writes canonical JSON, as RFC 8785
+    /// (JCS) defines it, for signing and hashing: minimized, with keys
+    /// sorted, numbers formatted canonically, and strings escaped
+    /// minimally.  Each record is on its own line.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut fmt = jsonxf::Formatter::canonicalizer();
+    /// assert_eq!(
+    ///     fmt.format("{\"b\": 1.0E2, \"a\": \"\\u00e9\"}\n-0.0").unwrap(),
+    ///     "{\"a\":\"é\",\"b\":100}\n0"
+    /// );
+    /// ```
+    pub fn canonicalizer() -> Formatter {
+        let mut xf = Formatter::minimizer();
+        xf.canonical = true;
+        xf
+    }
+
+    /// Returns to the start-of-input state, keeping the settings, so
+    /// that the next input is formatted as if it were the first.
+    pub fn reset(&mut self) {
+        self.depth = 0;
+        self.in_string = false;
+        self.in_backslash = false;
+        self.empty = false;
+        self.first = true;
+        self.in_bare = false;
+        self.utf8_len = 0;
+        self.records = 0;
+        self.sorting = Sorting::default();
+        self.inline = None;
+        self.validator = Validator::new();
+        self.comments = CommentStripper::new();
+        self.commas = TrailingCommaStripper::new();
+        self.kept = CommentKeeper::default();
+        self.value_next = false;
+    }
+
+    /// Returns the number of records begun in the input so far.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut fmt = jsonxf::Formatter::minimizer();
+    /// fmt.format("{\"a\": [1, 2]} \"b\" 3 [").unwrap();
+    /// assert_eq!(fmt.records(), 4);
+    /// ```
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Returns where the formatter is in its input, so that formatting
+    /// can be suspended and picked up later, even by another process,
+    /// with `restore`.  Settings are not included.
+    ///
+    /// Returns `None` while the formatter holds input it hasn't written
+    /// yet, or is partway through something only a setting looks for:
+    /// an array or object which may be written inline, a record being
+    /// sorted, a record being validated, or a comment or comma being
+    /// stripped or kept.  Try again after more input; between records,
+    /// nothing is held.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::{Formatter, FormatterState};
+    ///
+    /// let mut output: Vec<u8> = vec![];
+    /// let mut xf = Formatter::pretty_printer();
+    /// xf.format_buf(b"{\"a\": [\"one, ", &mut output).unwrap();
+    /// let saved = xf.state().unwrap().to_bytes();
+    ///
+    /// let mut xf = Formatter::pretty_printer();
+    /// xf.restore(&FormatterState::from_bytes(&saved).unwrap());
+    /// xf.format_buf(b"two\"]}", &mut output).unwrap();
+    /// assert_eq!(output, b"{\n  \"a\": [\n    \"one, two\"\n  ]\n}");
+    /// ```
+    pub fn state(&self) -> Option<FormatterState> {
+        if self.holding() {
+            return None;
+        }
+        Some(FormatterState {
+            depth: self.depth,
+            in_string: self.in_string,
+            in_backslash: self.in_backslash,
+            empty: self.empty,
+            first: self.first,
+            in_bare: self.in_bare,
+            utf8: self.utf8,
+            utf8_len: self.utf8_len,
+        })
+    }
+
+    // Is anything held, or partway through, which `state` can't carry?
+    fn holding(&self) -> bool {
+        let sorting = &self.sorting;
+        self.inline.is_some()
+            || sorting.holding
+            || sorting.in_string
+            || sorting.in_bare
+            || !self.validator.at_rest()
+            || !self.comments.at_rest()
+            || !self.commas.at_rest()
+            || !self.kept.at_rest()
+            || self.value_next
+    }
+
+    /// Returns to a state saved with `state`, keeping the settings.
+    pub fn restore(&mut self, state: &FormatterState) {
+        self.depth = state.depth;
+        self.in_string = state.in_string;
+        self.in_backslash = state.in_backslash;
+        self.empty = state.empty;
+        self.first = state.first;
+        self.in_bare = state.in_bare;
+        self.utf8 = state.utf8;
+        self.utf8_len = state.utf8_len;
+    }
+
     /// Formats a string of JSON-encoded data.
     ///
     /// Input must be valid JSON data in UTF-8 encoding.
@@ -232,15 +742,18 @@ impl Formatter {
                 }
             }
         }
-        output.write_all(self.trailing_output.as_bytes())?;
-        Ok(())
+        self.finish_buf(output)
     }
 
     /// Format directly from a buffer into a writer.
     ///
     /// This may be called on chunks of a JSON document to format it bit by bit.
     ///
-    /// As such, it does not add the `trailing_output` at the end.
+    /// As such, it does not add the `trailing_output` at the end; call
+    /// `finish_buf` for that after the last chunk.
+    ///
+    /// Returns an error of kind `InvalidData` at a `]` or `}` with no
+    /// array or object open, having written the input before it.
     ///
     /// # Example:
     ///
@@ -251,10 +764,181 @@ impl Formatter {
     /// fmt.format_buf(text.as_bytes(), &mut stdout).unwrap();
     /// ```
     pub fn format_buf(&mut self, buf: &[u8], writer: &mut impl Write) -> Result<(), Error> {
+        if !self.strip_comments && !self.strip_trailing_commas {
+            return self.format_cleaned(buf, writer);
+        }
+        let mut cleaned = std::mem::take(&mut self.cleaned);
+        cleaned.clear();
+        self.clean(buf, false, &mut cleaned);
+        let result = self.format_cleaned(&cleaned, writer);
+        self.cleaned = cleaned;
+        result
+    }
+
+    // Drops comments and trailing commas from a chunk of input, as
+    // asked, and at the end of input, anything held to decide.
+    fn clean(&mut self, buf: &[u8], end: bool, cleaned: &mut Vec<u8>) {
+        let mut uncommented = std::mem::take(&mut self.uncommented);
+        uncommented.clear();
+        let buf = if self.strip_comments {
+            self.comments.strip(buf, &mut uncommented);
+            if end {
+                self.comments.finish(&mut uncommented);
+            }
+            &uncommented[..]
+        } else {
+            buf
+        };
+        if self.strip_trailing_commas {
+            self.commas.strip(buf, cleaned);
+            if end {
+                self.commas.finish(cleaned);
+            }
+        } else {
+            cleaned.extend_from_slice(buf);
+        }
+        self.uncommented = uncommented;
+    }
+
+    // Formats a chunk with any comments and trailing commas dropped.
+    fn format_cleaned(&mut self, buf: &[u8], writer: &mut impl Write) -> Result<(), Error> {
+        if self.validate {
+            self.validator.feed(buf)?;
+        }
+        if self.sort_keys || self.canonical {
+            self.format_sorted(buf, writer)
+        } else {
+            self.format_bytes(buf, writer)
+        }
+    }
+
+    // Formats a chunk, holding each array or object record until it
+    // ends, then writing it with its keys sorted.  When canonicalizing,
+    // scalar records are held too.
+    fn format_sorted(&mut self, buf: &[u8], writer: &mut impl Write) -> Result<(), Error> {
+        let mut start = 0; // of the bytes neither formatted nor held
+        let mut i = 0;
+        while i < buf.len() {
+            let b = buf[i];
+            let sorting = &mut self.sorting;
+            if sorting.in_string {
+                if sorting.in_backslash {
+                    sorting.in_backslash = false;
+                } else if b == C_BACKSLASH {
+                    sorting.in_backslash = true;
+                } else if b == C_QUOTE {
+                    sorting.in_string = false;
+                    if sorting.holding && sorting.depth == 0 {
+                        // A string record ends
+                        self.hold(&buf[start..=i]);
+                        start = i + 1;
+                        self.write_sorted(writer)?;
+                    }
+                }
+                i += 1;
+                continue;
+            }
+            if sorting.in_bare {
+                if is_bare_byte(b) {
+                    i += 1;
+                    continue;
+                }
+                // A number or literal record ends
+                sorting.in_bare = false;
+                self.hold(&buf[start..i]);
+                start = i;
+                self.write_sorted(writer)?;
+            }
+            let sorting = &mut self.sorting;
+            let record_begins = sorting.depth == 0
+                && match b {
+                    C_LEFT_BRACE | C_LEFT_BRACKET => true,
+                    C_SPACE | C_LF | C_CR | C_TAB => false,
+                    C_RIGHT_BRACE | C_RIGHT_BRACKET | C_COMMA | C_COLON => false,
+                    _ => self.canonical,
+                };
+            if record_begins {
+                self.format_bytes(&buf[start..i], writer)?;
+                start = i;
+                self.sorting.holding = true;
+            }
+            let sorting = &mut self.sorting;
+            match b {
+                C_QUOTE => sorting.in_string = true,
+                C_LEFT_BRACE | C_LEFT_BRACKET => sorting.depth += 1,
+                C_RIGHT_BRACE | C_RIGHT_BRACKET if sorting.depth > 0 => {
+                    sorting.depth -= 1;
+                    if sorting.depth == 0 {
+                        self.hold(&buf[start..=i]);
+                        start = i + 1;
+                        self.write_sorted(writer)?;
+                    }
+                }
+                C_SPACE | C_LF | C_CR | C_TAB => {}
+                _ if record_begins => sorting.in_bare = true,
+                _ => {}
+            }
+            i += 1;
+        }
+        if self.sorting.holding {
+            self.hold(&buf[start..]);
+            Ok(())
+        } else {
+            self.format_bytes(&buf[start..], writer)
+        }
+    }
+
+    fn hold(&mut self, bytes: &[u8]) {
+        self.sorting.record.extend_from_slice(bytes);
+    }
+
+    // Formats the held record with its keys sorted, or as it was if it
+    // isn't strictly valid JSON, which parsing might not keep whole.
+    // When canonicalizing, such a record is an error instead.
+    fn write_sorted(&mut self, writer: &mut impl Write) -> Result<(), Error> {
+        let record = std::mem::take(&mut self.sorting.record);
+        self.sorting.holding = false;
+        let parsed = std::str::from_utf8(&record)
+            .ok()
+            .and_then(|json| value::parse(json).ok());
+        match parsed {
+            Some(value) if self.canonical => {
+                let canonical =
+                    jcs::to_canonical(&value).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                self.format_bytes(canonical.as_bytes(), writer)
+            }
+            Some(mut value) => {
+                value.sort_keys();
+                self.format_bytes(value.to_string().as_bytes(), writer)
+            }
+            None if self.canonical => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "cannot canonicalize record {}: not valid JSON",
+                    self.records + 1
+                ),
+            )),
+            None => self.format_bytes(&record, writer),
+        }
+    }
+
+    // Formats a chunk as it comes.
+    fn format_bytes(&mut self, buf: &[u8], writer: &mut impl Write) -> Result<(), Error> {
         let mut n = 0;
         while n < buf.len() {
+            if self.inline.is_some() {
+                n += self.hold_inline(&buf[n..], writer)?;
+                continue;
+            }
+
             let b = buf[n];
 
+            if self.fold_style == FoldStyle::Ellipsis && self.hides(b) {
+                self.skip_folded(b, writer)?;
+                n += 1;
+                continue;
+            }
+
             if self.in_string {
                 if self.in_backslash {
                     writer.write_all(&buf[n..n + 1])?;
@@ -263,15 +947,18 @@ impl Formatter {
                     match memchr::memchr2(C_QUOTE, C_BACKSLASH, &buf[n..]) {
                         None => {
                             // The whole rest of buf is part of the string
-                            writer.write_all(&buf[n..])?;
+                            self.write_string(&buf[n..], writer)?;
                             break;
                         }
                         Some(index) => {
                             let length = index + 1;
-                            writer.write_all(&buf[n..n + length])?;
+                            self.write_string(&buf[n..n + length], writer)?;
                             if buf[n + index] == C_QUOTE {
                                 // End of string
                                 self.in_string = false;
+                                if self.depth == 0 {
+                                    self.end_record(writer)?;
+                                }
                             } else {
                                 // Backslash
                                 self.in_backslash = true;
@@ -282,19 +969,53 @@ impl Formatter {
                     }
                 }
             } else {
+                if self.keep_comments {
+                    match self.kept.read(b) {
+                        Kept::Json => {}
+                        Kept::Comment => {
+                            if self.folded() || self.line_separator.is_empty() {
+                                self.kept.held.clear();
+                            }
+                            n += 1;
+                            continue;
+                        }
+                        Kept::Slash => {
+                            // Not a comment; pass it along, then look at `b` again
+                            writer.write_all(b"/")?;
+                            continue;
+                        }
+                    }
+                    if !self.kept.held.is_empty()
+                        && !matches!(b, C_SPACE | C_LF | C_CR | C_TAB | C_COMMA | C_COLON)
+                    {
+                        self.end_bare(writer)?;
+                        self.write_comments(b, writer)?;
+                    }
+                    if !matches!(b, C_SPACE | C_LF | C_CR | C_TAB) {
+                        self.value_next = b == C_COLON;
+                    }
+                }
+                if self.in_bare && !is_bare_byte(b) {
+                    self.end_bare(writer)?;
+                }
                 match b {
                     C_SPACE | C_LF | C_CR | C_TAB => {
                         // skip whitespace
                     }
 
                     C_LEFT_BRACKET | C_LEFT_BRACE => {
+                        if self.depth == 0 {
+                            self.records += 1;
+                        }
                         if self.first {
                             self.first = false;
                             writer.write_all(&buf[n..n + 1])?;
                         } else if self.empty {
-                            writer.write_all(self.line_separator.as_bytes())?;
-                            for _ in 0..self.depth {
-                                writer.write_all(self.indent.as_bytes())?;
+                            if !self.folded() {
+                                writer.write_all(self.line_separator.as_bytes())?;
+                                for _ in 0..self.depth {
+                                    writer.write_all(self.indent.as_bytes())?;
+                                }
                             }
                             writer.write_all(&buf[n..n + 1])?;
                         } else if !self.eager_record_separators && self.depth == 0 {
@@ -305,43 +1026,87 @@ impl Formatter {
                         }
                         self.depth += 1;
                         self.empty = true;
+                        if let Some(max_width) = self.inline_width(b) {
+                            self.inline = Some(Inline {
+                                close: b + 2, // `]` or `}`
+                                max_width,
+                                held: vec![],
+                                rendered: vec![],
+                                width: 1,
+                                in_string: false,
+                                in_backslash: false,
+                            });
+                        }
                     }
 
                     C_RIGHT_BRACKET | C_RIGHT_BRACE => {
-                        self.depth = self.depth.saturating_sub(1);
+                        if self.depth == 0 {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "unexpected closing bracket",
+                            ));
+                        }
+                        let folded = self.folded();
+                        self.depth -= 1;
                         if self.empty {
                             self.empty = false;
                             writer.write_all(&buf[n..n + 1])?;
                         } else {
-                            writer.write_all(self.line_separator.as_bytes())?;
-                            for _ in 0..self.depth {
-                                writer.write_all(self.indent.as_bytes())?;
+                            if !folded {
+                                writer.write_all(self.line_separator.as_bytes())?;
+                                for _ in 0..self.depth {
+                                    writer.write_all(self.indent.as_bytes())?;
+                                }
                             }
                             writer.write_all(&buf[n..n + 1])?;
                         }
-                        if self.eager_record_separators && self.depth == 0 {
-                            writer.write_all(self.record_separator.as_bytes())?;
+                        if self.depth == 0 {
+                            self.end_record(writer)?;
                         }
                     }
 
                     C_COMMA => {
                         writer.write_all(&buf[n..n + 1])?;
-                        writer.write_all(self.line_separator.as_bytes())?;
-                        for _ in 0..self.depth {
-                            writer.write_all(self.indent.as_bytes())?;
+                        if !self.folded() {
+                            writer.write_all(self.line_separator.as_bytes())?;
+                            for _ in 0..self.depth {
+                                writer.write_all(self.indent.as_bytes())?;
+                            }
                         }
                     }
 
                     C_COLON => {
                         writer.write_all(&buf[n..n + 1])?;
-                        writer.write_all(self.after_colon.as_bytes())?;
+                        if !self.folded() {
+                            writer.write_all(self.after_colon.as_bytes())?;
+                        }
+                    }
+
+                    _ if self.depth == 0 => {
+                        // A scalar record
+                        if !self.in_bare {
+                            self.records += 1;
+                            if self.first {
+                                self.first = false;
+                            } else if !self.eager_record_separators {
+                                writer.write_all(self.record_separator.as_bytes())?;
+                            }
+                            if b == C_QUOTE {
+                                self.in_string = true;
+                            } else {
+                                self.in_bare = true;
+                            }
+                        }
+                        writer.write_all(&buf[n..n + 1])?;
                     }
 
                     _ => {
                         if self.empty {
-                            writer.write_all(self.line_separator.as_bytes())?;
-                            for _ in 0..self.depth {
-                                writer.write_all(self.indent.as_bytes())?;
+                            if !self.folded() {
+                                writer.write_all(self.line_separator.as_bytes())?;
+                                for _ in 0..self.depth {
+                                    writer.write_all(self.indent.as_bytes())?;
+                                }
                             }
                             self.empty = false;
                         }
@@ -357,6 +1122,516 @@ impl Formatter {
 
         Ok(())
     }
+
+    /// Writes the end of the output, after the last chunk given to
+    /// `format_buf`: a record separator after a root-level number or
+    /// literal, when they are eager, and the `trailing_output`.  With
+    /// `require_complete`, returns an error instead of the
+    /// `trailing_output` if the input was cut off.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut output: Vec<u8> = vec![];
+    /// let mut fmt = jsonxf::Formatter::minimizer();
+    /// fmt.eager_record_separators = true;
+    /// fmt.trailing_output = String::from("# end\n");
+    /// fmt.format_buf(b"[1] 2", &mut output).unwrap();
+    /// fmt.finish_buf(&mut output).unwrap();
+    /// assert_eq!(output, b"[1]\n2\n# end\n");
+    /// ```
+    pub fn finish_buf(&mut self, writer: &mut impl Write) -> Result<(), Error> {
+        let in_comment = self.comments.in_block_comment();
+        if self.strip_comments || self.strip_trailing_commas {
+            let mut rest: Vec<u8> = vec![];
+            self.clean(&[], true, &mut rest);
+            self.format_cleaned(&rest, writer)?;
+        }
+        if self.validate {
+            self.validator.finish()?;
+        }
+        if self.sorting.in_bare {
+            // A number or literal record ends with the input
+            self.sorting.in_bare = false;
+            self.write_sorted(writer)?;
+        } else if self.sorting.holding {
+            // A record cut off by the end of input is formatted as it was
+            let sorting = std::mem::take(&mut self.sorting);
+            if self.canonical {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "cannot canonicalize a record cut off by the end of input",
+                ));
+            }
+            self.format_bytes(&sorting.record, writer)?;
+        }
+        if let Some(inline) = self.inline.take() {
+            // So is an array or object held to see whether it fits on a line
+            self.format_bytes(&inline.held, writer)?;
+        }
+        let in_comment = in_comment || self.kept.in_block_comment();
+        if self.kept.finish() {
+            writer.write_all(b"/")?;
+        }
+        self.end_bare(writer)?;
+        if !self.kept.held.is_empty() {
+            // Comments at the end are written as before a closing bracket
+            self.write_comments(C_RIGHT_BRACKET, writer)?;
+        }
+        if self.require_complete && in_comment {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "input ended inside a comment",
+            ));
+        } else if self.require_complete && self.in_string {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "input ended inside a string",
+            ));
+        } else if self.require_complete && self.depth > 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "input ended inside an array or object",
+            ));
+        }
+        writer.write_all(self.trailing_output.as_bytes())
+    }
+
+    // Writes part of a string, escaping non-ASCII characters if asked.
+    fn write_string(&mut self, bytes: &[u8], writer: &mut impl Write) -> Result<(), Error> {
+        if !self.escape_non_ascii {
+            return writer.write_all(bytes);
+        }
+        let mut start = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            if b.is_ascii() && self.utf8_len == 0 {
+                continue;
+            }
+            writer.write_all(&bytes[start..i])?;
+            start = i + 1;
+            self.utf8[self.utf8_len] = b;
+            self.utf8_len += 1;
+            let char_len = match self.utf8[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if self.utf8_len < char_len && (b & 0xc0 == 0x80 || self.utf8_len == 1) {
+                continue;
+            }
+            let pending = &self.utf8[..self.utf8_len];
+            self.utf8_len = 0;
+            match std::str::from_utf8(pending) {
+                Ok(s) => {
+                    for unit in s.encode_utf16() {
+                        write!(writer, "\\u{:04x}", unit)?;
+                    }
+                }
+                // Not UTF-8; pass it along as it was
+                Err(_) => writer.write_all(pending)?,
+            }
+        }
+        writer.write_all(&bytes[start..])
+    }
+
+    // Writes the comments held since the last token, before `b`, the
+    // next one: on their own lines, except between a key and its value.
+    fn write_comments(&mut self, b: u8, writer: &mut impl Write) -> Result<(), Error> {
+        let comments = std::mem::take(&mut self.kept.held);
+        let closing = b == C_RIGHT_BRACKET || b == C_RIGHT_BRACE;
+        for comment in &comments {
+            if self.depth == 0 {
+                if !self.first && !self.eager_record_separators {
+                    writer.write_all(self.record_separator.as_bytes())?;
+                }
+                writer.write_all(comment)?;
+                writer.write_all(self.line_separator.as_bytes())?;
+                // The next record begins a line, as at the start
+                self.first = true;
+                continue;
+            }
+            if self.value_next {
+                writer.write_all(comment)?;
+                if comment.starts_with(b"//") {
+                    // The value goes on the next line, indented further
+                    self.write_newline(writer)?;
+                    writer.write_all(self.indent.as_bytes())?;
+                } else {
+                    writer.write_all(b" ")?;
+                }
+                continue;
+            }
+            if self.empty || closing {
+                self.write_newline(writer)?;
+            }
+            self.empty = false;
+            writer.write_all(comment)?;
+            if !closing {
+                self.write_newline(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Writes a line separator and indentation for the current depth.
+    fn write_newline(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_all(self.line_separator.as_bytes())?;
+        for _ in 0..self.depth {
+            writer.write_all(self.indent.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // How wide may the array or object just begun with `b` be, to be
+    // written on one line?
+    fn inline_width(&self, b: u8) -> Option<usize> {
+        if self.line_separator.is_empty() || self.folded() {
+            return None;
+        }
+        match b {
+            C_LEFT_BRACKET => self.max_inline_array_width,
+            _ => self.max_inline_object_width,
+        }
+    }
+
+    // Holds the start of `buf` as part of an array or object which may
+    // fit on one line, and returns how many bytes were used.  At its
+    // end, if it fits, writes its contents on one line, leaving the `]`
+    // or `}` to be formatted as for an empty one.  Otherwise, once it
+    // can't fit, formats what was held as usual.
+    fn hold_inline(&mut self, buf: &[u8], writer: &mut impl Write) -> Result<usize, Error> {
+        let after_colon = self.after_colon.as_bytes();
+        let inline = self.inline.as_mut().unwrap();
+        let mut fits = true;
+        let mut used = buf.len();
+        for (i, &b) in buf.iter().enumerate() {
+            if inline.in_string {
+                if inline.in_backslash {
+                    inline.in_backslash = false;
+                } else if b == C_BACKSLASH {
+                    inline.in_backslash = true;
+                } else if b == C_QUOTE {
+                    inline.in_string = false;
+                }
+                if b & 0xc0 != 0x80 {
+                    inline.width += 1;
+                }
+                inline.rendered.push(b);
+            } else {
+                match b {
+                    C_SPACE | C_LF | C_CR | C_TAB => {}
+                    C_RIGHT_BRACKET | C_RIGHT_BRACE if b == inline.close => {
+                        inline.width += 1;
+                        used = i;
+                        break;
+                    }
+                    // A comment, kept or not, is as good as a nested array
+                    C_LEFT_BRACKET | C_LEFT_BRACE | C_RIGHT_BRACKET | C_RIGHT_BRACE | b'/' => {
+                        fits = false
+                    }
+                    C_COMMA => {
+                        inline.width += 2;
+                        inline.rendered.extend_from_slice(b", ");
+                    }
+                    C_COLON => {
+                        inline.width += 1 + after_colon.len();
+                        inline.rendered.push(b);
+                        inline.rendered.extend_from_slice(after_colon);
+                    }
+                    _ => {
+                        inline.in_string = b == C_QUOTE;
+                        inline.width += 1;
+                        inline.rendered.push(b);
+                    }
+                }
+            }
+            inline.held.push(b);
+            if !fits || inline.width > inline.max_width {
+                fits = false;
+                used = i + 1;
+                break;
+            }
+        }
+        if fits && used == buf.len() {
+            // The array goes on
+            return Ok(used);
+        }
+        let inline = self.inline.take().unwrap();
+        if fits && inline.width <= inline.max_width {
+            self.write_string(&inline.rendered, writer)?;
+            self.empty = true;
+        } else {
+            self.format_bytes(&inline.held, writer)?;
+        }
+        Ok(used)
+    }
+
+    // Is the next byte inside a folded array or object?
+    fn folded(&self) -> bool {
+        self.max_depth.is_some_and(|max| self.depth > max)
+    }
+
+    // Is `b` hidden by an ellipsis?  Everything inside a folded array or
+    // object is, except for its closing bracket.
+    fn hides(&self, b: u8) -> bool {
+        match self.max_depth {
+            Some(max) if self.depth == max + 1 => {
+                self.in_string || !matches!(b, C_RIGHT_BRACKET | C_RIGHT_BRACE)
+            }
+            Some(max) => self.depth > max + 1,
+            None => false,
+        }
+    }
+
+    // Passes over a byte hidden by an ellipsis, writing the ellipsis in
+    // place of the first byte of content.
+    fn skip_folded(&mut self, b: u8, writer: &mut impl Write) -> Result<(), Error> {
+        if self.in_string {
+            if self.in_backslash {
+                self.in_backslash = false;
+            } else if b == C_BACKSLASH {
+                self.in_backslash = true;
+            } else if b == C_QUOTE {
+                self.in_string = false;
+            }
+            return Ok(());
+        }
+        match b {
+            C_SPACE | C_LF | C_CR | C_TAB => return Ok(()),
+            C_QUOTE => self.in_string = true,
+            C_LEFT_BRACKET | C_LEFT_BRACE => self.depth += 1,
+            C_RIGHT_BRACKET | C_RIGHT_BRACE => self.depth -= 1,
+            _ => {}
+        }
+        if self.empty {
+            self.empty = false;
+            writer.write_all(b"...")?;
+        }
+        Ok(())
+    }
+
+    // Ends a root-level number or literal, as at the end of input.
+    fn end_bare(&mut self, writer: &mut impl Write) -> Result<(), Error> {
+        if self.in_bare {
+            self.in_bare = false;
+            self.end_record(writer)?;
+        }
+        Ok(())
+    }
+
+    // Follows a record just written with a record separator, if eager,
+    // and flushes the output, if asked to.
+    fn end_record(&mut self, writer: &mut impl Write) -> Result<(), Error> {
+        if self.eager_record_separators {
+            writer.write_all(self.record_separator.as_bytes())?;
+        }
+        if self.flush_records {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a `Formatter` is in its input, as saved by `Formatter::state`:
+/// how deeply nested, whether inside a string, and so on.
+///
+/// It converts to and from a few bytes, to be carried between
+/// processes, as when a large object is formatted in ranged parts by
+/// separate serverless invocations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatterState {
+    depth: usize,
+    in_string: bool,
+    in_backslash: bool,
+    empty: bool,
+    first: bool,
+    in_bare: bool,
+    utf8: [u8; 4],
+    utf8_len: usize,
+}
+
+// The version of `FormatterState::to_bytes`'s format.
+const STATE_VERSION: u8 = 1;
+
+impl FormatterState {
+    /// Returns how deeply nested the formatter is in arrays and objects.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns whether the formatter is inside a string.
+    pub fn in_string(&self) -> bool {
+        self.in_string
+    }
+
+    /// Encodes the state in a few bytes: a version, flags, the depth,
+    /// and any part of a character awaiting escaping.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let flags = [
+            self.in_string,
+            self.in_backslash,
+            self.empty,
+            self.first,
+            self.in_bare,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0_u8, |flags, (i, &flag)| flags | ((flag as u8) << i));
+        let mut bytes = vec![STATE_VERSION, flags];
+        bytes.extend_from_slice(&(self.depth as u64).to_le_bytes());
+        bytes.push(self.utf8_len as u8);
+        bytes.extend_from_slice(&self.utf8[..self.utf8_len]);
+        bytes
+    }
+
+    /// Decodes a state encoded by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<FormatterState, String> {
+        let invalid = || String::from("invalid formatter state");
+        if bytes.first() != Some(&STATE_VERSION) {
+            return Err(invalid());
+        }
+        let flags = *bytes.get(1).ok_or_else(invalid)?;
+        let depth = bytes.get(2..10).ok_or_else(invalid)?;
+        let depth = u64::from_le_bytes(depth.try_into().unwrap());
+        let utf8_len = *bytes.get(10).ok_or_else(invalid)? as usize;
+        if flags >> 5 != 0 || utf8_len > 3 || bytes.len() != 11 + utf8_len {
+            return Err(invalid());
+        }
+        let mut utf8 = [0; 4];
+        utf8[..utf8_len].copy_from_slice(&bytes[11..]);
+        Ok(FormatterState {
+            depth: usize::try_from(depth).map_err(|_| invalid())?,
+            in_string: flags & 1 != 0,
+            in_backslash: flags & 2 != 0,
+            empty: flags & 4 != 0,
+            first: flags & 8 != 0,
+            in_bare: flags & 16 != 0,
+            utf8,
+            utf8_len,
+        })
+    }
+}
+
+/// How `Formatter` folds arrays and objects nested deeper than its
+/// `max_depth`.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::{FoldStyle, Formatter};
+///
+/// let mut fmt = Formatter::pretty_printer();
+/// fmt.max_depth = Some(1);
+/// assert_eq!(
+///     fmt.format("{\"a\": {\"b\": [1, 2]}, \"c\": []}").unwrap(),
+///     "{\n  \"a\": {\"b\":[1,2]},\n  \"c\": []\n}"
+/// );
+///
+/// fmt.reset();
+/// fmt.fold_style = FoldStyle::Ellipsis;
+/// assert_eq!(
+///     fmt.format("{\"a\": {\"b\": [1, 2]}, \"c\": []}").unwrap(),
+///     "{\n  \"a\": {...},\n  \"c\": []\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldStyle {
+    /// Minimizes each folded array or object onto one line.
+    Minify,
+    /// Replaces the contents of each folded array or object with `...`,
+    /// for skimming.  The output is not valid JSON.
+    Ellipsis,
+}
+
+/// Returns the line ending which `input` uses, judging by its first
+/// line feed: `"\r\n"` if a carriage return comes before it, `"\n"` if
+/// not, and `None` if there is no line feed.  Pass it to
+/// `Formatter::set_line_ending` to keep a file's line endings as they
+/// were.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::detect_line_ending(b"{\r\n  \"a\": 1\r\n}"), Some("\r\n"));
+/// assert_eq!(jsonxf::detect_line_ending(b"{\n  \"a\": 1\n}"), Some("\n"));
+/// assert_eq!(jsonxf::detect_line_ending(b"{\"a\":1}"), None);
+/// ```
+pub fn detect_line_ending(input: &[u8]) -> Option<&'static str> {
+    let lf = memchr::memchr(C_LF, input)?;
+    if lf > 0 && input[lf - 1] == C_CR {
+        Some("\r\n")
+    } else {
+        Some("\n")
+    }
+}
+
+/// Returns the indentation which pretty-printed `input` uses for each
+/// level of nesting, judging by its first indented line, or `None` if
+/// it has none.  Set `Formatter::indent` to it to keep a file's
+/// indentation as it was.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::detect_indent(b"{\n    \"a\": [\n        1\n    ]\n}"), Some(String::from("    ")));
+/// assert_eq!(jsonxf::detect_indent(b"[\n\t[\n\t\t1\n\t]\n]"), Some(String::from("\t")));
+/// assert_eq!(jsonxf::detect_indent(b"{\"a\": 1}"), None);
+/// ```
+pub fn detect_indent(input: &[u8]) -> Option<String> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut in_backslash = false;
+    for (i, &b) in input.iter().enumerate() {
+        match b {
+            _ if in_backslash => in_backslash = false,
+            C_BACKSLASH if in_string => in_backslash = true,
+            C_QUOTE => in_string = !in_string,
+            _ if in_string => {}
+            C_LEFT_BRACE | C_LEFT_BRACKET => depth += 1,
+            C_RIGHT_BRACE | C_RIGHT_BRACKET => depth -= 1,
+            C_LF if depth > 0 => {
+                let line = &input[i + 1..];
+                let len = line
+                    .iter()
+                    .position(|&b| b != C_SPACE && b != C_TAB)
+                    .unwrap_or(line.len());
+                // A line which closes a level is indented one level less
+                let depth = match line.get(len) {
+                    Some(&C_RIGHT_BRACE) | Some(&C_RIGHT_BRACKET) => depth - 1,
+                    None | Some(&C_CR) | Some(&C_LF) => continue,
+                    Some(_) => depth,
+                };
+                if len == 0 || depth <= 0 || len % depth as usize != 0 {
+                    continue;
+                }
+                let unit = &line[..len / depth as usize];
+                if unit.repeat(depth as usize) == line[..len] {
+                    return std::str::from_utf8(unit).ok().map(String::from);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Returns true if `b` can continue a number or literal.
+fn is_bare_byte(b: u8) -> bool {
+    !matches!(
+        b,
+        C_SPACE
+            | C_LF
+            | C_CR
+            | C_TAB
+            | C_LEFT_BRACE
+            | C_RIGHT_BRACE
+            | C_LEFT_BRACKET
+            | C_RIGHT_BRACKET
+            | C_COMMA
+            | C_COLON
+            | C_QUOTE
+    )
 }
 
 /// Pretty-prints a string of JSON-encoded data.
@@ -0,0 +1,109 @@
+//! Unwrapping the log lines written by container runtimes, to get at
+//! the messages inside them.
+//!
+//! Docker's `json-file` logging driver writes each line of a
+//! container's output as a JSON object, like `{"log": "...\n",
+//! "stream": "stdout", "time": "..."}`.  Kubernetes runtimes which
+//! speak CRI, like containerd and CRI-O, write `TIMESTAMP STREAM FLAG
+//! MESSAGE` instead.  Both split long lines into several entries:
+//! Docker leaves off the `\n`, and CRI flags each part but the last
+//! with `P`.
+
+use value::{self, Value};
+
+/// Unwraps container log lines, rejoining the parts of long lines.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::logs::Unwrapper;
+///
+/// let mut unwrapper = Unwrapper::new();
+/// assert_eq!(
+///     unwrapper.unwrap("{\"log\":\"{\\\"a\\\":1}\\n\",\"stream\":\"stdout\"}"),
+///     Some(String::from("{\"a\":1}"))
+/// );
+/// assert_eq!(unwrapper.unwrap("2024-05-01T12:00:00.1Z stderr P {\"b\":"), None);
+/// assert_eq!(
+///     unwrapper.unwrap("2024-05-01T12:00:00.2Z stderr F 2}"),
+///     Some(String::from("{\"b\":2}"))
+/// );
+/// assert_eq!(unwrapper.unwrap("plain text"), Some(String::from("plain text")));
+/// ```
+#[derive(Debug, Default)]
+pub struct Unwrapper {
+    partial: Option<String>, // the start of a split line
+}
+
+impl Unwrapper {
+    pub fn new() -> Unwrapper {
+        Unwrapper::default()
+    }
+
+    /// Returns the message in one line of container logs, without a
+    /// trailing newline, or `None` if the line holds only the start of
+    /// a message.  Lines in neither format are returned as they are.
+    pub fn unwrap(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (message, complete) = match docker_log(line).or_else(|| cri_log(line)) {
+            Some(entry) => entry,
+            None => (String::from(line), true),
+        };
+        let message = match self.partial.take() {
+            Some(mut partial) => {
+                partial.push_str(&message);
+                partial
+            }
+            None => message,
+        };
+        if complete {
+            Some(message)
+        } else {
+            self.partial = Some(message);
+            None
+        }
+    }
+
+    /// Returns the start of a message which the input ended before
+    /// finishing, if any.
+    pub fn finish(&mut self) -> Option<String> {
+        self.partial.take()
+    }
+}
+
+// Returns the message in a Docker log entry, and whether it is the end
+// of a line.
+fn docker_log(line: &str) -> Option<(String, bool)> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    match value::parse(line).ok()?.get("log") {
+        Some(Value::String(log)) => match log.strip_suffix('\n') {
+            Some(log) => Some((String::from(log.trim_end_matches('\r')), true)),
+            None => Some((log.clone(), false)),
+        },
+        _ => None,
+    }
+}
+
+// Returns the message in a CRI log entry, and whether it is the end of
+// a line.
+fn cri_log(line: &str) -> Option<(String, bool)> {
+    let mut fields = line.splitn(4, ' ');
+    let timestamp = fields.next()?;
+    let stream = fields.next()?;
+    let flag = fields.next()?;
+    let message = fields.next().unwrap_or("");
+    let is_timestamp = timestamp.len() >= 20
+        && timestamp.as_bytes()[..4].iter().all(u8::is_ascii_digit)
+        && timestamp.as_bytes()[4] == b'-'
+        && timestamp.contains('T');
+    if !is_timestamp || !(stream == "stdout" || stream == "stderr") {
+        return None;
+    }
+    match flag {
+        "F" => Some((String::from(message), true)),
+        "P" => Some((String::from(message), false)),
+        _ => None,
+    }
+}
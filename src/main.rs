@@ -8,184 +8,5124 @@
   Run `jsonxf -h` for usage options.
 */
 
-use std::{fs::File, io::ErrorKind};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs::{File, OpenOptions},
+    io::{
+        BufRead, BufReader, BufWriter, Cursor, Error, ErrorKind, IsTerminal, Read, Seek, SeekFrom,
+        Write,
+    },
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 extern crate jsonxf;
+use jsonxf::color::ColorWriter;
+use jsonxf::compress::{self, Compression};
+use jsonxf::csv::CsvOptions;
+use jsonxf::diff::Change;
+use jsonxf::expr::Expr;
+use jsonxf::hash::Algorithm;
+use jsonxf::html::HtmlWriter;
+use jsonxf::index::IndexOf;
+use jsonxf::merge::Strategy;
+use jsonxf::redact::{Redactor, SecretScanner};
+use jsonxf::scanner::{Position, SyntaxError};
+use jsonxf::stats::{StatsCollector, StatsReader, StreamStats};
+use jsonxf::value::Value;
+use jsonxf::{FoldStyle, Formatter, FormatterState};
 
 extern crate getopts;
-use getopts::Options;
+use getopts::{Matches, Options};
+
+extern crate regex;
+use regex::Regex;
+
+extern crate terminal_size;
+
+#[cfg(windows)]
+extern crate windows_sys;
 
 fn main() {
     match do_main() {
         Ok(_) => { /* YAY */ }
         Err(e) => {
-            eprintln!("{}", e);
+            e.emit();
             std::process::exit(1);
         }
     };
 }
 
-fn do_main() -> Result<(), String> {
-    let args: Vec<String> = std::env::args().collect();
+// A command, with its description, the options it stands for, and the
+// groups of options it takes.  Without a command, the first whose mode
+// is chosen by an option (or else fmt) runs, and takes every option.
+struct Subcommand {
+    name: &'static str,
+    description: &'static str,
+    stands_for: &'static [&'static str],
+    chosen_by: &'static [&'static str],
+    groups: &'static [fn(&mut Options)],
+    run: fn(Invocation) -> Result<(), Diagnostic>,
+}
+
+impl Subcommand {
+    fn options(&self) -> Options {
+        let mut opts = Options::new();
+        for group in self.groups {
+            group(&mut opts);
+        }
+        opts
+    }
+}
 
+// Every option, in groups in the order they're listed in the help.
+fn all_options() -> Options {
     let mut opts = Options::new();
-    opts.optopt(
-        "s",
-        "string",
-        "use the given string as input, instead of a file",
-        "str",
-    );
-    opts.optopt(
-        "i",
-        "input",
-        "read input from the given file (default: stdin)",
-        "file",
-    );
-    opts.optopt(
-        "o",
-        "output",
-        "write output to the given file (default: stdout)",
-        "file",
-    );
-    opts.optopt(
-        "t",
-        "tab",
-        "use the given string to indent pretty-printed output (default: two spaces)",
-        "tabstr",
-    );
-    opts.optflag(
-        "m",
-        "minimize",
-        "minimize JSON instead of pretty-printing it",
-    );
-    opts.optflag("h", "help", "print this message and exit");
+    for group in ALL_OPTIONS {
+        group(&mut opts);
+    }
+    opts
+}
+
+const ALL_OPTIONS: &[fn(&mut Options)] = &[
+    input_options,
+    output_options,
+    tint_options,
+    batch_options,
+    rewrite_options,
+    list_options,
+    layout_options,
+    parsing_options,
+    get_options,
+    check_options,
+    diff_options,
+    merge_options,
+    split_options,
+    records_options,
+    schema_options,
+    transform_options,
+    redact_options,
+    conversion_options,
+    report_options,
+    stream_options,
+    general_options,
+];
+
+const FMT_OPTIONS: &[fn(&mut Options)] = &[
+    input_options,
+    output_options,
+    tint_options,
+    batch_options,
+    rewrite_options,
+    list_options,
+    layout_options,
+    parsing_options,
+    records_options,
+    transform_options,
+    redact_options,
+    conversion_options,
+    report_options,
+    stream_options,
+    general_options,
+];
+
+const CHECK_OPTIONS: &[fn(&mut Options)] = &[
+    input_options,
+    list_options,
+    layout_options,
+    parsing_options,
+    check_options,
+    general_options,
+];
+
+const COMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "fmt",
+        description: "pretty-print JSON (the default)",
+        stands_for: &[],
+        chosen_by: &[],
+        groups: FMT_OPTIONS,
+        run: fmt_command,
+    },
+    Subcommand {
+        name: "min",
+        description: "minimize JSON",
+        stands_for: &["-m"],
+        chosen_by: &[],
+        groups: FMT_OPTIONS,
+        run: fmt_command,
+    },
+    Subcommand {
+        name: "get",
+        description: "print the values at JSON Pointers: get POINTER... [-- file ...]",
+        stands_for: &[],
+        chosen_by: &["get"],
+        groups: &[
+            input_options,
+            output_options,
+            tint_options,
+            batch_options,
+            layout_options,
+            get_options,
+            general_options,
+        ],
+        run: get_command,
+    },
+    Subcommand {
+        name: "check",
+        description: "check that input is well-formed JSON, printing nothing",
+        stands_for: &["--check"],
+        chosen_by: &["check"],
+        groups: CHECK_OPTIONS,
+        run: check_command,
+    },
+    Subcommand {
+        name: "validate",
+        description: "same as check",
+        stands_for: &["--check"],
+        chosen_by: &[],
+        groups: CHECK_OPTIONS,
+        run: check_command,
+    },
+    Subcommand {
+        name: "diff",
+        description: "compare two JSON files structurally: diff FILE1 FILE2",
+        stands_for: &["--diff"],
+        chosen_by: &["diff", "assert-equal"],
+        groups: &[output_options, diff_options, general_options],
+        run: diff_command,
+    },
+    Subcommand {
+        name: "schema",
+        description: "describe the shape of the input, or infer a JSON Schema with --format schema",
+        stands_for: &["--schema"],
+        chosen_by: &["schema"],
+        groups: &[
+            input_options,
+            output_options,
+            tint_options,
+            batch_options,
+            layout_options,
+            schema_options,
+            general_options,
+        ],
+        run: schema_command,
+    },
+    Subcommand {
+        name: "split",
+        description:
+            "divide a large input into parts without breaking records: split --out TEMPLATE",
+        stands_for: &["--split"],
+        chosen_by: &["split"],
+        groups: &[
+            input_options,
+            layout_options,
+            parsing_options,
+            split_options,
+            records_options,
+            general_options,
+        ],
+        run: split_command,
+    },
+    Subcommand {
+        name: "merge",
+        description: "deep-merge JSON files, later ones over earlier: merge BASE OVERLAY...",
+        stands_for: &["--merge"],
+        chosen_by: &["merge"],
+        groups: &[
+            output_options,
+            tint_options,
+            layout_options,
+            merge_options,
+            redact_options,
+            general_options,
+        ],
+        run: merge_command,
+    },
+];
+
+// The command run without one being named: the first chosen by a mode
+// given, or else fmt.
+fn chosen_command(matches: &Matches) -> &'static Subcommand {
+    COMMANDS
+        .iter()
+        .find(|command| {
+            command
+                .chosen_by
+                .iter()
+                .any(|name| matches.opt_present(name))
+        })
+        .unwrap_or(&COMMANDS[0])
+}
+
+// What can be done with the input, each chosen by any of the options
+// first listed, and the options choosing other modes which it honours.
+// No other two modes can be given together, as one would be ignored.
+const MODES: &[(&[&str], &[&str])] = &[
+    (&["checkpoint"], &[]),
+    (&["diff"], &[]),
+    (&["assert-equal"], &[]),
+    (&["merge"], &["redact"]),
+    (&["split"], &["records"]),
+    (&["check"], &[]),
+    (&["get"], &[]),
+    (&["from"], &[]),
+    (&["to"], &[]),
+    (&["gron"], &[]),
+    (&["ungron"], &[]),
+    (&["paths"], &[]),
+    (&["path-index"], &[]),
+    (&["seek"], &[]),
+    (&["find-secrets"], &[]),
+    (&["hash"], &[]),
+    (&["hash-records"], &[]),
+    (&["schema"], &[]),
+    (&["key-stats"], &[]),
+    (&["patch"], &["grep", "expr", "redact"]),
+    (&["merge-patch"], &["grep", "expr", "redact"]),
+    (&["grep", "expr", "redact"], &[]),
+    (&["records"], &[]),
+    (&["index"], &[]),
+    (&["docker-logs", "k8s"], &["follow"]),
+    (&["mixed"], &["follow"]),
+    (&["on-error"], &["follow"]),
+    (&["verify"], &[]),
+    (&["audit"], &[]),
+    (&["cross-check"], &[]),
+    (&["interactive"], &[]),
+    (&["follow"], &[]),
+];
+
+// Options which only modify a mode, with the modes they modify.  A mode
+// may be given as an option and its value, as in `to html`.
+const MODIFIERS: &[(&str, &[&str])] = &[
+    ("raw-output", &["get"]),
+    ("strategy", &["merge"]),
+    ("ignore-order", &["diff", "assert-equal"]),
+    ("ignore-key-order", &["assert-equal"]),
+    ("with-records", &["hash-records"]),
+    ("format", &["schema"]),
+    ("out", &["split"]),
+    ("bytes", &["split"]),
+    ("array-elements", &["split"]),
+    ("invert-match", &["grep"]),
+    ("counts", &["paths"]),
+    ("types", &["paths"]),
+    ("html-stylesheet", &["to html"]),
+    ("details-depth", &["to markdown"]),
+    ("infer-types", &["from csv", "from tsv"]),
+    ("array", &["from csv", "from tsv"]),
+    ("index-of", &["index"]),
+    ("seek-index", &["seek"]),
+];
 
-    let matches = match opts.parse(&args[1..]) {
+// Options which change how JSON is laid out.
+const LAYOUT_OPTIONS: &[&str] = &[
+    "t",
+    "indent",
+    "tab",
+    "eol",
+    "m",
+    "ndjson",
+    "smart",
+    "ascii",
+    "sort-keys",
+    "canonical",
+    "jcs",
+    "max-depth",
+    "fold-style",
+    "inline-arrays",
+    "inline-objects",
+    "keep-comments",
+    "tint-levels",
+];
+
+// Modes which write something other than JSON, and so ignore its layout.
+const NOT_JSON_MODES: &[&str] = &[
+    "diff",
+    "assert-equal",
+    "check",
+    "to toml",
+    "to msgpack",
+    "to cbor",
+    "gron",
+    "paths",
+    "path-index",
+    "find-secrets",
+    "hash",
+    "hash-records",
+    "schema",
+    "key-stats",
+];
+
+// Options which change what input is accepted.
+const PARSING_OPTIONS: &[&str] = &["strict", "jsonc", "trailing-commas"];
+
+// Modes which parse the input their own way, and so ignore how it's to
+// be accepted.
+const OWN_PARSING_MODES: &[&str] = &[
+    "diff",
+    "assert-equal",
+    "merge",
+    "get",
+    "from",
+    "to toml",
+    "to msgpack",
+    "to cbor",
+    "to markdown",
+    "to rust",
+    "to rust-string",
+    "gron",
+    "ungron",
+    "paths",
+    "path-index",
+    "seek",
+    "find-secrets",
+    "hash",
+    "hash-records",
+    "schema",
+    "key-stats",
+    "patch",
+    "merge-patch",
+    "grep",
+    "expr",
+    "redact",
+];
+
+// The command line, with the options set by config files and JSONXF.
+struct Invocation {
+    args: Vec<String>,
+    command: Option<&'static Subcommand>,
+    matches: Matches,
+}
+
+fn do_main() -> Result<(), Diagnostic> {
+    let mut args: Vec<String> = std::env::args().collect();
+    // `--tab STR` and `--tab=STR` predate `--tab`, and are kept for
+    // compatibility, when STR is all spaces and tabs
+    let mut i = 1;
+    while i < args.len() && args[i] != "--" {
+        if let Some(tabstr) = args[i].strip_prefix("--tab=") {
+            args[i] = format!("--indent-string={}", tabstr);
+        } else if args[i] == "--tab" && args.get(i + 1).is_some_and(|arg| is_indent_string(arg)) {
+            let tabstr = args.remove(i + 1);
+            args[i] = format!("--indent-string={}", tabstr);
+        }
+        i += 1;
+    }
+    let command = args
+        .get(1)
+        .and_then(|arg| COMMANDS.iter().find(|command| command.name == arg));
+    if let Some(command) = command {
+        args.splice(
+            1..2,
+            command.stands_for.iter().map(|option| option.to_string()),
+        );
+    }
+
+    let opts = all_options();
+    let cli = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(e) => {
-            return Err(e.to_string());
+            return Err(e.to_string().into());
+        }
+    };
+    // Options from config files come first, then those from the JSONXF
+    // environment variable, unless given later on.  Those the command
+    // doesn't take are left out.
+    let takes = command.map_or_else(all_options, Subcommand::options);
+    let env_args = env_args(&opts, &takes, &args[1..])?;
+    let matches = if env_args.is_empty() {
+        cli.clone()
+    } else {
+        opts.parse(env_args.iter().chain(&args[1..]))
+            .map_err(|e| e.to_string())?
+    };
+    let matches = if matches.opt_present("no-config") {
+        matches
+    } else {
+        let defaults = config_args(&opts, &takes, &matches)?;
+        if defaults.is_empty() {
+            matches
+        } else {
+            opts.parse(defaults.iter().chain(&env_args).chain(&args[1..]))
+                .map_err(|e| e.to_string())?
         }
     };
+    match matches.opt_str("errors").as_deref() {
+        None | Some("text") => {}
+        Some("jsonl") => ERRORS_JSONL.store(true, Ordering::Relaxed),
+        Some(other) => return Err(format!("invalid errors format `{}`", other).into()),
+    }
 
     if matches.opt_present("h") {
-        let program = args[0].clone();
-        print_help(&program, &opts);
+        print_help(&args[0], command);
         return Ok(());
     }
 
-    // If these are set and match later, we need to take care not to
-    // truncate the input file.
-    let mut input_filename: Option<String> = None;
-    let mut output_filename: Option<String> = None;
-    let mut output_temp_filename: Option<String> = None;
+    check_modes(&cli, &args[1..], command)?;
+    let run = command.unwrap_or_else(|| chosen_command(&matches)).run;
+    run(Invocation {
+        args,
+        command,
+        matches,
+    })
+}
+
+// Pretty-prints or minimizes the input, or does what other options
+// choose, for fmt and min, or without a command.
+fn fmt_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let mode = fmt_mode(&invocation)?;
+    format_inputs(invocation, mode)
+}
+
+// Prints the values at JSON Pointers in each record, for get.
+fn get_command(mut invocation: Invocation) -> Result<(), Diagnostic> {
+    let matches = &mut invocation.matches;
+    let mut pointers = matches.opt_strs("get");
+    if invocation.command.is_some() {
+        // `get /a /b -- file` takes the arguments before `--` as pointers,
+        // and those after it, which getopts also leaves in `free`, as files
+        let args = &invocation.args;
+        let files = args
+            .iter()
+            .position(|arg| arg == "--")
+            .map_or(0, |i| args.len() - i - 1);
+        let given = matches.free.len() - files;
+        if given == 0 {
+            return Err(String::from("get needs one or more JSON Pointers").into());
+        }
+        if let Some(arg) = matches.free[..given]
+            .iter()
+            .find(|arg| !arg.is_empty() && !arg.starts_with('/'))
+        {
+            return Err(format!("`{}` is not a JSON Pointer; give files after `--`", arg).into());
+        }
+        pointers.extend(matches.free.drain(..given));
+    }
+    let raw = matches.opt_present("raw-output");
+    format_inputs(invocation, Mode::Get { pointers, raw })
+}
+
+// Checks that the input is well-formed JSON, printing nothing, for check.
+fn check_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let matches = &invocation.matches;
+    if matches.opt_present("list") || matches.opt_present("dry-run") {
+        // List the files which formatting would change, instead
+        return format_inputs(invocation, Mode::Format);
+    }
+    let stripped = matches.opt_present("jsonc") || matches.opt_present("trailing-commas");
+    format_inputs(invocation, Mode::Check { stripped })
+}
+
+// Describes the shape of the input, for schema.
+fn schema_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let format = match invocation.matches.opt_str("format").as_deref() {
+        None | Some("tsv") => SchemaFormat::Tsv,
+        Some("table") => SchemaFormat::Table,
+        Some("schema") => SchemaFormat::JsonSchema,
+        Some(other) => return Err(format!("invalid schema format `{}`", other).into()),
+    };
+    format_inputs(invocation, Mode::Schema(format))
+}
+
+// Compares two files structurally, for diff, or fails unless they're
+// equal, with --assert-equal.
+fn diff_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let matches = &invocation.matches;
+    let assert_equal = matches.opt_present("assert-equal");
+    let files = &matches.free;
+    if files.len() != 2 {
+        let mode = if assert_equal {
+            "--assert-equal"
+        } else {
+            "--diff"
+        };
+        return Err(format!("{} compares two files", mode).into());
+    }
+    let mut differ = jsonxf::diff::Differ::new();
+    differ.ignore_array_order = matches.opt_present("ignore-order");
+    if assert_equal {
+        differ.ignore_key_order = matches.opt_present("ignore-key-order");
+        return assert_files_equal(&differ, &files[0], &files[1]);
+    }
+    let color = use_color(matches)?;
+    let (output, output_file) = open_output(matches, true, files)?;
+    diff_files(&differ, &files[0], &files[1], color, output)?;
+    finish_output(output_file, Ok(())).map_err(|e| Diagnostic::from(&e))
+}
+
+// Deep-merges files, later ones over earlier, for merge.
+fn merge_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let matches = &invocation.matches;
+    let files = &matches.free;
+    if files.len() < 2 {
+        return Err(String::from("--merge needs two or more files").into());
+    }
+    let strategy = match matches.opt_str("strategy") {
+        None => Strategy::LastWins,
+        Some(name) => name.parse::<Strategy>()?,
+    };
+    let redactor = redactor(matches)?;
+    let mut xf = Layout::from_matches(matches)?.formatter(matches.opt_present("m"));
+    let (mut output, output_file) = open_output(matches, true, files)?;
+    if use_color(matches)? {
+        output = colored(output, matches);
+    }
+    let tidy = |record: &mut Value| {
+        if let Some(redactor) = &redactor {
+            redactor.redact_value(record);
+        }
+    };
+    merge_files(files, strategy, tidy, &mut xf, &mut output)?;
+    drop(output);
+    finish_output(output_file, Ok(())).map_err(|e| Diagnostic::from(&e))
+}
+
+// Divides the input into parts without breaking records, for split.
+fn split_command(invocation: Invocation) -> Result<(), Diagnostic> {
+    let matches = &invocation.matches;
+    let started = Instant::now();
+    let verbosity = verbosity(matches)?;
+    let files = input_files(matches)?;
+    if files.len() > 1 {
+        return Err(String::from("--split reads one input, without -w").into());
+    }
+    let parse_count = |name: &str| -> Result<Option<u64>, String> {
+        match matches.opt_str(name) {
+            None => Ok(None),
+            Some(s) => match s.parse::<u64>() {
+                Ok(n) if n > 0 => Ok(Some(n)),
+                _ => Err(format!("invalid --{} `{}`", name, s)),
+            },
+        }
+    };
+    let array_elements = parse_count("array-elements")?;
+    let mut limits = jsonxf::split::SplitLimits {
+        records: parse_count("records")?,
+        bytes: match matches.opt_str("bytes") {
+            None => None,
+            Some(s) => Some(parse_size(&s)?),
+        },
+    };
+    let template = match matches.opt_str("out") {
+        None => return Err(String::from("--split requires --out").into()),
+        Some(template) => template,
+    };
+    if array_elements.is_some() && limits == Default::default() {
+        // One array to each part
+        limits.records = Some(1);
+    } else if limits == Default::default() {
+        return Err(String::from("--split needs --records, --bytes, or --array-elements").into());
+    }
+    let mut xf = Layout::from_matches(matches)?.formatter(matches.opt_present("m"));
+    let (mut input, _) = open_input(&invocation, &files, false)?;
+    let parts = split_into_parts(
+        &mut xf,
+        &mut input,
+        &files,
+        &template,
+        array_elements,
+        limits,
+    )?;
+    verbosity.info(format_args!(
+        "wrote {} in {:.2?}",
+        count(parts as usize, "part"),
+        started.elapsed()
+    ));
+    Ok(())
+}
+
+// The input files given as arguments, with -r, --include, --exclude,
+// and --files-from.
+fn input_files(matches: &Matches) -> Result<Vec<String>, String> {
+    let mut files = expand_inputs(
+        &matches.free,
+        matches.opt_present("r"),
+        &matches.opt_strs("include"),
+        &matches.opt_strs("exclude"),
+    )?;
+    if let Some(list) = matches.opt_str("files-from") {
+        files.extend(read_file_list(&list)?);
+    }
+    if !files.is_empty() && (matches.opt_present("i") || matches.opt_present("s")) {
+        return Err(String::from(
+            "-i and -s cannot be combined with input files",
+        ));
+    }
+    Ok(files)
+}
+
+fn verbosity(matches: &Matches) -> Result<Verbosity, String> {
+    match (matches.opt_present("q"), matches.opt_present("v")) {
+        (true, true) => Err(String::from("use only one of -q and -v")),
+        (true, false) => Ok(Verbosity::Quiet),
+        (false, true) => Ok(Verbosity::Verbose),
+        (false, false) => Ok(Verbosity::Normal),
+    }
+}
 
-    let mut input_str = String::from("");
-    let mut input: Box<dyn std::io::Read> = match matches.opt_str("i") {
-        None => match matches.opt_str("s") {
-            None => Box::new(std::io::stdin()),
-            Some(json_str) => {
-                input_str.push_str(&json_str);
-                Box::new(input_str.as_bytes())
+// Opens the input given by -i or -s, or else stdin, and returns it with
+// the name of its file.  Input from a file or stdin is decompressed, and
+// with `follow`, read as it grows.  Rather than wait silently for JSON
+// to be typed, this points new users at the help; `-i -` reads from the
+// terminal regardless.
+fn open_input(
+    invocation: &Invocation,
+    files: &[String],
+    follow: bool,
+) -> Result<(Box<dyn Read>, Option<String>), String> {
+    let matches = &invocation.matches;
+    let program = &invocation.args[0];
+    let mut input_filename = None;
+    let mut input: Box<dyn Read> = match (matches.opt_str("i"), matches.opt_str("s")) {
+        (None, Some(json_str)) => return Ok((Box::new(Cursor::new(json_str)), None)),
+        (None, None)
+            if files.is_empty()
+                && !matches.opt_present("interactive")
+                && std::io::stdin().is_terminal() =>
+        {
+            return Err(format!(
+                "Usage: {0} [options] [file ...]\n\n\
+                 No input was given, and stdin is a terminal.  Run `{0} -h` for help,\n\
+                 `{0} --interactive` to paste snippets of JSON and format each one,\n\
+                 or `{0} -i -` to type JSON into the terminal.",
+                program
+            ));
+        }
+        (None, None) => Box::new(std::io::stdin()),
+        (Some(filename), _) if filename == "-" => Box::new(std::io::stdin()),
+        (Some(filename), _) => match File::open(&filename) {
+            Ok(f) => {
+                input_filename = Some(filename);
+                Box::new(f)
             }
+            Err(e) => return Err(format!("{}: {}", filename, e)),
         },
-        Some(filename) => {
-            if filename == *"-" {
-                Box::new(std::io::stdin())
-            } else {
-                input_filename = Some(String::from(&filename));
-                match File::open(&filename) {
-                    Ok(f) => Box::new(f),
-                    Err(e) => {
-                        let mut estr = filename;
-                        estr.push_str(": ");
-                        estr.push_str(&e.to_string());
-                        return Err(estr);
-                    }
+    };
+    if !files.is_empty() {
+        return Ok((input, input_filename));
+    }
+    if follow {
+        input = Box::new(Follow(input));
+    }
+    let input =
+        decompressed(input, input_filename.as_deref()).map_err(|e| match &input_filename {
+            Some(filename) => format!("{}: {}", filename, e),
+            None => e.to_string(),
+        })?;
+    Ok((input, input_filename))
+}
+
+// Opens the -o file, or else stdout, through a pager when it's a
+// terminal and `paged`.  An -o file which is also an input is written as
+// a temporary file, put in its place by finish_output.
+fn open_output(
+    matches: &Matches,
+    paged: bool,
+    inputs: &[String],
+) -> Result<(Box<dyn Write>, Option<OutputFile>), String> {
+    let filename = match matches.opt_str("o") {
+        None => {
+            // Page long output to a terminal, unless it streams on indefinitely
+            let rows = terminal_size::terminal_size().map(|(_, height)| height.0 as usize);
+            return Ok(match rows {
+                Some(rows)
+                    if std::io::stdout().is_terminal()
+                        && paged
+                        && !matches.opt_present("no-pager") =>
+                {
+                    (Box::new(Pager::new(rows)), None)
                 }
-            }
+                _ => (Box::new(std::io::stdout()), None),
+            });
         }
+        Some(filename) if filename == "-" => return Ok((Box::new(std::io::stdout()), None)),
+        Some(filename) => filename,
+    };
+    let created = if inputs.contains(&filename) {
+        create_temp_file(&filename).and_then(|(temp_filename, f)| {
+            let temp_file = Some((temp_filename, f.try_clone()?));
+            Ok((f, temp_file))
+        })
+    } else {
+        File::create(&filename).map(|f| (f, None))
     };
+    match created {
+        Ok((f, temp_file)) => Ok((
+            Box::new(f),
+            Some(OutputFile {
+                filename,
+                temp_file,
+            }),
+        )),
+        Err(e) => Err(format!("{}: {}", filename, e)),
+    }
+}
 
-    let mut output: Box<dyn std::io::Write> = match matches.opt_str("o") {
-        None => Box::new(std::io::stdout()),
-        Some(filename) => {
-            if filename == *"-" {
-                Box::new(std::io::stdout())
-            } else {
-                let mut temp_filename = String::from(&filename);
+// The -o file, and the temporary file written in its place when it's
+// also an input.
+struct OutputFile {
+    filename: String,
+    temp_file: Option<(String, File)>,
+}
 
-                match input_filename {
-                    None => (),
-                    Some(f) => {
-                        if f == filename {
-                            temp_filename.push_str(".tmp");
-                        }
-                    }
-                }
+// Puts the temporary file written for the -o file in its place, if all
+// went well, or else removes it.  The output must have been dropped.
+fn finish_output(output_file: Option<OutputFile>, result: Result<(), Error>) -> Result<(), Error> {
+    match output_file {
+        Some(OutputFile {
+            filename,
+            temp_file: Some((temp_filename, file)),
+        }) => {
+            let result = result.and_then(|_| finish_temp_file(file, &temp_filename, &filename));
+            if result.is_err() {
+                let _ = std::fs::remove_file(&temp_filename);
+            }
+            result
+        }
+        _ => result,
+    }
+}
 
-                output_filename = Some(String::from(&filename));
-                output_temp_filename = Some(String::from(&temp_filename));
+// Whether to color the output, by --color, NO_COLOR, and whether it's
+// going to a terminal.
+fn use_color(matches: &Matches) -> Result<bool, String> {
+    match matches.opt_str("color").as_deref() {
+        None | Some("auto") => {
+            // See https://no-color.org
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            Ok(matches.opt_str("o").is_none()
+                && std::io::stdout().is_terminal()
+                && !no_color
+                && enable_ansi_colors())
+        }
+        Some("always") => {
+            enable_ansi_colors();
+            Ok(true)
+        }
+        Some("never") => Ok(false),
+        Some(other) => Err(format!("invalid color option `{}`", other)),
+    }
+}
 
-                match File::create(&temp_filename) {
-                    Ok(f) => Box::new(f),
-                    Err(e) => {
-                        let mut estr = filename;
-                        estr.push_str(": ");
-                        estr.push_str(&e.to_string());
-                        return Err(estr);
+// Colors JSON written to `output`, tinted by level with --tint-levels.
+fn colored(output: Box<dyn Write>, matches: &Matches) -> Box<dyn Write> {
+    let mut writer = ColorWriter::new(output);
+    if matches.opt_present("tint-levels") {
+        let pointer = matches.opt_str("tint-levels");
+        writer.tint_by_level(pointer.as_deref().unwrap_or("/level"));
+    }
+    Box::new(writer)
+}
+
+// Applies --redact to records, when given.
+fn redactor(matches: &Matches) -> Result<Option<Redactor>, String> {
+    if !matches.opt_present("redact") {
+        return Ok(None);
+    }
+    let mut redactor = Redactor::new();
+    for profile in matches.opt_strs("redact") {
+        match profile.strip_prefix("custom:") {
+            None => redactor.add_profile(&profile)?,
+            Some(filename) => {
+                let patterns = std::fs::read_to_string(filename)
+                    .map_err(|e| format!("{}: {}", filename, e))?;
+                for line in patterns.lines().map(str::trim) {
+                    if !line.is_empty() && !line.starts_with('#') {
+                        redactor
+                            .add_key_pattern(line)
+                            .map_err(|e| format!("{}: {}", filename, e))?;
                     }
                 }
             }
         }
-    };
-
-    let indent = match matches.opt_str("t") {
-        None => String::from("  "),
-        Some(string) => string,
-    };
+    }
+    Ok(Some(redactor))
+}
 
-    let result = if matches.opt_present("m") {
-        let mut xf = jsonxf::Formatter::minimizer();
-        xf.format_stream(&mut input, &mut output)
-    } else {
-        let mut xf = jsonxf::Formatter::pretty_printer();
-        xf.indent = indent;
-        // Ensure a trailing newline, as expected on Unix
-        xf.eager_record_separators = true;
-        xf.format_stream(&mut input, &mut output)
-    };
+// How JSON is to be laid out and accepted, by the options.
+struct Layout {
+    indent: String,
+    indent_auto: bool,
+    eol: Option<&'static str>,
+    eol_auto: bool,
+    ndjson: bool,
+    smart: bool,
+    ascii: bool,
+    canonical: bool,
+    sort_keys: bool,
+    max_depth: Option<usize>,
+    fold_style: FoldStyle,
+    max_inline_array_width: Option<usize>,
+    max_inline_object_width: Option<usize>,
+    strict: bool,
+    jsonc: bool,
+    trailing_commas: bool,
+    keep_comments: bool,
+    follow: bool,
+}
 
-    match output_temp_filename {
-        None => (),
-        Some(temp_filename) => std::fs::rename(temp_filename, output_filename.unwrap()).unwrap(),
+impl Layout {
+    fn from_matches(matches: &Matches) -> Result<Layout, String> {
+        let indent = match (
+            matches.opt_str("t"),
+            matches.opt_str("indent"),
+            matches.opt_present("tab"),
+        ) {
+            (None, None, false) => String::from("  "),
+            (Some(string), None, false) => string,
+            (None, Some(auto), false) if auto == "auto" => String::from("  "),
+            (None, Some(n), false) => match n.parse::<usize>() {
+                Ok(n) => " ".repeat(n),
+                Err(_) => return Err(format!("invalid indent `{}`", n)),
+            },
+            (None, None, true) => String::from("\t"),
+            _ => return Err(String::from("use only one of -t, --indent, and --tab")),
+        };
+        let max_depth = match matches.opt_str("max-depth") {
+            None => None,
+            Some(s) => match s.parse::<usize>() {
+                Ok(depth) => Some(depth),
+                Err(_) => return Err(format!("invalid depth `{}`", s)),
+            },
+        };
+        let inline_width = |name: &str| match matches.opt_str(name) {
+            None => Ok(None),
+            Some(s) => match s.parse::<usize>() {
+                Ok(width) => Ok(Some(width)),
+                Err(_) => Err(format!("invalid width `{}`", s)),
+            },
+        };
+        let fold_style = match matches.opt_str("fold-style").as_deref() {
+            None | Some("minify") => FoldStyle::Minify,
+            Some("ellipsis") => FoldStyle::Ellipsis,
+            Some(other) => return Err(format!("invalid fold style `{}`", other)),
+        };
+        let eol = match matches.opt_str("eol").as_deref() {
+            None => None,
+            Some("lf") => Some("\n"),
+            Some("crlf") => Some("\r\n"),
+            Some("auto") => None,
+            Some(other) => return Err(format!("invalid line ending `{}`", other)),
+        };
+        let layout = Layout {
+            indent,
+            indent_auto: matches.opt_str("indent").as_deref() == Some("auto"),
+            eol,
+            eol_auto: matches.opt_str("eol").as_deref() == Some("auto"),
+            ndjson: matches.opt_present("ndjson"),
+            smart: matches.opt_present("smart"),
+            ascii: matches.opt_present("ascii"),
+            canonical: matches.opt_present("canonical") || matches.opt_present("jcs"),
+            sort_keys: matches.opt_present("sort-keys"),
+            max_depth,
+            fold_style,
+            max_inline_array_width: inline_width("inline-arrays")?,
+            max_inline_object_width: inline_width("inline-objects")?,
+            strict: matches.opt_present("strict"),
+            jsonc: matches.opt_present("jsonc"),
+            trailing_commas: matches.opt_present("trailing-commas"),
+            keep_comments: matches.opt_present("keep-comments"),
+            follow: matches.opt_present("f"),
+        };
+        if layout.keep_comments
+            && (layout.jsonc || layout.strict || layout.sort_keys || layout.canonical)
+        {
+            return Err(String::from(
+                "--keep-comments cannot be combined with --jsonc, --strict, --sort-keys, or --canonical",
+            ));
+        }
+        if layout.smart && (layout.ndjson || matches.opt_present("m")) {
+            return Err(String::from(
+                "--smart cannot be combined with -m or --ndjson",
+            ));
+        }
+        if layout.canonical && (layout.ascii || layout.max_depth.is_some()) {
+            return Err(String::from(
+                "--canonical cannot be combined with --ascii or --max-depth",
+            ));
+        }
+        Ok(layout)
     }
 
-    match result {
-        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
-        Err(e) => Err(e.to_string()),
-        Ok(_) => Ok(()),
+    // Returns a formatter set up by the options, to minimize or not.
+    fn formatter(&self, minimize: bool) -> Formatter {
+        let mut xf = if self.canonical {
+            // Without a trailing newline, so a document's bytes can be signed
+            jsonxf::Formatter::canonicalizer()
+        } else if self.ndjson {
+            jsonxf::Formatter::ndjson()
+        } else if minimize {
+            jsonxf::Formatter::minimizer()
+        } else {
+            let mut xf = jsonxf::Formatter::pretty_printer();
+            xf.indent = self.indent.clone();
+            // Ensure a trailing newline, as expected on Unix
+            xf.eager_record_separators = true;
+            xf
+        };
+        xf.escape_non_ascii = self.ascii;
+        xf.max_depth = self.max_depth;
+        xf.fold_style = self.fold_style;
+        xf.max_inline_array_width = self.max_inline_array_width;
+        xf.max_inline_object_width = self.max_inline_object_width;
+        xf.validate = self.strict;
+        xf.strip_comments = self.jsonc;
+        xf.strip_trailing_commas = self.jsonc || self.trailing_commas;
+        xf.keep_comments = self.keep_comments;
+        xf.sort_keys = self.sort_keys;
+        if let Some(eol) = self.eol {
+            xf.set_line_ending(eol);
+        }
+        if self.follow {
+            // End each record's line as soon as the record is complete
+            xf.eager_record_separators = true;
+        }
+        xf
     }
 }
 
-fn print_help(program_name: &str, opts: &Options) {
-    let desc = "Jsonxf is a JSON transformer.  It provides fast pretty-printing and
-minimizing of JSON-encoded UTF-8 data.";
+// Options for reading input.
+fn input_options(opts: &mut Options) {
+    opts.optopt(
+        "s",
+        "string",
+        "use the given string as input, instead of a file",
+        "str",
+    );
+    opts.optopt(
+        "i",
+        "input",
+        "read input from the given file (default: stdin)",
+        "file",
+    );
+    opts.optflag(
+        "r",
+        "recursive",
+        "format the files in directories given as arguments, and their subdirectories, \
+         skipping hidden ones",
+    );
+    opts.optmulti(
+        "",
+        "include",
+        "with -r, format only files matching the given glob (default: *.json)",
+        "glob",
+    );
+    opts.optmulti(
+        "",
+        "exclude",
+        "skip files matching the given glob when expanding directories and globs",
+        "glob",
+    );
+    opts.optopt(
+        "",
+        "files-from",
+        "also format the files listed in the given file (`-` for stdin), one per line \
+         or separated by NUL bytes, as from `find -print0`",
+        "list",
+    );
+    opts.optflag(
+        "",
+        "fail-fast",
+        "stop at the first file which fails (the default)",
+    );
+    opts.optflag(
+        "",
+        "keep-going",
+        "when a file fails, go on with the rest, then report all failures and exit non-zero",
+    );
+}
 
-    let examples = "
-Pretty-print a string to the terminal, using two spaces to indent:
+// Options for where output goes.
+fn output_options(opts: &mut Options) {
+    opts.optopt(
+        "o",
+        "output",
+        "write output to the given file (default: stdout)",
+        "file",
+    );
+    opts.optopt(
+        "",
+        "color",
+        "highlight JSON output with ANSI colors: auto (default; when writing to a terminal, \
+         and NO_COLOR is not set), always, or never",
+        "auto|always|never",
+    );
+    opts.optflag(
+        "",
+        "no-pager",
+        "don't send output longer than the terminal through $PAGER (default: less -R)",
+    );
+}
 
-    jsonxf -s '{\"a\": {\"b\": 2, \"c\": false}}'
+// Options for coloring JSON output.
+fn tint_options(opts: &mut Options) {
+    opts.optflagopt(
+        "",
+        "tint-levels",
+        "with --color, color whole records red or yellow when the log level at the given \
+         JSON Pointer (default: /level) is an error or warning",
+        "pointer",
+    );
+}
 
-Pretty-print and read a JSON file, using a tab character to indent:
+// Options for formatting many files, or compressing the output.
+fn batch_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "output-template",
+        "write each input file to its own output file, named by the template, \
+         where {dir}, {name}, {stem}, and {ext} are parts of the input's path, \
+         and {{ and }} are braces",
+        "template",
+    );
+    opts.optflagopt(
+        "j",
+        "jobs",
+        "with -w or --output-template, format this many files at once \
+         (default: 1, or one per CPU if given without a number)",
+        "n",
+    );
+    opts.optflag("", "gzip", "compress the output with gzip");
+    opts.optflag("", "zstd", "compress the output with zstd");
+    opts.optflagopt(
+        "",
+        "stats",
+        "after processing, print a summary to stderr: files, records, bytes in and out, \
+         time, and throughput; `--stats=json` prints it as JSON with more input statistics",
+        "json",
+    );
+}
 
-    jsonxf -t $'\\t' <foo.json | less
+// Options for rewriting files.
+fn rewrite_options(opts: &mut Options) {
+    opts.optflag(
+        "w",
+        "write",
+        "format the files given as arguments in place, instead of writing to stdout",
+    );
+    opts.optflagopt(
+        "",
+        "backup",
+        "with -w, first copy each file which will change to its name plus this suffix, \
+         given as --backup=SUFFIX (default: .bak)",
+        "suffix",
+    );
+    opts.optflag(
+        "",
+        "watch",
+        "format the input file again whenever it changes, to the output or, with -w, in place",
+    );
+    opts.optopt(
+        "",
+        "checkpoint",
+        "when formatting one input file to -o, save progress to the given file every \
+         64 MiB of input, and resume from it if it exists; it is removed when done",
+        "file",
+    );
+}
 
-Minimize a file and gzip it:
+// Options for listing the files which formatting would change.
+fn list_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "list",
+        "with -w or --check, print the files which would change, without changing them; \
+         with --check, fail if there are any",
+    );
+    opts.optflag("", "dry-run", "same as --list");
+}
 
-    jsonxf -m <foo.json | gzip -c >foo-min.json.gz
-";
+// Options for how JSON is laid out.
+fn layout_options(opts: &mut Options) {
+    opts.optopt(
+        "t",
+        "indent-string",
+        "use the given string to indent pretty-printed output (default: two spaces)",
+        "tabstr",
+    );
+    opts.optopt(
+        "",
+        "indent",
+        "indent pretty-printed output with the given number of spaces, or as the input \
+         is already indented (`auto`, judging by its first indented line)",
+        "N|auto",
+    );
+    opts.optflag(
+        "",
+        "tab",
+        "indent pretty-printed output with a tab character; `--tab STR`, for spaces and \
+         tabs, is the same as -t STR, as in earlier versions",
+    );
+    opts.optopt(
+        "",
+        "eol",
+        "end lines with a line feed (default) or a carriage return and line feed, or \
+         as the input does (`auto`, judging by its first line)",
+        "lf|crlf|auto",
+    );
+    opts.optflag(
+        "m",
+        "minimize",
+        "minimize JSON instead of pretty-printing it",
+    );
+    opts.optflag(
+        "",
+        "ndjson",
+        "write exactly one minimized record per line, however the input is laid out, \
+         flushing each as it ends",
+    );
+    opts.optflag(
+        "",
+        "smart",
+        "minimize input which looks pretty-printed, and pretty-print the rest \
+         (with -v, report which was done to each file)",
+    );
+    opts.optflag(
+        "",
+        "ascii",
+        "escape non-ASCII characters in strings as \\uXXXX",
+    );
+    opts.optflag(
+        "",
+        "sort-keys",
+        "sort the members of each object by key, for stable output",
+    );
+    opts.optflag(
+        "",
+        "canonical",
+        "write each record in the canonical form of RFC 8785 (JCS), for hashing, signing, \
+         and byte-exact comparison: minimized, with sorted keys and canonical numbers, \
+         records separated by newlines",
+    );
+    opts.optflag("", "jcs", "same as --canonical");
+    opts.optopt(
+        "",
+        "max-depth",
+        "fold arrays and objects nested deeper than this onto one line",
+        "depth",
+    );
+    opts.optopt(
+        "",
+        "fold-style",
+        "how --max-depth folds: `minify` (default), or `ellipsis` to show only `...`",
+        "style",
+    );
+    opts.optopt(
+        "",
+        "inline-arrays",
+        "pretty-print arrays of scalars on one line when they fit in this many columns",
+        "width",
+    );
+    opts.optopt(
+        "",
+        "inline-objects",
+        "pretty-print objects of scalars on one line when they fit in this many columns",
+        "width",
+    );
+    opts.optflag(
+        "",
+        "keep-comments",
+        "keep // and /* */ comments when pretty-printing, each on its own line before \
+         the element after it",
+    );
+}
 
-    let brief = format!("Usage: {} [options]\n\n{}", program_name, desc);
-    print!("{}", opts.usage(&brief));
+// Options for what input is accepted.
+fn parsing_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "strict",
+        "stop at the first syntax error in the input, reporting its line and column, \
+         instead of formatting it anyway",
+    );
+    opts.optflag(
+        "",
+        "jsonc",
+        "drop // and /* */ comments and trailing commas from the input, as in VS Code \
+         settings and tsconfig.json",
+    );
+    opts.optflag(
+        "",
+        "trailing-commas",
+        "accept a comma after the last element of an array or object, dropping it",
+    );
+}
+
+// Options for get.
+fn get_options(opts: &mut Options) {
+    opts.optmulti(
+        "",
+        "get",
+        "print the value at the given JSON Pointer in each record, instead of the record; \
+         may be given more than once",
+        "pointer",
+    );
+    opts.optflag(
+        "",
+        "raw-output",
+        "with --get, print strings as their raw text, without quotes or escapes; there \
+         is no short form, as -r is --recursive",
+    );
+}
+
+// Options for check.
+fn check_options(opts: &mut Options) {
+    opts.optflag(
+        "c",
+        "check",
+        "check that the input is well-formed JSON, printing nothing",
+    );
+}
+
+// Options for diff.
+fn diff_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "diff",
+        "compare two JSON files structurally, printing each difference, and exit with \
+         status 1 if there are any",
+    );
+    opts.optflag(
+        "",
+        "assert-equal",
+        "check that two JSON files hold equal values, ignoring whitespace, printing \
+         nothing if so; otherwise report the first difference and exit with status 1",
+    );
+    opts.optflag(
+        "",
+        "ignore-order",
+        "with --diff or --assert-equal, treat arrays with the same elements in any order \
+         as equal",
+    );
+    opts.optflag(
+        "",
+        "ignore-key-order",
+        "with --assert-equal, treat objects with the same members in any order as equal",
+    );
+}
+
+// Options for merge.
+fn merge_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "merge",
+        "deep-merge the given files, each over the ones before it, and format the result",
+    );
+    opts.optopt(
+        "",
+        "strategy",
+        "with --merge, how to resolve values which are not both objects: last-wins \
+         (default), error, or concat-arrays",
+        "strategy",
+    );
+}
+
+// Options for split.
+fn split_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "split",
+        "divide the input into parts, each written to a file named by --out, never \
+         breaking a record; limit parts with --records, --bytes, or --array-elements",
+    );
+    opts.optopt(
+        "",
+        "out",
+        "with --split, the name of each part, where `{seq}` is its number from 0000",
+        "template",
+    );
+    opts.optopt(
+        "",
+        "bytes",
+        "with --split, the most bytes to write to each part, e.g. 500M",
+        "size",
+    );
+    opts.optopt(
+        "",
+        "array-elements",
+        "with --split, divide top-level arrays into arrays of at most this many elements",
+        "n",
+    );
+}
+
+// Options for choosing records, or splitting by them.
+fn records_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "records",
+        "format only the given record, or inclusive range of records, counting from zero; \
+         with --split, the most records to write to each part",
+        "n|n-m|n-",
+    );
+}
+
+// Options for schema.
+fn schema_options(opts: &mut Options) {
+    opts.optflag(
+        "",
+        "schema",
+        "describe each distinct path in the input: types, count, presence, and examples",
+    );
+    opts.optopt(
+        "",
+        "format",
+        "with --schema, write tab-separated values (default), an aligned table with a \
+         header, or a draft JSON Schema document",
+        "tsv|table|schema",
+    );
+}
+
+// Options for changing each record.
+fn transform_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "patch",
+        "apply the JSON Patch (RFC 6902) in the given file to each input record",
+        "file",
+    );
+    opts.optopt(
+        "",
+        "merge-patch",
+        "apply the JSON Merge Patch (RFC 7386) in the given file to each input record",
+        "file",
+    );
+    opts.optopt(
+        "",
+        "grep",
+        "keep only the records whose minimized text matches the given regular expression",
+        "pattern",
+    );
+    opts.optflag(
+        "",
+        "invert-match",
+        "with --grep, keep only the records which don't match",
+    );
+    opts.optopt(
+        "",
+        "expr",
+        "transform each record with a jq-like expression: `.a.b`, `.items[]`, \
+         `del(.a)`, or `set(.a, JSON)`, joined with `|`",
+        "expr",
+    );
+}
+
+// Options for redacting values.
+fn redact_options(opts: &mut Options) {
+    opts.optmulti(
+        "",
+        "redact",
+        "replace sensitive values with \"[REDACTED]\": `credentials` (passwords, tokens, \
+         keys), `pii` (emails, phone numbers, SSNs), `secrets` (whatever --find-secrets \
+         finds), or `custom:FILE` for a file of key patterns, one regular expression per \
+         line; may be given more than once",
+        "profile",
+    );
+}
+
+// Options for converting to and from other formats.
+fn conversion_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "from",
+        "convert input in the given format to JSON before formatting",
+        "csv|tsv|yaml|msgpack|cbor",
+    );
+    opts.optopt(
+        "",
+        "to",
+        "convert output to the given format instead of formatting JSON",
+        "toml|msgpack|cbor|rust|rust-string|html|markdown",
+    );
+    opts.optflag(
+        "",
+        "html-stylesheet",
+        "with --to html, begin with a <style> element of default colors",
+    );
+    opts.optopt(
+        "",
+        "details-depth",
+        "with --to markdown, collapse top-level members nested deeper than this",
+        "DEPTH",
+    );
+    opts.optflag(
+        "",
+        "infer-types",
+        "with --from csv or tsv, convert numbers, booleans, and nulls from text",
+    );
+    opts.optflag(
+        "",
+        "array",
+        "with --from csv or tsv, output one array rather than a stream of objects",
+    );
+    opts.optflag(
+        "g",
+        "gron",
+        "print each value as a greppable assignment statement",
+    );
+    opts.optflag(
+        "u",
+        "ungron",
+        "reassemble assignment statements from --gron into JSON",
+    );
+}
+
+// Options for reporting on the input instead of formatting it.
+fn report_options(opts: &mut Options) {
+    opts.optflag(
+        "p",
+        "paths",
+        "list each distinct path in the input, with array indices as []",
+    );
+    opts.optflag(
+        "",
+        "counts",
+        "with --paths, show how many values were at each path",
+    );
+    opts.optflag(
+        "",
+        "types",
+        "with --paths, show the types of values at each path",
+    );
+    opts.optflag(
+        "",
+        "key-stats",
+        "profile each distinct path in the input: count, types, number min/max/mean, and string min/max length",
+    );
+    opts.optflagopt(
+        "",
+        "hash",
+        "print a hash of the input's canonical form, as --canonical writes it: sha256 \
+         (default), sha512, or xxh3, given as --hash=ALGORITHM",
+        "algorithm",
+    );
+    opts.optflagopt(
+        "",
+        "hash-records",
+        "print a hash of each record's canonical form, one per line: sha256 (default), \
+         sha512, or xxh3, given as --hash-records=ALGORITHM",
+        "algorithm",
+    );
+    opts.optflag(
+        "",
+        "with-records",
+        "with --hash-records, follow each hash with a tab and the record's canonical form",
+    );
+    opts.optflag(
+        "",
+        "find-secrets",
+        "print the record number, JSON Pointer, and kind of each string which looks like \
+         a credential, such as an access key, token, or long random-looking run of base64 \
+         or hex, separated by tabs; exits with status 1 if any are found",
+    );
+    opts.optopt(
+        "",
+        "index",
+        "write the offset and length of each record to the given file, or \
+         in place of formatted output if `-`",
+        "file",
+    );
+    opts.optopt(
+        "",
+        "index-of",
+        "with --index, index the input, the output (default), or both",
+        "input|output|both",
+    );
+    opts.optopt(
+        "",
+        "path-index",
+        "instead of formatting, list the location of each value down to the given depth",
+        "depth",
+    );
+    opts.optopt(
+        "",
+        "seek",
+        "print only the value at the given JSON Pointer, optionally preceded by a record \
+         number and `:`, using the --seek-index file to skip the rest of the input file",
+        "[record:]pointer",
+    );
+    opts.optopt(
+        "",
+        "seek-index",
+        "with --seek, a path index of the input file from --path-index",
+        "file",
+    );
+}
+
+// Options for formatting streams and checking the formatting.
+fn stream_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "on-error",
+        "format line-delimited input a line at a time, and copy through, skip, or stop at \
+         lines which aren't JSON",
+        "pass|skip|fail",
+    );
+    opts.optflag(
+        "",
+        "docker-logs",
+        "unwrap container logs, in Docker's JSON format or the CRI format used by \
+         Kubernetes, and format the JSON messages inside them",
+    );
+    opts.optflag("", "k8s", "same as --docker-logs");
+    opts.optflag(
+        "",
+        "mixed",
+        "format the JSON objects and arrays in input which mixes them with other text, \
+         like a build log, and copy the text through as it is",
+    );
+    opts.optflag(
+        "",
+        "verify",
+        "check that the output differs from the input only in whitespace, failing at the \
+         first other difference; for input which may not be quite valid JSON",
+    );
+    opts.optflag(
+        "",
+        "audit",
+        "check, as --verify does, that only whitespace was changed, reporting the offset, \
+         line, and column of the first input byte not carried through unchanged",
+    );
+    opts.optflag(
+        "",
+        "cross-check",
+        "check with serde_json that the output parses to the same values as the input, \
+         writing nothing for a file which fails (requires the `verify` feature)",
+    );
+    opts.optflag(
+        "",
+        "interactive",
+        "read snippets pasted into the terminal, formatting each at a blank line or Ctrl-D; \
+         Ctrl-D with nothing pasted quits",
+    );
+    opts.optflag(
+        "f",
+        "follow",
+        "keep reading the input as it grows, like `tail -f`, formatting records as they arrive",
+    );
+}
+
+// Options for every command.
+fn general_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "errors",
+        "how to write errors and warnings to stderr: `text` (default), or `jsonl` for one \
+         JSON object per line, with the file, offset, line, column, kind, and message",
+        "text|jsonl",
+    );
+    opts.optflag(
+        "q",
+        "quiet",
+        "don't report non-fatal problems, such as files which failed while watching",
+    );
+    opts.optflag(
+        "v",
+        "verbose",
+        "report each file as it is formatted, and a summary at the end, to stderr",
+    );
+    opts.optflag(
+        "",
+        "no-config",
+        "ignore ~/.config/jsonxf/config.toml and .jsonxf.toml files",
+    );
+    opts.optflag("h", "help", "print this message and exit");
+}
+
+// The mode chosen by the options for fmt.  check_modes has let through
+// only modes which honour each other, and the one first in MODES is what
+// honours the rest.
+fn fmt_mode(invocation: &Invocation) -> Result<Mode, String> {
+    let matches = &invocation.matches;
+    let is_algorithm = |arg: &str| arg.parse::<Algorithm>().is_ok();
+    for option in ["--hash", "--hash-records"] {
+        if let Some(name) = detached_value(&invocation.args, option, is_algorithm) {
+            return Err(format!("give the algorithm as {}={}", option, name));
+        }
+    }
+    let algorithm = |name: &str| match matches.opt_str(name) {
+        None => Ok(Algorithm::default()),
+        Some(name) => name.parse::<Algorithm>(),
+    };
+    let depth = |name: &str| match matches.opt_str(name) {
+        None => Ok(None),
+        Some(s) => match s.parse::<usize>() {
+            Ok(depth) => Ok(Some(depth)),
+            Err(_) => Err(format!("invalid depth `{}`", s)),
+        },
+    };
+    // Reordering or rewriting what's written is more than whitespace
+    let rewrites = ["ascii", "max-depth", "sort-keys", "canonical", "jcs"]
+        .iter()
+        .any(|name| matches.opt_present(name));
+    let strict = matches.opt_present("strict");
+    let given = MODES
+        .iter()
+        .find_map(|(options, _)| options.iter().find(|name| matches.opt_present(name)));
+    let mode = match given.copied() {
+        None | Some("checkpoint") => Mode::Format,
+        Some("from") => {
+            let csv = CsvOptions {
+                infer_types: matches.opt_present("infer-types"),
+                array: matches.opt_present("array"),
+                ..CsvOptions::default()
+            };
+            match matches.opt_str("from").unwrap_or_default().as_str() {
+                "csv" => Mode::FromCsv(csv),
+                "tsv" => Mode::FromCsv(CsvOptions {
+                    delimiter: b'\t',
+                    ..csv
+                }),
+                "yaml" if !cfg!(feature = "yaml") => {
+                    return Err(String::from(
+                        "YAML input requires jsonxf to be built with the `yaml` feature",
+                    ))
+                }
+                "yaml" => Mode::FromYaml,
+                "msgpack" if !cfg!(feature = "msgpack") => return Err(String::from(NO_MSGPACK)),
+                "msgpack" => Mode::FromMsgpack,
+                "cbor" => Mode::FromCbor,
+                other => return Err(format!("unknown input format `{}`", other)),
+            }
+        }
+        Some("to") => match matches.opt_str("to").unwrap_or_default().as_str() {
+            "toml" => Mode::ToToml,
+            "msgpack" if !cfg!(feature = "msgpack") => return Err(String::from(NO_MSGPACK)),
+            "msgpack" => Mode::ToMsgpack,
+            "cbor" => Mode::ToCbor,
+            "rust" => Mode::ToRust { string: false },
+            "rust-string" => Mode::ToRust { string: true },
+            "html" => Mode::ToHtml {
+                stylesheet: matches.opt_present("html-stylesheet"),
+            },
+            "markdown" => Mode::ToMarkdown {
+                details_depth: depth("details-depth")?,
+            },
+            other => return Err(format!("unknown output format `{}`", other)),
+        },
+        Some("gron") => Mode::Gron,
+        Some("ungron") => Mode::Ungron,
+        Some("paths") => Mode::Paths {
+            counts: matches.opt_present("counts"),
+            types: matches.opt_present("types"),
+        },
+        Some("path-index") => Mode::PathIndex(depth("path-index")?.unwrap_or_default()),
+        Some("seek") => {
+            let index = matches
+                .opt_str("seek-index")
+                .ok_or("--seek requires --seek-index")?;
+            let input = matches
+                .opt_str("i")
+                .ok_or("--seek requires an input file (-i)")?;
+            let (record, pointer) = parse_seek_target(&matches.opt_str("seek").unwrap())?;
+            Mode::Seek {
+                record,
+                pointer,
+                input,
+                index,
+            }
+        }
+        Some("find-secrets") => Mode::FindSecrets(SecretScanner::new()),
+        Some("hash") => Mode::Hash(algorithm("hash")?),
+        Some("hash-records") => Mode::HashRecords {
+            algorithm: algorithm("hash-records")?,
+            with_records: matches.opt_present("with-records"),
+        },
+        Some("key-stats") => Mode::KeyStats,
+        Some("patch") | Some("merge-patch") | Some("grep") | Some("expr") | Some("redact") => {
+            transform(matches)?
+        }
+        Some("records") => Mode::Records(parse_record_range(&matches.opt_str("records").unwrap())?),
+        Some("index") => Mode::Index {
+            // `--index -` writes the index in place of the formatted output
+            file: match matches.opt_str("index") {
+                Some(filename) if filename != "-" => match File::create(&filename) {
+                    Ok(f) => Some(f),
+                    Err(e) => return Err(format!("{}: {}", filename, e)),
+                },
+                _ => None,
+            },
+            of: match matches.opt_str("index-of") {
+                None => IndexOf::default(),
+                Some(s) => s.parse::<IndexOf>()?,
+            },
+        },
+        Some("docker-logs") | Some("k8s") => Mode::ContainerLogs,
+        Some("mixed") | Some("on-error") if strict => {
+            return Err(String::from(
+                "--strict cannot be combined with --mixed or --on-error",
+            ))
+        }
+        Some("mixed") => Mode::Mixed,
+        Some("on-error") => match matches.opt_str("on-error").unwrap_or_default().as_str() {
+            "pass" => Mode::OnError(OnError::Pass),
+            "skip" => Mode::OnError(OnError::Skip),
+            "fail" => Mode::OnError(OnError::Fail),
+            other => return Err(format!("invalid --on-error policy `{}`", other)),
+        },
+        Some("verify") if rewrites => return Err(String::from(
+            "--verify cannot be combined with --ascii, --max-depth, --sort-keys, or --canonical",
+        )),
+        Some("verify") => Mode::Verify,
+        Some("audit") if rewrites => {
+            return Err(String::from(
+                "--audit cannot be combined with --ascii, --max-depth, --sort-keys, or --canonical",
+            ))
+        }
+        Some("audit") => Mode::Audit,
+        Some("cross-check") if !cfg!(feature = "verify") => {
+            return Err(String::from(
+                "--cross-check requires jsonxf to be built with the `verify` feature",
+            ))
+        }
+        Some("cross-check") if matches.opt_present("max-depth") => {
+            return Err(String::from(
+                "--cross-check cannot be combined with --max-depth",
+            ))
+        }
+        Some("cross-check") => Mode::CrossCheck,
+        Some("interactive") => Mode::Interactive,
+        Some("follow") => Mode::Follow,
+        Some(other) => unreachable!("--{} chooses another command", other),
+    };
+    Ok(mode)
+}
+
+const NO_MSGPACK: &str =
+    "MessagePack conversion requires jsonxf to be built with the `msgpack` feature";
+
+// The changes made to each record by --patch or --merge-patch, --grep,
+// --expr, and --redact.  --expr alone is applied to the token stream.
+fn transform(matches: &Matches) -> Result<Mode, String> {
+    let expr = match matches.opt_str("expr") {
+        None => None,
+        Some(expr) => Some(expr.parse::<Expr>()?),
+    };
+    let read_patch = |name: &str| match matches.opt_str(name) {
+        None => Ok(None),
+        Some(filename) => read_json_file(&filename).map(Some),
+    };
+    let transform = Transform {
+        patch: read_patch("patch")?,
+        merge_patch: read_patch("merge-patch")?,
+        grep: match matches.opt_str("grep") {
+            None => None,
+            Some(pattern) => match Regex::new(&pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => return Err(format!("invalid --grep pattern: {}", e)),
+            },
+        },
+        invert_match: matches.opt_present("invert-match"),
+        expr: None,
+        redactor: redactor(matches)?,
+    };
+    match expr {
+        Some(expr)
+            if transform.patch.is_none()
+                && transform.merge_patch.is_none()
+                && transform.grep.is_none()
+                && transform.redactor.is_none() =>
+        {
+            Ok(Mode::Expr(expr))
+        }
+        expr => Ok(Mode::Transform(Transform { expr, ..transform })),
+    }
+}
+
+// What's done to each input.
+enum Mode {
+    Format,
+    Check {
+        stripped: bool,
+    },
+    Get {
+        pointers: Vec<String>,
+        raw: bool,
+    },
+    FromCsv(CsvOptions),
+    FromYaml,
+    FromMsgpack,
+    FromCbor,
+    ToToml,
+    ToMsgpack,
+    ToCbor,
+    ToRust {
+        string: bool,
+    },
+    ToHtml {
+        stylesheet: bool,
+    },
+    ToMarkdown {
+        details_depth: Option<usize>,
+    },
+    Gron,
+    Ungron,
+    Paths {
+        counts: bool,
+        types: bool,
+    },
+    PathIndex(usize),
+    Seek {
+        record: u64,
+        pointer: String,
+        input: String,
+        index: String,
+    },
+    FindSecrets(SecretScanner),
+    Hash(Algorithm),
+    HashRecords {
+        algorithm: Algorithm,
+        with_records: bool,
+    },
+    Schema(SchemaFormat),
+    KeyStats,
+    Transform(Transform),
+    Expr(Expr),
+    Records(RangeInclusive<u64>),
+    Index {
+        file: Option<File>,
+        of: IndexOf,
+    },
+    ContainerLogs,
+    Mixed,
+    OnError(OnError),
+    Verify,
+    Audit,
+    CrossCheck,
+    Interactive,
+    Follow,
+}
+
+// The changes made to each record, in order: --grep leaves it out, or
+// --patch or --merge-patch is applied, then --expr, and --redact to each
+// result.
+struct Transform {
+    grep: Option<Regex>,
+    invert_match: bool,
+    patch: Option<Value>,
+    merge_patch: Option<Value>,
+    expr: Option<Expr>,
+    redactor: Option<Redactor>,
+}
+
+impl Transform {
+    fn apply(&self, mut record: Value) -> Result<Vec<Value>, String> {
+        if let Some(regex) = &self.grep {
+            if regex.is_match(&record.to_string()) == self.invert_match {
+                return Ok(vec![]);
+            }
+        }
+        if let Some(patch) = &self.patch {
+            jsonxf::patch::apply_patch_value(&mut record, patch)?;
+        }
+        if let Some(merge_patch) = &self.merge_patch {
+            jsonxf::patch::apply_merge_patch_value(&mut record, merge_patch);
+        }
+        let mut results = match &self.expr {
+            None => vec![record],
+            Some(expr) => expr.apply(record)?,
+        };
+        if let Some(redactor) = &self.redactor {
+            for result in &mut results {
+                redactor.redact_value(result);
+            }
+        }
+        Ok(results)
+    }
+}
+
+// What the modes find across the inputs, to report once they're done.
+#[derive(Default)]
+struct Tally {
+    // Lines which couldn't be formatted, with --on-error
+    bad_lines: AtomicUsize,
+    // Strings found with --find-secrets
+    secrets: AtomicUsize,
+    // The file being read, to name when there are several
+    file: Mutex<Option<String>>,
+}
+
+impl Mode {
+    // Whether the output is JSON, to be colored.
+    fn writes_json(&self) -> bool {
+        !matches!(
+            self,
+            Mode::Check { .. }
+                | Mode::ToToml
+                | Mode::ToMsgpack
+                | Mode::ToCbor
+                | Mode::ToRust { .. }
+                | Mode::ToHtml { .. }
+                | Mode::ToMarkdown { .. }
+                | Mode::Gron
+                | Mode::Paths { .. }
+                | Mode::PathIndex(_)
+                | Mode::FindSecrets(_)
+                | Mode::Hash(_)
+                | Mode::HashRecords { .. }
+                | Mode::Schema(_)
+                | Mode::KeyStats
+                | Mode::Index { .. }
+        )
+    }
+
+    fn run(
+        &self,
+        xf: &mut Formatter,
+        mut input: &mut dyn Read,
+        mut output: &mut dyn Write,
+        tally: &Tally,
+    ) -> Result<(), Error> {
+        match self {
+            Mode::Format => xf.format_stream(&mut input, &mut output),
+            Mode::Check { stripped: true } => {
+                // Check what's left once comments and commas are dropped
+                xf.validate = true;
+                xf.format_stream(&mut input, &mut std::io::sink())
+            }
+            Mode::Check { stripped: false } => {
+                jsonxf::validate::Validator::new().validate_stream(&mut input)
+            }
+            Mode::Get { pointers, raw } => get_values(xf, input, output, pointers, *raw),
+            Mode::FromCsv(options) => {
+                let mut writer = FormattingWriter { xf, output };
+                jsonxf::csv_to_json_stream(&mut input, &mut writer, *options)
+                    .and_then(|_| writer.finish())
+            }
+            Mode::FromYaml => {
+                let mut yaml_str = String::new();
+                input
+                    .read_to_string(&mut yaml_str)
+                    .and_then(|_| {
+                        yaml_to_json(&yaml_str).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .and_then(|json_str| xf.format_stream(&mut json_str.as_bytes(), &mut output))
+            }
+            Mode::FromMsgpack => {
+                let mut writer = FormattingWriter { xf, output };
+                from_msgpack_stream(&mut input, &mut writer).and_then(|_| writer.finish())
+            }
+            Mode::FromCbor => {
+                let mut writer = FormattingWriter { xf, output };
+                jsonxf::from_cbor_stream(&mut input, &mut writer).and_then(|_| writer.finish())
+            }
+            Mode::ToToml => {
+                let mut json_str = String::new();
+                input
+                    .read_to_string(&mut json_str)
+                    .and_then(|_| {
+                        jsonxf::to_toml(&json_str)
+                            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .and_then(|toml_str| output.write_all(toml_str.as_bytes()))
+            }
+            Mode::ToMsgpack => to_msgpack_stream(&mut input, &mut output),
+            Mode::ToCbor => jsonxf::to_cbor_stream(&mut input, &mut output),
+            Mode::ToRust { string } => {
+                let mut json_str = String::new();
+                input
+                    .read_to_string(&mut json_str)
+                    .and_then(|_| {
+                        let rendered = if *string {
+                            jsonxf::to_rust_string(&json_str, xf)
+                        } else {
+                            jsonxf::to_json_macro(&json_str, xf)
+                        };
+                        rendered.map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .and_then(|rust_str| writeln!(output, "{}", rust_str))
+            }
+            Mode::ToHtml { stylesheet } => {
+                let stylesheet = if *stylesheet {
+                    jsonxf::html::STYLESHEET
+                } else {
+                    ""
+                };
+                output.write_all(stylesheet.as_bytes()).and_then(|_| {
+                    let mut writer = HtmlWriter::new(&mut output);
+                    xf.format_stream(&mut input, &mut writer)
+                        .and_then(|_| writer.finish())
+                })
+            }
+            Mode::ToMarkdown { details_depth } => {
+                let mut json_str = String::new();
+                input
+                    .read_to_string(&mut json_str)
+                    .and_then(|_| {
+                        jsonxf::to_markdown(&json_str, xf, *details_depth)
+                            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .and_then(|markdown| output.write_all(markdown.as_bytes()))
+            }
+            Mode::Gron => jsonxf::gron_stream(&mut input, &mut output),
+            Mode::Ungron => {
+                let mut gron_str = String::new();
+                input
+                    .read_to_string(&mut gron_str)
+                    .and_then(|_| {
+                        jsonxf::ungron(&gron_str).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+                    })
+                    .and_then(|json_str| xf.format_stream(&mut json_str.as_bytes(), &mut output))
+            }
+            Mode::Paths { counts, types } => {
+                jsonxf::list_paths_stream(&mut input).and_then(|paths| {
+                    for info in paths {
+                        let mut line = info.path;
+                        if *counts {
+                            line.push_str(&format!("\t{}", info.count));
+                        }
+                        if *types {
+                            line.push_str(&format!("\t{}", info.types.join(",")));
+                        }
+                        writeln!(output, "{}", line)?;
+                    }
+                    Ok(())
+                })
+            }
+            Mode::PathIndex(depth) => {
+                let mut writer = BufWriter::new(&mut output);
+                jsonxf::index::index_paths_stream(&mut input, *depth, &mut |entry| {
+                    writeln!(writer, "{}", entry)
+                })
+                .and_then(|_| writer.flush())
+            }
+            Mode::Seek {
+                record,
+                pointer,
+                input,
+                index,
+            } => File::open(index)
+                .map_err(|e| Error::new(e.kind(), format!("{}: {}", index, e)))
+                .and_then(|index| {
+                    jsonxf::index::extract_indexed(
+                        &mut File::open(input)?,
+                        &mut BufReader::new(index),
+                        *record,
+                        pointer,
+                    )
+                })
+                .and_then(|found| match found {
+                    Some(value) => xf.format_stream(&mut value.to_string().as_bytes(), &mut output),
+                    None => Err(Error::new(
+                        ErrorKind::NotFound,
+                        format!("no value at {} in record {}", pointer, record),
+                    )),
+                }),
+            Mode::FindSecrets(scanner) => {
+                let mut writer = BufWriter::new(&mut output);
+                let mut n = 0;
+                jsonxf::value::read_records(&mut input, &mut |record| {
+                    for secret in scanner.scan(&record) {
+                        tally.secrets.fetch_add(1, Ordering::Relaxed);
+                        // Name the file as grep does, when there are several
+                        if let Some(file) = tally.file.lock().unwrap().as_ref() {
+                            write!(writer, "{}\t", file)?;
+                        }
+                        writeln!(writer, "{}\t{}\t{}", n, secret.pointer, secret.kind)?;
+                    }
+                    n += 1;
+                    Ok(())
+                })
+                .and_then(|_| writer.flush())
+            }
+            Mode::Hash(algorithm) => jsonxf::canonical_hash_stream(&mut input, *algorithm)
+                .and_then(|digest| writeln!(output, "{}", digest)),
+            Mode::HashRecords {
+                algorithm,
+                with_records,
+            } => {
+                let mut writer = BufWriter::new(&mut output);
+                jsonxf::hash_records_stream(&mut input, *algorithm, &mut |digest, record| {
+                    if *with_records {
+                        writeln!(writer, "{}\t{}", digest, record)
+                    } else {
+                        writeln!(writer, "{}", digest)
+                    }
+                })
+                .and_then(|_| writer.flush())
+            }
+            Mode::Schema(format) => write_schema(xf, input, output, *format),
+            Mode::KeyStats => write_key_stats(input, output),
+            Mode::Transform(transform) => {
+                transform_records(xf, input, output, |record| transform.apply(record))
+            }
+            Mode::Expr(expr) => {
+                let mut writer = FormattingWriter { xf, output };
+                expr.apply_stream(&mut input, &mut writer)
+                    .and_then(|_| writer.finish())
+            }
+            Mode::Records(records) => {
+                jsonxf::split::select_records(xf, &mut input, &mut output, records.clone())
+            }
+            Mode::Index { file: None, of } => {
+                let mut sink = std::io::sink();
+                jsonxf::index::format_indexed(xf, &mut input, &mut sink, &mut output, *of)
+            }
+            Mode::Index {
+                file: Some(index),
+                of,
+            } => {
+                let mut index: &File = index;
+                jsonxf::index::format_indexed(xf, &mut input, &mut output, &mut index, *of)
+            }
+            Mode::ContainerLogs => format_container_logs(xf, input, output),
+            Mode::Mixed => jsonxf::mixed::format_mixed_stream(&mut input, &mut output, xf),
+            Mode::OnError(on_error) => format_lines(xf, input, output, *on_error).map(|n| {
+                tally.bad_lines.fetch_add(n, Ordering::Relaxed);
+            }),
+            Mode::Verify => jsonxf::verify::format_verified(xf, &mut input, &mut output),
+            Mode::Audit => jsonxf::verify::format_audited(xf, &mut input, &mut output),
+            Mode::CrossCheck => format_cross_checked(xf, input, output),
+            Mode::Interactive => format_interactive(xf, input, output),
+            Mode::Follow => format_following(xf, input, output),
+        }
+    }
+}
+
+// Writes the paths in the input, with their counts, types, presence, and
+// examples, or a JSON Schema for it.
+fn write_schema(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    format: SchemaFormat,
+) -> Result<(), Error> {
+    if format == SchemaFormat::JsonSchema {
+        return jsonxf::schema::json_schema_stream(input)
+            .and_then(|schema| xf.format_stream(&mut schema.to_string().as_bytes(), output));
+    }
+    let schema = jsonxf::infer_schema_stream(input)?;
+    let rows: Vec<Vec<String>> = schema
+        .into_iter()
+        .map(|path| {
+            let types: Vec<String> = path
+                .types
+                .iter()
+                .map(|(t, n)| format!("{}({})", t, n))
+                .collect();
+            let presence = match path.required {
+                Some(true) => "required",
+                Some(false) => "optional",
+                None => "-",
+            };
+            vec![
+                path.path,
+                path.count.to_string(),
+                types.join("|"),
+                String::from(presence),
+                path.examples.join(", "),
+            ]
+        })
+        .collect();
+    if format == SchemaFormat::Table {
+        write_table(
+            output,
+            &["PATH", "COUNT", "TYPES", "PRESENCE", "EXAMPLES"],
+            &rows,
+        )
+    } else {
+        rows.iter()
+            .try_for_each(|row| writeln!(output, "{}", row.join("\t")))
+    }
+}
+
+// Writes each path in the input with its count, types, and the range of
+// its numbers and lengths.
+fn write_key_stats(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    fn show<T: ToString>(x: Option<T>) -> String {
+        x.map_or(String::from("-"), |x| x.to_string())
+    }
+    for path in jsonxf::key_stats_stream(input)? {
+        let types: Vec<String> = path
+            .types
+            .iter()
+            .map(|(t, n)| format!("{}({})", t, n))
+            .collect();
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            path.path,
+            path.count,
+            types.join("|"),
+            show(path.min),
+            show(path.max),
+            show(path.mean),
+            show(path.min_length),
+            show(path.max_length)
+        )?;
+    }
+    Ok(())
+}
+
+// Does `mode` to each input, writing the results to the output, or to
+// the input files in place, or to the files --output-template names.
+fn format_inputs(invocation: Invocation, mode: Mode) -> Result<(), Diagnostic> {
+    let matches = &invocation.matches;
+    let in_place = matches.opt_present("w");
+    let files = input_files(matches)?;
+    if matches.opt_present("files-from") && files.is_empty() {
+        // Nothing to do, as for `find | xargs` with no matches
+        return Ok(());
+    }
+    let interactive = matches!(mode, Mode::Interactive);
+    if interactive && (!files.is_empty() || matches.opt_present("i") || matches.opt_present("s")) {
+        return Err(String::from("--interactive reads only from stdin").into());
+    }
+    let verbosity = verbosity(matches)?;
+    let started = Instant::now();
+    let follow = matches.opt_present("f");
+    if interactive && in_place {
+        return Err(String::from("--interactive cannot be combined with -w").into());
+    }
+    if follow && (in_place || files.len() > 1 || matches.opt_present("s")) {
+        return Err(String::from("-f follows only one input file or stdin").into());
+    }
+    let checkpoint = matches.opt_str("checkpoint");
+    if checkpoint.is_some() {
+        let input_file = matches.opt_str("i").filter(|f| f != "-");
+        let one_input = matches!((files.as_slice(), input_file), ([_], None) | ([], Some(_)));
+        if !one_input || matches.opt_str("o").is_none_or(|f| f == "-") {
+            return Err(String::from("--checkpoint requires one input file and -o").into());
+        }
+        let unsupported = NOT_CHECKPOINTED
+            .iter()
+            .find(|name| matches.opt_present(name))
+            .map(|name| format!("--{}", name))
+            .or_else(|| {
+                ["eol", "indent"]
+                    .iter()
+                    .find(|name| matches.opt_str(name).as_deref() == Some("auto"))
+                    .map(|name| format!("--{} auto", name))
+            });
+        if let Some(option) = unsupported {
+            return Err(format!("--checkpoint cannot be combined with {}", option).into());
+        }
+    }
+    let watch = matches.opt_present("watch");
+    if watch && !in_place {
+        let input_file = matches.opt_str("i").filter(|f| f != "-");
+        let watched = match (files.as_slice(), input_file) {
+            ([filename], None) => filename.clone(),
+            ([], Some(filename)) => filename,
+            _ => return Err(String::from("--watch requires one input file, or -w").into()),
+        };
+        if matches.opt_str("o") == Some(watched) {
+            return Err(
+                String::from("--watch cannot write to its input file; use -w instead").into(),
+            );
+        }
+    }
+    let backup_suffix = if matches.opt_present("backup") {
+        if !in_place {
+            return Err(String::from("--backup requires -w").into());
+        }
+        let is_suffix = |arg: &str| arg.starts_with('.') || arg.starts_with('~');
+        if let Some(suffix) = detached_value(&invocation.args, "--backup", is_suffix) {
+            return Err(format!("give the suffix as --backup={}", suffix).into());
+        }
+        Some(
+            matches
+                .opt_str("backup")
+                .unwrap_or_else(|| String::from(".bak")),
+        )
+    } else {
+        None
+    };
+    let dry_run = matches.opt_present("list") || matches.opt_present("dry-run");
+    let checking = matches.opt_present("check");
+    if dry_run && (!(in_place || checking) || watch) {
+        return Err(String::from("--list requires -w or --check, without --watch").into());
+    }
+    if dry_run && files.is_empty() {
+        return Err(String::from("--list requires one or more files").into());
+    }
+    if watch && follow {
+        return Err(String::from("--watch cannot be combined with -f").into());
+    }
+    if in_place {
+        if files.is_empty() {
+            return Err(String::from("-w requires one or more files").into());
+        }
+        if matches.opt_present("i") || matches.opt_present("o") || matches.opt_present("s") {
+            return Err(String::from("-w cannot be combined with -i, -o, or -s").into());
+        }
+    }
+    let output_template = matches.opt_str("output-template");
+    if matches!(mode, Mode::FindSecrets(_)) && (in_place || output_template.is_some()) {
+        return Err(
+            String::from("--find-secrets cannot be combined with -w or --output-template").into(),
+        );
+    }
+    if output_template.is_some() {
+        if files.is_empty() {
+            return Err(String::from("--output-template requires one or more files").into());
+        }
+        if in_place || follow || watch || matches.opt_present("o") {
+            return Err(String::from(
+                "--output-template cannot be combined with -w, -o, -f, or --watch",
+            )
+            .into());
+        }
+    }
+    if matches.opt_present("fail-fast") && matches.opt_present("keep-going") {
+        return Err(String::from("use only one of --fail-fast and --keep-going").into());
+    }
+    let failures = Failures::new(matches.opt_present("keep-going"));
+    let jobs = match matches.opt_str("j") {
+        None if matches.opt_present("j") => {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        }
+        None => 1,
+        Some(s) => match s.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("invalid number of jobs `{}`", s).into()),
+        },
+    };
+    if matches.opt_present("j") {
+        if !in_place && output_template.is_none() {
+            return Err(String::from("-j requires -w or --output-template").into());
+        }
+        if watch || dry_run || matches.opt_present("stats") {
+            return Err(
+                String::from("-j cannot be combined with --watch, --list, or --stats").into(),
+            );
+        }
+    }
+
+    let mut stats_collector = StatsCollector::new();
+    let (mut input, input_filename) = open_input(&invocation, &files, follow)?;
+    let paged = !(follow || watch || in_place || interactive);
+    let (mut output, output_file) = if checkpoint.is_some() {
+        // Opened without truncating, by format_checkpointed
+        (Box::new(std::io::sink()) as Box<dyn Write>, None)
+    } else {
+        let mut inputs = files.clone();
+        inputs.extend(input_filename);
+        open_output(matches, paged, &inputs)?
+    };
+
+    // Count the bytes written, after compression, for --stats
+    let bytes_out = Rc::new(Cell::new(0_u64));
+    let stats = matches.opt_present("stats");
+    let stats_json = match matches.opt_str("stats").as_deref() {
+        None => false,
+        Some("json") => true,
+        Some(other) => return Err(format!("invalid stats format `{}`", other).into()),
+    };
+    if stats {
+        output = Box::new(CountingWriter {
+            output,
+            count: bytes_out.clone(),
+        });
+    }
+    let report_stats = |collector: StatsCollector, files: usize| -> Result<(), String> {
+        let stats = collector.finish().map_err(|e| e.to_string())?;
+        eprintln!(
+            "{}",
+            stats_summary(
+                &stats,
+                files,
+                bytes_out.get(),
+                started.elapsed(),
+                stats_json
+            )
+        );
+        Ok(())
+    };
+    // Adds the size of a written file to the bytes out
+    let add_bytes_out = |filename: &str| {
+        if let Ok(metadata) = std::fs::metadata(filename) {
+            bytes_out.set(bytes_out.get() + metadata.len());
+        }
+    };
+
+    let output_compression = match (matches.opt_present("gzip"), matches.opt_present("zstd")) {
+        (false, false) => None,
+        (true, false) => Some(Compression::Gzip),
+        (false, true) => Some(Compression::Zstd),
+        (true, true) => return Err(String::from("use only one of --gzip and --zstd").into()),
+    };
+    if output_compression.is_some() && !cfg!(feature = "compression") {
+        return Err(String::from(
+            "compressed output requires jsonxf to be built with the `compression` feature",
+        )
+        .into());
+    }
+    if output_compression.is_some() && in_place {
+        return Err(String::from("-w cannot be combined with --gzip or --zstd").into());
+    }
+    // Only formatted JSON is colored, not other formats or reports
+    if use_color(matches)? && mode.writes_json() && output_compression.is_none() {
+        output = colored(output, matches);
+    }
+
+    let layout = Layout::from_matches(matches)?;
+    let minimize = matches.opt_present("m");
+    let mut xf = layout.formatter(minimize);
+
+    if let Some(checkpoint_filename) = &checkpoint {
+        let input_filename = matches.opt_str("i").unwrap_or_else(|| files[0].clone());
+        let output_filename = matches.opt_str("o").unwrap();
+        let resumed = format_checkpointed(
+            &mut xf,
+            &input_filename,
+            &output_filename,
+            checkpoint_filename,
+        )
+        .map_err(|e| Diagnostic::in_file(&input_filename, &e))?;
+        if resumed > 0 {
+            verbosity.info(format_args!(
+                "{}: resumed from {} at byte {}",
+                input_filename, checkpoint_filename, resumed
+            ));
+        }
+        return Ok(());
+    }
+
+    let tally = Tally::default();
+    let report_bad_lines = || {
+        let n = tally.bad_lines.load(Ordering::Relaxed);
+        if n > 0 {
+            let done = if matches!(mode, Mode::OnError(OnError::Pass)) {
+                "passed through"
+            } else {
+                "skipped"
+            };
+            verbosity.warn(format!(
+                "{} {} which could not be formatted",
+                done,
+                count(n, "line")
+            ));
+        }
+    };
+    // Does the mode to one input, and returns what was done to it, for
+    // --verbose.
+    let run = |xf: &mut Formatter,
+               input: &mut dyn Read,
+               output: &mut dyn Write|
+     -> Result<&'static str, Error> {
+        let mut done = "formatted";
+        let mut peeked;
+        let input: &mut dyn Read = if layout.smart || layout.eol_auto || layout.indent_auto {
+            peeked = BufReader::new(input);
+            let start = peeked.fill_buf()?;
+            if layout.smart {
+                // Go the other way from how the input starts out
+                let minimize = looks_pretty_printed(start);
+                *xf = layout.formatter(minimize);
+                done = if minimize {
+                    "minimized"
+                } else {
+                    "pretty-printed"
+                };
+            }
+            if layout.eol_auto {
+                xf.set_line_ending(jsonxf::detect_line_ending(start).unwrap_or("\n"));
+            }
+            if layout.indent_auto && !xf.line_separator.is_empty() {
+                xf.indent = jsonxf::detect_indent(start).unwrap_or_else(|| layout.indent.clone());
+            }
+            &mut peeked
+        } else {
+            input
+        };
+        mode.run(xf, input, output, &tally).map(|_| done)
+    };
+
+    if in_place {
+        if let Some(filename) = files
+            .iter()
+            .find(|f| Compression::from_filename(f).is_some())
+        {
+            return Err(format!("{}: cannot write compressed files in place", filename).into());
+        }
+    }
+    if dry_run {
+        let mut changed = 0;
+        for filename in &files {
+            xf.reset();
+            let result = std::fs::read(filename).and_then(|original| {
+                let mut formatted: Vec<u8> = vec![];
+                run(&mut xf, &mut &original[..], &mut formatted)?;
+                Ok(formatted != original)
+            });
+            match result {
+                Ok(true) => {
+                    println!("{}", filename);
+                    changed += 1;
+                }
+                Ok(false) => {}
+                Err(e) => failures.fail(Diagnostic::in_file(filename, &e))?,
+            }
+        }
+        report_bad_lines();
+        failures.finish(files.len())?;
+        if checking && changed > 0 {
+            return Err(format!("{} would change", count(changed, "file")).into());
+        }
+        return Ok(());
+    }
+    if in_place {
+        if jobs > 1 {
+            in_parallel(
+                &files,
+                jobs,
+                |filename| {
+                    let mut xf = layout.formatter(minimize);
+                    write_in_place(filename, backup_suffix.as_deref(), &mut |input, output| {
+                        run(&mut xf, input, output)
+                    })
+                },
+                |filename, result| match result {
+                    Ok(done) => {
+                        verbosity.info(format_args!("{}: {} in place", filename, done));
+                        Ok(())
+                    }
+                    Err(e) => failures.fail(e),
+                },
+            )?;
+        } else {
+            let mut changed: Vec<&String> = files.iter().collect();
+            loop {
+                for filename in changed {
+                    xf.reset();
+                    let result =
+                        write_in_place(filename, backup_suffix.as_deref(), &mut |input, output| {
+                            if stats {
+                                run(
+                                    &mut xf,
+                                    &mut StatsReader::new(input, &mut stats_collector),
+                                    output,
+                                )
+                            } else {
+                                run(&mut xf, input, output)
+                            }
+                        });
+                    match result {
+                        Ok(done) => {
+                            add_bytes_out(filename);
+                            verbosity.info(format_args!("{}: {} in place", filename, done))
+                        }
+                        // Keep watching; the file may have been caught mid-write
+                        Err(e) if watch => verbosity.warn(e),
+                        Err(e) => failures.fail(e)?,
+                    }
+                }
+                if !watch {
+                    break;
+                }
+                changed = wait_for_changes(&files);
+            }
+        }
+        report_bad_lines();
+        failures.finish(files.len())?;
+        verbosity.info(format_args!(
+            "formatted {} in {:.2?}",
+            count(files.len(), "file"),
+            started.elapsed()
+        ));
+        if stats {
+            report_stats(stats_collector, files.len())?;
+        }
+        return Ok(());
+    }
+
+    if let Some(template) = &output_template {
+        let mut output_filenames: Vec<String> = vec![];
+        for filename in &files {
+            let output_filename = fill_template(template, filename)?;
+            if files.contains(&output_filename) {
+                return Err(format!(
+                    "{}: output template would overwrite an input file",
+                    output_filename
+                )
+                .into());
+            }
+            if output_filenames.contains(&output_filename) {
+                return Err(format!(
+                    "{}: output template gives more than one file this name",
+                    output_filename
+                )
+                .into());
+            }
+            output_filenames.push(output_filename);
+        }
+        if jobs > 1 {
+            let pairs: Vec<(&String, &String)> = files.iter().zip(&output_filenames).collect();
+            in_parallel(
+                &pairs,
+                jobs,
+                |(filename, output_filename)| {
+                    let mut xf = layout.formatter(minimize);
+                    File::open(filename)
+                        .and_then(|file| decompressed(file, Some(filename)))
+                        .and_then(|mut input| {
+                            replace_file(output_filename, &mut |output| match output_compression {
+                                None => run(&mut xf, &mut input, output),
+                                Some(compression) => compressing(output, compression, |output| {
+                                    run(&mut xf, &mut input, output)
+                                }),
+                            })
+                        })
+                        .map_err(|e| Diagnostic::in_file(filename, &e))
+                },
+                |(filename, output_filename), result| match result {
+                    Ok(done) => {
+                        verbosity.info(format_args!(
+                            "{}: {} to {}",
+                            filename, done, output_filename
+                        ));
+                        Ok(())
+                    }
+                    Err(e) => failures.fail(e),
+                },
+            )?;
+        } else {
+            for (filename, output_filename) in files.iter().zip(&output_filenames) {
+                xf.reset();
+                let done = File::open(filename)
+                    .and_then(|file| decompressed(file, Some(filename)))
+                    .and_then(|input| {
+                        let mut input: Box<dyn Read> = if stats {
+                            Box::new(StatsReader::new(input, &mut stats_collector))
+                        } else {
+                            input
+                        };
+                        replace_file(output_filename, &mut |output| match output_compression {
+                            None => run(&mut xf, &mut input, output),
+                            Some(compression) => compressing(output, compression, |output| {
+                                run(&mut xf, &mut input, output)
+                            }),
+                        })
+                    })
+                    .map_err(|e| Diagnostic::in_file(filename, &e));
+                match done {
+                    Ok(done) => {
+                        add_bytes_out(output_filename);
+                        verbosity.info(format_args!(
+                            "{}: {} to {}",
+                            filename, done, output_filename
+                        ));
+                    }
+                    Err(e) => failures.fail(e)?,
+                }
+            }
+        }
+        report_bad_lines();
+        failures.finish(files.len())?;
+        verbosity.info(format_args!(
+            "formatted {} in {:.2?}",
+            count(files.len(), "file"),
+            started.elapsed()
+        ));
+        if stats {
+            report_stats(stats_collector, files.len())?;
+        }
+        return Ok(());
+    }
+
+    if watch {
+        let filename = files
+            .first()
+            .cloned()
+            .or_else(|| matches.opt_str("i"))
+            .unwrap();
+        let watched = [filename.clone()];
+        loop {
+            xf.reset();
+            let result = File::open(&filename)
+                .and_then(|file| decompressed(file, Some(&filename)))
+                .and_then(|mut input| match &output_file {
+                    Some(output_file) => replace_file(&output_file.filename, &mut |output| {
+                        run(&mut xf, &mut input, output)
+                    }),
+                    None => run(&mut xf, &mut input, &mut output)
+                        .and_then(|done| output.flush().map(|_| done)),
+                });
+            match result {
+                Err(e) => verbosity.warn(Diagnostic::in_file(&filename, &e)),
+                Ok(done) => verbosity.info(format_args!("{}: {}", filename, done)),
+            }
+            wait_for_changes(&watched);
+        }
+    }
+
+    let mut run_inputs = |output: &mut dyn Write| -> Result<(), Error> {
+        if files.is_empty() {
+            if stats {
+                run(
+                    &mut xf,
+                    &mut StatsReader::new(&mut input, &mut stats_collector),
+                    output,
+                )
+            } else {
+                run(&mut xf, &mut input, output)
+            }
+            .map(|_| ())
+        } else {
+            // Concatenate the output for each file, stopping at the first
+            // error unless keeping going
+            files.iter().try_for_each(|filename| {
+                if files.len() > 1 {
+                    *tally.file.lock().unwrap() = Some(filename.clone());
+                }
+                let done = File::open(filename)
+                    .and_then(|file| {
+                        let mut file = if follow {
+                            decompressed(Follow(file), Some(filename))?
+                        } else {
+                            decompressed(file, Some(filename))?
+                        };
+                        if stats {
+                            run(
+                                &mut xf,
+                                &mut StatsReader::new(&mut file, &mut stats_collector),
+                                output,
+                            )
+                        } else {
+                            run(&mut xf, &mut file, output)
+                        }
+                    })
+                    .map_err(|e| {
+                        let file = filename.clone();
+                        Error::new(e.kind(), FileError { file, error: e })
+                    });
+                match done {
+                    Ok(done) => verbosity.info(format_args!("{}: {}", filename, done)),
+                    Err(e) if e.kind() == ErrorKind::BrokenPipe => return Err(e),
+                    Err(e) => {
+                        failures.fail(Diagnostic::from(&e)).map_err(|_| e)?;
+                        xf.reset();
+                    }
+                }
+                Ok(())
+            })
+        }
+    };
+    let result = match output_compression {
+        None => run_inputs(&mut output),
+        Some(compression) => compressing(&mut output, compression, run_inputs),
+    };
+
+    // Flushing resets the terminal's color, if a token was left open
+    let result = result.and_then(|_| output.flush());
+
+    drop(input);
+    if result.is_ok() {
+        let inputs = if files.is_empty() {
+            String::from("the input")
+        } else {
+            count(files.len(), "file")
+        };
+        verbosity.info(format_args!(
+            "formatted {} in {:.2?}",
+            inputs,
+            started.elapsed()
+        ));
+    }
+    if stats && result.is_ok() {
+        report_stats(stats_collector, files.len())?;
+    }
+
+    drop(output);
+    let result = finish_output(output_file, result);
+
+    report_bad_lines();
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => Err(Diagnostic::from(&e)),
+        Ok(_) => {
+            failures.finish(files.len())?;
+            match tally.secrets.load(Ordering::Relaxed) {
+                0 => Ok(()),
+                n => Err(format!("found {}", count(n, "possible secret")).into()),
+            }
+        }
+    }
+}
+
+// The files which failed, with --keep-going.
+struct Failures {
+    keep_going: bool,
+    errors: RefCell<Vec<Diagnostic>>,
+}
+
+impl Failures {
+    fn new(keep_going: bool) -> Failures {
+        Failures {
+            keep_going,
+            errors: RefCell::new(vec![]),
+        }
+    }
+
+    // Returns the error, to stop at it, or notes it when keeping going.
+    fn fail(&self, error: Diagnostic) -> Result<(), Diagnostic> {
+        if !self.keep_going {
+            return Err(error);
+        }
+        self.errors.borrow_mut().push(error);
+        Ok(())
+    }
+
+    // Reports the failures noted, and returns an error if there were any.
+    fn finish(&self, files: usize) -> Result<(), Diagnostic> {
+        let errors = self.errors.borrow();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        for error in errors.iter() {
+            error.emit();
+        }
+        Err(format!("{} of {} failed", errors.len(), count(files, "file")).into())
+    }
+}
+
+// Set by --errors=jsonl, to write each diagnostic as a JSON object.
+static ERRORS_JSONL: AtomicBool = AtomicBool::new(false);
+
+// An error or warning for stderr, with the file and position it was
+// found at, when they are known.
+struct Diagnostic {
+    kind: &'static str, // "error" or "warning"
+    file: Option<String>,
+    position: Option<Position>,
+    message: String,
+}
+
+impl Diagnostic {
+    // Describes an error reading or formatting a file.
+    fn in_file(file: &str, e: &Error) -> Diagnostic {
+        Diagnostic {
+            file: Some(String::from(file)),
+            ..Diagnostic::from(e)
+        }
+    }
+
+    fn emit(&self) {
+        if !ERRORS_JSONL.load(Ordering::Relaxed) {
+            eprintln!("{}", self);
+            return;
+        }
+        let or_null = |n: Option<u64>| n.map_or(String::from("null"), |n| n.to_string());
+        eprintln!(
+            "{{\"kind\":\"{}\",\"file\":{},\"offset\":{},\"line\":{},\"column\":{},\"message\":{}}}",
+            self.kind,
+            self.file.as_deref().map_or(String::from("null"), jsonxf::value::escape),
+            or_null(self.position.map(|p| p.offset)),
+            or_null(self.position.map(|p| p.line)),
+            or_null(self.position.map(|p| p.column)),
+            jsonxf::value::escape(&self.message)
+        );
+    }
+}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Diagnostic {
+        Diagnostic {
+            kind: "error",
+            file: None,
+            position: None,
+            message,
+        }
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(e: &Error) -> Diagnostic {
+        let inner = e.get_ref();
+        if let Some(FileError { file, error }) = inner.and_then(|e| e.downcast_ref()) {
+            return Diagnostic::in_file(file, error);
+        }
+        match inner.and_then(|e| e.downcast_ref::<SyntaxError>()) {
+            Some(e) => Diagnostic {
+                position: Some(e.position),
+                ..Diagnostic::from(e.message.clone())
+            },
+            None => Diagnostic::from(e.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        f.write_str(&self.message)?;
+        match &self.position {
+            Some(position) => write!(f, " at {}", position),
+            None => Ok(()),
+        }
+    }
+}
+
+// An error in one of several input files, naming it.
+#[derive(Debug)]
+struct FileError {
+    file: String,
+    error: Error,
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file, self.error)
+    }
+}
+
+impl std::error::Error for FileError {}
+
+// How much to report on stderr, besides fatal errors.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Verbosity {
+    // Reports a problem which doesn't stop jsonxf.
+    fn warn<T: Into<Diagnostic>>(self, warning: T) {
+        if self != Verbosity::Quiet {
+            let warning = Diagnostic {
+                kind: "warning",
+                ..warning.into()
+            };
+            warning.emit();
+        }
+    }
+
+    // Reports progress.
+    fn info<T: std::fmt::Display>(self, message: T) {
+        if self == Verbosity::Verbose {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+// Returns e.g. "1 file" or "3 files".
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+// Returns e.g. "512 B" or "1.5 MiB".
+fn size(bytes: f64) -> String {
+    let mut size = bytes;
+    for unit in &["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 || *unit == "GiB" {
+            return if *unit == "B" {
+                format!("{:.0} {}", size, unit)
+            } else {
+                format!("{:.1} {}", size, unit)
+            };
+        }
+        size /= 1024.0;
+    }
+    unreachable!()
+}
+
+// Describes a run for --stats, as one line of text or as JSON with the
+// input's statistics.  `files` is zero for standard input.
+fn stats_summary(
+    stats: &StreamStats,
+    files: usize,
+    bytes_out: u64,
+    elapsed: Duration,
+    json: bool,
+) -> String {
+    let seconds = elapsed.as_secs_f64();
+    let ratio = if stats.bytes > 0 {
+        bytes_out as f64 / stats.bytes as f64
+    } else {
+        1.0
+    };
+    let throughput = if seconds > 0.0 {
+        stats.bytes as f64 / seconds
+    } else {
+        0.0
+    };
+    if json {
+        let mut summary = stats.to_string();
+        summary.pop(); // the closing brace
+        format!(
+            "{},\"files\":{},\"bytes_out\":{},\"ratio\":{:.3},\"seconds\":{:.3},\
+             \"bytes_per_second\":{:.0}}}",
+            summary, files, bytes_out, ratio, seconds, throughput
+        )
+    } else {
+        let inputs = if files == 0 {
+            String::from("the input")
+        } else {
+            count(files, "file")
+        };
+        format!(
+            "{}, {}, {} in, {} out ({:.1}%), {:.2?}, {}/s",
+            inputs,
+            count(stats.records as usize, "record"),
+            size(stats.bytes as f64),
+            size(bytes_out as f64),
+            ratio * 100.0,
+            elapsed,
+            size(throughput)
+        )
+    }
+}
+
+// Counts the bytes written through it into a shared cell.
+struct CountingWriter<W: Write> {
+    output: W,
+    count: Rc<Cell<u64>>,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let n = self.output.write(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.output.flush()
+    }
+}
+
+// Makes sure the terminal on stdout will show ANSI color codes as
+// colors, and returns whether it will.  The Windows console must be
+// asked to, and older versions of it can't.
+#[cfg(windows)]
+fn enable_ansi_colors() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE,
+        ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+    };
+    // SAFETY: these only query and set the mode of our own stdout, and
+    // fail harmlessly when it isn't a console
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        let mut mode: CONSOLE_MODE = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_ansi_colors() -> bool {
+    true
+}
+
+// Holds output for the terminal until it is longer than the screen,
+// then starts $PAGER and sends the output through it.  Short output is
+// written to stdout when the pager is dropped.
+struct Pager {
+    rows: usize,
+    held: Vec<u8>,
+    lines: usize,  // the lines in `held`
+    unpaged: bool, // whether the pager couldn't be started
+    child: Option<Child>,
+}
+
+impl Pager {
+    fn new(rows: usize) -> Pager {
+        Pager {
+            rows,
+            held: vec![],
+            lines: 0,
+            unpaged: false,
+            child: None,
+        }
+    }
+
+    // Starts the pager, or returns None if it can't be run.
+    fn spawn() -> Option<Child> {
+        let pager = std::env::var("PAGER").unwrap_or_default();
+        let mut words = pager.split_whitespace();
+        let mut command = match words.next() {
+            Some(program) => Command::new(program),
+            None => {
+                let mut less = Command::new("less");
+                less.arg("-R");
+                less
+            }
+        };
+        command.args(words).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            // Keep color, and stay on screen after quitting, as git does
+            command.env("LESS", "FRX");
+        }
+        command.spawn().ok()
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if let Some(child) = &mut self.child {
+            return child.stdin.as_mut().unwrap().write(buf);
+        }
+        if self.unpaged {
+            return std::io::stdout().write(buf);
+        }
+        self.held.extend_from_slice(buf);
+        self.lines += buf.iter().filter(|&&b| b == b'\n').count();
+        if self.lines >= self.rows {
+            match Pager::spawn() {
+                Some(mut child) => {
+                    child.stdin.as_mut().unwrap().write_all(&self.held)?;
+                    self.child = Some(child);
+                }
+                None => {
+                    std::io::stdout().write_all(&self.held)?;
+                    self.unpaged = true;
+                }
+            }
+            self.held.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        match &mut self.child {
+            Some(child) => child.stdin.as_mut().unwrap().flush(),
+            None if self.unpaged => std::io::stdout().flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        match &mut self.child {
+            Some(child) => {
+                // Let the pager see the end of its input, and wait for the
+                // reader to quit it
+                drop(child.stdin.take());
+                let _ = child.wait();
+            }
+            None => {
+                let mut stdout = std::io::stdout();
+                let _ = stdout.write_all(&self.held).and_then(|_| stdout.flush());
+            }
+        }
+    }
+}
+
+// Expands directories (with `recursive`) and glob patterns among the
+// input arguments into a list of files.  Other arguments are kept as
+// given.  Globs which the shell left alone, like `'**/*.json'`, are
+// expanded here.
+fn expand_inputs(
+    args: &[String],
+    recursive: bool,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<String>, String> {
+    let default_include = [String::from("*.json")];
+    let include = if include.is_empty() {
+        &default_include[..]
+    } else {
+        include
+    };
+    let excluded = |path: &str| exclude.iter().any(|glob| path_matches(glob, path));
+
+    let mut files: Vec<String> = vec![];
+    for arg in args {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            if !recursive {
+                return Err(format!("{}: is a directory (use -r to format it)", arg));
+            }
+            for file in walk(path)? {
+                let relative = &file[arg.len()..].trim_start_matches('/');
+                if include.iter().any(|glob| path_matches(glob, relative)) && !excluded(relative) {
+                    files.push(file);
+                }
+            }
+        } else if !path.exists() && arg.contains(['*', '?', '[']) {
+            // walk from the last directory before the first wildcard
+            let wildcard = arg.find(['*', '?', '[']).unwrap();
+            let base = match arg[..wildcard].rfind('/') {
+                Some(slash) => &arg[..=slash],
+                None => "",
+            };
+            let found: Vec<String> = walk(Path::new(if base.is_empty() { "." } else { base }))?
+                .into_iter()
+                .map(|file| match base {
+                    "" => String::from(file.trim_start_matches("./")),
+                    _ => file,
+                })
+                .filter(|file| glob_match(arg.as_bytes(), file.as_bytes()) && !excluded(file))
+                .collect();
+            if found.is_empty() {
+                return Err(format!("{}: no matching files", arg));
+            }
+            files.extend(found);
+        } else {
+            files.push(arg.clone());
+        }
+    }
+    Ok(files)
+}
+
+// Names an output file for `filename` by filling in `template`:
+// `{dir}` is its directory, `{name}` its filename, and `{stem}` and
+// `{ext}` its filename before and after the last `.`.  `{{` and `}}`
+// are literal braces.
+fn fill_template(template: &str, filename: &str) -> Result<String, String> {
+    let path = Path::new(filename);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy(),
+        _ => ".".into(),
+    };
+    let part =
+        |part: Option<&std::ffi::OsStr>| part.unwrap_or_default().to_string_lossy().into_owned();
+
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // A lone `}` is literal too
+        if let Some(brace) = ["{{", "}}", "}"]
+            .iter()
+            .find(|brace| rest.starts_with(*brace))
+        {
+            output.push_str(&brace[..1]);
+            rest = &rest[brace.len()..];
+            continue;
+        }
+        let end = rest
+            .find('}')
+            .ok_or_else(|| format!("unclosed `{{` in output template `{}`", template))?;
+        let placeholder = &rest[..=end];
+        match placeholder {
+            "{dir}" => output.push_str(&dir),
+            "{name}" => output.push_str(&part(path.file_name())),
+            "{stem}" => output.push_str(&part(path.file_stem())),
+            "{ext}" => output.push_str(&part(path.extension())),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder `{}` in output template",
+                    placeholder
+                ))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+// Returns true if the start of some JSON has a line break inside an
+// array or object, as pretty-printed JSON does.
+fn looks_pretty_printed(prefix: &[u8]) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut in_backslash = false;
+    for &b in prefix {
+        match b {
+            _ if in_backslash => in_backslash = false,
+            b'\\' if in_string => in_backslash = true,
+            b'"' => in_string = !in_string,
+            _ if in_string => {}
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth -= 1,
+            b'\n' if depth > 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// Reads a list of filenames, one per line or separated by NUL bytes.
+fn read_file_list(list: &str) -> Result<Vec<String>, String> {
+    let mut bytes = vec![];
+    let result = if list == "-" {
+        std::io::stdin().read_to_end(&mut bytes)
+    } else {
+        File::open(list).and_then(|mut f| f.read_to_end(&mut bytes))
+    };
+    result.map_err(|e| format!("{}: {}", list, e))?;
+    let text =
+        String::from_utf8(bytes).map_err(|_| format!("{}: filenames must be UTF-8", list))?;
+    let separator = if text.contains('\0') { '\0' } else { '\n' };
+    Ok(text
+        .split(separator)
+        .map(|name| name.strip_suffix('\r').unwrap_or(name))
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+// Lists the files under `dir`, in sorted order.  Symbolic links to
+// directories are skipped rather than followed, and hidden files and directories, whose
+// names start with `.`, are skipped, as a shell's `*` skips them.
+fn walk(dir: &Path) -> Result<Vec<String>, String> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, Error>>())
+        .map_err(|e| format!("{}: {}", dir.display(), e))?;
+    entries.retain(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut files: Vec<String> = vec![];
+    for entry in entries {
+        let path = dir.join(entry.file_name());
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => files.extend(walk(&path)?),
+            Ok(t) if t.is_symlink() && path.is_dir() => {}
+            _ => files.push(path.to_string_lossy().into_owned()),
+        }
+    }
+    Ok(files)
+}
+
+// Globs containing a `/` match the whole path; others match the
+// file name alone.
+fn path_matches(glob: &str, path: &str) -> bool {
+    if glob.contains('/') {
+        glob_match(glob.as_bytes(), path.as_bytes())
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(glob.as_bytes(), name.as_bytes())
+    }
+}
+
+// Matches a path against a glob, where `*` and `?` match within one
+// path segment, `**/` matches any number of whole segments, and
+// `[...]` matches a character class.
+fn glob_match(glob: &[u8], path: &[u8]) -> bool {
+    if let Some(rest) = glob.strip_prefix(b"**/") {
+        return glob_match(rest, path)
+            || path
+                .iter()
+                .enumerate()
+                .any(|(i, &b)| b == b'/' && glob_match(rest, &path[i + 1..]));
+    }
+    if glob == b"**" {
+        return true;
+    }
+    match glob.first() {
+        None => path.is_empty(),
+        Some(b'*') => {
+            let rest = &glob[1..];
+            let segment = path.iter().position(|&b| b == b'/').unwrap_or(path.len());
+            (0..=segment).any(|i| glob_match(rest, &path[i..]))
+        }
+        Some(b'?') => !path.is_empty() && path[0] != b'/' && glob_match(&glob[1..], &path[1..]),
+        Some(b'[') => match (glob.iter().position(|&b| b == b']'), path.first()) {
+            (Some(end), Some(&c)) if end > 1 => {
+                let (negated, class) = match glob[1] {
+                    b'!' | b'^' => (true, &glob[2..end]),
+                    _ => (false, &glob[1..end]),
+                };
+                let mut found = false;
+                let mut i = 0;
+                while i < class.len() {
+                    if i + 2 < class.len() && class[i + 1] == b'-' {
+                        found |= class[i] <= c && c <= class[i + 2];
+                        i += 3;
+                    } else {
+                        found |= class[i] == c;
+                        i += 1;
+                    }
+                }
+                found != negated && c != b'/' && glob_match(&glob[end + 1..], &path[1..])
+            }
+            _ => false,
+        },
+        Some(&b) => path.first() == Some(&b) && glob_match(&glob[1..], &path[1..]),
+    }
+}
+
+// Runs `f` from `filename` to a temporary file, then replaces
+// `filename` with the result.  On error the original is left alone.
+//
+// With a `backup_suffix`, the original is first copied to its name
+// plus the suffix, unless `f` leaves it unchanged.
+fn write_in_place<T, F>(
+    filename: &str,
+    backup_suffix: Option<&str>,
+    f: &mut F,
+) -> Result<T, Diagnostic>
+where
+    F: FnMut(&mut dyn Read, &mut dyn Write) -> Result<T, Error>,
+{
+    let result = match backup_suffix {
+        None => File::open(filename).and_then(|input| {
+            let mut input = BufReader::new(input);
+            replace_file(filename, &mut |output| f(&mut input, output))
+        }),
+        Some(suffix) => std::fs::read(filename).and_then(|original| {
+            let mut formatted: Vec<u8> = vec![];
+            let value = f(&mut &original[..], &mut formatted)?;
+            if formatted != original {
+                std::fs::copy(filename, format!("{}{}", filename, suffix))?;
+                replace_file(filename, &mut |output| output.write_all(&formatted))?;
+            }
+            Ok(value)
+        }),
+    };
+    result.map_err(|e| Diagnostic::in_file(filename, &e))
+}
+
+// Runs `f` to a temporary file, then replaces `filename` with the
+// result.  On error `filename` is left alone.
+fn replace_file<T, F>(filename: &str, f: &mut F) -> Result<T, Error>
+where
+    F: FnMut(&mut dyn Write) -> Result<T, Error>,
+{
+    let (temp_filename, output) = create_temp_file(filename)?;
+    let mut output = BufWriter::new(output);
+    let result = f(&mut output).and_then(|value| {
+        let output = output.into_inner().map_err(|e| e.into_error())?;
+        finish_temp_file(output, &temp_filename, filename).map(|_| value)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_filename);
+    }
+    result
+}
+
+// Prints the changes from one file to another, for --diff, exiting with
+// status 1 if there are any, as diff(1) does.
+fn diff_files(
+    differ: &jsonxf::diff::Differ,
+    filename: &str,
+    other_filename: &str,
+    color: bool,
+    mut output: Box<dyn Write>,
+) -> Result<(), Diagnostic> {
+    let changes = differ.diff_values(&read_json_file(filename)?, &read_json_file(other_filename)?);
+    for change in &changes {
+        let line = change.to_string();
+        if color {
+            // Green for added, red for removed, yellow for changed
+            let sgr = match change {
+                Change::Added { .. } => "32",
+                Change::Removed { .. } => "31",
+                Change::Changed { .. } => "33",
+            };
+            writeln!(output, "\x1b[{}m{}\x1b[0m", sgr, line)
+        } else {
+            writeln!(output, "{}", line)
+        }
+        .map_err(|e| e.to_string())?;
+    }
+    output.flush().map_err(|e| e.to_string())?;
+    if !changes.is_empty() {
+        drop(output); // finish paging before exiting
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Returns an error naming the first difference between two files, for
+// --assert-equal.
+fn assert_files_equal(
+    differ: &jsonxf::diff::Differ,
+    filename: &str,
+    other_filename: &str,
+) -> Result<(), Diagnostic> {
+    let changes = differ.diff_values(&read_json_file(filename)?, &read_json_file(other_filename)?);
+    let change = match changes.first() {
+        None => return Ok(()),
+        Some(change) => change,
+    };
+    let at = match change.path() {
+        "" => "the top level",
+        path => path,
+    };
+    let difference = match change {
+        Change::Added { value, .. } => format!("only in {}: {}", other_filename, value),
+        Change::Removed { value, .. } => format!("only in {}: {}", filename, value),
+        Change::Changed { old, new, .. } => format!("{} -> {}", old, new),
+    };
+    Err(format!(
+        "{} and {} differ at {}: {}",
+        filename, other_filename, at, difference
+    )
+    .into())
+}
+
+// Deep-merges files, each over those before it, for --merge, and writes
+// the result tidied by `tidy`.
+fn merge_files<F>(
+    files: &[String],
+    strategy: Strategy,
+    tidy: F,
+    xf: &mut Formatter,
+    output: &mut dyn Write,
+) -> Result<(), Diagnostic>
+where
+    F: Fn(&mut Value),
+{
+    let mut merged = read_json_file(&files[0])?;
+    for filename in &files[1..] {
+        jsonxf::merge::merge_values(&mut merged, read_json_file(filename)?, strategy)
+            .map_err(|e| format!("{}: {}", filename, e))?;
+    }
+    tidy(&mut merged);
+    xf.format_stream(&mut merged.to_string().as_bytes(), output)
+        .and_then(|_| output.flush())
+        .map_err(|e| Diagnostic::from(&e))
+}
+
+// Divides the input, or the one file, into parts named by `template`,
+// for --split, and returns how many were written.
+fn split_into_parts(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    files: &[String],
+    template: &str,
+    array_elements: Option<u64>,
+    limits: jsonxf::split::SplitLimits,
+) -> Result<u64, String> {
+    let mut open_part = |n: u64| File::create(template.replace("{seq}", &format!("{:04}", n)));
+    let mut split_input = |input: &mut dyn Read| match array_elements {
+        Some(max_elements) => {
+            jsonxf::split::rechunk_array(xf, input, max_elements, limits, &mut open_part)
+        }
+        None => jsonxf::split::split_records(xf, input, limits, &mut open_part),
+    };
+    match files.first() {
+        None => split_input(input).map_err(|e| e.to_string()),
+        Some(filename) => File::open(filename)
+            .and_then(|file| decompressed(file, Some(filename)))
+            .and_then(|mut file| split_input(&mut file))
+            .map_err(|e| format!("{}: {}", filename, e)),
+    }
+}
+
+// Rejects options which would be ignored: two modes which don't honour
+// each other, modifiers without their modes, options which lay out or
+// accept JSON with modes which don't write or parse it that way, and
+// options the command doesn't take.  Only the command line is checked,
+// as config files and JSONXF set options for every mode.
+fn check_modes(
+    matches: &Matches,
+    args: &[String],
+    command: Option<&Subcommand>,
+) -> Result<(), String> {
+    let is_get = command.is_some_and(|command| command.name == "get");
+    // Whether a mode is given, as an option or an option and its value
+    let given = |mode: &str| match mode.split_once(' ') {
+        Some((name, value)) => matches.opt_str(name).as_deref() == Some(value),
+        None => matches.opt_present(mode) || is_get && mode == "get",
+    };
+    let named = |mode: &str| {
+        let name = mode.split(' ').next().unwrap_or(mode);
+        match command {
+            Some(command)
+                if command.name == name
+                    || command.stands_for.contains(&format!("--{}", name).as_str()) =>
+            {
+                command.name.to_string()
+            }
+            _ => format!("--{}", mode),
+        }
+    };
+    let flag = |name: &str| {
+        if name.len() == 1 {
+            format!("-{}", name)
+        } else {
+            format!("--{}", name)
+        }
+    };
+
+    let modes: Vec<(&str, &[&str])> = MODES
+        .iter()
+        .filter_map(|(options, honours)| {
+            options
+                .iter()
+                .find(|name| given(name))
+                .map(|name| (*name, *honours))
+        })
+        .collect();
+    for (i, (mode, honours)) in modes.iter().enumerate() {
+        for (other, other_honours) in &modes[i + 1..] {
+            if !honours.contains(other) && !other_honours.contains(mode) {
+                return Err(format!(
+                    "{} cannot be combined with {}",
+                    named(mode),
+                    named(other)
+                ));
+            }
+        }
+    }
+    for (modifier, modes) in MODIFIERS {
+        if matches.opt_present(modifier) && !modes.iter().any(|mode| given(mode)) {
+            let modes: Vec<String> = modes.iter().map(|mode| format!("--{}", mode)).collect();
+            return Err(format!("--{} requires {}", modifier, modes.join(" or ")));
+        }
+    }
+    // A JSON Schema is laid out like any JSON, and check --list checks
+    // the layout
+    let writes_schema = matches.opt_str("format").as_deref() == Some("schema");
+    let lists = matches.opt_present("list") || matches.opt_present("dry-run");
+    let ignored = [
+        (NOT_JSON_MODES, LAYOUT_OPTIONS),
+        (OWN_PARSING_MODES, PARSING_OPTIONS),
+    ];
+    for (modes, options) in ignored {
+        let mode = modes.iter().find(|mode| match **mode {
+            "schema" => given(mode) && !writes_schema,
+            "check" => given(mode) && !lists,
+            _ => given(mode),
+        });
+        let option = options.iter().find(|name| matches.opt_present(name));
+        if let (Some(mode), Some(option)) = (mode, option) {
+            return Err(format!(
+                "{} cannot be combined with {}",
+                named(mode),
+                flag(option)
+            ));
+        }
+    }
+
+    let (command, name) = match command {
+        Some(command) => (command, command.name.to_string()),
+        None => {
+            let command = chosen_command(matches);
+            let name = command
+                .chosen_by
+                .iter()
+                .find(|name| matches.opt_present(name))
+                .map_or_else(|| command.name.to_string(), |name| format!("--{}", name));
+            (command, name)
+        }
+    };
+    match command.options().parse(args) {
+        Err(getopts::Fail::UnrecognizedOption(option)) => Err(format!(
+            "{} cannot be combined with {}",
+            name,
+            flag(&option)
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Options besides the other modes which --checkpoint can't honour, as
+// it only formats one file into another, a chunk at a time.
+const NOT_CHECKPOINTED: &[&str] = &[
+    "write",
+    "watch",
+    "output-template",
+    "gzip",
+    "zstd",
+    "smart",
+    "stats",
+    "tint-levels",
+];
+
+// How much input to format between checkpoints, with --checkpoint.
+const CHECKPOINT_INTERVAL: usize = 64 * 1024 * 1024;
+
+// How far --checkpoint has gotten: the input read, the output written
+// and synced, and the formatter's state between them.
+struct Checkpoint {
+    input: String,
+    output: String,
+    input_offset: u64,
+    output_offset: u64,
+    state: FormatterState,
+}
+
+impl Checkpoint {
+    fn to_value(&self) -> Value {
+        let number = |n: u64| Value::Number(n.to_string());
+        let state = self.state.to_bytes().into_iter();
+        Value::Object(vec![
+            (String::from("input"), Value::String(self.input.clone())),
+            (String::from("output"), Value::String(self.output.clone())),
+            (String::from("input_offset"), number(self.input_offset)),
+            (String::from("output_offset"), number(self.output_offset)),
+            (
+                String::from("state"),
+                Value::Array(state.map(|b| number(b as u64)).collect()),
+            ),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Option<Checkpoint> {
+        let string = |key| match value.get(key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let number = |value: Option<&Value>| match value {
+            Some(Value::Number(n)) => n.parse::<u64>().ok(),
+            _ => None,
+        };
+        let state = match value.get("state") {
+            Some(Value::Array(bytes)) => bytes
+                .iter()
+                .map(|b| number(Some(b)).and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()?,
+            _ => return None,
+        };
+        Some(Checkpoint {
+            input: string("input")?,
+            output: string("output")?,
+            input_offset: number(value.get("input_offset"))?,
+            output_offset: number(value.get("output_offset"))?,
+            state: FormatterState::from_bytes(&state).ok()?,
+        })
+    }
+}
+
+// Formats one file into another, saving a checkpoint every
+// CHECKPOINT_INTERVAL bytes of input, and picking up from the
+// checkpoint if there is one.  Returns the input offset it picked up
+// from.
+fn format_checkpointed(
+    xf: &mut Formatter,
+    input_filename: &str,
+    output_filename: &str,
+    checkpoint_filename: &str,
+) -> Result<u64, Error> {
+    let mut input = BufReader::new(File::open(input_filename)?);
+    let compression = compress::detect(&mut input)?;
+    if compression
+        .or_else(|| Compression::from_filename(input_filename))
+        .is_some()
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot checkpoint compressed input",
+        ));
+    }
+    let mut checkpoint = match std::fs::read_to_string(checkpoint_filename) {
+        Ok(json_str) => jsonxf::value::parse(&json_str)
+            .ok()
+            .as_ref()
+            .and_then(Checkpoint::from_value)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}: invalid checkpoint", checkpoint_filename),
+                )
+            })?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Checkpoint {
+            input: String::from(input_filename),
+            output: String::from(output_filename),
+            input_offset: 0,
+            output_offset: 0,
+            // Nothing is held before any input
+            state: xf.state().unwrap(),
+        },
+        Err(e) => {
+            return Err(Error::new(
+                e.kind(),
+                format!("{}: {}", checkpoint_filename, e),
+            ))
+        }
+    };
+    if checkpoint.input != input_filename || checkpoint.output != output_filename {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{}: checkpoint is for formatting {} to {}",
+                checkpoint_filename, checkpoint.input, checkpoint.output
+            ),
+        ));
+    }
+    let resumed = checkpoint.input_offset;
+    xf.restore(&checkpoint.state);
+    input.seek(SeekFrom::Start(checkpoint.input_offset))?;
+    // Anything written after the checkpoint is written again
+    let mut output = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(output_filename)
+        .map_err(|e| Error::new(e.kind(), format!("{}: {}", output_filename, e)))?;
+    output.set_len(checkpoint.output_offset)?;
+    output.seek(SeekFrom::Start(checkpoint.output_offset))?;
+    let mut output = BufWriter::new(output);
+
+    let mut buf = vec![0_u8; 1024 * 64];
+    let mut unsaved = 0; // bytes of input since the last checkpoint
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        xf.format_buf(&buf[..n], &mut output)?;
+        checkpoint.input_offset += n as u64;
+        unsaved += n;
+        // While the formatter holds input, wait for the next chunk
+        if let (true, Some(state)) = (unsaved >= CHECKPOINT_INTERVAL, xf.state()) {
+            unsaved = 0;
+            // The checkpoint must never be ahead of the output on disk
+            output.flush()?;
+            output.get_ref().sync_data()?;
+            checkpoint.output_offset = output.get_mut().stream_position()?;
+            checkpoint.state = state;
+            replace_file(checkpoint_filename, &mut |file| {
+                writeln!(file, "{}", checkpoint.to_value())
+            })?;
+        }
+    }
+    xf.finish_buf(&mut output)?;
+    output.flush()?;
+    output.get_ref().sync_data()?;
+    match std::fs::remove_file(checkpoint_filename) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(resumed),
+    }
+}
+
+// Creates a new temporary file in the same directory as `filename`,
+// so that it can be renamed over `filename` by `finish_temp_file`.
+fn create_temp_file(filename: &str) -> Result<(String, File), Error> {
+    let path = Path::new(filename);
+    let temp_path = match path.file_name() {
+        Some(name) => path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        )),
+        None => PathBuf::from(format!("{}.{}.tmp", filename, std::process::id())),
+    };
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)?;
+    Ok((temp_path.to_string_lossy().into_owned(), file))
+}
+
+// Gives a temporary file the permissions and, where allowed, the owner
+// of `filename`, syncs it to disk, and renames it over `filename`.
+fn finish_temp_file(file: File, temp_filename: &str, filename: &str) -> Result<(), Error> {
+    if let Ok(metadata) = std::fs::metadata(filename) {
+        copy_owner(&file, &metadata);
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(temp_filename, filename)
+}
+
+#[cfg(unix)]
+fn copy_owner(file: &File, metadata: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    // Only root may give files away; others keep the file as their own
+    let _ = std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_file: &File, _metadata: &std::fs::Metadata) {}
+
+// Runs `f` on each of `items`, on `jobs` threads at once, and passes
+// the results to `report` in the order of `items`.  No more items are
+// started once `report` returns an error.
+fn in_parallel<I, T, E, F, R>(items: &[I], jobs: usize, f: F, mut report: R) -> Result<(), E>
+where
+    I: Sync,
+    T: Send,
+    F: Fn(&I) -> T + Sync,
+    R: FnMut(&I, T) -> Result<(), E>,
+{
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let sender = sender.clone();
+            let (next, stop, f) = (&next, &stop, &f);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() || sender.send((i, f(&items[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Results which arrived before those of earlier items
+        let mut waiting: BTreeMap<usize, T> = BTreeMap::new();
+        let mut reported = 0;
+        for (i, result) in receiver {
+            waiting.insert(i, result);
+            while let Some(result) = waiting.remove(&reported) {
+                if let Err(e) = report(&items[reported], result) {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+                reported += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+// Waits until any of `files` is modified, created, or removed, and
+// returns those which were.
+fn wait_for_changes(files: &[String]) -> Vec<&String> {
+    let versions = |files: &[String]| -> Vec<Option<(std::time::SystemTime, u64)>> {
+        files
+            .iter()
+            .map(|f| std::fs::metadata(f).ok())
+            .map(|m| m.and_then(|m| Some((m.modified().ok()?, m.len()))))
+            .collect()
+    };
+    let seen = versions(files);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = versions(files);
+        let changed: Vec<&String> = files
+            .iter()
+            .zip(seen.iter().zip(&current))
+            .filter(|(_, (before, now))| before != now)
+            .map(|(f, _)| f)
+            .collect();
+        if !changed.is_empty() {
+            return changed;
+        }
+    }
+}
+
+// Returns the argument after `option`, which takes an optional value,
+// if it looks like that value and doesn't name a file.  getopts takes
+// such a value only when attached with `=`, and would read this one as
+// a filename.
+fn detached_value<'a, F>(args: &'a [String], option: &str, is_value: F) -> Option<&'a str>
+where
+    F: Fn(&str) -> bool,
+{
+    let args = &args[..args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len())];
+    args.windows(2)
+        .find(|pair| pair[0] == option && is_value(&pair[1]) && !Path::new(&pair[1]).exists())
+        .map(|pair| pair[1].as_str())
+}
+
+// Could `arg` be a string to indent with, rather than a filename?
+fn is_indent_string(arg: &str) -> bool {
+    !arg.is_empty() && arg.bytes().all(|b| b == b' ' || b == b'\t')
+}
+
+// Options which choose the indent, and so override each other.
+const INDENT_OPTIONS: &[&str] = &["indent-string", "indent", "tab"];
+
+// Options which config files and the JSONXF environment variable may
+// set.  These change only how output looks, or what input is accepted,
+// so that a project's file can't make a plain `jsonxf file.json` write
+// files, read others, or leave records out.
+const CONFIG_OPTIONS: &[&str] = &[
+    "indent-string",
+    "indent",
+    "tab",
+    "eol",
+    "minimize",
+    "ndjson",
+    "ascii",
+    "sort-keys",
+    "max-depth",
+    "fold-style",
+    "inline-arrays",
+    "inline-objects",
+    "strict",
+    "jsonc",
+    "trailing-commas",
+    "keep-comments",
+    "color",
+    "no-pager",
+    "errors",
+];
+
+// A setting in a config file.
+enum Setting {
+    Flag(bool),
+    Values(Vec<String>),
+}
+
+// Returns the options set in the JSONXF environment variable, leaving
+// out those given on the command line, and those which `takes` (the
+// command's options) doesn't include.
+fn env_args(opts: &Options, takes: &Options, cli_args: &[String]) -> Result<Vec<String>, String> {
+    let value = match std::env::var("JSONXF") {
+        Ok(value) => value,
+        Err(std::env::VarError::NotPresent) => return Ok(vec![]),
+        Err(e) => return Err(format!("JSONXF: {}", e)),
+    };
+    let words = split_words(&value).map_err(|e| format!("JSONXF: {}", e))?;
+    let cli = opts.parse(cli_args).map_err(|e| e.to_string())?;
+    let indent_given = INDENT_OPTIONS.iter().any(|name| cli.opt_present(name));
+
+    let mut args = vec![];
+    let mut words = words.into_iter();
+    while let Some(word) = words.next() {
+        // An option's value may be the next word
+        let mut option = vec![word];
+        if let Err(getopts::Fail::ArgumentMissing(_)) = opts.parse(&option) {
+            option.extend(words.next());
+        }
+        let matches = opts.parse(&option).map_err(|e| format!("JSONXF: {}", e))?;
+        if !matches.free.is_empty() || option[0] == "--" {
+            return Err(format!("JSONXF: `{}` is not an option", option[0]));
+        }
+        if !CONFIG_OPTIONS.iter().any(|name| matches.opt_present(name)) {
+            return Err(format!(
+                "JSONXF: `{}` is not a formatting option, and can only be given on the \
+                 command line",
+                option[0]
+            ));
+        }
+        let sets_indent = INDENT_OPTIONS.iter().any(|name| matches.opt_present(name));
+        let duplicated = matches!(
+            opts.parse(option.iter().chain(cli_args)),
+            Err(getopts::Fail::OptionDuplicated(_))
+        );
+        if takes_option(takes, &option) && !(duplicated || sets_indent && indent_given) {
+            args.extend(option);
+        }
+    }
+    Ok(args)
+}
+
+// Splits a string into words at whitespace, as a shell would, except
+// within quotes or after a backslash.
+fn split_words(s: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => words.extend(word.take()),
+            '\\' => {
+                let escaped = chars.next().ok_or("unfinished escape at end")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some('\\') if c == '"' => {
+                            word.extend(chars.next());
+                        }
+                        Some(other) => word.push(other),
+                        None => return Err(format!("unterminated {} quote", c)),
+                    }
+                }
+            }
+            _ => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// Returns the options set in the user's and the project's config files,
+// as arguments, leaving out those already given elsewhere, and those
+// which `takes` (the command's options) doesn't include.  A project's
+// settings take precedence over the user's.
+fn config_args(opts: &Options, takes: &Options, given: &Matches) -> Result<Vec<String>, String> {
+    let mut settings: Vec<(String, Setting)> = vec![];
+    for path in config_files() {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let file_settings =
+            parse_config(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // Check each file on its own, so that errors name it
+        opts.parse(setting_args(&file_settings))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some((key, _)) = file_settings
+            .iter()
+            .find(|(key, _)| !CONFIG_OPTIONS.contains(&key.as_str()))
+        {
+            return Err(format!(
+                "{}: `{}` is not a formatting option, and can only be given on the \
+                 command line",
+                path.display(),
+                key
+            ));
+        }
+        let is_indent = |key: &str| INDENT_OPTIONS.contains(&key);
+        if file_settings.iter().any(|(key, _)| is_indent(key)) {
+            settings.retain(|(key, _)| !is_indent(key));
+        }
+        for (key, setting) in file_settings {
+            settings.retain(|(k, _)| *k != key);
+            settings.push((key, setting));
+        }
+    }
+
+    let indent_given = INDENT_OPTIONS.iter().any(|name| given.opt_present(name));
+    settings.retain(|(key, _)| {
+        let overridden = indent_given && INDENT_OPTIONS.contains(&key.as_str());
+        !(overridden || given.opt_present(key))
+    });
+    settings.retain(|setting| takes_option(takes, &setting_args(std::slice::from_ref(setting))));
+    Ok(setting_args(&settings))
+}
+
+// Whether `opts` includes the option in `args`.
+fn takes_option(opts: &Options, args: &[String]) -> bool {
+    !matches!(opts.parse(args), Err(getopts::Fail::UnrecognizedOption(_)))
+}
+
+fn setting_args(settings: &[(String, Setting)]) -> Vec<String> {
+    let mut args = vec![];
+    for (key, setting) in settings {
+        let short = key.len() == 1;
+        match setting {
+            Setting::Flag(true) if short => args.push(format!("-{}", key)),
+            Setting::Flag(true) => args.push(format!("--{}", key)),
+            Setting::Flag(false) => {}
+            Setting::Values(values) => {
+                for value in values {
+                    // Attached, so that values may start with `-`
+                    if short {
+                        args.push(format!("-{}{}", key, value));
+                    } else {
+                        args.push(format!("--{}={}", key, value));
+                    }
+                }
+            }
+        }
+    }
+    args
+}
+
+// Returns the user's config file, then the nearest project config file
+// in the current directory or above.
+fn config_files() -> Vec<PathBuf> {
+    let mut files = vec![];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from));
+    if let Some(dir) = config_home {
+        files.push(dir.join("jsonxf").join("config.toml"));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        if let Some(file) = cwd
+            .ancestors()
+            .map(|dir| dir.join(".jsonxf.toml"))
+            .find(|file| file.is_file())
+        {
+            files.push(file);
+        }
+    }
+    files
+}
+
+// Parses the subset of TOML used for config files: `key = value` lines,
+// where values are booleans, numbers, strings, or arrays of strings.
+// Underscores in keys are read as dashes, so `sort_keys` means
+// `--sort-keys`.
+fn parse_config(text: &str) -> Result<Vec<(String, Setting)>, String> {
+    let mut settings = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let err = |message: &str| format!("line {}: {}", i + 1, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(err("tables are not supported"));
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| err("expected `key = value`"))?;
+        let key = key.trim().trim_matches('"').replace('_', "-");
+        let (setting, rest) =
+            parse_config_value(value.trim_start()).ok_or_else(|| err("invalid value"))?;
+        let rest = rest.trim_start();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(err("unexpected text after value"));
+        }
+        settings.push((key, setting));
+    }
+    Ok(settings)
+}
+
+// Parses a value from the start of `s`, returning the rest of `s`.
+fn parse_config_value(s: &str) -> Option<(Setting, &str)> {
+    if let Some(rest) = s.strip_prefix("true") {
+        return Some((Setting::Flag(true), rest));
+    }
+    if let Some(rest) = s.strip_prefix("false") {
+        return Some((Setting::Flag(false), rest));
+    }
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = vec![];
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Some((Setting::Values(values), rest));
+            }
+            let (value, after) = parse_config_string(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+    if s.starts_with('"') || s.starts_with('\'') {
+        let (value, rest) = parse_config_string(s)?;
+        return Some((Setting::Values(vec![value]), rest));
+    }
+    let end = s
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(s.len());
+    let number = &s[..end];
+    number.replace('_', "").parse::<f64>().ok()?;
+    Some((Setting::Values(vec![number.replace('_', "")]), &s[end..]))
+}
+
+// Parses a basic ("...") or literal ('...') string from the start of
+// `s`, returning the rest of `s`.
+fn parse_config_string(s: &str) -> Option<(String, &str)> {
+    if let Some(literal) = s.strip_prefix('\'') {
+        let end = literal.find('\'')?;
+        return Some((String::from(&literal[..end]), &literal[end + 1..]));
+    }
+    if !s.starts_with('"') {
+        return None;
+    }
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some((jsonxf::value::unescape(&s[..=i]), &s[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Reads a file containing one JSON value.
+fn read_json_file(filename: &str) -> Result<Value, String> {
+    let mut json_str = String::new();
+    File::open(filename)
+        .and_then(|mut f| f.read_to_string(&mut json_str))
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    jsonxf::value::parse(&json_str).map_err(|e| format!("{}: {}", filename, e))
+}
+
+#[cfg(feature = "yaml")]
+use jsonxf::yaml::yaml_to_json;
+
+#[cfg(not(feature = "yaml"))]
+fn yaml_to_json(_yaml_string: &str) -> Result<String, String> {
+    unreachable!()
+}
+
+#[cfg(feature = "verify")]
+use jsonxf::verify::format_cross_checked;
+
+#[cfg(not(feature = "verify"))]
+fn format_cross_checked(
+    _xf: &mut Formatter,
+    _input: &mut dyn Read,
+    _output: &mut dyn Write,
+) -> Result<(), Error> {
+    unreachable!()
+}
+
+#[cfg(feature = "msgpack")]
+use jsonxf::msgpack::{from_msgpack_stream, to_msgpack_stream};
+
+#[cfg(not(feature = "msgpack"))]
+fn from_msgpack_stream(_input: &mut dyn Read, _output: &mut dyn Write) -> Result<(), Error> {
+    unreachable!()
+}
+
+#[cfg(not(feature = "msgpack"))]
+fn to_msgpack_stream(_input: &mut dyn Read, _output: &mut dyn Write) -> Result<(), Error> {
+    unreachable!()
+}
+
+// Returns `input`, decompressing it if its first bytes or its
+// filename show that it is compressed.
+fn decompressed<'a, R: Read + 'a>(
+    input: R,
+    filename: Option<&str>,
+) -> Result<Box<dyn Read + 'a>, Error> {
+    let mut input = BufReader::new(input);
+    let compression = compress::detect(&mut input)?;
+    match compression.or_else(|| filename.and_then(Compression::from_filename)) {
+        None => Ok(Box::new(input)),
+        Some(compression) => decoder(input, compression),
+    }
+}
+
+#[cfg(feature = "compression")]
+use jsonxf::compress::decoder;
+
+// Runs `f` with a writer which compresses into `output`.
+#[cfg(feature = "compression")]
+fn compressing<T, F>(output: &mut dyn Write, compression: Compression, f: F) -> Result<T, Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<T, Error>,
+{
+    let mut encoder = jsonxf::compress::Encoder::new(output, compression)?;
+    let value = f(&mut encoder)?;
+    encoder.finish().map(|_| value)
+}
+
+#[cfg(not(feature = "compression"))]
+fn compressing<T, F>(_output: &mut dyn Write, _compression: Compression, _f: F) -> Result<T, Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<T, Error>,
+{
+    unreachable!()
+}
+
+#[cfg(not(feature = "compression"))]
+fn decoder<'a, R: Read + 'a>(
+    _input: R,
+    compression: Compression,
+) -> Result<Box<dyn Read + 'a>, Error> {
+    Err(Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "{} input requires jsonxf to be built with the `compression` feature",
+            compression
+        ),
+    ))
+}
+
+// How long `Follow` and `--watch` wait before checking for changes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+// Reads input which may still be growing, waiting for more at the end
+// rather than stopping, like `tail -f`.
+struct Follow<R: Read>(R);
+
+impl<R: Read> Read for Follow<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            match self.0.read(buf) {
+                Ok(0) if !buf.is_empty() => std::thread::sleep(POLL_INTERVAL),
+                result => return result,
+            }
+        }
+    }
+}
+
+// Formats input as it arrives, flushing the output after each chunk
+// so that complete records appear promptly.
+fn format_following(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut buf = [0_u8; 8192];
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                xf.format_buf(&buf[..n], &mut writer)?;
+                writer.flush()?;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    xf.finish_buf(&mut writer)?;
+    writer.flush()
+}
+
+// Formats snippets of JSON pasted into a terminal, each ending at a
+// blank line or Ctrl-D.  Snippets which fail are reported, as other
+// errors are, and the next one is read.
+fn format_interactive(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut input = BufReader::new(input);
+    if std::io::stdin().is_terminal() {
+        eprintln!("Paste JSON, then press Enter on a blank line or Ctrl-D to format it.");
+        eprintln!("Press Ctrl-D with nothing pasted to quit.");
+    }
+    let mut snippet = String::new();
+    loop {
+        let len = snippet.len();
+        // A terminal's Ctrl-D ends one read, not the input
+        let at_end = input.read_line(&mut snippet)? == 0;
+        let blank = snippet[len..].trim().is_empty();
+        if at_end && snippet.trim().is_empty() {
+            return Ok(());
+        }
+        if !blank {
+            continue;
+        }
+        if !snippet.trim().is_empty() {
+            xf.reset();
+            let mut formatted = vec![];
+            match xf.format_stream(&mut snippet.as_bytes(), &mut formatted) {
+                Ok(()) => {
+                    output.write_all(&formatted)?;
+                    if !formatted.ends_with(b"\n") {
+                        output.write_all(b"\n")?;
+                    }
+                    output.flush()?;
+                }
+                Err(e) => Diagnostic::from(&e).emit(),
+            }
+        }
+        snippet.clear();
+    }
+}
+
+// Formats JSON as it is written.
+struct FormattingWriter<'a> {
+    xf: &'a mut Formatter,
+    output: &'a mut dyn Write,
+}
+
+impl<'a> Write for FormattingWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.xf.format_buf(buf, &mut self.output)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.output.flush()
+    }
+}
+
+impl<'a> FormattingWriter<'a> {
+    // Writes the rest of the output, as at the end of input.
+    fn finish(&mut self) -> Result<(), Error> {
+        self.xf.finish_buf(&mut self.output)?;
+        self.output.flush()
+    }
+}
+
+// Parses a `--seek` target: a JSON Pointer, optionally preceded by a
+// record number and a colon.
+fn parse_seek_target(target: &str) -> Result<(u64, String), String> {
+    match target.find(':') {
+        Some(colon) if !target[..colon].contains('/') => match target[..colon].parse::<u64>() {
+            Ok(record) => Ok((record, String::from(&target[colon + 1..]))),
+            Err(_) => Err(format!("invalid record number in `{}`", target)),
+        },
+        _ => Ok((0, String::from(target))),
+    }
+}
+
+// Parses a size in bytes, with an optional binary suffix: `K`, `M`, or
+// `G`, e.g. `500M`.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, scale) = match s.char_indices().last() {
+        Some((i, 'K')) | Some((i, 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M')) | Some((i, 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G')) | Some((i, 'g')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    match digits.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n * scale),
+        _ => Err(format!("invalid size `{}`", s)),
+    }
+}
+
+// Parses a `--records` range: `n`, `n-m`, or `n-`.
+fn parse_record_range(s: &str) -> Result<std::ops::RangeInclusive<u64>, String> {
+    let number = |n: &str| {
+        n.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid record range `{}`", s))
+    };
+    match s.find('-') {
+        None => number(s).map(|n| n..=n),
+        Some(dash) if dash + 1 == s.len() => number(&s[..dash]).map(|n| n..=u64::MAX),
+        Some(dash) => {
+            let (first, last) = (number(&s[..dash])?, number(&s[dash + 1..])?);
+            if last < first {
+                return Err(format!("invalid record range `{}`", s));
+            }
+            Ok(first..=last)
+        }
+    }
+}
+
+// Parses each input record, applies `f` to it, and formats each record
+// `f` returns.
+fn transform_records<F>(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    mut f: F,
+) -> Result<(), Error>
+where
+    F: FnMut(Value) -> Result<Vec<Value>, String>,
+{
+    let mut writer = BufWriter::new(output);
+    jsonxf::value::read_records(&mut BufReader::new(input), &mut |record| {
+        for record in f(record).map_err(|e| Error::new(ErrorKind::InvalidData, e))? {
+            xf.format_buf(record.to_string().as_bytes(), &mut writer)?;
+            // Keep a scalar from running into the next record
+            xf.format_buf(b"\n", &mut writer)?;
+        }
+        Ok(())
+    })?;
+    xf.finish_buf(&mut writer)?;
+    writer.flush()
+}
+
+// How --schema describes the input.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SchemaFormat {
+    Tsv,
+    Table,
+    JsonSchema,
+}
+
+// Writes rows under a header, in columns padded to line up.
+fn write_table(output: &mut dyn Write, header: &[&str], rows: &[Vec<String>]) -> Result<(), Error> {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i + 1 == row.len() {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:<w$}  ", cell, w = width));
+            }
+        }
+        writeln!(output, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+// What to do with a line of line-delimited input which isn't JSON.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnError {
+    Pass,
+    Skip,
+    Fail,
+}
+
+// Formats line-delimited input a line at a time, so that a line which
+// isn't a JSON value can be copied through verbatim or skipped, as
+// `on_error` says, without losing the rest.  Returns the number of
+// such lines.
+fn format_lines(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    on_error: OnError,
+) -> Result<usize, Error> {
+    // A line which isn't strictly JSON is an error, rather than formatted
+    // anyway
+    xf.validate = true;
+    let mut reader = BufReader::new(input);
+    let mut line: Vec<u8> = vec![];
+    let mut bad = 0;
+    for number in 1.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let formatted = std::str::from_utf8(&line)
+            .map_err(|e| e.to_string())
+            .and_then(|text| match text.trim() {
+                "" => Ok(String::new()),
+                text => {
+                    xf.reset();
+                    xf.format(text)
+                }
+            });
+        match formatted {
+            Ok(mut formatted) => {
+                if !formatted.is_empty() && !formatted.ends_with('\n') {
+                    formatted.push_str(&xf.record_separator);
+                }
+                output.write_all(formatted.as_bytes())?;
+            }
+            Err(e) => {
+                bad += 1;
+                match on_error {
+                    OnError::Pass => output.write_all(&line)?,
+                    OnError::Skip => {}
+                    OnError::Fail => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("line {}: {}", number, e),
+                        ))
+                    }
+                }
+            }
+        }
+    }
+    output.flush()?;
+    Ok(bad)
+}
+
+// Unwraps container logs a line at a time, formatting the messages
+// which are JSON and copying the rest through.
+fn format_container_logs(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut unwrapper = jsonxf::logs::Unwrapper::new();
+    let mut write_message = |message: String| -> Result<(), Error> {
+        let text = message.trim();
+        let is_json =
+            (text.starts_with('{') || text.starts_with('[')) && jsonxf::value::parse(text).is_ok();
+        if !is_json {
+            return writeln!(output, "{}", message);
+        }
+        xf.reset();
+        let formatted = xf
+            .format(text)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        output.write_all(formatted.as_bytes())?;
+        if !formatted.ends_with('\n') {
+            output.write_all(xf.record_separator.as_bytes())?;
+        }
+        output.flush()
+    };
+    let mut reader = BufReader::new(input);
+    let mut line: Vec<u8> = vec![];
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if let Some(message) = unwrapper.unwrap(&String::from_utf8_lossy(&line)) {
+            write_message(message)?;
+        }
+    }
+    match unwrapper.finish() {
+        Some(message) => write_message(message),
+        None => Ok(()),
+    }
+}
+
+// Writes the values at `pointers` in each record, in order, one to a
+// line, skipping those which a record doesn't have.  It's an error for
+// a pointer to find nothing in the whole input.
+fn get_values(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    pointers: &[String],
+    raw: bool,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut found = vec![false; pointers.len()];
+    let mut line: Vec<u8> = vec![];
+    jsonxf::value::read_records(&mut BufReader::new(input), &mut |record| {
+        for (pointer, found) in pointers.iter().zip(found.iter_mut()) {
+            let value = match record.pointer(pointer) {
+                Some(value) => value,
+                None => continue,
+            };
+            *found = true;
+            line.clear();
+            match (raw, value) {
+                (true, Value::String(s)) => line.extend_from_slice(s.as_bytes()),
+                _ => {
+                    // Each value alone, however records are separated
+                    xf.reset();
+                    xf.format_buf(value.to_string().as_bytes(), &mut line)?;
+                    xf.finish_buf(&mut line)?;
+                    let separator = xf.record_separator.as_bytes();
+                    if line.ends_with(separator) {
+                        line.truncate(line.len() - separator.len());
+                    }
+                }
+            }
+            writer.write_all(&line)?;
+            writer.write_all(xf.record_separator.as_bytes())?;
+        }
+        Ok(())
+    })?;
+    if let Some(i) = found.iter().position(|found| !found) {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no value at {}", pointers[i]),
+        ));
+    }
+    writer.flush()
+}
+
+fn print_help(program_name: &str, command: Option<&Subcommand>) {
+    if let Some(command) = command {
+        // Only the command's own options
+        let brief = format!(
+            "Usage: {0} {1} [options] [file ...]\n\n{1}: {2}",
+            program_name, command.name, command.description
+        );
+        print!("{}", command.options().usage(&brief));
+        return;
+    }
+    let desc = "Jsonxf is a JSON transformer.  It provides fast pretty-printing and
+minimizing of JSON-encoded UTF-8 data.";
+
+    let examples = "
+Pretty-print a string to the terminal, using two spaces to indent:
+
+    jsonxf -s '{\"a\": {\"b\": 2, \"c\": false}}'
+
+Pull one value out of an API response, or out of each record in a stream:
+
+    jsonxf get /data/items/0/id -- response.json
+    jsonxf get /user/name <events.ndjson
+
+Pull several values from each record, one per line:
+
+    jsonxf get /id /status /user/name -m <events.ndjson
+
+Pull out plain strings for other tools, without quotes or escapes:
+
+    jsonxf get /user/name --raw-output <events.ndjson | sort | uniq -c
+
+Drop a noisy member from each record, or pull out the elements of an array:
+
+    jsonxf --expr 'del(.debug) | set(.reviewed, false)' events.ndjson
+    jsonxf --expr '.items[] | del(.internal_notes)' -m order.json
+
+Keep only the records which mention a customer, whole, however they are laid out:
+
+    jsonxf --grep '\"customer_id\":\"c-1042\"' events.ndjson
+
+Check that files are well-formed JSON, or compare two of them:
+
+    jsonxf check *.json
+    jsonxf diff old.json new.json
+
+Check files in CI, reporting each problem as a JSON object with its position:
+
+    jsonxf check --keep-going --errors=jsonl config/*.json
+
+Check the JSON files staged for a commit, as a pre-commit hook:
+
+    git diff --cached --name-only --diff-filter=d -- '*.json' | xargs -r jsonxf -c
+
+Pretty-print generated JSON, stopping at the first syntax error instead of
+passing it along:
+
+    jsonxf --strict -i generated.json -o generated-pretty.json
+
+Compare two API responses whose lists may come back in any order:
+
+    jsonxf diff --ignore-order before.json after.json
+
+Fail a test unless a response matches its expected value, in any key order:
+
+    jsonxf --assert-equal --ignore-key-order expected.json actual.json
+
+Divide a huge export into files of a million records each, or a single huge
+array into arrays of 10,000 elements:
+
+    jsonxf split --records 1000000 --out 'part-{seq}.ndjson' -m big.ndjson
+    jsonxf split --array-elements 10000 --out 'chunk-{seq}.json' -m big.json
+
+Layer environment settings and secrets over a base config:
+
+    jsonxf merge base.json prod.json secrets.json -o config.json
+    jsonxf merge --strategy error defaults.json overrides.json
+
+Pretty-print and read a JSON file, using a tab character to indent:
+
+    jsonxf --tab <foo.json | less
+
+Pretty-print with four spaces, or any string, to indent:
+
+    jsonxf --indent 4 <foo.json
+    jsonxf -t '| ' <foo.json
+
+Pretty-print with Windows line endings:
+
+    jsonxf --eol crlf <foo.json >foo-windows.json
+
+Reformat files in place, keeping each one's line endings as they were:
+
+    jsonxf --eol auto -w config/*.json
+
+Reformat a file after editing it, keeping its indentation and line endings:
+
+    jsonxf --indent auto --eol auto -w settings.json
+
+Pretty-print VS Code settings, dropping their comments:
+
+    jsonxf --jsonc .vscode/settings.json
+
+Reindent a tsconfig.json in place, keeping its comments:
+
+    jsonxf --keep-comments -w tsconfig.json
+
+Fix hand-edited files with commas after their last elements:
+
+    jsonxf --trailing-commas --indent auto -w fixtures/*.json
+
+Pretty-print with short arrays, like coordinates, kept on one line:
+
+    jsonxf --inline-arrays 40 <shapes.json
+
+Pretty-print with small objects, like points, kept on one line too:
+
+    jsonxf --inline-arrays 60 --inline-objects 30 <shapes.json
+
+Minimize with only ASCII characters, for pasting into older systems:
+
+    jsonxf -m --ascii <foo.json
+
+Normalize two documents before diffing them:
+
+    diff <(jsonxf --sort-keys <a.json) <(jsonxf --sort-keys <b.json)
+
+Page through a file with syntax highlighting.  Output longer than the terminal
+goes through $PAGER, or `less -R`, unless --no-pager is given:
+
+    jsonxf foo.json
+    PAGER='less -RS' jsonxf foo.json
+
+Follow a service's logs, with errors in red and warnings in yellow:
+
+    jsonxf -f -m --tint-levels=/severity service.log
+
+Read the JSON logged by a container, out of Docker's or Kubernetes' log format:
+
+    jsonxf --docker-logs /var/lib/docker/containers/*/*-json.log
+    jsonxf -f --k8s /var/log/containers/api-7d4b9_default_api-1a2b3c.log
+
+Pretty-print the JSON in a build log, leaving the rest of it alone:
+
+    make test 2>&1 | jsonxf --mixed
+
+Paste snippets of JSON into the terminal, formatting each at a blank line:
+
+    jsonxf --interactive
+
+Minimize a file and gzip it:
+
+    jsonxf -m <foo.json | gzip -c >foo-min.json.gz
+
+Flip a file between pretty-printed and minimized, whichever it is now:
+
+    jsonxf --smart -w foo.json
+
+Reformat vendored files in place, refusing any change but whitespace:
+
+    jsonxf --verify -w vendor/*.json
+
+Minimize records for an audit trail, failing at the first input byte that
+isn't carried through:
+
+    jsonxf -m --audit -i claims.json -o claims-min.json
+
+Minimize a file, checking with serde_json that it parses to the same values
+(with the `verify` feature):
+
+    jsonxf -m --cross-check -i ledger.json -o ledger-min.json
+
+Minimize each file into a copy beside it:
+
+    jsonxf -m --output-template '{dir}/{stem}.min.json' data/*.json
+
+Pretty-print config files in place:
+
+    jsonxf -w config.json settings.json
+
+List the config files which aren't pretty-printed yet, leaving them alone:
+
+    jsonxf -w --list config.json settings.json
+
+Minimize a production config in place, keeping the original as `app.json.orig`:
+
+    jsonxf -m -w --backup=.orig app.json
+
+Keep a formatted copy of a machine-written status file up to date:
+
+    jsonxf --watch status.json -o status-pretty.json
+
+Combine several files into one stream, with one record per line:
+
+    jsonxf --ndjson jan.json feb.json mar.json >q1.ndjson
+
+Read compressed log archives (with the `compression` feature):
+
+    jsonxf app.log.1.gz app.log.2.zst | less
+
+Minimize a large file into a compressed archive, in one process:
+
+    jsonxf -m --zstd -o out.json.zst big.json
+
+Pretty-print every JSON fixture in a repository, except generated ones:
+
+    jsonxf -w -r tests/ --exclude 'generated/**'
+    jsonxf -w 'fixtures/**/*.json'
+
+Pretty-print files found by `find`, however many there are:
+
+    find . -name '*.json' -mtime -1 -print0 | jsonxf -w --files-from -
+
+Pretty-print a large tree of fixtures using every CPU:
+
+    jsonxf -j -w -r fixtures/
+
+Find the paths to every `name` in a file, then turn them back into JSON:
+
+    jsonxf -g -i foo.json | grep '\\.name = ' | jsonxf -u
+
+Skim a huge document, showing only its top two levels:
+
+    jsonxf --max-depth 2 --fold-style ellipsis <huge.json
+
+Explore an unknown dataset, or draft a JSON Schema for it:
+
+    jsonxf schema --format table <big.ndjson
+    jsonxf schema --format schema <big.ndjson >big.schema.json
+
+Get a feel for an unfamiliar dataset:
+
+    jsonxf -p --counts --types <big.ndjson
+
+Compute a content hash which ignores formatting, key order, and how numbers are written:
+
+    jsonxf --hash <doc.json
+
+Drop records which are duplicates, however they are formatted, keeping the first:
+
+    jsonxf --hash-records=xxh3 --with-records <events.ndjson | awk -F'\\t' '!seen[$1]++ {print $2}'
+
+Check in a shell script whether two documents hold the same data:
+
+    cmp -s <(jsonxf --canonical a.json) <(jsonxf --canonical b.json)
+
+Sign a document's RFC 8785 canonical form:
+
+    jsonxf --canonical payload.json | openssl dgst -sha256 -sign key.pem >payload.sig
+
+Minimize a large archive, indexing where each record starts in the output:
+
+    jsonxf -m --index archive.idx <archive.json >archive.ndjson
+
+Index a huge document, then read one value from it without scanning:
+
+    jsonxf --path-index 2 -i big.json >big.idx
+    jsonxf --seek /results/4123 --seek-index big.idx -i big.json
+
+Show the record that a bug report complains about, and the one after it:
+
+    jsonxf --records 8214551-8214552 <events.ndjson
+
+Turn a spreadsheet export into newline-delimited JSON:
+
+    jsonxf --from csv --infer-types -m <people.csv >people.ndjson
+
+Generate a TOML config file from JSON:
+
+    jsonxf --to toml <config.json >config.toml
+
+Pack logs into MessagePack for retention, and read them back later:
+
+    jsonxf --to msgpack <app.ndjson >app.msgpack
+    jsonxf --from msgpack -m <app.msgpack
+
+Pretty-print a CBOR payload from a device:
+
+    jsonxf --from cbor <reading.cbor
+
+Turn an API response into a fixture for a Rust test:
+
+    jsonxf --to rust <response.json
+
+Highlight a payload for an internal web page:
+
+    jsonxf --to html --html-stylesheet <payload.json >payload.html
+
+Sanitize a customer's payload before attaching it to a ticket:
+
+    jsonxf --redact credentials --redact pii <payload.json >payload-redacted.json
+
+Check fixtures for leaked credentials before committing them:
+
+    jsonxf --find-secrets tests/fixtures/*.json
+
+Paste a large payload into an issue comment, collapsing its deep parts:
+
+    jsonxf --to markdown --details-depth 2 <payload.json | pbcopy
+
+Minimize an ingest batch, logging records, sizes, and throughput to stderr:
+
+    jsonxf -m --stats <batch.ndjson >batch-min.ndjson
+
+Minimize a huge export, picking up where it left off if interrupted:
+
+    jsonxf -m --checkpoint export.ckpt -i export.json -o export-min.json
+
+Keep a JSON record of each nightly reformatting run:
+
+    jsonxf -w --stats=json exports/*.json 2>>format-runs.ndjson
+
+Clean up a directory of exports, reporting every file that fails at the end
+instead of stopping at the first:
+
+    jsonxf -w --keep-going exports/*.json
+
+Minimize a log stream without losing the lines which aren't JSON:
+
+    ./server 2>&1 | jsonxf -m --on-error pass >server.log
+
+Apply a JSON Patch to a config file:
+
+    jsonxf --patch changes.json -i config.json
+
+Indent a project's JSON with 4 spaces and sorted keys, by default, in a
+`.jsonxf.toml` file at its root:
+
+    indent = 4
+    sort_keys = true
+
+Set the same defaults in the environment, as in a CI job:
+
+    JSONXF=\"--indent 4 --sort-keys\" jsonxf data.json
+";
+
+    let mut commands = String::new();
+    for command in COMMANDS {
+        commands.push_str(&format!(
+            "    {:<10}{}\n",
+            command.name, command.description
+        ));
+    }
+    let brief = format!(
+        "Usage: {0} [command] [options] [file ...]\n\n{1}\n\nCommands:\n{2}\n\
+         Without a command, input is pretty-printed.  To read a file named\n\
+         like a command, write e.g. `./fmt`.",
+        program_name, desc, commands
+    );
+    print!("{}", all_options().usage(&brief));
     println!("{}", examples);
 }
@@ -0,0 +1,108 @@
+//! Markdown output, for pasting formatted JSON into issues and
+//! comments.
+//!
+//! Output is a fenced `json` code block.  Optionally, members of
+//! top-level objects which nest more deeply than a given depth are
+//! moved out of the block into collapsible `<details>` sections of
+//! their own, so that large payloads do not swamp the page.
+
+use value::{self, Value};
+use Formatter;
+
+/// Formats a string of JSON-encoded data with `xf`, and returns it as
+/// a fenced Markdown code block.
+///
+/// If `details_depth` is given, each member of a top-level object whose
+/// value nests more deeply than that is written in a `<details>` block
+/// after the rest of its record.  Scalars have depth 0, and each level
+/// of array or object adds one.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// let json = "{\"id\": 7, \"tags\": [\"a\"], \"body\": {\"x\": [1]}}";
+/// assert_eq!(
+///     jsonxf::to_markdown(json, &mut xf, None).unwrap(),
+///     "```json\n{\"id\":7,\"tags\":[\"a\"],\"body\":{\"x\":[1]}}\n```\n"
+/// );
+/// assert_eq!(
+///     jsonxf::to_markdown(json, &mut xf, Some(1)).unwrap(),
+///     "```json\n{\"id\":7,\"tags\":[\"a\"]}\n```\n\
+///      <details><summary><code>body</code></summary>\n\
+///      \n\
+///      ```json\n{\"x\":[1]}\n```\n\
+///      \n\
+///      </details>\n"
+/// );
+/// ```
+pub fn to_markdown(
+    json_string: &str,
+    xf: &mut Formatter,
+    details_depth: Option<usize>,
+) -> Result<String, String> {
+    let max_depth = match details_depth {
+        None => {
+            xf.reset();
+            return Ok(fenced(&xf.format(json_string)?));
+        }
+        Some(depth) => depth,
+    };
+
+    let mut out = String::new();
+    for record in value::parse_records(json_string)? {
+        let members = match record {
+            Value::Object(members) => members,
+            other => {
+                out.push_str(&fenced(&format_value(xf, &other)?));
+                continue;
+            }
+        };
+        let (deep, shallow): (Vec<_>, Vec<_>) = members
+            .into_iter()
+            .partition(|(_, value)| depth(value) > max_depth);
+        out.push_str(&fenced(&format_value(xf, &Value::Object(shallow))?));
+        for (key, value) in deep {
+            out.push_str("<details><summary><code>");
+            out.push_str(&html_escape(&key));
+            out.push_str("</code></summary>\n\n");
+            out.push_str(&fenced(&format_value(xf, &value)?));
+            out.push_str("\n</details>\n");
+        }
+    }
+    Ok(out)
+}
+
+fn format_value(xf: &mut Formatter, value: &Value) -> Result<String, String> {
+    xf.reset();
+    xf.format(&value.to_string())
+}
+
+// Returns `text` in a fenced code block, with a fence longer than any
+// run of backticks inside it.
+fn fenced(text: &str) -> String {
+    let text = text.trim_end_matches(['\r', '\n']);
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}json\n{}\n{}\n", fence, text, fence)
+}
+
+// Returns how deeply `value` nests.
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        Value::Object(members) => 1 + members.iter().map(|(_, v)| depth(v)).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
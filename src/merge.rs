@@ -0,0 +1,115 @@
+//! Deep merging of JSON documents.
+
+use std::str::FromStr;
+
+use diff::Differ;
+use path::{PathSegment, Pointer};
+use value::{self, Value};
+
+/// How `merge` resolves two values at the same path, when they are not
+/// both objects.  Objects are always merged member by member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// The value from the later document replaces the earlier one.
+    LastWins,
+
+    /// Differing values are an error.
+    Error,
+
+    /// Arrays are concatenated; other values follow `LastWins`.
+    ConcatArrays,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    /// Parses a strategy name: `last-wins`, `error`, or `concat-arrays`.
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "last-wins" => Ok(Strategy::LastWins),
+            "error" => Ok(Strategy::Error),
+            "concat-arrays" => Ok(Strategy::ConcatArrays),
+            _ => Err(format!("unknown merge strategy `{}`", s)),
+        }
+    }
+}
+
+/// Deep-merges strings of JSON-encoded data, each containing one
+/// value, and returns the minimized result.
+///
+/// Objects are merged recursively, with members from later documents
+/// appended after those of earlier ones.  Other conflicts are resolved
+/// by `strategy`.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::merge::Strategy;
+///
+/// let base = "{\"db\": {\"host\": \"localhost\", \"port\": 5432}, \"tags\": [\"a\"]}";
+/// let prod = "{\"db\": {\"host\": \"db.internal\"}, \"tags\": [\"b\"]}";
+/// assert_eq!(
+///     jsonxf::merge(&[base, prod], Strategy::LastWins).unwrap(),
+///     "{\"db\":{\"host\":\"db.internal\",\"port\":5432},\"tags\":[\"b\"]}"
+/// );
+/// assert_eq!(
+///     jsonxf::merge(&[base, prod], Strategy::ConcatArrays).unwrap(),
+///     "{\"db\":{\"host\":\"db.internal\",\"port\":5432},\"tags\":[\"a\",\"b\"]}"
+/// );
+/// assert_eq!(
+///     jsonxf::merge(&[base, prod], Strategy::Error).unwrap_err(),
+///     "conflicting values at /db/host"
+/// );
+/// ```
+pub fn merge(inputs: &[&str], strategy: Strategy) -> Result<String, String> {
+    let mut values = inputs.iter().map(|input| value::parse(input));
+    let mut merged = match values.next() {
+        Some(first) => first?,
+        None => return Err(String::from("nothing to merge")),
+    };
+    for overlay in values {
+        merge_values(&mut merged, overlay?, strategy)?;
+    }
+    Ok(merged.to_string())
+}
+
+/// Deep-merges `overlay` into `base`.
+pub fn merge_values(base: &mut Value, overlay: Value, strategy: Strategy) -> Result<(), String> {
+    merge_at(&mut vec![], base, overlay, strategy)
+}
+
+fn merge_at(
+    path: &mut Vec<PathSegment>,
+    base: &mut Value,
+    overlay: Value,
+    strategy: Strategy,
+) -> Result<(), String> {
+    match (base, overlay) {
+        (Value::Object(members), Value::Object(overlay_members)) => {
+            for (key, value) in overlay_members {
+                match members.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        path.push(PathSegment::Key(key));
+                        merge_at(path, &mut members[i].1, value, strategy)?;
+                        path.pop();
+                    }
+                    None => members.push((key, value)),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(items), Value::Array(overlay_items))
+            if strategy == Strategy::ConcatArrays =>
+        {
+            items.extend(overlay_items);
+            Ok(())
+        }
+        (base, overlay) => {
+            if strategy == Strategy::Error && !Differ::new().equal(base, &overlay) {
+                return Err(format!("conflicting values at {}", Pointer(path)));
+            }
+            *base = overlay;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Reporting progress while formatting a stream, for services which
+//! export metrics like Prometheus counters.
+
+use std::io::prelude::*;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+use Formatter;
+use BUF_SIZE;
+
+/// Totals for a stream being formatted, as reported by
+/// `format_stream_with_metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Bytes read from the input.
+    pub bytes_in: u64,
+    /// Bytes written to the output.
+    pub bytes_out: u64,
+    /// Records begun in the input.
+    pub records: u64,
+    /// Time since formatting began.
+    pub elapsed: Duration,
+}
+
+impl Metrics {
+    /// Returns the growth in each total since `earlier`, for adding to
+    /// counters.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::metrics::Metrics;
+    /// use std::time::Duration;
+    ///
+    /// let earlier = Metrics { bytes_in: 10, bytes_out: 8, records: 1, elapsed: Duration::from_secs(1) };
+    /// let later = Metrics { bytes_in: 25, bytes_out: 20, records: 3, elapsed: Duration::from_secs(3) };
+    /// assert_eq!(
+    ///     later.since(&earlier),
+    ///     Metrics { bytes_in: 15, bytes_out: 12, records: 2, elapsed: Duration::from_secs(2) }
+    /// );
+    /// ```
+    pub fn since(&self, earlier: &Metrics) -> Metrics {
+        Metrics {
+            bytes_in: self.bytes_in.saturating_sub(earlier.bytes_in),
+            bytes_out: self.bytes_out.saturating_sub(earlier.bytes_out),
+            records: self.records.saturating_sub(earlier.records),
+            elapsed: self.elapsed.saturating_sub(earlier.elapsed),
+        }
+    }
+}
+
+/// Formats a stream of JSON-encoded data as `Formatter::format_stream`
+/// does, calling `report` with the totals so far about once per
+/// `interval`, and once more at the end.  Returns the final totals.
+///
+/// Reports are made between chunks of input, on the formatting thread,
+/// so a slow `report` slows formatting.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::metrics::{format_stream_with_metrics, Metrics};
+/// use std::time::Duration;
+///
+/// let mut reported = Metrics::default();
+/// let mut output: Vec<u8> = vec![];
+/// let totals = format_stream_with_metrics(
+///     &mut jsonxf::Formatter::minimizer(),
+///     &mut "{\"a\": 1}\n{\"a\": 2}\n".as_bytes(),
+///     &mut output,
+///     Duration::from_secs(10),
+///     &mut |metrics| {
+///         // e.g. BYTES_IN.inc_by(metrics.since(&reported).bytes_in)
+///         reported = *metrics;
+///     },
+/// )
+/// .unwrap();
+/// assert_eq!((totals.bytes_in, totals.bytes_out, totals.records), (18, 15, 2));
+/// assert_eq!(reported, totals);
+/// ```
+pub fn format_stream_with_metrics(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    interval: Duration,
+    report: &mut dyn FnMut(&Metrics),
+) -> Result<Metrics, Error> {
+    let started = Instant::now();
+    let mut last_report = started;
+    let mut metrics = Metrics::default();
+    let mut output = Counter {
+        inner: std::io::BufWriter::new(output),
+        count: 0,
+    };
+    let records_before = xf.records();
+    let mut buf = [0_u8; BUF_SIZE];
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        xf.format_buf(&buf[..n], &mut output)?;
+        metrics.bytes_in += n as u64;
+        let now = Instant::now();
+        if now.duration_since(last_report) >= interval {
+            last_report = now;
+            metrics.bytes_out = output.count;
+            metrics.records = xf.records() - records_before;
+            metrics.elapsed = now.duration_since(started);
+            report(&metrics);
+        }
+    }
+    xf.finish_buf(&mut output)?;
+    output.flush()?;
+    metrics.bytes_out = output.count;
+    metrics.records = xf.records() - records_before;
+    metrics.elapsed = started.elapsed();
+    report(&metrics);
+    Ok(metrics)
+}
+
+// Counts the bytes written through it.
+struct Counter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,183 @@
+//! Formatting the JSON in streams which mix it with other text, like
+//! build logs and REPL transcripts.
+//!
+//! Everything that isn't a complete JSON object or array is copied
+//! through untouched.  At each `{` or `[` the stream is read ahead, up
+//! to `MAX_VALUE_LEN` bytes, for the end of a value; if what it finds
+//! is strictly valid JSON it is formatted, and otherwise the bracket is
+//! copied through and the search goes on after it.
+
+use std::io::prelude::*;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use validate::Validator;
+use Formatter;
+use BUF_SIZE;
+
+/// The longest JSON value which will be recognized, in bytes.  Longer
+/// ones are copied through as text.
+pub const MAX_VALUE_LEN: usize = 1024 * 1024;
+
+/// Formats the JSON objects and arrays in a string of text, leaving
+/// the rest as it is.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// assert_eq!(
+///     jsonxf::mixed::format_mixed("got { \"ok\": true } [see above]\n", &mut xf).unwrap(),
+///     "got {\"ok\":true} [see above]\n"
+/// );
+/// ```
+pub fn format_mixed(text: &str, xf: &mut Formatter) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    format_mixed_stream(&mut text.as_bytes(), &mut output, xf).map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Formats the JSON objects and arrays in a stream of text, leaving
+/// the rest as it is.
+pub fn format_mixed_stream(
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    xf: &mut Formatter,
+) -> Result<(), Error> {
+    let mut pending: Vec<u8> = vec![];
+    let mut chunk = [0_u8; BUF_SIZE];
+    let mut eof = false;
+    let mut offset: u64 = 0; // of the start of `pending` in the input
+    loop {
+        // Copy through the text before the next possible value
+        match memchr::memchr2(b'{', b'[', &pending) {
+            None => {
+                output.write_all(&pending)?;
+                offset += pending.len() as u64;
+                pending.clear();
+            }
+            Some(start) => {
+                output.write_all(&pending[..start])?;
+                offset += start as u64;
+                pending.drain(..start);
+                match value_len(&pending, eof) {
+                    Scan::Value(len) => {
+                        // Formatted in place, without a record separator
+                        let mut formatted: Vec<u8> = vec![];
+                        xf.reset();
+                        xf.format_buf(&pending[..len], &mut formatted)?;
+                        let separator = xf.record_separator.as_bytes();
+                        let formatted = formatted.strip_suffix(separator).unwrap_or(&formatted);
+                        output.write_all(formatted)?;
+                        offset += len as u64;
+                        pending.drain(..len);
+                        continue;
+                    }
+                    Scan::NeedMore => {}
+                    scan => {
+                        log_copied(&scan, offset);
+                        output.write_all(&pending[..1])?;
+                        offset += 1;
+                        pending.drain(..1);
+                        continue;
+                    }
+                }
+            }
+        }
+        // Show what's done before waiting on input, as when following
+        output.flush()?;
+        if eof {
+            return Ok(());
+        }
+        match input.read(&mut chunk) {
+            Ok(0) => eof = true,
+            Ok(n) => pending.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+enum Scan {
+    Value(usize), // a valid value, this many bytes long
+    NotValue,
+    Invalid, // balanced brackets, but not JSON
+    TooLong, // no end within `MAX_VALUE_LEN` bytes
+    NeedMore,
+}
+
+// Reports, with the `log` feature, a bracket which began something
+// that looked like JSON but was copied through as text.
+#[cfg(feature = "log")]
+fn log_copied(scan: &Scan, offset: u64) {
+    match *scan {
+        Scan::Invalid => log::debug!(
+            offset = offset;
+            "text at byte {} looks like JSON but isn't valid; copied as text",
+            offset
+        ),
+        Scan::TooLong => log::warn!(
+            offset = offset,
+            max_len = MAX_VALUE_LEN;
+            "value at byte {} is longer than {} bytes; copied as text",
+            offset,
+            MAX_VALUE_LEN
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(not(feature = "log"))]
+fn log_copied(_: &Scan, _: u64) {}
+
+// Finds the end of the object or array at the start of `buf`, and
+// checks that it is valid JSON.
+fn value_len(buf: &[u8], eof: bool) -> Scan {
+    let mut stack: Vec<u8> = vec![];
+    let mut in_string = false;
+    let mut in_backslash = false;
+    for (i, &b) in buf.iter().enumerate().take(MAX_VALUE_LEN) {
+        if in_string {
+            if in_backslash {
+                in_backslash = false;
+            } else if b == b'\\' {
+                in_backslash = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' => stack.push(b'}'),
+            b'[' => stack.push(b']'),
+            b'}' | b']' => {
+                if stack.pop() != Some(b) {
+                    return Scan::NotValue;
+                }
+                if stack.is_empty() {
+                    let len = i + 1;
+                    return match is_json(&buf[..len]) {
+                        true => Scan::Value(len),
+                        false => Scan::Invalid,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    if buf.len() >= MAX_VALUE_LEN {
+        Scan::TooLong
+    } else if eof {
+        Scan::NotValue
+    } else {
+        Scan::NeedMore
+    }
+}
+
+// Returns true if `buf` is strictly valid JSON, in UTF-8.  It holds one
+// value, as `value_len` found its end.
+fn is_json(buf: &[u8]) -> bool {
+    let mut validator = Validator::new();
+    std::str::from_utf8(buf).is_ok() && validator.feed(buf).is_ok() && validator.finish().is_ok()
+}
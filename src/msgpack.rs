@@ -0,0 +1,288 @@
+//! Conversion between JSON and MessagePack.
+//!
+//! Available with the `msgpack` feature.  Each top-level JSON record
+//! becomes one MessagePack value, and a stream of concatenated
+//! MessagePack values becomes newline-delimited JSON.
+//!
+//! Integers are encoded as MessagePack integers when they fit in 64
+//! bits, and other numbers as 64-bit floats.  Binary values become
+//! base64 strings; extension types have no JSON equivalent and are
+//! errors.
+
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use rmp::encode;
+use rmp::Marker;
+
+use value::{self, Value};
+
+/// Converts a string of JSON-encoded data to MessagePack, one value
+/// per top-level record.
+///
+/// # Example:
+///
+/// ```
+/// let packed = jsonxf::msgpack::to_msgpack("{\"a\": [1, -2.5, null]}").unwrap();
+/// assert_eq!(
+///     packed,
+///     vec![0x81, 0xa1, b'a', 0x93, 0x01, 0xcb, 0xc0, 0x04, 0, 0, 0, 0, 0, 0, 0xc0]
+/// );
+/// assert_eq!(
+///     jsonxf::msgpack::from_msgpack(&packed).unwrap(),
+///     "{\"a\":[1,-2.5,null]}\n"
+/// );
+/// ```
+pub fn to_msgpack(json_string: &str) -> Result<Vec<u8>, String> {
+    let mut output: Vec<u8> = vec![];
+    to_msgpack_stream(&mut json_string.as_bytes(), &mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+/// Converts a stream of JSON-encoded data to MessagePack, one value
+/// per top-level record.
+///
+/// Only one record is held in memory at a time.
+pub fn to_msgpack_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut buf: Vec<u8> = vec![];
+    value::read_records(input, &mut |record| {
+        buf.clear();
+        encode_value(&mut buf, &record)?;
+        writer.write_all(&buf)
+    })?;
+    writer.flush()
+}
+
+/// Converts a sequence of MessagePack values to minimized JSON, one
+/// record per line, each followed by a newline.
+pub fn from_msgpack(bytes: &[u8]) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    from_msgpack_stream(&mut &bytes[..], &mut output).map_err(|e| e.to_string())?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+/// Converts a stream of MessagePack values to minimized JSON, one
+/// record per line, each followed by a newline.
+///
+/// Values are written as they are decoded, so memory use depends on
+/// the size of the largest string, not of the largest record.
+pub fn from_msgpack_stream(input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    loop {
+        let mut byte = [0u8];
+        if input.read(&mut byte)? == 0 {
+            break;
+        }
+        decode_value(input, &mut writer, Marker::from_u8(byte[0]))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+fn encode_value(buf: &mut Vec<u8>, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Null => encode::write_nil(buf)?,
+        Value::Bool(b) => encode::write_bool(buf, *b)?,
+        Value::Number(n) => encode_number(buf, n)?,
+        Value::String(s) => encode::write_str(buf, s)?,
+        Value::Array(items) => {
+            encode::write_array_len(buf, length(items.len())?)?;
+            for item in items {
+                encode_value(buf, item)?;
+            }
+        }
+        Value::Object(members) => {
+            encode::write_map_len(buf, length(members.len())?)?;
+            for (key, value) in members {
+                encode::write_str(buf, key)?;
+                encode_value(buf, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_number(buf: &mut Vec<u8>, n: &str) -> Result<(), Error> {
+    if let Ok(i) = n.parse::<i64>() {
+        encode::write_sint(buf, i)?;
+    } else if let Ok(u) = n.parse::<u64>() {
+        encode::write_uint(buf, u)?;
+    } else {
+        match n.parse::<f64>() {
+            Ok(f) if f.is_finite() => encode::write_f64(buf, f)?,
+            _ => return Err(invalid(format!("invalid number `{}`", n))),
+        }
+    }
+    Ok(())
+}
+
+fn length(len: usize) -> Result<u32, Error> {
+    if len > u32::MAX as usize {
+        return Err(invalid(String::from(
+            "arrays and objects are limited to 2^32 - 1 entries",
+        )));
+    }
+    Ok(len as u32)
+}
+
+fn decode_value(input: &mut dyn Read, output: &mut dyn Write, marker: Marker) -> Result<(), Error> {
+    match marker {
+        Marker::Null => output.write_all(b"null"),
+        Marker::True => output.write_all(b"true"),
+        Marker::False => output.write_all(b"false"),
+        Marker::FixPos(n) => write!(output, "{}", n),
+        Marker::FixNeg(n) => write!(output, "{}", n),
+        Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 => {
+            let size = uint_size(marker);
+            write!(output, "{}", read_uint(input, size)?)
+        }
+        Marker::I8 | Marker::I16 | Marker::I32 | Marker::I64 => {
+            let size = uint_size(marker);
+            let bits = size * 8;
+            // sign-extend from the encoded width
+            let n = ((read_uint(input, size)? << (64 - bits)) as i64) >> (64 - bits);
+            write!(output, "{}", n)
+        }
+        Marker::F32 => {
+            let f = f32::from_bits(read_uint(input, 4)? as u32);
+            write_float(output, f.is_finite(), &format!("{:?}", f))
+        }
+        Marker::F64 => {
+            let f = f64::from_bits(read_uint(input, 8)?);
+            write_float(output, f.is_finite(), &format!("{:?}", f))
+        }
+        Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+            let s = read_string(input, marker)?;
+            output.write_all(value::escape(&s).as_bytes())
+        }
+        Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => {
+            let len = read_len(input, marker)?;
+            let bytes = read_bytes(input, len)?;
+            output.write_all(value::escape(&value::base64(&bytes)).as_bytes())
+        }
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
+            let len = read_len(input, marker)?;
+            output.write_all(b"[")?;
+            for i in 0..len {
+                if i > 0 {
+                    output.write_all(b",")?;
+                }
+                let marker = read_marker(input)?;
+                decode_value(input, output, marker)?;
+            }
+            output.write_all(b"]")
+        }
+        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            let len = read_len(input, marker)?;
+            output.write_all(b"{")?;
+            for i in 0..len {
+                if i > 0 {
+                    output.write_all(b",")?;
+                }
+                let key = read_key(input)?;
+                output.write_all(value::escape(&key).as_bytes())?;
+                output.write_all(b":")?;
+                let marker = read_marker(input)?;
+                decode_value(input, output, marker)?;
+            }
+            output.write_all(b"}")
+        }
+        Marker::FixExt1
+        | Marker::FixExt2
+        | Marker::FixExt4
+        | Marker::FixExt8
+        | Marker::FixExt16
+        | Marker::Ext8
+        | Marker::Ext16
+        | Marker::Ext32 => Err(invalid(String::from(
+            "MessagePack extension types cannot be represented in JSON",
+        ))),
+        Marker::Reserved => Err(invalid(String::from("invalid MessagePack marker 0xc1"))),
+    }
+}
+
+// Object keys must be strings in JSON; other scalar keys are
+// converted to their JSON text.
+fn read_key(input: &mut dyn Read) -> Result<String, Error> {
+    let marker = read_marker(input)?;
+    match marker {
+        Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+            read_string(input, marker)
+        }
+        Marker::FixArray(_)
+        | Marker::Array16
+        | Marker::Array32
+        | Marker::FixMap(_)
+        | Marker::Map16
+        | Marker::Map32 => Err(invalid(String::from(
+            "only scalars can be used as object keys",
+        ))),
+        _ => {
+            let mut key: Vec<u8> = vec![];
+            decode_value(input, &mut key, marker)?;
+            String::from_utf8(key).map_err(|e| invalid(e.to_string()))
+        }
+    }
+}
+
+fn write_float(output: &mut dyn Write, finite: bool, text: &str) -> Result<(), Error> {
+    if !finite {
+        return Err(invalid(format!("{} cannot be represented in JSON", text)));
+    }
+    output.write_all(text.as_bytes())
+}
+
+fn read_marker(input: &mut dyn Read) -> Result<Marker, Error> {
+    Ok(Marker::from_u8(read_uint(input, 1)? as u8))
+}
+
+fn read_string(input: &mut dyn Read, marker: Marker) -> Result<String, Error> {
+    let len = read_len(input, marker)?;
+    String::from_utf8(read_bytes(input, len)?).map_err(|e| invalid(e.to_string()))
+}
+
+// Returns the length of a string, binary, array, or map.
+fn read_len(input: &mut dyn Read, marker: Marker) -> Result<usize, Error> {
+    let len = match marker {
+        Marker::FixStr(n) | Marker::FixArray(n) | Marker::FixMap(n) => n as u64,
+        Marker::Str8 | Marker::Bin8 => read_uint(input, 1)?,
+        Marker::Str16 | Marker::Bin16 | Marker::Array16 | Marker::Map16 => read_uint(input, 2)?,
+        _ => read_uint(input, 4)?,
+    };
+    Ok(len as usize)
+}
+
+fn uint_size(marker: Marker) -> usize {
+    match marker {
+        Marker::U8 | Marker::I8 => 1,
+        Marker::U16 | Marker::I16 => 2,
+        Marker::U32 | Marker::I32 => 4,
+        _ => 8,
+    }
+}
+
+// Reads a big-endian unsigned integer of `size` bytes.
+fn read_uint(input: &mut dyn Read, size: usize) -> Result<u64, Error> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf[8 - size..])?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_bytes(input: &mut dyn Read, len: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes: Vec<u8> = vec![];
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "unexpected end of input",
+        ));
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
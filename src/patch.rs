@@ -0,0 +1,395 @@
+//! Applying JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7386)
+//! documents, and generating JSON Patches.
+
+use diff::Differ;
+use path::{self, PathSegment, Pointer};
+use value::{self, Value};
+
+/// Applies a JSON Patch (RFC 6902) to a string containing one JSON
+/// value, and returns the minimized result.
+///
+/// The patch is applied atomically: if any operation fails, an error
+/// describing it is returned and no output is produced.
+///
+/// # Example:
+///
+/// ```
+/// let doc = "{\"name\": \"ana\", \"roles\": [\"dev\"]}";
+/// let patch = "[
+///     {\"op\": \"replace\", \"path\": \"/name\", \"value\": \"Ana\"},
+///     {\"op\": \"add\", \"path\": \"/roles/-\", \"value\": \"ops\"},
+///     {\"op\": \"move\", \"from\": \"/roles\", \"path\": \"/groups\"}
+/// ]";
+/// assert_eq!(
+///     jsonxf::apply_patch(doc, patch).unwrap(),
+///     "{\"name\":\"Ana\",\"groups\":[\"dev\",\"ops\"]}"
+/// );
+/// ```
+pub fn apply_patch(json_string: &str, patch_string: &str) -> Result<String, String> {
+    let mut doc = value::parse(json_string)?;
+    let patch = value::parse(patch_string)?;
+    apply_patch_value(&mut doc, &patch)?;
+    Ok(doc.to_string())
+}
+
+/// Applies a parsed JSON Patch (RFC 6902) to a parsed document.
+///
+/// The patch is applied atomically: if any operation fails, `doc` is
+/// left unchanged.
+pub fn apply_patch_value(doc: &mut Value, patch: &Value) -> Result<(), String> {
+    let operations = match patch {
+        Value::Array(operations) => operations,
+        _ => return Err(String::from("JSON Patch must be an array of operations")),
+    };
+    let mut patched = doc.clone();
+    for (i, operation) in operations.iter().enumerate() {
+        apply_operation(&mut patched, operation).map_err(|e| format!("operation {}: {}", i, e))?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+/// Applies a JSON Merge Patch (RFC 7386) to a string containing one
+/// JSON value, and returns the minimized result.
+///
+/// Objects in the patch are merged recursively into the document, and
+/// `null` members delete the corresponding members of the document.
+/// Any other patch value replaces the target outright.
+///
+/// # Example:
+///
+/// ```
+/// let doc = "{\"a\": \"b\", \"c\": {\"d\": \"e\", \"f\": \"g\"}}";
+/// let patch = "{\"a\": \"z\", \"c\": {\"f\": null}}";
+/// assert_eq!(
+///     jsonxf::apply_merge_patch(doc, patch).unwrap(),
+///     "{\"a\":\"z\",\"c\":{\"d\":\"e\"}}"
+/// );
+/// ```
+pub fn apply_merge_patch(json_string: &str, patch_string: &str) -> Result<String, String> {
+    let mut doc = value::parse(json_string)?;
+    let patch = value::parse(patch_string)?;
+    apply_merge_patch_value(&mut doc, &patch);
+    Ok(doc.to_string())
+}
+
+/// Applies a parsed JSON Merge Patch (RFC 7386) to a parsed document.
+/// Merge patches cannot fail.
+pub fn apply_merge_patch_value(doc: &mut Value, patch: &Value) {
+    let patch_members = match patch {
+        Value::Object(patch_members) => patch_members,
+        _ => {
+            *doc = patch.clone();
+            return;
+        }
+    };
+    if !matches!(doc, Value::Object(_)) {
+        *doc = Value::Object(vec![]);
+    }
+    let members = match doc {
+        Value::Object(members) => members,
+        _ => unreachable!(),
+    };
+    for (key, patch_value) in patch_members {
+        let existing = members.iter().position(|(k, _)| k == key);
+        match (existing, patch_value) {
+            (Some(i), Value::Null) => {
+                members.remove(i);
+            }
+            (None, Value::Null) => {}
+            (Some(i), _) => apply_merge_patch_value(&mut members[i].1, patch_value),
+            (None, _) => {
+                let mut value = Value::Null;
+                apply_merge_patch_value(&mut value, patch_value);
+                members.push((key.clone(), value));
+            }
+        }
+    }
+}
+
+fn apply_operation(doc: &mut Value, operation: &Value) -> Result<(), String> {
+    let op = string_member(operation, "op")?;
+    let path = string_member(operation, "path")?;
+    match op {
+        "add" => add(doc, path, value_member(operation)?.clone()),
+        "remove" => remove(doc, path).map(|_| ()),
+        "replace" => {
+            let value = value_member(operation)?.clone();
+            match doc.pointer_mut(path) {
+                Some(target) => {
+                    *target = value;
+                    Ok(())
+                }
+                None => Err(format!("path not found: {}", path)),
+            }
+        }
+        "move" => {
+            let from = string_member(operation, "from")?;
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(format!("cannot move {} into itself", from));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        "copy" => {
+            let from = string_member(operation, "from")?;
+            let value = match doc.pointer(from) {
+                Some(value) => value.clone(),
+                None => return Err(format!("path not found: {}", from)),
+            };
+            add(doc, path, value)
+        }
+        "test" => {
+            let expected = value_member(operation)?;
+            match doc.pointer(path) {
+                Some(actual) if Differ::new().equal(actual, expected) => Ok(()),
+                Some(_) => Err(format!("test failed at {}", path)),
+                None => Err(format!("path not found: {}", path)),
+            }
+        }
+        _ => Err(format!("unknown op `{}`", op)),
+    }
+}
+
+fn string_member<'a>(operation: &'a Value, name: &str) -> Result<&'a str, String> {
+    match operation.get(name) {
+        Some(Value::String(s)) => Ok(s),
+        Some(_) => Err(format!("`{}` must be a string", name)),
+        None => Err(format!("missing `{}`", name)),
+    }
+}
+
+fn value_member(operation: &Value) -> Result<&Value, String> {
+    operation
+        .get("value")
+        .ok_or_else(|| String::from("missing `value`"))
+}
+
+// Splits a pointer into its parent's pointer and its last reference token.
+fn split_last(pointer: &str) -> Result<(&str, String), String> {
+    match pointer.rfind('/') {
+        Some(i) => {
+            let token = pointer[i + 1..].replace("~1", "/").replace("~0", "~");
+            Ok((&pointer[..i], token))
+        }
+        None => Err(format!("JSON Pointer must start with `/`: {}", pointer)),
+    }
+}
+
+fn add(doc: &mut Value, pointer: &str, value: Value) -> Result<(), String> {
+    if pointer.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, token) = split_last(pointer)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(members)) => {
+            match members.iter().position(|(k, _)| *k == token) {
+                Some(i) => members[i].1 = value,
+                None => members.push((token, value)),
+            }
+            Ok(())
+        }
+        Some(Value::Array(items)) => {
+            let index = if token == "-" {
+                items.len()
+            } else {
+                match path::parse_index(&token) {
+                    Some(index) if index <= items.len() => index,
+                    _ => return Err(format!("invalid array index: {}", pointer)),
+                }
+            };
+            items.insert(index, value);
+            Ok(())
+        }
+        Some(_) => Err(format!("cannot add to a scalar: {}", pointer)),
+        None => Err(format!("path not found: {}", parent)),
+    }
+}
+
+fn remove(doc: &mut Value, pointer: &str) -> Result<Value, String> {
+    if pointer.is_empty() {
+        return Ok(std::mem::replace(doc, Value::Null));
+    }
+    let (parent, token) = split_last(pointer)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(members)) => match members.iter().position(|(k, _)| *k == token) {
+            Some(i) => Ok(members.remove(i).1),
+            None => Err(format!("path not found: {}", pointer)),
+        },
+        Some(Value::Array(items)) => match path::parse_index(&token) {
+            Some(index) if index < items.len() => Ok(items.remove(index)),
+            _ => Err(format!("path not found: {}", pointer)),
+        },
+        _ => Err(format!("path not found: {}", pointer)),
+    }
+}
+
+/// How `generate_patch` describes changes to arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayStrategy {
+    /// Replace a changed array with its new value in one operation.
+    Replace,
+
+    /// Compare elements at the same index, then add or remove elements
+    /// at the end.  Cheap, but an insertion near the start of an array
+    /// changes every element after it.
+    ByIndex,
+
+    /// Find the longest common subsequence of elements, and add and
+    /// remove elements around it.  This gives the smallest patches for
+    /// insertions and deletions, at quadratic cost in array length.
+    Lcs,
+}
+
+/// Generates a JSON Patch (RFC 6902) which transforms the first string
+/// of JSON-encoded data into the second, and returns it minimized.
+/// Each string must contain one JSON value.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::patch::ArrayStrategy;
+///
+/// let a = "{\"name\": \"ana\", \"roles\": [\"dev\", \"ops\"]}";
+/// let b = "{\"roles\": [\"admin\", \"dev\", \"ops\"]}";
+/// let patch = jsonxf::generate_patch(a, b, ArrayStrategy::Lcs).unwrap();
+/// assert_eq!(
+///     patch,
+///     "[{\"op\":\"remove\",\"path\":\"/name\"},{\"op\":\"add\",\"path\":\"/roles/0\",\"value\":\"admin\"}]"
+/// );
+/// assert_eq!(
+///     jsonxf::minimize(&jsonxf::apply_patch(a, &patch).unwrap()).unwrap(),
+///     jsonxf::minimize(b).unwrap()
+/// );
+/// ```
+pub fn generate_patch(a: &str, b: &str, arrays: ArrayStrategy) -> Result<String, String> {
+    let a = value::parse(a)?;
+    let b = value::parse(b)?;
+    Ok(generate_patch_values(&a, &b, arrays).to_string())
+}
+
+/// Generates a JSON Patch (RFC 6902) which transforms `a` into `b`.
+pub fn generate_patch_values(a: &Value, b: &Value, arrays: ArrayStrategy) -> Value {
+    let mut operations = vec![];
+    let mut generator = Generator {
+        arrays,
+        differ: Differ::new(),
+        path: vec![],
+        operations: &mut operations,
+    };
+    generator.generate(a, b);
+    Value::Array(operations)
+}
+
+struct Generator<'a> {
+    arrays: ArrayStrategy,
+    differ: Differ,
+    path: Vec<PathSegment>,
+    operations: &'a mut Vec<Value>,
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&mut self, a: &Value, b: &Value) {
+        if self.differ.equal(a, b) {
+            return;
+        }
+        match (a, b) {
+            (Value::Object(xs), Value::Object(ys)) => {
+                for (key, x) in xs {
+                    self.path.push(PathSegment::Key(key.clone()));
+                    match b.get(key) {
+                        Some(y) => self.generate(x, y),
+                        None => self.push("remove", None),
+                    }
+                    self.path.pop();
+                }
+                for (key, y) in ys {
+                    if a.get(key).is_none() {
+                        self.path.push(PathSegment::Key(key.clone()));
+                        self.push("add", Some(y));
+                        self.path.pop();
+                    }
+                }
+            }
+            (Value::Array(xs), Value::Array(ys)) => match self.arrays {
+                ArrayStrategy::Replace => self.push("replace", Some(b)),
+                ArrayStrategy::ByIndex => self.generate_by_index(xs, ys),
+                ArrayStrategy::Lcs => self.generate_lcs(xs, ys),
+            },
+            _ => self.push("replace", Some(b)),
+        }
+    }
+
+    fn generate_by_index(&mut self, xs: &[Value], ys: &[Value]) {
+        for (i, (x, y)) in xs.iter().zip(ys).enumerate() {
+            self.path.push(PathSegment::Index(i));
+            self.generate(x, y);
+            self.path.pop();
+        }
+        for i in (ys.len()..xs.len()).rev() {
+            self.path.push(PathSegment::Index(i));
+            self.push("remove", None);
+            self.path.pop();
+        }
+        for (i, y) in ys.iter().enumerate().skip(xs.len()) {
+            self.path.push(PathSegment::Index(i));
+            self.push("add", Some(y));
+            self.path.pop();
+        }
+    }
+
+    fn generate_lcs(&mut self, xs: &[Value], ys: &[Value]) {
+        let (n, m) = (xs.len(), ys.len());
+        // lcs[i][j] is the length of the LCS of xs[i..] and ys[j..]
+        let mut lcs = vec![vec![0_usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if self.differ.equal(&xs[i], &ys[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        // k is the index in the partially-patched array
+        let (mut i, mut j, mut k) = (0, 0, 0);
+        while i < n || j < m {
+            self.path.push(PathSegment::Index(k));
+            if i < n && j < m && self.differ.equal(&xs[i], &ys[j]) {
+                i += 1;
+                j += 1;
+                k += 1;
+            } else if i < n && j < m && lcs[i][j] == lcs[i + 1][j + 1] {
+                // neither element is part of the LCS: change one into the other
+                self.generate(&xs[i], &ys[j]);
+                i += 1;
+                j += 1;
+                k += 1;
+            } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                self.push("add", Some(&ys[j]));
+                j += 1;
+                k += 1;
+            } else {
+                self.push("remove", None);
+                i += 1;
+            }
+            self.path.pop();
+        }
+    }
+
+    fn push(&mut self, op: &str, value: Option<&Value>) {
+        let mut members = vec![
+            (String::from("op"), Value::String(String::from(op))),
+            (
+                String::from("path"),
+                Value::String(Pointer(&self.path).to_string()),
+            ),
+        ];
+        if let Some(value) = value {
+            members.push((String::from("value"), value.clone()));
+        }
+        self.operations.push(Value::Object(members));
+    }
+}
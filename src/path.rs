@@ -0,0 +1,257 @@
+//! Tracking the location of each token within a JSON document.
+
+use std::fmt;
+
+use scanner::Token;
+use value;
+
+/// One step of a path into a JSON document: an object key or an
+/// array index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A JSON Pointer (RFC 6901) rendering of a path, e.g. `/users/0/name`.
+/// The root path is the empty string.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::path::{Pointer, PathSegment};
+/// let path = vec![PathSegment::Key(String::from("a/b")), PathSegment::Index(0)];
+/// assert_eq!(Pointer(&path).to_string(), "/a~1b/0");
+/// ```
+pub struct Pointer<'a>(pub &'a [PathSegment]);
+
+impl<'a> fmt::Display for Pointer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in self.0 {
+            match segment {
+                PathSegment::Key(key) => {
+                    write!(f, "/{}", key.replace('~', "~0").replace('/', "~1"))?;
+                }
+                PathSegment::Index(index) => {
+                    write!(f, "/{}", index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a JSON Pointer (RFC 6901) into its unescaped reference
+/// tokens.  The empty pointer refers to the whole document.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::path::parse_pointer("/a~1b/0").unwrap(), vec!["a/b", "0"]);
+/// assert_eq!(jsonxf::path::parse_pointer("").unwrap(), Vec::<String>::new());
+/// assert!(jsonxf::path::parse_pointer("a").is_err());
+/// ```
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    if !pointer.starts_with('/') {
+        return Err(format!("JSON Pointer must start with `/`: {}", pointer));
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Returns the array index named by a JSON Pointer reference token, or
+/// `None` if the token is not a canonical decimal index.
+pub fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// A JavaScript-style rendering of a path, e.g. `json.users[0].name`.
+/// Keys which are not valid identifiers are written in brackets as
+/// JSON strings.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::path::{Dotted, PathSegment};
+/// let path = vec![PathSegment::Key(String::from("a b")), PathSegment::Index(0)];
+/// assert_eq!(Dotted("json", &path).to_string(), "json[\"a b\"][0]");
+/// ```
+pub struct Dotted<'a>(pub &'a str, pub &'a [PathSegment]);
+
+impl<'a> fmt::Display for Dotted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)?;
+        for segment in self.1 {
+            match segment {
+                PathSegment::Key(key) if is_identifier(key) => {
+                    write!(f, ".{}", key)?;
+                }
+                PathSegment::Key(key) => {
+                    f.write_str("[")?;
+                    value::write_escaped(f, key)?;
+                    f.write_str("]")?;
+                }
+                PathSegment::Index(index) => {
+                    write!(f, "[{}]", index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A jq-style rendering of a path with every array index replaced by
+/// `[]`, e.g. `.users[].name` or `.[]["a b"]`, so that all elements of
+/// an array share one path.  The root path is `.`.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::path::{Generalized, PathSegment};
+/// let path = vec![PathSegment::Key(String::from("users")), PathSegment::Index(3)];
+/// assert_eq!(Generalized(&path).to_string(), ".users[]");
+/// assert_eq!(Generalized(&[]).to_string(), ".");
+/// ```
+pub struct Generalized<'a>(pub &'a [PathSegment]);
+
+impl<'a> fmt::Display for Generalized<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.first() {
+            None => return f.write_str("."),
+            Some(PathSegment::Key(key)) if is_identifier(key) => {}
+            Some(_) => f.write_str(".")?,
+        }
+        for segment in self.0 {
+            match segment {
+                PathSegment::Key(key) if is_identifier(key) => {
+                    write!(f, ".{}", key)?;
+                }
+                PathSegment::Key(key) => {
+                    f.write_str("[")?;
+                    value::write_escaped(f, key)?;
+                    f.write_str("]")?;
+                }
+                PathSegment::Index(_) => {
+                    f.write_str("[]")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `key` can be written as a bare JavaScript identifier.
+pub fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object,
+    Array(Option<usize>),
+}
+
+/// `PathTracker` follows a stream of `Token`s and reports the path of
+/// the most recent one.
+///
+/// After `update` is called with a value token, `path()` is the path
+/// of that value; after a `Key`, it is the path of the member the key
+/// introduces; after a closing bracket, it is the path of the closed
+/// container.  Each top-level record starts again at the root.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::path::{PathTracker, Pointer};
+/// use jsonxf::scanner::{Scanner, Token};
+///
+/// let mut paths = vec![];
+/// let mut tracker = PathTracker::new();
+/// Scanner::new()
+///     .scan_stream(&mut "{\"a\": [true, null]}".as_bytes(), &mut |t: Token| {
+///         tracker.update(&t);
+///         paths.push(Pointer(tracker.path()).to_string());
+///         Ok(())
+///     })
+///     .unwrap();
+/// assert_eq!(paths, vec!["", "/a", "/a", "/a/0", "/a/1", "/a", ""]);
+/// ```
+#[derive(Debug, Default)]
+pub struct PathTracker {
+    frames: Vec<Frame>,
+    segments: Vec<PathSegment>,
+}
+
+impl PathTracker {
+    /// Returns a new `PathTracker`, positioned at the root.
+    pub fn new() -> PathTracker {
+        PathTracker::default()
+    }
+
+    /// Returns the current nesting depth.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the path of the most recent token.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Advances the tracker past `token`.
+    pub fn update(&mut self, token: &Token) {
+        match *token {
+            Token::Key(raw) => {
+                self.segments.truncate(self.frames.len().saturating_sub(1));
+                self.segments.push(PathSegment::Key(value::unescape(raw)));
+            }
+            Token::EndObject | Token::EndArray => {
+                self.frames.pop();
+                self.segments.truncate(self.frames.len());
+            }
+            _ => {
+                self.enter_value();
+                match *token {
+                    Token::BeginObject => self.frames.push(Frame::Object),
+                    Token::BeginArray => self.frames.push(Frame::Array(None)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Moves to the position of the next value in the current container.
+    fn enter_value(&mut self) {
+        match self.frames.last_mut() {
+            None => {
+                self.segments.clear();
+            }
+            Some(Frame::Object) => {}
+            Some(Frame::Array(index)) => {
+                let next = match *index {
+                    None => 0,
+                    Some(i) => i + 1,
+                };
+                *index = Some(next);
+                self.segments.truncate(self.frames.len() - 1);
+                self.segments.push(PathSegment::Index(next));
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Listing the distinct paths present in a stream of JSON data.
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::Error;
+
+use path::{Generalized, PathSegment, PathTracker};
+use scanner::{Scanner, Token};
+
+/// One distinct path found by `list_paths`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PathInfo {
+    /// The path, in `jsonxf::path::Generalized` form (e.g. `.users[].name`).
+    pub path: String,
+
+    /// The number of values found at this path.
+    pub count: usize,
+
+    /// The type names of the values found at this path, in order of
+    /// first appearance.
+    pub types: Vec<&'static str>,
+}
+
+/// Lists every distinct path in a string of JSON-encoded data, in order
+/// of first appearance.  Array indices are generalized to `[]`, so all
+/// elements of an array share a path.
+///
+/// # Example:
+///
+/// ```
+/// let paths = jsonxf::list_paths("{\"a\": [1, \"x\"]}\n{\"a\": null}").unwrap();
+/// let names: Vec<&str> = paths.iter().map(|p| p.path.as_str()).collect();
+/// assert_eq!(names, vec![".", ".a", ".a[]"]);
+/// assert_eq!(paths[1].count, 2);
+/// assert_eq!(paths[1].types, vec!["array", "null"]);
+/// assert_eq!(paths[2].types, vec!["number", "string"]);
+/// ```
+pub fn list_paths(json_string: &str) -> Result<Vec<PathInfo>, String> {
+    list_paths_stream(&mut json_string.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Lists every distinct path in a stream of JSON-encoded data, in order
+/// of first appearance.
+///
+/// Memory use depends on the number of distinct paths, not the size
+/// of the input.
+pub fn list_paths_stream(input: &mut dyn Read) -> Result<Vec<PathInfo>, Error> {
+    let mut table: PathTable<PathInfo> = PathTable::new();
+    scan_values(input, &mut table, &mut |row, token| {
+        row.data.count += 1;
+        let type_name = token.type_name().unwrap();
+        if !row.data.types.contains(&type_name) {
+            row.data.types.push(type_name);
+        }
+    })?;
+
+    Ok(table
+        .rows
+        .into_iter()
+        .map(|row| PathInfo {
+            path: row.path,
+            ..row.data
+        })
+        .collect())
+}
+
+/// Per-path accumulators, keyed by generalized path, in order of
+/// first appearance.
+pub(crate) struct PathTable<T> {
+    pub rows: Vec<PathRow<T>>,
+    index: HashMap<String, usize>,
+}
+
+pub(crate) struct PathRow<T> {
+    pub path: String,
+    pub parent: Option<usize>, // the row of the containing array or object
+    pub key: Option<String>,   // the member name, if this path is an object member
+    pub data: T,
+}
+
+impl<T: Default> PathTable<T> {
+    pub fn new() -> PathTable<T> {
+        PathTable {
+            rows: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    // Returns the row index for `path`, adding a row if needed.
+    fn row(&mut self, path: &[PathSegment]) -> usize {
+        let key = Generalized(path).to_string();
+        if let Some(&i) = self.index.get(&key) {
+            return i;
+        }
+        let parent = match path.split_last() {
+            Some((_, parent_path)) => self
+                .index
+                .get(&Generalized(parent_path).to_string())
+                .cloned(),
+            None => None,
+        };
+        self.index.insert(key.clone(), self.rows.len());
+        self.rows.push(PathRow {
+            path: key,
+            parent,
+            key: match path.last() {
+                Some(PathSegment::Key(key)) => Some(key.clone()),
+                _ => None,
+            },
+            data: T::default(),
+        });
+        self.rows.len() - 1
+    }
+}
+
+// Scans a stream, passing each value-starting token to `f` along with
+// the table row for its path.
+pub(crate) fn scan_values<T, F>(
+    input: &mut dyn Read,
+    table: &mut PathTable<T>,
+    f: &mut F,
+) -> Result<(), Error>
+where
+    T: Default,
+    F: FnMut(&mut PathRow<T>, Token),
+{
+    let mut tracker = PathTracker::new();
+    Scanner::new().scan_stream(&mut BufReader::new(input), &mut |token| {
+        tracker.update(&token);
+        if token.is_value_start() {
+            let i = table.row(tracker.path());
+            f(&mut table.rows[i], token);
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,116 @@
+//! Rendering JSON as Rust source, for pasting into tests and fixtures.
+//!
+//! Each record is formatted with the given `Formatter`, then written
+//! either as a `serde_json::json!` macro invocation or as a Rust
+//! string literal, one per line.
+
+use value::{self, Value};
+use Formatter;
+
+/// Renders each record in a string of JSON-encoded data as a
+/// `serde_json::json!` invocation, laid out by `xf`.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::pretty_printer();
+/// assert_eq!(
+///     jsonxf::to_json_macro("{\"a\": \"tab\\there\", \"b\": [1, null]}", &mut xf).unwrap(),
+///     "json!({\n  \"a\": \"tab\\there\",\n  \"b\": [\n    1,\n    null\n  ]\n})"
+/// );
+/// ```
+pub fn to_json_macro(json_string: &str, xf: &mut Formatter) -> Result<String, String> {
+    render_records(json_string, xf, &mut |formatted| {
+        let mut out = String::with_capacity(formatted.len() + 7);
+        out.push_str("json!(");
+        let mut rest = formatted;
+        while let Some(start) = rest.find('"') {
+            let len = string_len(&rest[start..]);
+            out.push_str(&rest[..start]);
+            out.push_str(&rust_escape(&value::unescape(&rest[start..start + len])));
+            rest = &rest[start + len..];
+        }
+        out.push_str(rest);
+        out.push(')');
+        out
+    })
+}
+
+/// Renders each record in a string of JSON-encoded data as a Rust
+/// string literal, laid out by `xf`.
+///
+/// # Example:
+///
+/// ```
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// assert_eq!(
+///     jsonxf::to_rust_string("{\"path\": \"C:\\\\tmp\"}", &mut xf).unwrap(),
+///     r#""{\"path\":\"C:\\\\tmp\"}""#
+/// );
+/// ```
+pub fn to_rust_string(json_string: &str, xf: &mut Formatter) -> Result<String, String> {
+    render_records(json_string, xf, &mut |formatted| {
+        let mut out = String::with_capacity(formatted.len() + 2);
+        out.push('"');
+        for c in formatted.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    })
+}
+
+fn render_records<F>(
+    json_string: &str,
+    xf: &mut Formatter,
+    render: &mut F,
+) -> Result<String, String>
+where
+    F: FnMut(&str) -> String,
+{
+    let records: Vec<Value> = value::parse_records(json_string)?;
+    let mut rendered: Vec<String> = vec![];
+    for record in records {
+        xf.reset();
+        let formatted = xf.format(&record.to_string())?;
+        rendered.push(render(formatted.trim_end()));
+    }
+    Ok(rendered.join("\n"))
+}
+
+// Returns the length of the JSON string at the start of `s`, including
+// its quotes.
+fn string_len(s: &str) -> usize {
+    let mut backslash = false;
+    for (i, b) in s.bytes().enumerate().skip(1) {
+        match b {
+            b'\\' => backslash = !backslash,
+            b'"' if !backslash => return i + 1,
+            _ => backslash = false,
+        }
+    }
+    s.len()
+}
+
+// Returns `s` as a Rust string literal.
+fn rust_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,502 @@
+//! A streaming tokenizer for JSON-encoded UTF-8 data.
+//!
+//! `Scanner` turns chunks of input into a sequence of `Token`s,
+//! carrying partial tokens across chunk boundaries.  Like the rest of
+//! Jsonxf it does not validate its input: unrecognized barewords are
+//! reported as numbers, and stray closing brackets are passed along.
+
+use std::error;
+use std::fmt;
+use std::io::prelude::*;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use super::{BUF_SIZE, C_BACKSLASH, C_COLON, C_COMMA, C_CR, C_LEFT_BRACE, C_LEFT_BRACKET, C_LF};
+use super::{C_QUOTE, C_RIGHT_BRACE, C_RIGHT_BRACKET, C_SPACE, C_TAB};
+
+/// A single lexical element of a JSON document.
+///
+/// Strings, keys, and numbers are given as raw JSON text, exactly as
+/// they appeared in the input.  Strings and keys include their
+/// surrounding quotes; use `jsonxf::value::unescape` to decode them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Key(&'a str),
+    String(&'a str),
+    Number(&'a str),
+    Bool(bool),
+    Null,
+}
+
+impl<'a> Token<'a> {
+    /// Returns true if this token starts a value (a scalar, or the
+    /// opening bracket of an array or object).
+    pub fn is_value_start(&self) -> bool {
+        !matches!(self, Token::EndObject | Token::EndArray | Token::Key(_))
+    }
+
+    /// Returns the type name of the value this token starts, as in
+    /// `jsonxf::value::Value::type_name`, or `None` if it does not
+    /// start a value.
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self {
+            Token::BeginObject => Some("object"),
+            Token::BeginArray => Some("array"),
+            Token::String(_) => Some("string"),
+            Token::Number(_) => Some("number"),
+            Token::Bool(_) => Some("boolean"),
+            Token::Null => Some("null"),
+            Token::EndObject | Token::EndArray | Token::Key(_) => None,
+        }
+    }
+}
+
+/// The location of a token in the input, as byte offsets from the
+/// start of the stream.  `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Span {
+    /// Returns the number of bytes in this span.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Returns true if this span covers no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+}
+
+/// A location in the input: a byte offset from the start of the
+/// stream, and the line and column it falls on.  Lines and columns
+/// count from 1, and columns count bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub offset: u64,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Counts lines in a stream fed to it in chunks, to find the
+/// `Position` of an offset within the latest chunk.
+#[derive(Debug, Clone)]
+pub struct LineCounter {
+    offset: u64,     // the bytes counted so far
+    line: u64,       // the line at `offset`
+    line_start: u64, // the offset at which that line starts
+}
+
+impl Default for LineCounter {
+    fn default() -> LineCounter {
+        LineCounter {
+            offset: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+}
+
+impl LineCounter {
+    pub fn new() -> LineCounter {
+        LineCounter::default()
+    }
+
+    /// Returns the position of `offset` in `chunk`, the next chunk to be
+    /// counted.  Offsets outside of `chunk` are placed at its nearest
+    /// end.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::scanner::LineCounter;
+    ///
+    /// let mut lines = LineCounter::new();
+    /// lines.count(b"{\n  \"a\": 1");
+    /// let position = lines.position(b",\n}}", 13);
+    /// assert_eq!((position.line, position.column), (3, 2));
+    /// ```
+    pub fn position(&self, chunk: &[u8], offset: u64) -> Position {
+        let offset = offset.clamp(self.offset, self.offset + chunk.len() as u64);
+        let mut lines = self.clone();
+        lines.count(&chunk[..(offset - self.offset) as usize]);
+        Position {
+            offset,
+            line: lines.line,
+            column: offset - lines.line_start + 1,
+        }
+    }
+
+    /// Counts the lines in the next chunk of the stream.
+    pub fn count(&mut self, chunk: &[u8]) {
+        for i in memchr::memchr_iter(C_LF, chunk) {
+            self.line += 1;
+            self.line_start = self.offset + i as u64 + 1;
+        }
+        self.offset += chunk.len() as u64;
+    }
+}
+
+/// An error in the syntax of the input, and where it was found.
+///
+/// These are returned inside `std::io::Error`s; `SyntaxError::of`
+/// finds one there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl SyntaxError {
+    /// Wraps this error in an `std::io::Error` of the given kind.
+    pub fn into_io_error(self, kind: ErrorKind) -> Error {
+        Error::new(kind, self)
+    }
+
+    /// Returns the `SyntaxError` inside an `std::io::Error`, if there
+    /// is one.
+    pub fn of(error: &Error) -> Option<&SyntaxError> {
+        error.get_ref()?.downcast_ref::<SyntaxError>()
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl error::Error for SyntaxError {}
+
+/// `Scanner` tokenizes JSON-encoded UTF-8 data fed to it in chunks.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::scanner::{Scanner, Token};
+///
+/// let mut tokens = vec![];
+/// let mut scanner = Scanner::new();
+/// let mut f = |t: Token| {
+///     tokens.push(format!("{:?}", t));
+///     Ok(())
+/// };
+/// scanner.feed(b"{\"a\": [tr", &mut f).unwrap();
+/// scanner.feed(b"ue, 1]}", &mut f).unwrap();
+/// scanner.finish(&mut f).unwrap();
+/// assert_eq!(
+///     tokens.join(" "),
+///     "BeginObject Key(\"\\\"a\\\"\") BeginArray Bool(true) Number(\"1\") EndArray EndObject"
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct Scanner {
+    stack: Vec<u8>,     // open brackets, innermost last
+    expect_key: bool,   // is the next string an object key?
+    in_string: bool,    // is the next byte part of a string?
+    in_backslash: bool, // does the next byte follow a backslash in a string?
+    in_bare: bool,      // is the next byte part of a number or literal?
+    pending: Vec<u8>,   // the token being accumulated
+    start: u64,         // the offset of the token being accumulated
+    offset: u64,        // the offset of the next chunk of input
+}
+
+impl Scanner {
+    /// Returns a new `Scanner`, ready for the start of input.
+    pub fn new() -> Scanner {
+        Scanner::default()
+    }
+
+    /// Returns the current nesting depth.  This is zero between
+    /// top-level records.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the number of bytes fed to this scanner so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Tokenizes a chunk of input, passing each complete token to `f`.
+    ///
+    /// Tokens which are cut off at the end of `buf` are held until
+    /// the next call to `feed` or `finish`.
+    pub fn feed<F>(&mut self, buf: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token) -> Result<(), Error>,
+    {
+        self.feed_spanned(buf, &mut |token, _| f(token))
+    }
+
+    /// Like `feed`, but also passes the location of each token in
+    /// the input.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::scanner::{Scanner, Span, Token};
+    ///
+    /// let mut spans = vec![];
+    /// let mut scanner = Scanner::new();
+    /// let mut f = |_: Token, span: Span| {
+    ///     spans.push(span);
+    ///     Ok(())
+    /// };
+    /// scanner.feed_spanned(b"[\"ab", &mut f).unwrap();
+    /// scanner.feed_spanned(b"c\", 12", &mut f).unwrap();
+    /// scanner.finish_spanned(&mut f).unwrap();
+    /// assert_eq!(
+    ///     spans,
+    ///     vec![
+    ///         Span { start: 0, end: 1 },
+    ///         Span { start: 1, end: 6 },
+    ///         Span { start: 8, end: 10 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn feed_spanned<F>(&mut self, buf: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        let result = self.feed_chunk(buf, f);
+        self.offset += buf.len() as u64;
+        result
+    }
+
+    fn feed_chunk<F>(&mut self, buf: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        let base = self.offset;
+        let mut n = 0;
+        while n < buf.len() {
+            let b = buf[n];
+
+            if self.in_string {
+                if self.in_backslash {
+                    self.pending.push(b);
+                    self.in_backslash = false;
+                    n += 1;
+                    continue;
+                }
+                match memchr::memchr2(C_QUOTE, C_BACKSLASH, &buf[n..]) {
+                    None => {
+                        self.pending.extend_from_slice(&buf[n..]);
+                        break;
+                    }
+                    Some(index) => {
+                        self.pending.extend_from_slice(&buf[n..n + index + 1]);
+                        n += index + 1;
+                        if buf[n - 1] == C_QUOTE {
+                            self.in_string = false;
+                            self.emit_string(base + n as u64, f)?;
+                        } else {
+                            self.in_backslash = true;
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            if self.in_bare {
+                if !is_delimiter(b) {
+                    self.pending.push(b);
+                    n += 1;
+                    continue;
+                }
+                self.in_bare = false;
+                self.emit_bare(base + n as u64, f)?;
+            }
+
+            let here = Span {
+                start: base + n as u64,
+                end: base + n as u64 + 1,
+            };
+            match b {
+                C_SPACE | C_LF | C_CR | C_TAB => {}
+                C_LEFT_BRACE => {
+                    self.stack.push(b);
+                    self.expect_key = true;
+                    f(Token::BeginObject, here)?;
+                }
+                C_LEFT_BRACKET => {
+                    self.stack.push(b);
+                    self.expect_key = false;
+                    f(Token::BeginArray, here)?;
+                }
+                C_RIGHT_BRACE => {
+                    self.stack.pop();
+                    self.expect_key = false;
+                    f(Token::EndObject, here)?;
+                }
+                C_RIGHT_BRACKET => {
+                    self.stack.pop();
+                    self.expect_key = false;
+                    f(Token::EndArray, here)?;
+                }
+                C_COMMA => {
+                    self.expect_key = self.stack.last() == Some(&C_LEFT_BRACE);
+                }
+                C_COLON => {
+                    self.expect_key = false;
+                }
+                C_QUOTE => {
+                    self.in_string = true;
+                    self.start = here.start;
+                    self.pending.clear();
+                    self.pending.push(b);
+                }
+                _ => {
+                    self.in_bare = true;
+                    self.start = here.start;
+                    self.pending.clear();
+                    self.pending.push(b);
+                }
+            }
+            n += 1;
+        }
+        Ok(())
+    }
+
+    /// Signals the end of input, passing any final token to `f`.
+    ///
+    /// Returns an error if input ended inside a string.
+    pub fn finish<F>(&mut self, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token) -> Result<(), Error>,
+    {
+        self.finish_spanned(&mut |token, _| f(token))
+    }
+
+    /// Like `finish`, but also passes the location of the final token.
+    pub fn finish_spanned<F>(&mut self, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        if self.in_bare {
+            self.in_bare = false;
+            self.emit_bare(self.offset, f)?;
+        }
+        if self.in_string {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "unterminated string"));
+        }
+        Ok(())
+    }
+
+    /// Tokenizes an entire stream of JSON-encoded data, passing each
+    /// token to `f`.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut count = 0;
+    /// jsonxf::scanner::Scanner::new()
+    ///     .scan_stream(&mut "[1, 2, 3]".as_bytes(), &mut |_| {
+    ///         count += 1;
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(count, 5);
+    /// ```
+    pub fn scan_stream<F>(&mut self, input: &mut dyn Read, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token) -> Result<(), Error>,
+    {
+        self.scan_stream_spanned(input, &mut |token, _| f(token))
+    }
+
+    /// Like `scan_stream`, but also passes the location of each token
+    /// in the input.
+    pub fn scan_stream_spanned<F>(&mut self, input: &mut dyn Read, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        let mut buf = [0_u8; BUF_SIZE];
+        loop {
+            match input.read(&mut buf) {
+                Ok(0) => {
+                    break;
+                }
+                Ok(n) => {
+                    self.feed_spanned(&buf[0..n], f)?;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        self.finish_spanned(f)
+    }
+
+    fn emit_string<F>(&mut self, end: u64, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        let is_key = self.expect_key && self.stack.last() == Some(&C_LEFT_BRACE);
+        self.expect_key = false;
+        let span = Span {
+            start: self.start,
+            end,
+        };
+        let s = pending_str(&self.pending)?;
+        if is_key {
+            f(Token::Key(s), span)
+        } else {
+            f(Token::String(s), span)
+        }
+    }
+
+    fn emit_bare<F>(&mut self, end: u64, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(Token, Span) -> Result<(), Error>,
+    {
+        let span = Span {
+            start: self.start,
+            end,
+        };
+        match &self.pending[..] {
+            b"true" => f(Token::Bool(true), span),
+            b"false" => f(Token::Bool(false), span),
+            b"null" => f(Token::Null, span),
+            _ => f(Token::Number(pending_str(&self.pending)?), span),
+        }
+    }
+}
+
+fn pending_str(pending: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(pending).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+pub(crate) fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        C_SPACE
+            | C_LF
+            | C_CR
+            | C_TAB
+            | C_COMMA
+            | C_COLON
+            | C_QUOTE
+            | C_LEFT_BRACE
+            | C_LEFT_BRACKET
+            | C_RIGHT_BRACE
+            | C_RIGHT_BRACKET
+    )
+}
@@ -0,0 +1,220 @@
+//! Inferring the shape of a stream of JSON data.
+
+use std::io::prelude::*;
+use std::io::Error;
+
+use paths::{scan_values, PathTable};
+use scanner::Token;
+use value::Value;
+
+const MAX_EXAMPLES: usize = 3;
+
+/// The inferred schema of one path, as found by `infer_schema`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSchema {
+    /// The path, in `jsonxf::path::Generalized` form (e.g. `.users[].name`).
+    pub path: String,
+
+    /// The number of values found at this path.
+    pub count: usize,
+
+    /// The type names of the values found at this path, with the number
+    /// of values of each type, in order of first appearance.
+    pub types: Vec<(&'static str, usize)>,
+
+    /// True if any value at this path was `null`.
+    pub nullable: bool,
+
+    /// For object members, whether the member was present in every
+    /// object at the parent path.  `None` for the root and array elements.
+    pub required: Option<bool>,
+
+    /// Up to three distinct scalar values found at this path, as JSON.
+    pub examples: Vec<String>,
+}
+
+#[derive(Default)]
+struct Observed {
+    count: usize,
+    types: Vec<(&'static str, usize)>,
+    examples: Vec<String>,
+}
+
+impl Observed {
+    fn type_count(&self, type_name: &str) -> usize {
+        self.types
+            .iter()
+            .find(|(t, _)| *t == type_name)
+            .map_or(0, |(_, n)| *n)
+    }
+}
+
+/// Infers a schema for a string of JSON-encoded data, returning one
+/// entry per distinct path in order of first appearance.  Array indices
+/// are generalized to `[]`, and each top-level record counts as one
+/// instance of the root path `.`.
+///
+/// # Example:
+///
+/// ```
+/// let schema = jsonxf::infer_schema(
+///     "{\"id\": 1, \"name\": \"ana\"}\n{\"id\": 2, \"name\": null, \"admin\": true}",
+/// )
+/// .unwrap();
+///
+/// let name = &schema[2];
+/// assert_eq!(name.path, ".name");
+/// assert_eq!(name.types, vec![("string", 1), ("null", 1)]);
+/// assert!(name.nullable);
+/// assert_eq!(name.required, Some(true));
+/// assert_eq!(name.examples, vec!["\"ana\""]);
+///
+/// let admin = &schema[3];
+/// assert_eq!(admin.path, ".admin");
+/// assert_eq!(admin.required, Some(false));
+/// ```
+pub fn infer_schema(json_string: &str) -> Result<Vec<PathSchema>, String> {
+    infer_schema_stream(&mut json_string.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Infers a schema for a stream of JSON-encoded data, returning one
+/// entry per distinct path in order of first appearance.
+///
+/// Memory use depends on the number of distinct paths, not the size
+/// of the input.
+pub fn infer_schema_stream(input: &mut dyn Read) -> Result<Vec<PathSchema>, Error> {
+    let table = observe(input)?;
+    Ok(table
+        .rows
+        .iter()
+        .map(|row| {
+            let required = match row.parent {
+                Some(parent) if row.key.is_some() => {
+                    Some(row.data.count >= table.rows[parent].data.type_count("object"))
+                }
+                _ => None,
+            };
+            PathSchema {
+                path: row.path.clone(),
+                count: row.data.count,
+                types: row.data.types.clone(),
+                nullable: row.data.type_count("null") > 0,
+                required,
+                examples: row.data.examples.clone(),
+            }
+        })
+        .collect())
+}
+
+// Scans a stream, observing the values at each path.
+fn observe(input: &mut dyn Read) -> Result<PathTable<Observed>, Error> {
+    let mut table: PathTable<Observed> = PathTable::new();
+    scan_values(input, &mut table, &mut |row, token| {
+        let observed = &mut row.data;
+        observed.count += 1;
+        let type_name = token.type_name().unwrap();
+        match observed.types.iter_mut().find(|(t, _)| *t == type_name) {
+            Some((_, n)) => *n += 1,
+            None => observed.types.push((type_name, 1)),
+        }
+        let example = match token {
+            Token::String(raw) | Token::Number(raw) => raw,
+            Token::Bool(true) => "true",
+            Token::Bool(false) => "false",
+            _ => return,
+        };
+        if observed.examples.len() < MAX_EXAMPLES && !observed.examples.iter().any(|e| e == example)
+        {
+            observed.examples.push(String::from(example));
+        }
+    })?;
+    Ok(table)
+}
+
+/// Infers a draft JSON Schema (2020-12) document for a string of
+/// JSON-encoded data, and returns it minimized.  Every record is taken
+/// to be an instance of the schema.
+///
+/// # Example:
+///
+/// ```
+/// let schema = jsonxf::schema::json_schema("{\"id\": 1, \"tags\": [\"a\"]}\n{\"id\": 2}").unwrap();
+/// assert_eq!(
+///     schema,
+///     "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"type\":\"object\",\
+///      \"properties\":{\"id\":{\"type\":\"number\"},\
+///      \"tags\":{\"type\":\"array\",\"items\":{\"type\":\"string\"}}},\
+///      \"required\":[\"id\"]}"
+/// );
+/// ```
+pub fn json_schema(json_string: &str) -> Result<String, String> {
+    json_schema_stream(&mut json_string.as_bytes())
+        .map(|schema| schema.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Infers a draft JSON Schema (2020-12) document for a stream of
+/// JSON-encoded data.  Each path's types are listed in order of first
+/// appearance; object members found in every object at their path are
+/// required.
+pub fn json_schema_stream(input: &mut dyn Read) -> Result<Value, Error> {
+    let table = observe(input)?;
+    let mut children: Vec<Vec<usize>> = vec![vec![]; table.rows.len()];
+    for (i, row) in table.rows.iter().enumerate() {
+        if let Some(parent) = row.parent {
+            children[parent].push(i);
+        }
+    }
+    let mut schema = vec![(
+        String::from("$schema"),
+        Value::String(String::from("https://json-schema.org/draft/2020-12/schema")),
+    )];
+    if !table.rows.is_empty() {
+        if let Value::Object(members) = schema_at(&table, &children, 0) {
+            schema.extend(members);
+        }
+    }
+    Ok(Value::Object(schema))
+}
+
+// Builds the schema for the path at row `i`, and those under it.
+fn schema_at(table: &PathTable<Observed>, children: &[Vec<usize>], i: usize) -> Value {
+    let observed = &table.rows[i].data;
+    let string = |s: &str| Value::String(String::from(s));
+    let mut schema = vec![];
+    let types: Vec<Value> = observed.types.iter().map(|(t, _)| string(t)).collect();
+    schema.push((
+        String::from("type"),
+        match types.len() {
+            1 => types[0].clone(),
+            _ => Value::Array(types),
+        },
+    ));
+
+    let objects = observed.type_count("object");
+    if objects > 0 {
+        let mut properties = vec![];
+        let mut required = vec![];
+        for &child in &children[i] {
+            if let Some(key) = &table.rows[child].key {
+                properties.push((key.clone(), schema_at(table, children, child)));
+                if table.rows[child].data.count >= objects {
+                    required.push(string(key));
+                }
+            }
+        }
+        schema.push((String::from("properties"), Value::Object(properties)));
+        if !required.is_empty() {
+            schema.push((String::from("required"), Value::Array(required)));
+        }
+    }
+    if observed.type_count("array") > 0 {
+        let element = children[i]
+            .iter()
+            .find(|&&child| table.rows[child].key.is_none());
+        if let Some(&element) = element {
+            schema.push((String::from("items"), schema_at(table, children, element)));
+        }
+    }
+    Value::Object(schema)
+}
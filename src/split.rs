@@ -0,0 +1,495 @@
+//! Fast division of JSON streams into top-level records.
+//!
+//! `RecordSplitter` finds record boundaries by tracking only nesting
+//! depth and strings, without tokenizing, so records which are not
+//! wanted can be skipped at little more than the cost of reading them.
+//! `split_records` uses it to divide a stream into size-limited
+//! outputs without ever breaking a record in two, and `rechunk_array`
+//! to divide huge arrays into smaller ones.
+
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::ops::RangeInclusive;
+
+use Formatter;
+use {BUF_SIZE, C_BACKSLASH, C_COLON, C_COMMA, C_CR, C_LEFT_BRACE, C_LEFT_BRACKET, C_LF};
+use {C_QUOTE, C_RIGHT_BRACE, C_RIGHT_BRACKET, C_SPACE, C_TAB};
+
+/// `RecordSplitter` divides JSON-encoded data, fed to it in chunks,
+/// into top-level records.  Whitespace and commas between records are
+/// dropped.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::split::RecordSplitter;
+///
+/// let mut records: Vec<Vec<u8>> = vec![];
+/// let mut splitter = RecordSplitter::new();
+/// let mut f = |record: u64, bytes: &[u8], _end: bool| {
+///     if records.len() as u64 == record {
+///         records.push(vec![]);
+///     }
+///     records[record as usize].extend_from_slice(bytes);
+///     Ok(())
+/// };
+/// splitter.feed(b"{\"a\": \"}\"} [1,", &mut f).unwrap();
+/// splitter.feed(b" 2]\n3", &mut f).unwrap();
+/// splitter.finish(&mut f).unwrap();
+/// assert_eq!(records, vec![&b"{\"a\": \"}\"}"[..], b"[1, 2]", b"3"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordSplitter {
+    record: u64,         // the number of the current or next record
+    in_record: bool,     // is the next byte part of a record?
+    unwrap_arrays: bool, // are top-level arrays' elements the records?
+    in_array: bool,      // is the next byte inside an unwrapped array?
+    depth: usize,        // current nesting depth
+    in_string: bool,     // is the next byte part of a string?
+    in_backslash: bool,  // does the next byte follow a backslash in a string?
+    in_bare: bool,       // is the next byte part of a number or literal?
+}
+
+impl RecordSplitter {
+    /// Returns a new `RecordSplitter`, ready for the start of input.
+    pub fn new() -> RecordSplitter {
+        RecordSplitter::default()
+    }
+
+    /// Returns a new `RecordSplitter` which treats the elements of
+    /// top-level arrays as records, rather than the arrays themselves.
+    /// Other top-level values are records as usual.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::split::RecordSplitter;
+    ///
+    /// let mut elements: Vec<String> = vec![];
+    /// let mut splitter = RecordSplitter::array_elements();
+    /// let mut f = |_, bytes: &[u8], _| {
+    ///     elements.push(String::from_utf8(bytes.to_vec()).unwrap());
+    ///     Ok(())
+    /// };
+    /// splitter.feed(b"[{\"a\": [1]}, 2, \"three\"]", &mut f).unwrap();
+    /// splitter.finish(&mut f).unwrap();
+    /// assert_eq!(elements, vec!["{\"a\": [1]}", "2", "\"three\""]);
+    /// ```
+    pub fn array_elements() -> RecordSplitter {
+        RecordSplitter {
+            unwrap_arrays: true,
+            ..RecordSplitter::default()
+        }
+    }
+
+    /// Returns the number of records completed so far.
+    pub fn records(&self) -> u64 {
+        self.record
+    }
+
+    /// Splits a chunk of input, passing each piece of a record in it
+    /// to `f`, along with the record's number (counting from zero) and
+    /// whether the piece ends the record.
+    pub fn feed<F>(&mut self, buf: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u64, &[u8], bool) -> Result<(), Error>,
+    {
+        let mut start = 0; // where the current record begins in `buf`
+        let mut n = 0;
+        while n < buf.len() {
+            let b = buf[n];
+
+            if self.in_string {
+                if self.in_backslash {
+                    self.in_backslash = false;
+                    n += 1;
+                    continue;
+                }
+                match memchr::memchr2(C_QUOTE, C_BACKSLASH, &buf[n..]) {
+                    None => break,
+                    Some(index) => {
+                        n += index + 1;
+                        if buf[n - 1] == C_QUOTE {
+                            self.in_string = false;
+                            if self.depth == 0 {
+                                self.end_record(&buf[start..n], f)?;
+                            }
+                        } else {
+                            self.in_backslash = true;
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            if self.in_bare {
+                if !is_delimiter(b) {
+                    n += 1;
+                    continue;
+                }
+                self.in_bare = false;
+                if self.depth == 0 {
+                    self.end_record(&buf[start..n], f)?;
+                }
+            }
+
+            match b {
+                C_SPACE | C_LF | C_CR | C_TAB => {}
+                C_COMMA | C_COLON if !self.in_record => {}
+                C_LEFT_BRACKET if !self.in_record && self.unwrap_arrays && !self.in_array => {
+                    self.in_array = true;
+                }
+                C_RIGHT_BRACKET if !self.in_record && self.in_array => {
+                    self.in_array = false;
+                }
+                _ => {
+                    if !self.in_record {
+                        self.in_record = true;
+                        start = n;
+                    }
+                    match b {
+                        C_LEFT_BRACE | C_LEFT_BRACKET => self.depth += 1,
+                        C_RIGHT_BRACE | C_RIGHT_BRACKET => {
+                            self.depth = self.depth.saturating_sub(1);
+                            if self.depth == 0 {
+                                self.end_record(&buf[start..n + 1], f)?;
+                            }
+                        }
+                        C_QUOTE => self.in_string = true,
+                        C_COMMA | C_COLON => {}
+                        _ => self.in_bare = true,
+                    }
+                }
+            }
+            n += 1;
+        }
+
+        if self.in_record {
+            f(self.record, &buf[start..], false)?;
+        }
+        Ok(())
+    }
+
+    /// Signals the end of input, ending any record in progress.
+    pub fn finish<F>(&mut self, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u64, &[u8], bool) -> Result<(), Error>,
+    {
+        self.in_bare = false;
+        self.in_string = false;
+        self.in_array = false;
+        self.depth = 0;
+        if self.in_record {
+            self.end_record(&[], f)?;
+        }
+        Ok(())
+    }
+
+    fn end_record<F>(&mut self, bytes: &[u8], f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(u64, &[u8], bool) -> Result<(), Error>,
+    {
+        f(self.record, bytes, true)?;
+        self.in_record = false;
+        self.record += 1;
+        Ok(())
+    }
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        C_SPACE
+            | C_LF
+            | C_CR
+            | C_TAB
+            | C_COMMA
+            | C_COLON
+            | C_QUOTE
+            | C_LEFT_BRACE
+            | C_LEFT_BRACKET
+            | C_RIGHT_BRACE
+            | C_RIGHT_BRACKET
+    )
+}
+
+/// Formats only the records of a stream whose numbers (counting from
+/// zero) are in `records`, and stops reading once they are done.
+/// Other records are skipped without being formatted.
+///
+/// # Example:
+///
+/// ```
+/// let mut output: Vec<u8> = vec![];
+/// jsonxf::split::select_records(
+///     &mut jsonxf::Formatter::minimizer(),
+///     &mut "{\"n\": 0}\n{\"n\": 1}\n{\"n\": 2}\n{\"n\": 3}".as_bytes(),
+///     &mut output,
+///     1..=2,
+/// )
+/// .unwrap();
+/// assert_eq!(output, b"{\"n\":1}\n{\"n\":2}");
+/// ```
+pub fn select_records(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    records: RangeInclusive<u64>,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(output);
+    let mut splitter = RecordSplitter::new();
+    let mut buf = [0_u8; BUF_SIZE];
+    let mut f = |record, bytes: &[u8], end| {
+        if records.contains(&record) {
+            xf.format_buf(bytes, &mut writer)?;
+            if end {
+                // Keep a scalar from running into the next record
+                xf.format_buf(b"\n", &mut writer)?;
+            }
+        }
+        Ok(())
+    };
+    while splitter.records() <= *records.end() {
+        match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => splitter.feed(&buf[..n], &mut f)?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    splitter.finish(&mut f)?;
+    writer.write_all(xf.trailing_output.as_bytes())?;
+    writer.flush()
+}
+
+/// Limits on the size of each output of `split_records`.  A record
+/// which alone exceeds `bytes` is written to an output of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SplitLimits {
+    /// The most records to write to each output.
+    pub records: Option<u64>,
+
+    /// The most bytes of formatted output to write to each output.
+    pub bytes: Option<u64>,
+}
+
+/// Formats a stream of records into a series of outputs, each within
+/// `limits`.  `open` is called with each output's number, counting
+/// from zero, when the first record for it is ready.  Returns the
+/// number of outputs written.
+///
+/// Every record in an output is followed by the formatter's
+/// `record_separator`.  Only one record is held in memory at a time.
+///
+/// # Example:
+///
+/// ```no_run
+/// use jsonxf::split::SplitLimits;
+///
+/// let limits = SplitLimits {
+///     records: Some(1_000_000),
+///     bytes: Some(256 * 1024 * 1024),
+/// };
+/// jsonxf::split::split_records(
+///     &mut jsonxf::Formatter::minimizer(),
+///     &mut std::io::stdin(),
+///     limits,
+///     &mut |n| std::fs::File::create(format!("part-{}.ndjson", n)),
+/// )
+/// .unwrap();
+/// ```
+pub fn split_records<W, F>(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    limits: SplitLimits,
+    open: &mut F,
+) -> Result<u64, Error>
+where
+    W: Write,
+    F: FnMut(u64) -> Result<W, Error>,
+{
+    let mut outputs = Outputs::new(limits, open, &xf.trailing_output);
+    let mut formatted: Vec<u8> = vec![];
+    xf.reset();
+    feed_all(input, &mut RecordSplitter::new(), &mut |_, bytes, end| {
+        xf.format_buf(bytes, &mut formatted)?;
+        if end {
+            end_record(xf, &mut formatted);
+            outputs.write(&formatted)?;
+            formatted.clear();
+        }
+        Ok(())
+    })?;
+    outputs.finish()
+}
+
+/// Rewrites the elements of a stream of top-level arrays as a series
+/// of arrays of at most `max_elements` elements each.  The new arrays
+/// are written as records to a series of outputs, as in `split_records`.
+///
+/// Only one element is held in memory at a time.
+///
+/// # Example:
+///
+/// ```no_run
+/// use jsonxf::split::SplitLimits;
+///
+/// // Write arrays of 1000 elements, each to its own file
+/// let limits = SplitLimits {
+///     records: Some(1),
+///     bytes: None,
+/// };
+/// jsonxf::split::rechunk_array(
+///     &mut jsonxf::Formatter::minimizer(),
+///     &mut std::io::stdin(),
+///     1000,
+///     limits,
+///     &mut |n| std::fs::File::create(format!("chunk-{}.json", n)),
+/// )
+/// .unwrap();
+/// ```
+pub fn rechunk_array<W, F>(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    max_elements: u64,
+    limits: SplitLimits,
+    open: &mut F,
+) -> Result<u64, Error>
+where
+    W: Write,
+    F: FnMut(u64) -> Result<W, Error>,
+{
+    if max_elements == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "arrays must hold at least one element",
+        ));
+    }
+    let mut outputs = Outputs::new(limits, open, &xf.trailing_output);
+    let mut formatted: Vec<u8> = vec![];
+    let mut elements = 0; // in the current chunk
+    let mut in_element = false;
+    xf.reset();
+    feed_all(
+        input,
+        &mut RecordSplitter::array_elements(),
+        &mut |_, bytes, end| {
+            if !in_element {
+                in_element = true;
+                let punctuation: &[u8] = if elements == 0 { b"[" } else { b"," };
+                xf.format_buf(punctuation, &mut formatted)?;
+            }
+            xf.format_buf(bytes, &mut formatted)?;
+            if end {
+                in_element = false;
+                elements += 1;
+                if elements == max_elements {
+                    xf.format_buf(b"]", &mut formatted)?;
+                    end_record(xf, &mut formatted);
+                    outputs.write(&formatted)?;
+                    formatted.clear();
+                    elements = 0;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    if elements > 0 {
+        xf.format_buf(b"]", &mut formatted)?;
+        end_record(xf, &mut formatted);
+        outputs.write(&formatted)?;
+    }
+    outputs.finish()
+}
+
+// Reads all of `input` through `splitter`.
+fn feed_all<F>(input: &mut dyn Read, splitter: &mut RecordSplitter, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(u64, &[u8], bool) -> Result<(), Error>,
+{
+    let mut buf = [0_u8; BUF_SIZE];
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => splitter.feed(&buf[..n], f)?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    splitter.finish(f)
+}
+
+// Terminates a formatted record with a record separator, and readies
+// the formatter for the next one.
+fn end_record(xf: &mut Formatter, formatted: &mut Vec<u8>) {
+    if !formatted.ends_with(xf.record_separator.as_bytes()) {
+        formatted.extend_from_slice(xf.record_separator.as_bytes());
+    }
+    xf.reset();
+}
+
+// A series of outputs, each holding records up to some limits.
+struct Outputs<'a, W: Write, F> {
+    limits: SplitLimits,
+    open: &'a mut F,
+    trailing_output: String,
+    current: Option<(BufWriter<W>, u64, u64)>, // output, records, bytes
+    count: u64,
+}
+
+impl<'a, W, F> Outputs<'a, W, F>
+where
+    W: Write,
+    F: FnMut(u64) -> Result<W, Error>,
+{
+    fn new(limits: SplitLimits, open: &'a mut F, trailing_output: &str) -> Outputs<'a, W, F> {
+        Outputs {
+            limits,
+            open,
+            trailing_output: String::from(trailing_output),
+            current: None,
+            count: 0,
+        }
+    }
+
+    // Writes a formatted record, starting a new output first if the
+    // record would not fit in the current one.
+    fn write(&mut self, record: &[u8]) -> Result<(), Error> {
+        let full = match &self.current {
+            None => true,
+            Some((_, records, size)) => {
+                self.limits.records.is_some_and(|max| *records >= max)
+                    || self
+                        .limits
+                        .bytes
+                        .is_some_and(|max| size + record.len() as u64 > max)
+            }
+        };
+        if full {
+            self.close()?;
+            self.current = Some((BufWriter::new((self.open)(self.count)?), 0, 0));
+            self.count += 1;
+        }
+        let (writer, records, size) = self.current.as_mut().unwrap();
+        writer.write_all(record)?;
+        *records += 1;
+        *size += record.len() as u64;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        if let Some((mut writer, _, _)) = self.current.take() {
+            writer.write_all(self.trailing_output.as_bytes())?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // Closes the last output, returning the number of outputs written.
+    fn finish(mut self) -> Result<u64, Error> {
+        self.close()?;
+        Ok(self.count)
+    }
+}
@@ -0,0 +1,321 @@
+//! Statistics over streams of JSON data.
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io::Error;
+
+use paths::{scan_values, PathTable};
+use scanner::{Scanner, Span, Token};
+use value;
+
+/// Statistics for one path, as found by `key_stats`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyStats {
+    /// The path, in `jsonxf::path::Generalized` form (e.g. `.users[].name`).
+    pub path: String,
+
+    /// The number of values found at this path.
+    pub count: usize,
+
+    /// The type names of the values found at this path, with the number
+    /// of values of each type, in order of first appearance.
+    pub types: Vec<(&'static str, usize)>,
+
+    /// The smallest number found at this path.
+    pub min: Option<f64>,
+
+    /// The largest number found at this path.
+    pub max: Option<f64>,
+
+    /// The mean of the numbers found at this path.
+    pub mean: Option<f64>,
+
+    /// The length, in characters, of the shortest string found at this path.
+    pub min_length: Option<usize>,
+
+    /// The length, in characters, of the longest string found at this path.
+    pub max_length: Option<usize>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    stats: KeyStats,
+    numbers: usize,
+    sum: f64,
+}
+
+/// Computes statistics for each distinct path in a string of
+/// JSON-encoded data, in order of first appearance.  Array indices are
+/// generalized to `[]`.
+///
+/// # Example:
+///
+/// ```
+/// let stats = jsonxf::key_stats("{\"n\": 1, \"s\": \"ab\"}\n{\"n\": 4.5, \"s\": \"abcd\"}").unwrap();
+///
+/// let n = &stats[1];
+/// assert_eq!(n.path, ".n");
+/// assert_eq!((n.min, n.max, n.mean), (Some(1.0), Some(4.5), Some(2.75)));
+///
+/// let s = &stats[2];
+/// assert_eq!(s.path, ".s");
+/// assert_eq!((s.min_length, s.max_length), (Some(2), Some(4)));
+/// ```
+pub fn key_stats(json_string: &str) -> Result<Vec<KeyStats>, String> {
+    key_stats_stream(&mut json_string.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Computes statistics for each distinct path in a stream of
+/// JSON-encoded data, in order of first appearance.
+///
+/// Memory use depends on the number of distinct paths, not the size
+/// of the input.
+pub fn key_stats_stream(input: &mut dyn Read) -> Result<Vec<KeyStats>, Error> {
+    let mut table: PathTable<Accumulator> = PathTable::new();
+    scan_values(input, &mut table, &mut |row, token| {
+        let acc = &mut row.data;
+        acc.stats.count += 1;
+        let type_name = token.type_name().unwrap();
+        match acc.stats.types.iter_mut().find(|(t, _)| *t == type_name) {
+            Some((_, n)) => *n += 1,
+            None => acc.stats.types.push((type_name, 1)),
+        }
+        match token {
+            Token::Number(raw) => {
+                if let Ok(n) = raw.parse::<f64>() {
+                    acc.numbers += 1;
+                    acc.sum += n;
+                    acc.stats.min = Some(acc.stats.min.map_or(n, |min| min.min(n)));
+                    acc.stats.max = Some(acc.stats.max.map_or(n, |max| max.max(n)));
+                }
+            }
+            Token::String(raw) => {
+                let length = value::unescape(raw).chars().count();
+                acc.stats.min_length = Some(acc.stats.min_length.map_or(length, |l| l.min(length)));
+                acc.stats.max_length = Some(acc.stats.max_length.map_or(length, |l| l.max(length)));
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(table
+        .rows
+        .into_iter()
+        .map(|row| {
+            let acc = row.data;
+            KeyStats {
+                path: row.path,
+                mean: if acc.numbers > 0 {
+                    Some(acc.sum / acc.numbers as f64)
+                } else {
+                    None
+                },
+                ..acc.stats
+            }
+        })
+        .collect())
+}
+
+/// Summary statistics for a stream of JSON data, as found by
+/// `stream_stats` or `StatsCollector`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamStats {
+    /// The number of top-level values.
+    pub records: u64,
+
+    /// The number of bytes of input.
+    pub bytes: u64,
+
+    /// The deepest nesting of arrays and objects.
+    pub max_depth: usize,
+
+    /// The size in bytes of the largest record, excluding the
+    /// whitespace around it.
+    pub max_record_bytes: u64,
+
+    /// The number of object keys.
+    pub keys: u64,
+
+    /// The number of string values, not counting keys.
+    pub strings: u64,
+
+    /// The number of number values.
+    pub numbers: u64,
+
+    /// The number of `true` and `false` values.
+    pub bools: u64,
+
+    /// The number of `null` values.
+    pub nulls: u64,
+}
+
+/// Stream statistics are displayed as a one-line JSON object.
+///
+/// # Example:
+///
+/// ```
+/// let stats = jsonxf::stream_stats("[1, \"a\", null]").unwrap();
+/// assert_eq!(
+///     stats.to_string(),
+///     "{\"records\":1,\"bytes\":14,\"max_depth\":1,\"max_record_bytes\":14,\
+///      \"keys\":0,\"strings\":1,\"numbers\":1,\"bools\":0,\"nulls\":1}"
+/// );
+/// ```
+impl fmt::Display for StreamStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{\"records\":{},\"bytes\":{},\"max_depth\":{},\"max_record_bytes\":{},\
+             \"keys\":{},\"strings\":{},\"numbers\":{},\"bools\":{},\"nulls\":{}}}",
+            self.records,
+            self.bytes,
+            self.max_depth,
+            self.max_record_bytes,
+            self.keys,
+            self.strings,
+            self.numbers,
+            self.bools,
+            self.nulls
+        )
+    }
+}
+
+/// Computes summary statistics for a string of JSON-encoded data.
+///
+/// # Example:
+///
+/// ```
+/// let stats = jsonxf::stream_stats("{\"a\": [true, {\"b\": null}]}\n{}").unwrap();
+/// assert_eq!(stats.records, 2);
+/// assert_eq!(stats.max_depth, 3);
+/// assert_eq!(stats.keys, 2);
+/// assert_eq!((stats.bools, stats.nulls), (1, 1));
+/// ```
+pub fn stream_stats(json_string: &str) -> Result<StreamStats, String> {
+    stream_stats_stream(&mut json_string.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Computes summary statistics for a stream of JSON-encoded data.
+pub fn stream_stats_stream(input: &mut dyn Read) -> Result<StreamStats, Error> {
+    let mut collector = StatsCollector::new();
+    std::io::copy(
+        &mut StatsReader::new(input, &mut collector),
+        &mut std::io::sink(),
+    )?;
+    collector.finish()
+}
+
+/// `StatsCollector` computes `StreamStats` from JSON-encoded data fed
+/// to it in chunks, so that statistics can be gathered in the same
+/// pass as other work.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::stats::{StatsCollector, StatsReader};
+///
+/// let mut collector = StatsCollector::new();
+/// let mut input = StatsReader::new("[1,2] [3]".as_bytes(), &mut collector);
+/// let mut output: Vec<u8> = vec![];
+/// jsonxf::minimize_stream(&mut input, &mut output).unwrap();
+///
+/// let stats = collector.finish().unwrap();
+/// assert_eq!(output, b"[1,2]\n[3]");
+/// assert_eq!(stats.records, 2);
+/// assert_eq!(stats.max_record_bytes, 5);
+/// ```
+#[derive(Debug, Default)]
+pub struct StatsCollector {
+    stats: StreamStats,
+    scanner: Scanner,
+    depth: usize,
+    record_start: u64,
+}
+
+impl StatsCollector {
+    /// Returns a new `StatsCollector`, ready for the start of input.
+    pub fn new() -> StatsCollector {
+        StatsCollector::default()
+    }
+
+    /// Adds a chunk of input.
+    pub fn feed(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let StatsCollector {
+            stats,
+            scanner,
+            depth,
+            record_start,
+        } = self;
+        scanner.feed_spanned(buf, &mut |token, span| {
+            observe(stats, depth, record_start, token, span);
+            Ok(())
+        })
+    }
+
+    /// Signals the end of input, and returns the statistics.
+    pub fn finish(mut self) -> Result<StreamStats, Error> {
+        let StatsCollector {
+            stats,
+            scanner,
+            depth,
+            record_start,
+        } = &mut self;
+        scanner.finish_spanned(&mut |token, span| {
+            observe(stats, depth, record_start, token, span);
+            Ok(())
+        })?;
+        stats.bytes = scanner.offset();
+        Ok(self.stats)
+    }
+}
+
+fn observe(
+    stats: &mut StreamStats,
+    depth: &mut usize,
+    record_start: &mut u64,
+    token: Token,
+    span: Span,
+) {
+    if *depth == 0 && token.is_value_start() {
+        stats.records += 1;
+        *record_start = span.start;
+    }
+    match token {
+        Token::BeginObject | Token::BeginArray => {
+            *depth += 1;
+            stats.max_depth = stats.max_depth.max(*depth);
+        }
+        Token::EndObject | Token::EndArray => *depth = depth.saturating_sub(1),
+        Token::Key(_) => stats.keys += 1,
+        Token::String(_) => stats.strings += 1,
+        Token::Number(_) => stats.numbers += 1,
+        Token::Bool(_) => stats.bools += 1,
+        Token::Null => stats.nulls += 1,
+    }
+    if *depth == 0 && !matches!(token, Token::Key(_)) {
+        let size = span.end.saturating_sub(*record_start);
+        stats.max_record_bytes = stats.max_record_bytes.max(size);
+    }
+}
+
+/// `StatsReader` passes input through from another reader, feeding
+/// it to a `StatsCollector` along the way.
+pub struct StatsReader<'a, R> {
+    inner: R,
+    collector: &'a mut StatsCollector,
+}
+
+impl<'a, R: Read> StatsReader<'a, R> {
+    /// Wraps `inner`, feeding everything read from it to `collector`.
+    pub fn new(inner: R, collector: &'a mut StatsCollector) -> StatsReader<'a, R> {
+        StatsReader { inner, collector }
+    }
+}
+
+impl<'a, R: Read> Read for StatsReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = self.inner.read(buf)?;
+        self.collector.feed(&buf[..n])?;
+        Ok(n)
+    }
+}
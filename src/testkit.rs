@@ -0,0 +1,374 @@
+//! Helpers for testing code which formats JSON with Jsonxf.
+//!
+//! Each check comes in two forms: `check_*`, which returns a
+//! description of what went wrong, and `assert_*`, which panics with it,
+//! for use in `#[test]` functions.
+//!
+//! For property testing, `Generator` makes random but structurally
+//! valid JSON, with random whitespace, string escapes, and nesting, and
+//! `check_generated` runs the checks against as many of its documents
+//! as you like.  Generators are seeded, so a failure can be reproduced.
+
+use value;
+use {minimize, Formatter};
+
+/// Checks that formatting is stable: that formatting the output of
+/// `xf` again with `xf` leaves it unchanged.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::testkit::check_stable;
+/// use jsonxf::Formatter;
+///
+/// let mut xf = Formatter::pretty_printer();
+/// xf.indent = String::from("\t");
+/// assert_eq!(check_stable(&mut xf, "{\"a\": [1, {}]}"), Ok(()));
+///
+/// // Prefixing each record isn't stable: the prefix gets formatted too
+/// let mut xf = Formatter::minimizer();
+/// xf.record_separator = String::from("\n[]");
+/// assert!(check_stable(&mut xf, "1 2").is_err());
+/// ```
+pub fn check_stable(xf: &mut Formatter, input: &str) -> Result<(), String> {
+    let once = format(xf, input)?;
+    let twice = format(xf, &once)?;
+    compare("formatting is not stable", "once", &once, "twice", &twice)
+}
+
+/// Panics unless formatting is stable, as for `check_stable`.
+pub fn assert_stable(xf: &mut Formatter, input: &str) {
+    if let Err(e) = check_stable(xf, input) {
+        panic!("{}", e);
+    }
+}
+
+/// Checks that formatting with `xf` changes only whitespace, such that
+/// minimizing its output gives the same result as minimizing its input.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::testkit::check_round_trip;
+/// use jsonxf::Formatter;
+///
+/// let input = "[\"é\", {\"a\": null}]";
+/// assert_eq!(check_round_trip(&mut Formatter::pretty_printer(), input), Ok(()));
+///
+/// let mut xf = Formatter::pretty_printer();
+/// xf.escape_non_ascii = true;
+/// assert!(check_round_trip(&mut xf, input).is_err());
+/// ```
+pub fn check_round_trip(xf: &mut Formatter, input: &str) -> Result<(), String> {
+    let formatted = format(xf, input)?;
+    let expected = minimize(input)?;
+    let actual = minimize(&formatted)?;
+    compare(
+        "formatting changed more than whitespace",
+        "input minimized",
+        &expected,
+        "output minimized",
+        &actual,
+    )
+}
+
+/// Panics unless formatting changes only whitespace, as for
+/// `check_round_trip`.
+pub fn assert_round_trip(xf: &mut Formatter, input: &str) {
+    if let Err(e) = check_round_trip(xf, input) {
+        panic!("{}", e);
+    }
+}
+
+/// Checks that the output of `xf` parses to the same values as its
+/// input, which must be valid JSON.  Unlike `check_round_trip`, this
+/// allows changes like escaping non-ASCII characters.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::testkit::check_same_values;
+/// use jsonxf::Formatter;
+///
+/// let mut xf = Formatter::minimizer();
+/// xf.escape_non_ascii = true;
+/// assert_eq!(check_same_values(&mut xf, "[\"é\"]"), Ok(()));
+/// ```
+pub fn check_same_values(xf: &mut Formatter, input: &str) -> Result<(), String> {
+    let formatted = format(xf, input)?;
+    let expected = value::parse_records(input).map_err(|e| format!("invalid input: {}", e))?;
+    let actual = value::parse_records(&formatted)
+        .map_err(|e| format!("output is not valid JSON: {}\n  output: {:?}", e, formatted))?;
+    if expected.len() != actual.len() {
+        return Err(format!(
+            "formatting changed the number of records from {} to {}\n  output: {:?}",
+            expected.len(),
+            actual.len(),
+            formatted
+        ));
+    }
+    match expected.iter().zip(&actual).position(|(a, b)| a != b) {
+        None => Ok(()),
+        Some(i) => Err(format!(
+            "formatting changed record {}:\n  input: {}\n  output: {}",
+            i + 1,
+            expected[i],
+            actual[i]
+        )),
+    }
+}
+
+/// Panics unless the output parses to the same values as the input, as
+/// for `check_same_values`.
+pub fn assert_same_values(xf: &mut Formatter, input: &str) {
+    if let Err(e) = check_same_values(xf, input) {
+        panic!("{}", e);
+    }
+}
+
+/// Checks the invariants that should hold for any formatting of valid
+/// JSON: that it is stable, and that its output parses to the same
+/// values as its input.
+pub fn check_invariants(xf: &mut Formatter, input: &str) -> Result<(), String> {
+    check_stable(xf, input)?;
+    check_same_values(xf, input)
+}
+
+/// Panics unless the invariants hold, as for `check_invariants`.
+pub fn assert_invariants(xf: &mut Formatter, input: &str) {
+    if let Err(e) = check_invariants(xf, input) {
+        panic!("{}", e);
+    }
+}
+
+/// Checks the invariants, as `check_invariants` does, for `cases`
+/// streams of records made by a `Generator` with the given seed.  The
+/// error names the seed and case, and gives the input.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::testkit::check_generated;
+/// use jsonxf::Formatter;
+///
+/// let mut xf = Formatter::pretty_printer();
+/// xf.indent = String::from("\t");
+/// assert_eq!(check_generated(&mut xf, 42, 100), Ok(()));
+/// ```
+pub fn check_generated(xf: &mut Formatter, seed: u64, cases: usize) -> Result<(), String> {
+    let mut generator = Generator::new(seed);
+    for case in 1..=cases {
+        let count = generator.below(3) + 1;
+        let input = generator.records(count);
+        if let Err(e) = check_invariants(xf, &input) {
+            return Err(format!(
+                "seed {}, case {}: {}\n  input: {:?}",
+                seed, case, e, input
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Panics unless the invariants hold for generated input, as for
+/// `check_generated`.
+pub fn assert_generated(xf: &mut Formatter, seed: u64, cases: usize) {
+    if let Err(e) = check_generated(xf, seed, cases) {
+        panic!("{}", e);
+    }
+}
+
+/// Makes random, structurally valid JSON documents, for property
+/// testing.
+///
+/// Documents have random whitespace around every token, strings with
+/// every kind of escape and multi-byte characters (some of them
+/// brackets and quotes), numbers in every form JSON allows, and arrays
+/// and objects nested up to `max_depth` deep.  The same seed always
+/// makes the same documents.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::testkit::Generator;
+///
+/// let mut generator = Generator::new(7);
+/// let json = generator.document();
+/// assert!(jsonxf::value::parse(&json).is_ok());
+/// assert_eq!(Generator::new(7).document(), json);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    /// How deeply arrays and objects may nest.
+    pub max_depth: usize,
+
+    /// The most items in an array or members in an object.
+    pub max_items: usize,
+
+    state: u64,
+}
+
+// Pieces of string contents, both escaped and not.
+const STRING_PIECES: &[&str] = &[
+    "a",
+    "Z",
+    "0",
+    " ",
+    "key",
+    "{",
+    "}",
+    "[",
+    "]",
+    ",",
+    ":",
+    "\\\"",
+    "\\\\",
+    "\\/",
+    "\\b",
+    "\\f",
+    "\\n",
+    "\\r",
+    "\\t",
+    "\\u0000",
+    "\\u001f",
+    "\\u00e9",
+    "\\uD83D\\uDE00",
+    "é",
+    "漢",
+    "😀",
+];
+
+const WHITESPACE: &[&str] = &["", "", " ", "  ", "\t", "\n", "\r\n"];
+
+impl Generator {
+    /// Returns a generator seeded with `seed`, making documents up to 4
+    /// deep with up to 5 items per array or object.
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            max_depth: 4,
+            max_items: 5,
+            state: seed,
+        }
+    }
+
+    /// Returns one JSON value, surrounded by whitespace.
+    pub fn document(&mut self) -> String {
+        let mut json = String::new();
+        self.whitespace(&mut json);
+        self.value(&mut json, 0);
+        self.whitespace(&mut json);
+        json
+    }
+
+    /// Returns `count` JSON values, separated by at least one
+    /// whitespace character, as in a stream of records.
+    pub fn records(&mut self, count: usize) -> String {
+        let mut json = String::new();
+        for i in 0..count {
+            if i > 0 {
+                json.push_str(["\n", " ", "\r\n", "\n\n"][self.below(4)]);
+            }
+            json.push_str(&self.document());
+        }
+        json
+    }
+
+    fn value(&mut self, json: &mut String, depth: usize) {
+        let kinds = if depth < self.max_depth { 6 } else { 4 };
+        match self.below(kinds) {
+            0 => json.push_str(["true", "false", "null"][self.below(3)]),
+            1 => self.number(json),
+            2 | 3 => self.string(json),
+            4 => self.container(json, depth, false),
+            _ => self.container(json, depth, true),
+        }
+    }
+
+    fn container(&mut self, json: &mut String, depth: usize, object: bool) {
+        json.push(if object { '{' } else { '[' });
+        let items = self.below(self.max_items + 1);
+        for i in 0..items {
+            self.whitespace(json);
+            if i > 0 {
+                json.push(',');
+                self.whitespace(json);
+            }
+            if object {
+                self.string(json);
+                self.whitespace(json);
+                json.push(':');
+                self.whitespace(json);
+            }
+            self.value(json, depth + 1);
+        }
+        self.whitespace(json);
+        json.push(if object { '}' } else { ']' });
+    }
+
+    fn string(&mut self, json: &mut String) {
+        json.push('"');
+        for _ in 0..self.below(6) {
+            json.push_str(STRING_PIECES[self.below(STRING_PIECES.len())]);
+        }
+        json.push('"');
+    }
+
+    fn number(&mut self, json: &mut String) {
+        if self.below(3) == 0 {
+            json.push('-');
+        }
+        match self.below(3) {
+            0 => json.push('0'),
+            1 => json.push_str(&(self.below(9) + 1).to_string()),
+            _ => json.push_str(&self.next().to_string()),
+        }
+        if self.below(3) == 0 {
+            json.push('.');
+            json.push_str(&self.below(1000).to_string());
+        }
+        if self.below(4) == 0 {
+            json.push_str(["e", "E", "e+", "E-"][self.below(4)]);
+            json.push_str(&self.below(400).to_string());
+        }
+    }
+
+    fn whitespace(&mut self, json: &mut String) {
+        json.push_str(WHITESPACE[self.below(WHITESPACE.len())]);
+    }
+
+    // A number from 0 up to but not including `n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // SplitMix64, which is small and good enough for test data.
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+// Formats `input` from the start-of-input state.
+fn format(xf: &mut Formatter, input: &str) -> Result<String, String> {
+    xf.reset();
+    xf.format(input)
+}
+
+// Describes the first difference between two strings, if any.
+fn compare(problem: &str, a_name: &str, a: &str, b_name: &str, b: &str) -> Result<(), String> {
+    if a == b {
+        return Ok(());
+    }
+    let offset = a
+        .bytes()
+        .zip(b.bytes())
+        .position(|(x, y)| x != y)
+        .unwrap_or_else(|| a.len().min(b.len()));
+    Err(format!(
+        "{} (first difference at byte {}):\n  {}: {:?}\n  {}: {:?}",
+        problem, offset, a_name, a, b_name, b
+    ))
+}
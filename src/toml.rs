@@ -0,0 +1,219 @@
+//! Conversion of JSON documents to TOML.
+//!
+//! A document can be converted if it is an object, contains no nulls,
+//! and has no integers beyond the 64-bit range.  Within each table,
+//! plain keys are written before subtables, as TOML requires; arrays
+//! made up entirely of objects become arrays of tables.
+
+use std::fmt::Write;
+
+use path::{PathSegment, Pointer};
+use value::{self, Value};
+
+/// Converts a string containing one JSON object to TOML.
+///
+/// # Example:
+///
+/// ```
+/// let json = "{\"package\": {\"name\": \"demo\", \"version\": \"0.1.0\"}, \
+///             \"bin\": [{\"name\": \"a\"}, {\"name\": \"b\"}], \"debug\": true}";
+/// assert_eq!(
+///     jsonxf::to_toml(json).unwrap(),
+///     "debug = true\n\
+///      \n\
+///      [package]\n\
+///      name = \"demo\"\n\
+///      version = \"0.1.0\"\n\
+///      \n\
+///      [[bin]]\n\
+///      name = \"a\"\n\
+///      \n\
+///      [[bin]]\n\
+///      name = \"b\"\n"
+/// );
+/// assert_eq!(
+///     jsonxf::to_toml("{\"a\": null}").unwrap_err(),
+///     "null at /a cannot be represented in TOML"
+/// );
+/// ```
+pub fn to_toml(json_string: &str) -> Result<String, String> {
+    value_to_toml(&value::parse(json_string)?)
+}
+
+/// Converts a JSON object to TOML.
+pub fn value_to_toml(value: &Value) -> Result<String, String> {
+    let members = match value {
+        Value::Object(members) => members,
+        other => {
+            return Err(format!(
+                "TOML documents must be objects, not {}",
+                other.type_name()
+            ))
+        }
+    };
+    let mut out = String::new();
+    write_table(&mut out, &mut vec![], members, None)?;
+    Ok(out)
+}
+
+fn is_table(value: &Value) -> bool {
+    matches!(value, Value::Object(_))
+}
+
+fn is_table_array(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty() && items.iter().all(is_table),
+        _ => false,
+    }
+}
+
+// Writes a table's members, preceded by `header` if given.
+fn write_table(
+    out: &mut String,
+    path: &mut Vec<PathSegment>,
+    members: &[(String, Value)],
+    header: Option<String>,
+) -> Result<(), String> {
+    if let Some(header) = header {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&header);
+        out.push('\n');
+    }
+
+    for (key, value) in members {
+        if is_table(value) || is_table_array(value) {
+            continue;
+        }
+        path.push(PathSegment::Key(key.clone()));
+        write_key(out, key);
+        out.push_str(" = ");
+        write_inline(out, path, value)?;
+        out.push('\n');
+        path.pop();
+    }
+
+    for (key, value) in members {
+        path.push(PathSegment::Key(key.clone()));
+        match value {
+            Value::Object(sub) => {
+                let header = format!("[{}]", dotted_key(path));
+                write_table(out, path, sub, Some(header))?;
+            }
+            Value::Array(items) if is_table_array(value) => {
+                for (i, item) in items.iter().enumerate() {
+                    let header = format!("[[{}]]", dotted_key(path));
+                    path.push(PathSegment::Index(i));
+                    if let Value::Object(sub) = item {
+                        write_table(out, path, sub, Some(header))?;
+                    }
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+// The TOML key for a table header: the object keys along `path`.
+// Array indices are left out, since a header inside an array of
+// tables refers to its latest element.
+fn dotted_key(path: &[PathSegment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        if let PathSegment::Key(key) = segment {
+            if !out.is_empty() {
+                out.push('.');
+            }
+            write_key(&mut out, key);
+        }
+    }
+    out
+}
+
+fn write_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if bare {
+        out.push_str(key);
+    } else {
+        write_string(out, key);
+    }
+}
+
+fn write_inline(
+    out: &mut String,
+    path: &mut Vec<PathSegment>,
+    value: &Value,
+) -> Result<(), String> {
+    match value {
+        Value::Null => {
+            return Err(format!(
+                "null at {} cannot be represented in TOML",
+                Pointer(path)
+            ))
+        }
+        Value::Bool(b) => write!(out, "{}", b).unwrap(),
+        Value::Number(n) => {
+            let integer = !n.contains(['.', 'e', 'E']);
+            if integer && n.parse::<i64>().is_err() {
+                return Err(format!(
+                    "{} at {} is out of range for a TOML integer",
+                    n,
+                    Pointer(path)
+                ));
+            }
+            out.push_str(n);
+        }
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                path.push(PathSegment::Index(i));
+                write_inline(out, path, item)?;
+                path.pop();
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            out.push('{');
+            for (i, (key, item)) in members.iter().enumerate() {
+                out.push_str(if i > 0 { ", " } else { " " });
+                path.push(PathSegment::Key(key.clone()));
+                write_key(out, key);
+                out.push_str(" = ");
+                write_inline(out, path, item)?;
+                path.pop();
+            }
+            out.push_str(if members.is_empty() { "}" } else { " }" });
+        }
+    }
+    Ok(())
+}
+
+// Writes a TOML basic string.
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c < ' ' || c == '\u{7f}' => write!(out, "\\u{:04X}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
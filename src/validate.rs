@@ -0,0 +1,332 @@
+//! Strict checking of JSON syntax, for `Formatter::validate`.
+//!
+//! Jsonxf formats whatever it is given, so garbage in is garbage out.
+//! `Validator` checks a stream fed to it in chunks against the JSON
+//! grammar of RFC 8259, and reports the first mistake with its
+//! `Position`.  A stream may hold any number of records, with or
+//! without whitespace between them.  Strings are not checked for valid
+//! UTF-8.
+
+use std::io::prelude::*;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use scanner::{is_delimiter, Position, SyntaxError};
+use value;
+use BUF_SIZE;
+use {C_BACKSLASH, C_COLON, C_COMMA, C_CR, C_LEFT_BRACE, C_LEFT_BRACKET, C_LF, C_QUOTE};
+use {C_RIGHT_BRACE, C_RIGHT_BRACKET, C_SPACE, C_TAB};
+
+/// `Validator` checks JSON-encoded data fed to it in chunks.
+///
+/// Errors are `std::io::Error`s holding a `scanner::SyntaxError`: of
+/// kind `InvalidData` for a mistake, or `UnexpectedEof` for input which
+/// ends partway through a record.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::validate::Validator;
+///
+/// let mut validator = Validator::new();
+/// validator.feed(b"{\"a\": [1, 2]}\n{\"b\": tru").unwrap();
+/// assert_eq!(
+///     validator.feed(b"e, \"c\" 3}").unwrap_err().to_string(),
+///     "expected `:`, found `3` at line 2, column 17"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Validator {
+    stack: Vec<(u8, Position)>, // open brackets and where, innermost last
+    expect: Expect,             // what may come next
+    in_string: bool,            // is the next byte part of a string?
+    escape: Escape,             // where the next byte falls in an escape
+    literal: Vec<u8>,           // the number or literal being read
+    start: Position,            // where that or the current string began
+    offset: u64,                // the offset of the next byte
+    line: u64,                  // the line of the next byte
+    line_start: u64,            // the offset at which that line starts
+}
+
+// What may come next, outside of strings, numbers, and literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Record,     // a value or the end of input, at the root
+    Value,      // a value, after `:` or `,` in an array
+    ValueOrEnd, // a value or `]`, after `[`
+    Key,        // a key, after `,` in an object
+    KeyOrEnd,   // a key or `}`, after `{`
+    Colon,      // a `:`, after a key
+    CommaOrEnd, // a `,` or closing bracket, after a value in an array or object
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    Backslash, // the next byte follows a backslash
+    Hex(u8),   // this many hex digits of a `\u` escape are left
+}
+
+impl Default for Validator {
+    fn default() -> Validator {
+        Validator {
+            stack: vec![],
+            expect: Expect::Record,
+            in_string: false,
+            escape: Escape::None,
+            literal: vec![],
+            start: Position::default(),
+            offset: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+}
+
+impl Validator {
+    /// Returns a new `Validator`, ready for the start of input.
+    pub fn new() -> Validator {
+        Validator::default()
+    }
+
+    /// Checks the next chunk of input, returning an error at the first
+    /// mistake.
+    pub fn feed(&mut self, buf: &[u8]) -> Result<(), Error> {
+        for &b in buf {
+            self.byte(b)?;
+            if b == C_LF {
+                self.line += 1;
+                self.line_start = self.offset + 1;
+            }
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    /// Signals the end of input, returning an error if it fell inside
+    /// a record, and readies this validator for new input.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::validate::Validator;
+    ///
+    /// let mut validator = Validator::new();
+    /// validator.feed(b"[1, 2,\n  [3").unwrap();
+    /// assert_eq!(
+    ///     validator.finish().unwrap_err().to_string(),
+    ///     "unclosed `[` at line 2, column 3"
+    /// );
+    /// ```
+    pub fn finish(&mut self) -> Result<(), Error> {
+        let validator = std::mem::take(self);
+        if validator.in_string {
+            return Err(eof_error("unterminated string", validator.start));
+        }
+        if !validator.literal.is_empty() {
+            validator.end_literal()?;
+        }
+        match validator.stack.last() {
+            None => Ok(()),
+            Some(&(bracket, position)) => Err(eof_error(
+                &format!("unclosed `{}`", bracket as char),
+                position,
+            )),
+        }
+    }
+
+    /// Checks an entire stream of JSON-encoded data.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use jsonxf::validate::Validator;
+    ///
+    /// let mut input = &b"{\"a\": [1, 2]}\n[3, 4 5]"[..];
+    /// assert_eq!(
+    ///     Validator::new().validate_stream(&mut input).unwrap_err().to_string(),
+    ///     "expected `,` or `]`, found `5` at line 2, column 7"
+    /// );
+    /// ```
+    pub fn validate_stream(&mut self, input: &mut dyn Read) -> Result<(), Error> {
+        let mut buf = [0_u8; BUF_SIZE];
+        loop {
+            match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => self.feed(&buf[..n])?,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        self.finish()
+    }
+
+    // Is this validator between records, so that a new one would carry
+    // on the same?
+    pub(crate) fn at_rest(&self) -> bool {
+        self.stack.is_empty() && !self.in_string && self.literal.is_empty()
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.offset - self.line_start + 1,
+        }
+    }
+
+    fn byte(&mut self, b: u8) -> Result<(), Error> {
+        if self.in_string {
+            return self.string_byte(b);
+        }
+        if !self.literal.is_empty() {
+            if !is_delimiter(b) {
+                self.literal.push(b);
+                return Ok(());
+            }
+            self.end_literal()?;
+            self.literal.clear();
+        }
+
+        match (b, self.expect) {
+            (C_SPACE | C_LF | C_CR | C_TAB, _) => {}
+            (C_COMMA, Expect::CommaOrEnd) => {
+                self.expect = match self.stack.last() {
+                    Some(&(C_LEFT_BRACE, _)) => Expect::Key,
+                    _ => Expect::Value,
+                }
+            }
+            (C_COLON, Expect::Colon) => self.expect = Expect::Value,
+            (C_RIGHT_BRACKET, Expect::ValueOrEnd | Expect::CommaOrEnd)
+            | (C_RIGHT_BRACE, Expect::KeyOrEnd | Expect::CommaOrEnd)
+                if self.stack.last().map(|&(open, _)| open + 2) == Some(b) =>
+            {
+                self.stack.pop();
+                self.expect = self.after_value();
+            }
+            (C_QUOTE, Expect::Key | Expect::KeyOrEnd) => {
+                self.start_string();
+                self.expect = Expect::Colon;
+            }
+            (_, Expect::Record | Expect::Value | Expect::ValueOrEnd) if is_value_start(b) => {
+                self.expect = self.after_value();
+                match b {
+                    C_LEFT_BRACE => {
+                        self.stack.push((b, self.position()));
+                        self.expect = Expect::KeyOrEnd;
+                    }
+                    C_LEFT_BRACKET => {
+                        self.stack.push((b, self.position()));
+                        self.expect = Expect::ValueOrEnd;
+                    }
+                    C_QUOTE => self.start_string(),
+                    _ => {
+                        self.start = self.position();
+                        self.literal.push(b);
+                    }
+                }
+            }
+            _ => {
+                let found = if b.is_ascii_graphic() {
+                    format!("`{}`", b as char)
+                } else {
+                    format!("byte 0x{:02x}", b)
+                };
+                let message = format!("expected {}, found {}", self.expected(), found);
+                return Err(error(&message, self.position()));
+            }
+        }
+        Ok(())
+    }
+
+    fn string_byte(&mut self, b: u8) -> Result<(), Error> {
+        self.escape = match (self.escape, b) {
+            (Escape::None, C_QUOTE) => {
+                self.in_string = false;
+                Escape::None
+            }
+            (Escape::None, C_BACKSLASH) => Escape::Backslash,
+            (_, 0..=0x1f) => {
+                return Err(error(
+                    "unescaped control character in string",
+                    self.position(),
+                ))
+            }
+            (Escape::None, _) => Escape::None,
+            (Escape::Backslash, b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                Escape::None
+            }
+            (Escape::Backslash, b'u') => Escape::Hex(4),
+            (Escape::Hex(left), _) if b.is_ascii_hexdigit() => match left {
+                1 => Escape::None,
+                _ => Escape::Hex(left - 1),
+            },
+            _ => return Err(error("invalid escape in string", self.position())),
+        };
+        Ok(())
+    }
+
+    fn start_string(&mut self) {
+        self.in_string = true;
+        self.start = self.position();
+    }
+
+    // Checks the number or literal just read.
+    fn end_literal(&self) -> Result<(), Error> {
+        match std::str::from_utf8(&self.literal) {
+            Ok("true" | "false" | "null") => Ok(()),
+            Ok(number) if value::is_number(number) => Ok(()),
+            _ => Err(error(
+                &format!(
+                    "invalid number or literal `{}`",
+                    String::from_utf8_lossy(&self.literal)
+                ),
+                self.start,
+            )),
+        }
+    }
+
+    fn after_value(&self) -> Expect {
+        if self.stack.is_empty() {
+            Expect::Record
+        } else {
+            Expect::CommaOrEnd
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self.expect {
+            Expect::Record | Expect::Value => "a value",
+            Expect::ValueOrEnd => "a value or `]`",
+            Expect::Key => "a key",
+            Expect::KeyOrEnd => "a key or `}`",
+            Expect::Colon => "`:`",
+            Expect::CommaOrEnd => match self.stack.last() {
+                Some(&(C_LEFT_BRACE, _)) => "`,` or `}`",
+                _ => "`,` or `]`",
+            },
+        }
+    }
+}
+
+// Does `b` begin a value?  Anything else not a delimiter begins a
+// number or literal, to be checked when it ends.
+fn is_value_start(b: u8) -> bool {
+    matches!(b, C_LEFT_BRACE | C_LEFT_BRACKET | C_QUOTE) || !is_delimiter(b)
+}
+
+fn error(message: &str, position: Position) -> Error {
+    SyntaxError {
+        message: String::from(message),
+        position,
+    }
+    .into_io_error(ErrorKind::InvalidData)
+}
+
+fn eof_error(message: &str, position: Position) -> Error {
+    SyntaxError {
+        message: String::from(message),
+        position,
+    }
+    .into_io_error(ErrorKind::UnexpectedEof)
+}
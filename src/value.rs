@@ -0,0 +1,584 @@
+//! An in-memory representation of JSON documents, for the tasks
+//! which need a whole document at once.
+//!
+//! Object members keep their input order, and numbers keep their
+//! input text, so a parsed document can be written back out without
+//! changing anything but whitespace.
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use path;
+use scanner::{LineCounter, Scanner, SyntaxError, Token};
+use validate::Validator;
+use BUF_SIZE;
+
+/// A JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, as it appeared in the input.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// An object's members, in input order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the value of the first member named `key`, if this is an
+    /// object which has one.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the first member named `key`, if this is an
+    /// object which has one, for modification.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Object(members) => members.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value at a JSON Pointer (RFC 6901), if there is one.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let value = jsonxf::value::parse("{\"a\": [1, {\"b/c\": 2}]}").unwrap();
+    /// assert_eq!(value.pointer("/a/1/b~1c").unwrap().to_string(), "2");
+    /// assert_eq!(value.pointer("").unwrap(), &value);
+    /// assert!(value.pointer("/a/2").is_none());
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut target = self;
+        for token in path::parse_pointer(pointer).ok()? {
+            target = match target {
+                Value::Object(_) => target.get(&token)?,
+                Value::Array(items) => items.get(path::parse_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Returns the value at a JSON Pointer (RFC 6901), if there is one,
+    /// for modification.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut target = self;
+        for token in path::parse_pointer(pointer).ok()? {
+            target = match target {
+                Value::Object(_) => target.get_mut(&token)?,
+                Value::Array(items) => items.get_mut(path::parse_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Sorts the members of this object, and of every object within it,
+    /// by key.  Keys are compared by their UTF-16 code units, as in
+    /// RFC 8785.  Members with equal keys keep their relative order.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// let mut value = jsonxf::value::parse("{\"b\": [{\"d\": 1, \"c\": 2}], \"a\": 3}").unwrap();
+    /// value.sort_keys();
+    /// assert_eq!(value.to_string(), "{\"a\":3,\"b\":[{\"c\":2,\"d\":1}]}");
+    /// ```
+    pub fn sort_keys(&mut self) {
+        match self {
+            Value::Array(items) => items.iter_mut().for_each(Value::sort_keys),
+            Value::Object(members) => {
+                members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+                members.iter_mut().for_each(|(_, v)| v.sort_keys());
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns a short name for this value's type: `"null"`, `"boolean"`,
+    /// `"number"`, `"string"`, `"array"`, or `"object"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+/// Values are displayed as minimized JSON.
+///
+/// # Example:
+///
+/// ```
+/// let value = jsonxf::value::parse("{ \"a\": [1, \"two\\n\"] }").unwrap();
+/// assert_eq!(value.to_string(), "{\"a\":[1,\"two\\n\"]}");
+/// ```
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => f.write_str(n),
+            Value::String(s) => write_escaped(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Parses a string containing exactly one JSON value.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::value::Value;
+/// assert_eq!(
+///     jsonxf::value::parse("[true, null]").unwrap(),
+///     Value::Array(vec![Value::Bool(true), Value::Null])
+/// );
+/// assert!(jsonxf::value::parse("[1] [2]").is_err());
+/// ```
+pub fn parse(json_string: &str) -> Result<Value, String> {
+    let mut records = parse_records(json_string)?;
+    match records.len() {
+        0 => Err(String::from("no JSON value in input")),
+        1 => Ok(records.remove(0)),
+        _ => Err(String::from("more than one JSON value in input")),
+    }
+}
+
+/// Parses a string containing any number of concatenated or
+/// newline-delimited JSON values.
+///
+/// # Example:
+///
+/// ```
+/// let records = jsonxf::value::parse_records("{\"a\":1}\n{\"a\":2}").unwrap();
+/// assert_eq!(records.len(), 2);
+/// ```
+pub fn parse_records(json_string: &str) -> Result<Vec<Value>, String> {
+    let mut records = vec![];
+    read_records(&mut json_string.as_bytes(), &mut |value| {
+        records.push(value);
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(records)
+}
+
+/// Parses a stream of concatenated or newline-delimited JSON values,
+/// passing each top-level value to `f` as soon as it is complete.
+///
+/// Only one record is held in memory at a time.  The input must be
+/// strictly valid JSON, as checked by `jsonxf::validate::Validator`.
+/// Errors in the input carry a `jsonxf::scanner::SyntaxError`, giving
+/// their position.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::scanner::SyntaxError;
+///
+/// let mut input = &b"{\"a\": 1}\n[2]]"[..];
+/// let error = jsonxf::value::read_records(&mut input, &mut |_| Ok(())).unwrap_err();
+/// assert_eq!(error.to_string(), "unexpected closing bracket at line 2, column 4");
+/// assert_eq!(SyntaxError::of(&error).unwrap().position.offset, 12);
+///
+/// let mut input = &b"[1 2]"[..];
+/// let error = jsonxf::value::read_records(&mut input, &mut |_| Ok(())).unwrap_err();
+/// assert_eq!(error.to_string(), "expected `,` or `]`, found `2` at line 1, column 4");
+/// ```
+pub fn read_records<F>(input: &mut dyn Read, f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(Value) -> Result<(), Error>,
+{
+    let mut builder = Builder::default();
+    let mut scanner = Scanner::new();
+    // The scanner and builder don't check for commas, colons, or
+    // escapes, so each token's bytes are checked too
+    let mut validator = Validator::new();
+    let mut lines = LineCounter::new();
+    let mut buf = [0_u8; BUF_SIZE];
+    let mut offset: u64 = 0; // of the start of the next chunk
+                             // Places an error from the builder at the token which caused it
+    let locate = |error: Error, lines: &LineCounter, chunk: &[u8], offset: u64| {
+        let syntax_error = SyntaxError {
+            message: error.to_string(),
+            position: lines.position(chunk, offset),
+        };
+        syntax_error.into_io_error(error.kind())
+    };
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let chunk = &buf[..n];
+        let chunk_start = offset;
+        let mut failed_at = None;
+        let mut validated = 0;
+        scanner
+            .feed_spanned(chunk, &mut |token, span| {
+                #[cfg(feature = "log")]
+                log_duplicate_key(&builder, &token, &lines, chunk, span.start);
+                let pushed = builder.push(token).inspect_err(|_| {
+                    failed_at = Some(span.start);
+                })?;
+                let end = (span.end.saturating_sub(chunk_start) as usize).min(chunk.len());
+                if end > validated {
+                    validator.feed(&chunk[validated..end])?;
+                    validated = end;
+                }
+                match pushed {
+                    Some(value) => f(value),
+                    None => Ok(()),
+                }
+            })
+            .and_then(|_| validator.feed(&chunk[validated..]))
+            .map_err(|e| match failed_at {
+                Some(offset) => locate(e, &lines, chunk, offset),
+                None => e,
+            })?;
+        lines.count(chunk);
+        offset += n as u64;
+    }
+    // Errors at the end of input are placed there
+    scanner
+        .finish(&mut |token| match builder.push(token) {
+            Ok(Some(value)) => f(value),
+            Ok(None) => Ok(()),
+            // A bad literal at the end is placed by the validator
+            Err(e) => Err(validator.finish().err().unwrap_or(e)),
+        })
+        .and_then(|_| builder.finish())
+        .and_then(|_| validator.finish())
+        .map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => locate(e, &lines, &[], u64::MAX),
+            _ => e,
+        })
+}
+
+/// Assembles `Value`s from a stream of `Token`s.
+///
+/// Bare words which aren't numbers or literals are rejected, but commas
+/// and colons aren't tokens, so a caller which needs strictly valid JSON
+/// also feeds its input to a `jsonxf::validate::Validator`, as
+/// `read_records` does.
+#[derive(Debug, Default)]
+pub struct Builder {
+    stack: Vec<(Value, Option<String>)>, // open containers, with pending keys
+}
+
+impl Builder {
+    /// Adds a token, returning a top-level value if this token
+    /// completed one.
+    pub fn push(&mut self, token: Token) -> Result<Option<Value>, Error> {
+        let value = match token {
+            Token::BeginObject => {
+                self.stack.push((Value::Object(vec![]), None));
+                return Ok(None);
+            }
+            Token::BeginArray => {
+                self.stack.push((Value::Array(vec![]), None));
+                return Ok(None);
+            }
+            Token::Key(raw) => {
+                match self.stack.last_mut() {
+                    Some((Value::Object(_), key)) => *key = Some(unescape(raw)),
+                    _ => return Err(invalid("unexpected object key")),
+                }
+                return Ok(None);
+            }
+            Token::EndObject | Token::EndArray => match self.stack.pop() {
+                Some((value @ Value::Object(_), _)) if token == Token::EndObject => value,
+                Some((value @ Value::Array(_), _)) if token == Token::EndArray => value,
+                _ => return Err(invalid("unexpected closing bracket")),
+            },
+            Token::String(raw) => Value::String(unescape(raw)),
+            Token::Number(raw) if is_number(raw) => Value::Number(String::from(raw)),
+            Token::Number(raw) => {
+                return Err(invalid(&format!("invalid number or literal `{}`", raw)))
+            }
+            Token::Bool(b) => Value::Bool(b),
+            Token::Null => Value::Null,
+        };
+
+        match self.stack.last_mut() {
+            None => Ok(Some(value)),
+            Some((Value::Array(items), _)) => {
+                items.push(value);
+                Ok(None)
+            }
+            Some((Value::Object(members), key)) => match key.take() {
+                Some(key) => {
+                    members.push((key, value));
+                    Ok(None)
+                }
+                None => Err(invalid("object member without a key")),
+            },
+            Some(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an error if a value was left incomplete.
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.stack.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "unexpected end of input",
+            ))
+        }
+    }
+}
+
+// Warns of a key which repeats one already in its object, with the
+// `log` feature.  Only the last of the repeated members is kept by
+// most JSON parsers, and all of them by Jsonxf.
+#[cfg(feature = "log")]
+fn log_duplicate_key(
+    builder: &Builder,
+    token: &Token,
+    lines: &LineCounter,
+    chunk: &[u8],
+    offset: u64,
+) {
+    let raw = match token {
+        Token::Key(raw) if log::log_enabled!(log::Level::Warn) => raw,
+        _ => return,
+    };
+    let key = unescape(raw);
+    if let Some((Value::Object(members), _)) = builder.stack.last() {
+        if members.iter().any(|(k, _)| *k == key) {
+            let position = lines.position(chunk, offset);
+            log::warn!(
+                key = key.as_str(),
+                offset = position.offset,
+                line = position.line,
+                column = position.column;
+                "duplicate object key {:?} at {}",
+                key,
+                position
+            );
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Returns true if `s` is a number in JSON syntax.
+///
+/// # Example:
+///
+/// ```
+/// assert!(jsonxf::value::is_number("-12.5e+3"));
+/// assert!(!jsonxf::value::is_number("012"));
+/// assert!(!jsonxf::value::is_number("1."));
+/// ```
+pub fn is_number(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s).as_bytes();
+    let digits = |s: &[u8]| s.iter().take_while(|b| b.is_ascii_digit()).count();
+    let int = digits(s);
+    if int == 0 || (int > 1 && s[0] == b'0') {
+        return false;
+    }
+    let mut rest = &s[int..];
+    if let Some(b'.') = rest.first() {
+        let frac = digits(&rest[1..]);
+        if frac == 0 {
+            return false;
+        }
+        rest = &rest[1 + frac..];
+    }
+    if let Some(b'e') | Some(b'E') = rest.first() {
+        rest = &rest[1..];
+        if let Some(b'+') | Some(b'-') = rest.first() {
+            rest = &rest[1..];
+        }
+        let exp = digits(rest);
+        if exp == 0 {
+            return false;
+        }
+        rest = &rest[exp..];
+    }
+    rest.is_empty()
+}
+
+/// Decodes a raw JSON string, with or without its surrounding quotes.
+/// Malformed escapes are passed through as-is.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::value::unescape("\"a\\tb\\u00e9\""), "a\tb\u{e9}");
+/// ```
+pub fn unescape(raw: &str) -> String {
+    let raw = raw.strip_prefix('"').unwrap_or(raw);
+    let raw = raw.strip_suffix('"').unwrap_or(raw);
+    if !raw.contains('\\') {
+        return String::from(raw);
+    }
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let rest = chars.as_str();
+                match parse_hex4(rest) {
+                    Some(hi @ 0xd800..=0xdbff) => {
+                        let lo = match rest.get(4..6) {
+                            Some("\\u") => parse_hex4(&rest[6..]),
+                            _ => None,
+                        };
+                        match lo {
+                            Some(lo @ 0xdc00..=0xdfff) => {
+                                let code = 0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00);
+                                out.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                                chars = rest[10..].chars();
+                            }
+                            _ => {
+                                out.push('\u{fffd}');
+                                chars = rest[4..].chars();
+                            }
+                        }
+                    }
+                    Some(code) => {
+                        out.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        chars = rest[4..].chars();
+                    }
+                    None => out.push_str("\\u"),
+                }
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn parse_hex4(s: &str) -> Option<u32> {
+    match s.get(0..4) {
+        Some(hex) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            u32::from_str_radix(hex, 16).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Writes `s` as a quoted JSON string, escaping quotes, backslashes,
+/// and control characters.
+pub fn write_escaped(f: &mut dyn fmt::Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{8}' => "\\b",
+            '\u{c}' => "\\f",
+            c if c < ' ' => "",
+            _ => continue,
+        };
+        f.write_str(&s[start..i])?;
+        if escape.is_empty() {
+            write!(f, "\\u{:04x}", c as u32)?;
+        } else {
+            f.write_str(escape)?;
+        }
+        start = i + c.len_utf8();
+    }
+    f.write_str(&s[start..])?;
+    f.write_char('"')
+}
+
+/// Returns `s` as a quoted JSON string.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::value::escape("say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+/// ```
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    write_escaped(&mut out, s).unwrap();
+    out
+}
+
+/// Encodes `bytes` as standard, padded base64, for formats whose
+/// binary values have no JSON equivalent.
+///
+/// # Example:
+///
+/// ```
+/// assert_eq!(jsonxf::value::base64(b"jsonxf"), "anNvbnhm");
+/// assert_eq!(jsonxf::value::base64(b"json"), "anNvbg==");
+/// ```
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
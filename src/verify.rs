@@ -0,0 +1,389 @@
+//! Checking that formatting changed nothing but whitespace.
+//!
+//! Jsonxf doesn't validate its input, so input that is not quite JSON
+//! may come out changed in more than its layout.  `format_verified`
+//! formats as usual while minimizing both the input and the output, and
+//! fails as soon as the two minimized forms differ.
+//!
+//! The minimizing here is independent of `Formatter`, so that a mistake
+//! in one can't hide a mistake in the other, and it keeps one space
+//! wherever whitespace separates two numbers or literals, so that
+//! `[1 2]` can't quietly become `[12]`.  Like formatting, it is a single
+//! pass over the bytes, so verifying costs about twice the time of
+//! formatting alone.
+//!
+//! `format_audited` makes the same check, but reports a difference at
+//! its position in the input, for audit trails which need to say which
+//! byte of a record was not carried through.
+//!
+//! With the `verify` feature, `format_cross_checked` checks instead
+//! that `serde_json` parses the output, and parses it to the same values
+//! as the input.  It holds the whole input and output in memory, and
+//! costs several times more, for pipelines which need their formatting
+//! checked by a JSON implementation other than Jsonxf's own.
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use scanner::{Position, SyntaxError};
+use Formatter;
+use BUF_SIZE;
+
+/// Formats a stream of JSON-encoded data, and checks that the output
+/// differs from the input only in whitespace.
+///
+/// Fails with `ErrorKind::InvalidData` at the first difference, or if
+/// `xf` is set to change more than whitespace, as with
+/// `escape_non_ascii` or `max_depth`.  Output written before a
+/// difference is found is not taken back.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::verify::format_verified;
+///
+/// let mut output: Vec<u8> = vec![];
+/// let mut xf = jsonxf::Formatter::pretty_printer();
+/// format_verified(&mut xf, &mut "[1, 2]".as_bytes(), &mut output).unwrap();
+/// assert_eq!(output, b"[\n  1,\n  2\n]");
+///
+/// // Jsonxf tidies the whitespace inside `tr ue`, which isn't JSON
+/// let mut xf = jsonxf::Formatter::pretty_printer();
+/// let error = format_verified(&mut xf, &mut "[tr ue]".as_bytes(), &mut vec![]).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "verification failed: output differs from input at byte 3 of their minimized forms"
+/// );
+/// ```
+pub fn format_verified(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    format_checked(xf, input, output, Verifier::new())
+}
+
+/// Formats a stream of JSON-encoded data, and checks that every byte of
+/// the input but its whitespace appears in the output, in order, with
+/// nothing added but whitespace.
+///
+/// This is the check `format_verified` makes, but the error for a
+/// difference carries a `jsonxf::scanner::SyntaxError` giving the
+/// offset, line, and column of the first input byte which the output
+/// doesn't match.  Input bytes inside strings count, whitespace or not.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::scanner::SyntaxError;
+/// use jsonxf::verify::format_audited;
+///
+/// let mut output: Vec<u8> = vec![];
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// format_audited(&mut xf, &mut "{\"a\": [1, 2]}".as_bytes(), &mut output).unwrap();
+/// assert_eq!(output, b"{\"a\":[1,2]}");
+///
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// let input = "{\"amount\":\n  [10 00]}";
+/// let error = format_audited(&mut xf, &mut input.as_bytes(), &mut vec![]).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "audit failed: output diverges from input byte 17 at line 2, column 7"
+/// );
+/// assert_eq!(SyntaxError::of(&error).unwrap().position.offset, 17);
+/// ```
+pub fn format_audited(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut verifier = Verifier::new();
+    verifier.audit = true;
+    format_checked(xf, input, output, verifier)
+}
+
+fn format_checked(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    verifier: Verifier,
+) -> Result<(), Error> {
+    if xf.escape_non_ascii || xf.max_depth.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "cannot verify output with non-ASCII escaping or folding",
+        ));
+    }
+    let mut input = BufReader::new(input);
+    let mut writer = BufWriter::new(Tee { output, verifier });
+    let mut buf = [0_u8; BUF_SIZE];
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                writer.get_mut().verifier.input(&buf[..n])?;
+                xf.format_buf(&buf[..n], &mut writer)?;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    xf.finish_buf(&mut writer)?;
+    writer.flush()?;
+    writer.get_mut().verifier.finish()
+}
+
+/// Formats a stream of JSON-encoded data, and checks with `serde_json`
+/// that the output parses to the same values as the input, writing the
+/// output only if it does.  Requires the `verify` feature.
+///
+/// Fails with `ErrorKind::InvalidData` if the input or output is not
+/// valid JSON, or if any record of the output parses differently from
+/// the input.  Unlike `format_verified`, escaping non-ASCII characters
+/// passes, since it doesn't change the values.
+///
+/// # Example:
+///
+/// ```
+/// use jsonxf::verify::format_cross_checked;
+///
+/// let mut output: Vec<u8> = vec![];
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// xf.escape_non_ascii = true;
+/// format_cross_checked(&mut xf, &mut "{\"é\": [1, 2]}".as_bytes(), &mut output).unwrap();
+/// assert_eq!(output, b"{\"\\u00e9\":[1,2]}");
+///
+/// // An extra record after each one
+/// let mut xf = jsonxf::Formatter::minimizer();
+/// xf.record_separator = String::from("\n[]");
+/// let error = format_cross_checked(&mut xf, &mut "1 2".as_bytes(), &mut vec![]).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "cross-check failed: record 2 of the output parses differently from the input"
+/// );
+/// ```
+#[cfg(feature = "verify")]
+pub fn format_cross_checked(
+    xf: &mut Formatter,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut input_bytes: Vec<u8> = vec![];
+    input.read_to_end(&mut input_bytes)?;
+    let mut output_bytes: Vec<u8> = vec![];
+    xf.format_buf(&input_bytes, &mut output_bytes)?;
+    xf.finish_buf(&mut output_bytes)?;
+
+    let failed = |message: String| Error::new(ErrorKind::InvalidData, message);
+    let parse = |bytes: &[u8], which: &str| {
+        ::serde_json::Deserializer::from_slice(bytes)
+            .into_iter::<::serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                failed(format!(
+                    "cross-check failed: {} is not valid JSON: {}",
+                    which, e
+                ))
+            })
+    };
+    let expected = parse(&input_bytes, "input")?;
+    let actual = parse(&output_bytes, "output")?;
+    let records = expected.len().max(actual.len());
+    if let Some(i) = (0..records).find(|&i| expected.get(i) != actual.get(i)) {
+        return Err(failed(format!(
+            "cross-check failed: record {} of the output parses differently from the input",
+            i + 1
+        )));
+    }
+    output.write_all(&output_bytes)?;
+    output.flush()
+}
+
+// Minimizes the input and output side by side, comparing as it goes.
+#[derive(Default)]
+struct Verifier {
+    input_stripper: Stripper,
+    output_stripper: Stripper,
+    input_min: Vec<u8>,             // minimized input not yet compared
+    output_min: Vec<u8>,            // minimized output not yet compared
+    compared: usize,                // bytes of each minimized form already compared
+    audit: bool,                    // report differences at their input positions?
+    input_positions: Vec<Position>, // of each byte of `input_min`, when auditing
+    input_end: Position,            // of the end of the input so far
+}
+
+impl Verifier {
+    fn new() -> Verifier {
+        Verifier::default()
+    }
+
+    fn input(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let positions = if self.audit {
+            Some(&mut self.input_positions)
+        } else {
+            None
+        };
+        self.input_end = self
+            .input_stripper
+            .strip(buf, &mut self.input_min, positions);
+        self.compare()
+    }
+
+    fn output(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.output_stripper.strip(buf, &mut self.output_min, None);
+        self.compare()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.compare()?;
+        if self.input_min.len() != self.output_min.len() {
+            return Err(self.mismatch(self.input_min.len().min(self.output_min.len())));
+        }
+        Ok(())
+    }
+
+    // Compares as much as both sides have, keeping the rest.
+    fn compare(&mut self) -> Result<(), Error> {
+        let len = self.input_min.len().min(self.output_min.len());
+        let (input, output) = (&self.input_min[..len], &self.output_min[..len]);
+        if let Some(i) = input.iter().zip(output).position(|(a, b)| a != b) {
+            return Err(self.mismatch(i));
+        }
+        self.input_min.drain(..len);
+        self.output_min.drain(..len);
+        if self.audit {
+            self.input_positions.drain(..len);
+        }
+        self.compared += len;
+        Ok(())
+    }
+
+    fn mismatch(&self, i: usize) -> Error {
+        if self.audit {
+            // Output beyond the end of the input diverges at its end
+            let position = self.input_positions.get(i).cloned();
+            let position = position.unwrap_or(self.input_end);
+            let syntax_error = SyntaxError {
+                message: format!(
+                    "audit failed: output diverges from input byte {}",
+                    position.offset
+                ),
+                position,
+            };
+            return syntax_error.into_io_error(ErrorKind::InvalidData);
+        }
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "verification failed: output differs from input at byte {} of their \
+                 minimized forms",
+                self.compared + i
+            ),
+        )
+    }
+}
+
+// Removes the whitespace outside strings, except for one space between
+// two numbers or literals.
+#[derive(Default)]
+struct Stripper {
+    in_string: bool,
+    in_backslash: bool,
+    after_bare: bool, // was the last byte kept part of a number or literal?
+    in_space: bool,   // has whitespace been skipped since then?
+    offset: u64,      // bytes stripped so far
+    line: u64,        // lines begun before the current one
+    line_start: u64,  // the offset at which the current line starts
+}
+
+impl Stripper {
+    // Strips `buf` onto `stripped`, and, if given `positions`, the input
+    // position of each byte kept.  Returns the position after `buf`.
+    fn strip(
+        &mut self,
+        buf: &[u8],
+        stripped: &mut Vec<u8>,
+        mut positions: Option<&mut Vec<Position>>,
+    ) -> Position {
+        for &b in buf {
+            let kept = stripped.len();
+            self.strip_byte(b, stripped);
+            if let Some(positions) = positions.as_mut() {
+                // A space kept between numbers sits where the next one starts
+                for _ in kept..stripped.len() {
+                    positions.push(self.position());
+                }
+            }
+            self.offset += 1;
+            if b == b'\n' {
+                self.line += 1;
+                self.line_start = self.offset;
+            }
+        }
+        self.position()
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line + 1,
+            column: self.offset - self.line_start + 1,
+        }
+    }
+
+    fn strip_byte(&mut self, b: u8, stripped: &mut Vec<u8>) {
+        if self.in_string {
+            if self.in_backslash {
+                self.in_backslash = false;
+            } else if b == b'\\' {
+                self.in_backslash = true;
+            } else if b == b'"' {
+                self.in_string = false;
+            }
+            stripped.push(b);
+            return;
+        }
+        match b {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                self.in_space = true;
+                return;
+            }
+            b'"' => {
+                self.in_string = true;
+                self.after_bare = false;
+            }
+            b'{' | b'}' | b'[' | b']' | b',' | b':' => self.after_bare = false,
+            _ => {
+                if self.after_bare && self.in_space {
+                    stripped.push(b' ');
+                }
+                self.after_bare = true;
+            }
+        }
+        self.in_space = false;
+        stripped.push(b);
+    }
+}
+
+// Passes output along, verifying it on the way.
+struct Tee<'a> {
+    output: &'a mut dyn Write,
+    verifier: Verifier,
+}
+
+impl<'a> Write for Tee<'a> {
+    // Verifies before writing, so that a difference, once found, is
+    // never written, even if the write is retried
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.verifier.output(buf)?;
+        self.output.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.output.flush()
+    }
+}
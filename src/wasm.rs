@@ -0,0 +1,213 @@
+//! JavaScript bindings, for formatting JSON in the browser.
+//!
+//! Built with the `wasm` feature, for use with wasm-bindgen.  Besides
+//! formatting whole strings (`prettyPrint()`, `minimize()`) and chunks
+//! at a time (`Formatter`), it formats Web Streams (`formatStream()`),
+//! reading a `ReadableStream` of bytes and writing a `WritableStream`
+//! one chunk at a time, so that files of hundreds of megabytes can be
+//! formatted without holding them in memory:
+//!
+//! ```js
+//! import { Formatter, formatStream } from "jsonxf";
+//!
+//! const response = await fetch("/exports/events.json");
+//! const file = await window.showSaveFilePicker();
+//! await formatStream(Formatter.prettyPrinter(), response.body, await file.createWritable());
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{
+    ReadableStream, ReadableStreamDefaultReader, ReadableStreamReadResult, WritableStream,
+    WritableStreamDefaultWriter,
+};
+
+/// Pretty-prints a string of JSON-encoded data, as `jsonxf::pretty_print`
+/// does.
+#[wasm_bindgen(js_name = prettyPrint)]
+pub fn pretty_print(json: &str) -> Result<String, JsValue> {
+    ::pretty_print(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Minimizes a string of JSON-encoded data, as `jsonxf::minimize` does.
+#[wasm_bindgen]
+pub fn minimize(json: &str) -> Result<String, JsValue> {
+    ::minimize(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Formats JSON-encoded data which arrives in chunks, known to
+/// JavaScript as `Formatter`.
+#[wasm_bindgen(js_name = Formatter)]
+pub struct WasmFormatter {
+    xf: ::Formatter,
+}
+
+#[wasm_bindgen(js_class = Formatter)]
+impl WasmFormatter {
+    /// Returns a formatter for pretty-printing, indenting with the given
+    /// string or two spaces.  Each record ends with a newline.
+    #[wasm_bindgen(js_name = prettyPrinter)]
+    pub fn pretty_printer(indent: Option<String>) -> WasmFormatter {
+        let mut xf = ::Formatter::pretty_printer();
+        if let Some(indent) = indent {
+            xf.indent = indent;
+        }
+        xf.eager_record_separators = true;
+        WasmFormatter { xf }
+    }
+
+    /// Returns a formatter for minimizing, with each record on its own
+    /// line.
+    pub fn minimizer() -> WasmFormatter {
+        let mut xf = ::Formatter::minimizer();
+        xf.eager_record_separators = true;
+        WasmFormatter { xf }
+    }
+
+    /// Formats a chunk of input, returning as much output as is ready.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = vec![];
+        self.xf
+            .format_buf(chunk, &mut output)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(output)
+    }
+
+    /// Returns the rest of the output, as at the end of input, and
+    /// readies the formatter for new input.
+    pub fn finish(&mut self) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = vec![];
+        self.xf
+            .finish_buf(&mut output)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.xf.reset();
+        Ok(output)
+    }
+}
+
+/// Formats a `ReadableStream` of `Uint8Array` chunks into a
+/// `WritableStream`, and closes it.  Returns a promise which resolves
+/// when the output is closed, or rejects at the first error, having
+/// aborted the output and cancelled the input.  Either way, both streams
+/// are released for the caller to use again.
+///
+/// Only one chunk is held at a time, and each is written before the
+/// next is read, so a slow output slows the input rather than filling
+/// memory.
+#[wasm_bindgen(js_name = formatStream)]
+pub fn format_stream(
+    formatter: WasmFormatter,
+    input: ReadableStream,
+    output: WritableStream,
+) -> Result<Promise, JsValue> {
+    let reader: ReadableStreamDefaultReader = input.get_reader().unchecked_into();
+    let writer = output.get_writer()?;
+    let state = State::Reading(JsFuture::from(reader.read()));
+    Ok(future_to_promise(Pump {
+        formatter,
+        reader,
+        writer,
+        state,
+    }))
+}
+
+// Moves chunks from a reader, through a formatter, to a writer.
+struct Pump {
+    formatter: WasmFormatter,
+    reader: ReadableStreamDefaultReader,
+    writer: WritableStreamDefaultWriter,
+    state: State,
+}
+
+thread_local! {
+    // Handles a promise's rejection by ignoring it.
+    static IGNORE: Closure<dyn FnMut(JsValue)> = Closure::new(|_| {});
+}
+
+// What a `Pump` is waiting on.
+enum State {
+    Reading(JsFuture),
+    Writing(JsFuture, bool), // and whether it's the last write
+    Closing(JsFuture),
+}
+
+impl Future for Pump {
+    type Output = Result<JsValue, JsValue>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let pump = &mut *self;
+        match pump.advance(cx) {
+            Poll::Ready(Err(e)) => Poll::Ready(Err(pump.fail(e))),
+            poll => poll,
+        }
+    }
+}
+
+impl Pump {
+    // Moves chunks along until waiting on the reader or writer, or done.
+    fn advance(&mut self, cx: &mut Context) -> Poll<Result<JsValue, JsValue>> {
+        let pump = self;
+        loop {
+            pump.state = match &mut pump.state {
+                State::Reading(read) => {
+                    let result: ReadableStreamReadResult = match Pin::new(read).poll(cx) {
+                        Poll::Ready(result) => result?.unchecked_into(),
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    let done = result.get_done() == Some(true);
+                    let output = if done {
+                        pump.formatter.finish()?
+                    } else {
+                        let chunk = Uint8Array::new(&result.get_value()).to_vec();
+                        pump.formatter.push(&chunk)?
+                    };
+                    if output.is_empty() && !done {
+                        State::Reading(JsFuture::from(pump.reader.read()))
+                    } else {
+                        let chunk = Uint8Array::from(&output[..]);
+                        State::Writing(JsFuture::from(pump.writer.write_with_chunk(&chunk)), done)
+                    }
+                }
+                State::Writing(write, last) => {
+                    match Pin::new(write).poll(cx) {
+                        Poll::Ready(result) => result?,
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    if *last {
+                        State::Closing(JsFuture::from(pump.writer.close()))
+                    } else {
+                        State::Reading(JsFuture::from(pump.reader.read()))
+                    }
+                }
+                State::Closing(close) => {
+                    match Pin::new(close).poll(cx) {
+                        Poll::Ready(result) => result?,
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    pump.writer.release_lock();
+                    pump.reader.release_lock();
+                    return Poll::Ready(Ok(JsValue::UNDEFINED));
+                }
+            };
+        }
+    }
+
+    // Gives up at an error.  Aborting the output lets it discard what was
+    // written, as a file being saved does.  Either stream may have failed
+    // already, rejecting its abort or cancel, which is of no more use.
+    fn fail(&mut self, error: JsValue) -> JsValue {
+        IGNORE.with(|ignore| {
+            let _ = self.writer.abort_with_reason(&error).catch(ignore);
+            let _ = self.reader.cancel_with_reason(&error).catch(ignore);
+        });
+        self.writer.release_lock();
+        self.reader.release_lock();
+        error
+    }
+}
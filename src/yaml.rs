@@ -0,0 +1,71 @@
+//! Conversion of YAML documents to JSON.
+//!
+//! Available with the `yaml` feature.  Anchors and aliases are
+//! expanded.  Values with no JSON equivalent, such as infinite
+//! numbers or mappings used as keys, are errors.
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use value::{self, Value};
+
+/// Converts a string of YAML to minimized JSON, one record per
+/// document, separated by newlines.
+///
+/// # Example:
+///
+/// ```
+/// let yaml = "name: ana\ntags: [a, b]\nage: 30\n---\nname: bo\n";
+/// assert_eq!(
+///     jsonxf::yaml::yaml_to_json(yaml).unwrap(),
+///     "{\"name\":\"ana\",\"tags\":[\"a\",\"b\"],\"age\":30}\n{\"name\":\"bo\"}"
+/// );
+/// ```
+pub fn yaml_to_json(yaml_string: &str) -> Result<String, String> {
+    let records = yaml_to_values(yaml_string)?;
+    let records: Vec<String> = records.iter().map(|v| v.to_string()).collect();
+    Ok(records.join("\n"))
+}
+
+/// Converts a string of YAML to one `Value` per document.
+pub fn yaml_to_values(yaml_string: &str) -> Result<Vec<Value>, String> {
+    let docs = YamlLoader::load_from_str(yaml_string).map_err(|e| e.to_string())?;
+    docs.iter().map(convert).collect()
+}
+
+fn convert(yaml: &Yaml) -> Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Integer(i) => Value::Number(i.to_string()),
+        Yaml::Real(s) => Value::Number(real(s)?),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Array(items) => Value::Array(items.iter().map(convert).collect::<Result<_, _>>()?),
+        Yaml::Hash(hash) => Value::Object(
+            hash.iter()
+                .map(|(k, v)| Ok((key(k)?, convert(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Yaml::Alias(_) | Yaml::BadValue => return Err(String::from("unresolvable alias")),
+    })
+}
+
+// Returns a YAML float in JSON syntax.
+fn real(s: &str) -> Result<String, String> {
+    if value::is_number(s) {
+        return Ok(String::from(s));
+    }
+    match s.parse::<f64>() {
+        Ok(f) if f.is_finite() => Ok(f.to_string()),
+        _ => Err(format!("{} cannot be represented in JSON", s)),
+    }
+}
+
+fn key(yaml: &Yaml) -> Result<String, String> {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Ok(s.clone()),
+        Yaml::Integer(i) => Ok(i.to_string()),
+        Yaml::Boolean(b) => Ok(b.to_string()),
+        Yaml::Null => Ok(String::from("null")),
+        _ => Err(String::from("only scalars can be used as object keys")),
+    }
+}
@@ -0,0 +1,80 @@
+extern crate jsonxf;
+use jsonxf::{from_cbor, to_cbor};
+
+#[test]
+fn cbor_round_trips_records() {
+    let json = "{\"s\":\"caf\\u00e9\",\"n\":[0,23,24,-1,-25,65536,-18446744073709551616],\
+                \"f\":[0.1,1.5,-0.0],\"t\":true,\"z\":null,\"o\":{}}\n[]\n\"x\"\n";
+    let cbor = to_cbor(json).unwrap();
+    assert_eq!(
+        from_cbor(&cbor).unwrap(),
+        "{\"s\":\"café\",\"n\":[0,23,24,-1,-25,65536,-18446744073709551616],\
+         \"f\":[0.1,1.5,-0.0],\"t\":true,\"z\":null,\"o\":{}}\n[]\n\"x\"\n"
+    );
+}
+
+#[test]
+fn cbor_uses_shortest_heads() {
+    assert_eq!(
+        to_cbor("[23, 24, 256, -500]").unwrap(),
+        vec![0x84, 0x17, 0x18, 0x18, 0x19, 0x01, 0x00, 0x39, 0x01, 0xf3]
+    );
+}
+
+#[test]
+fn cbor_byte_strings_become_base64() {
+    // definite and indefinite-length byte strings
+    let cbor = [
+        0x82, 0x44, b'j', b's', b'o', b'n', 0x5f, 0x42, b'j', b's', 0x41, b'o', 0xff,
+    ];
+    assert_eq!(from_cbor(&cbor).unwrap(), "[\"anNvbg==\",\"anNv\"]\n");
+}
+
+#[test]
+fn cbor_indefinite_containers_are_decoded() {
+    // {_ "a": [_ 1, 2], "b": "x" "y" streamed}
+    let cbor = [
+        0xbf, 0x61, b'a', 0x9f, 0x01, 0x02, 0xff, 0x61, b'b', 0x7f, 0x61, b'x', 0x61, b'y', 0xff,
+        0xff,
+    ];
+    assert_eq!(from_cbor(&cbor).unwrap(), "{\"a\":[1,2],\"b\":\"xy\"}\n");
+}
+
+#[test]
+fn cbor_tags_are_unwrapped() {
+    // self-describe tag, then a date string, an epoch time, and a bignum
+    let cbor = [
+        0xd9, 0xd9, 0xf7, 0x83, 0xc0, 0x64, b'2', b'0', b'2', b'6', 0xc1, 0x1a, 0x5a, 0x00, 0x00,
+        0x00, 0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    assert_eq!(
+        from_cbor(&cbor).unwrap(),
+        "[\"2026\",1509949440,18446744073709551616]\n"
+    );
+}
+
+#[test]
+fn cbor_half_floats_and_undefined_are_decoded() {
+    let cbor = [0x83, 0xf9, 0x3e, 0x00, 0xf9, 0x00, 0x01, 0xf7];
+    assert_eq!(from_cbor(&cbor).unwrap(), "[1.5,5.9604645e-8,null]\n");
+}
+
+#[test]
+fn cbor_scalar_keys_are_stringified() {
+    let cbor = [0xa2, 0x01, 0xf5, 0x21, 0xf6];
+    assert_eq!(from_cbor(&cbor).unwrap(), "{\"1\":true,\"-2\":null}\n");
+}
+
+#[test]
+fn cbor_values_without_json_equivalents_are_errors() {
+    assert_eq!(
+        from_cbor(&[0xf9, 0x7c, 0x00]).unwrap_err(),
+        "inf cannot be represented in JSON"
+    );
+    assert_eq!(
+        from_cbor(&[0xa1, 0x80, 0x01]).unwrap_err(),
+        "only scalars can be used as object keys"
+    );
+    assert!(from_cbor(&[0x82, 0x01]).is_err());
+    assert!(from_cbor(&[0x65, b'a']).is_err());
+}
@@ -0,0 +1,1398 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Runs jsonxf with `args`, which may begin with a command, on `input`,
+// ignoring config files, and returns whether it succeeded, with its
+// output and errors.
+fn jsonxf(args: &[&str], input: &str) -> (bool, String, String) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_jsonxf"));
+    command.args(args).arg("--no-config").env_remove("JSONXF");
+    run(command, input)
+}
+
+// Runs jsonxf in `dir`, reading the project's config file from there or
+// above, the user's from `dir/user`, and options from `env` as JSONXF.
+fn jsonxf_in(dir: &Path, env: &str, args: &[&str], input: &str) -> (bool, String, String) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_jsonxf"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", dir.join("user"))
+        .env("JSONXF", env);
+    run(command, input)
+}
+
+fn run(mut command: Command, input: &str) -> (bool, String, String) {
+    let mut child = command
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // jsonxf may fail before reading all of its input
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// Returns a new, empty directory for a test to work in.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jsonxf-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn tab_indents_with_a_tab_or_the_given_string() {
+    let input = "{\"a\": 1}";
+    assert_eq!(jsonxf(&["--tab"], input).1, "{\n\t\"a\": 1\n}\n");
+    assert_eq!(jsonxf(&["--tab", "    "], input).1, "{\n    \"a\": 1\n}\n");
+    assert_eq!(jsonxf(&["--tab=   "], input).1, "{\n   \"a\": 1\n}\n");
+}
+
+#[test]
+fn canonical_writes_rfc_8785_canonical_json() {
+    let input = "{\"b\": [1.50, 2e2], \"a\": 1}\n[3, 4]";
+    let expected = "{\"a\":1,\"b\":[1.5,200]}\n[3,4]";
+    assert_eq!(jsonxf(&["--canonical"], input).1, expected);
+    assert_eq!(jsonxf(&["--jcs"], input).1, expected);
+    assert_eq!(
+        jsonxf(&["--canonical", "--grep", "b"], input).1,
+        "{\"a\":1,\"b\":[1.5,200]}"
+    );
+    // Keys sort by UTF-16 code unit, and strings and numbers are written
+    // the one way RFC 8785 allows
+    assert_eq!(
+        jsonxf(
+            &["--canonical"],
+            "{\"\\u00e9\": \"\\u00e9\", \"9\": 1E-7, \"10\": 1.0}"
+        )
+        .1,
+        "{\"10\":1,\"9\":1e-7,\"é\":\"é\"}"
+    );
+
+    for (input, error) in [
+        ("{\"a\": 1, \"a\": 2}", "duplicate member name \"a\""),
+        (
+            "[1e400]",
+            "number `1e400` is out of range for canonical JSON",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf(&["--canonical"], input);
+        assert!(!ok);
+        assert!(errors.contains(error), "{}", errors);
+    }
+}
+
+#[test]
+fn sort_keys_applies_to_each_way_of_formatting() {
+    let input = "{\"b\": 1, \"a\": 2}\n{\"d\": 1, \"c\": 2}\n";
+    assert_eq!(
+        jsonxf(&["-m", "--sort-keys", "--records", "1"], input).1,
+        "{\"c\":2,\"d\":1}"
+    );
+    assert_eq!(
+        jsonxf(&["-m", "--sort-keys", "--on-error", "skip"], input).1,
+        "{\"a\":2,\"b\":1}\n{\"c\":2,\"d\":1}\n"
+    );
+    assert_eq!(
+        jsonxf(
+            &["-m", "--sort-keys", "--mixed"],
+            "x {\"b\": 1, \"a\": 2} y"
+        )
+        .1,
+        "x {\"a\":2,\"b\":1} y"
+    );
+}
+
+#[test]
+fn grep_is_not_silently_dropped() {
+    let input = "{\"a\": 1}\n{\"b\": 2}\n";
+    for args in [
+        &["--hash", "--grep", "z"][..],
+        &["--records", "1", "--expr", ".a"],
+        &["--get", "/a", "--redact", "pii"],
+        &["-g", "--grep", "a"],
+    ] {
+        let (ok, _, errors) = jsonxf(args, input);
+        assert!(!ok, "{:?}", args);
+        assert!(errors.contains("cannot be combined"), "{}", errors);
+    }
+}
+
+#[test]
+fn backup_suffix_must_be_attached() {
+    let (ok, _, errors) = jsonxf(&["-w", "--backup", ".orig", "missing.json"], "");
+    assert!(!ok);
+    assert!(
+        errors.contains("give the suffix as --backup=.orig"),
+        "{}",
+        errors
+    );
+}
+
+#[test]
+fn hash_algorithm_must_be_attached() {
+    let (ok, _, errors) = jsonxf(&["--hash-records", "xxh3"], "{}");
+    assert!(!ok);
+    assert!(
+        errors.contains("give the algorithm as --hash-records=xxh3"),
+        "{}",
+        errors
+    );
+    let (ok, output, _) = jsonxf(&["--hash-records=xxh3"], "{}\n[]");
+    assert!(ok);
+    assert_eq!(output.lines().count(), 2);
+}
+
+#[test]
+fn get_takes_pointers_after_options() {
+    let input = "{\"a\": \"x\", \"b\": [1, 2]}";
+    assert_eq!(jsonxf(&["get", "--raw-output", "/a"], input).1, "x\n");
+    assert_eq!(
+        jsonxf(&["get", "/a", "-m", "/b"], input).1,
+        "\"x\"\n[1,2]\n"
+    );
+    let (ok, _, errors) = jsonxf(&["get", "-m"], input);
+    assert!(!ok);
+    assert!(
+        errors.contains("get needs one or more JSON Pointers"),
+        "{}",
+        errors
+    );
+}
+
+#[test]
+fn on_error_catches_lines_which_are_not_strictly_json() {
+    let input = "{\"a\": 1}\nx\nnul\n[1,,2]\n{\"a\" 1}\n[2]\n";
+    let (ok, output, errors) = jsonxf(&["-m", "--on-error", "skip"], input);
+    assert!(ok);
+    assert_eq!(output, "{\"a\":1}\n[2]\n");
+    assert!(errors.contains("skipped 4 lines"), "{}", errors);
+    let (_, output, _) = jsonxf(&["-m", "--on-error", "pass"], input);
+    assert_eq!(output, "{\"a\":1}\nx\nnul\n[1,,2]\n{\"a\" 1}\n[2]\n");
+    for line in ["x", "nul", "[1,,2]", "{\"a\" 1}"] {
+        let (ok, _, errors) = jsonxf(&["--on-error", "fail"], line);
+        assert!(!ok, "{}", line);
+        assert!(errors.contains("line 1: "), "{}", errors);
+    }
+}
+
+#[test]
+fn checkpoint_rejects_options_it_would_drop() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("jsonxf-checkpoint-{}.json", std::process::id()));
+    std::fs::write(&input, "{\"a\": 1}").unwrap();
+    let input = input.to_str().unwrap();
+    for (option, name) in [
+        (&["--to", "toml"][..], "--to"),
+        (&["--grep", "a"], "--grep"),
+        (&["--sort-keys", "--records", "0"], "--records"),
+        (&["--indent", "auto"], "--indent auto"),
+    ] {
+        let mut args = vec!["--checkpoint", "unused.ck", "-i", input, "-o", "unused.out"];
+        args.extend(option);
+        let (ok, _, errors) = jsonxf(&args, "");
+        assert!(!ok, "{:?}", option);
+        assert!(
+            errors.contains(&format!("--checkpoint cannot be combined with {}", name)),
+            "{}",
+            errors
+        );
+    }
+    std::fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn modes_which_would_ignore_each_other_are_rejected() {
+    let input = "{\"a\": 1}\n";
+    for (args, error) in [
+        (
+            &["--from", "csv", "--to", "toml"][..],
+            "--from cannot be combined with --to",
+        ),
+        (
+            &["--docker-logs", "--mixed"],
+            "--docker-logs cannot be combined with --mixed",
+        ),
+        (
+            &["--verify", "--records", "0"],
+            "--records cannot be combined with --verify",
+        ),
+        (&["check", "-g"], "check cannot be combined with --gron"),
+        (
+            &["get", "/a", "--schema"],
+            "get cannot be combined with --schema",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf(args, input);
+        assert!(!ok, "{:?}", args);
+        assert!(errors.contains(error), "{}", errors);
+    }
+
+    // --patch honours --grep
+    let patch = std::env::temp_dir().join(format!("jsonxf-patch-{}.json", std::process::id()));
+    std::fs::write(
+        &patch,
+        "[{\"op\": \"add\", \"path\": \"/b\", \"value\": 2}]",
+    )
+    .unwrap();
+    let patch = patch.to_str().unwrap();
+    let (ok, output, _) = jsonxf(
+        &["-m", "--patch", patch, "--grep", "c"],
+        "{\"a\": 1}\n{\"c\": 3}\n",
+    );
+    assert!(ok);
+    assert_eq!(output, "{\"c\":3,\"b\":2}");
+    std::fs::remove_file(patch).unwrap();
+}
+
+#[test]
+fn options_which_a_mode_would_ignore_are_rejected() {
+    let input = "{\"a\": 1}\n";
+    for (args, error) in [
+        (
+            &["--gron", "--sort-keys"][..],
+            "--gron cannot be combined with --sort-keys",
+        ),
+        (
+            &["--to", "toml", "-m"],
+            "--to toml cannot be combined with -m",
+        ),
+        (
+            &["--hash", "--indent", "4"],
+            "--hash cannot be combined with --indent",
+        ),
+        (
+            &["--expr", ".a", "--strict"],
+            "--expr cannot be combined with --strict",
+        ),
+        (
+            &["check", "--sort-keys"],
+            "check cannot be combined with --sort-keys",
+        ),
+        (
+            &["check", "--stats"],
+            "check cannot be combined with --stats",
+        ),
+        (
+            &["get", "/a", "--jsonc"],
+            "get cannot be combined with --jsonc",
+        ),
+        (&["fmt", "--check"], "fmt cannot be combined with --check"),
+        (&["--check", "-w"], "--check cannot be combined with -w"),
+        (&["--counts"], "--counts requires --paths"),
+        (
+            &["--to", "markdown", "--html-stylesheet"],
+            "--html-stylesheet requires --to html",
+        ),
+        (
+            &["--infer-types"],
+            "--infer-types requires --from csv or --from tsv",
+        ),
+        (
+            &["--ignore-order"],
+            "--ignore-order requires --diff or --assert-equal",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf(args, input);
+        assert!(!ok, "{:?}", args);
+        assert!(errors.contains(error), "{}", errors);
+    }
+
+    // A JSON Schema is JSON, and laid out as such
+    let (ok, output, _) = jsonxf(&["schema", "--format", "schema", "-m"], input);
+    assert!(ok);
+    assert!(output.starts_with("{\"$schema\":"), "{}", output);
+
+    // Defaults from the environment apply only where they'd be honoured
+    let dir = temp_dir("ignored");
+    let (ok, output, _) = jsonxf_in(&dir, "--sort-keys --indent 4", &["--gron"], input);
+    assert!(ok);
+    assert_eq!(output, "json = {};\njson.a = 1;\n");
+    let (ok, _, _) = jsonxf_in(&dir, "--sort-keys", &["check"], input);
+    assert!(ok);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_files_set_formatting_defaults() {
+    let dir = temp_dir("config");
+    let src = dir.join("project/src");
+    std::fs::create_dir_all(src.join("user/jsonxf")).unwrap();
+    std::fs::write(
+        src.join("user/jsonxf/config.toml"),
+        "indent = 8\nsort_keys = true # always\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("project/.jsonxf.toml"), "indent = 4\n").unwrap();
+    let input = "{\"b\": 1, \"a\": 2}";
+
+    // The project's file is found from below, and wins over the user's
+    let (ok, output, _) = jsonxf_in(&src, "", &[], input);
+    assert!(ok);
+    assert_eq!(output, "{\n    \"a\": 2,\n    \"b\": 1\n}\n");
+
+    // JSONXF wins over both, and the command line over all
+    let (_, output, _) = jsonxf_in(&src, "--indent 1", &[], input);
+    assert_eq!(output, "{\n \"a\": 2,\n \"b\": 1\n}\n");
+    let (_, output, _) = jsonxf_in(&src, "--indent 1", &["--tab"], input);
+    assert_eq!(output, "{\n\t\"a\": 2,\n\t\"b\": 1\n}\n");
+    let (_, output, _) = jsonxf_in(&src, "", &["--no-config"], input);
+    assert_eq!(output, "{\n  \"b\": 1,\n  \"a\": 2\n}\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_files_cannot_set_other_options() {
+    let dir = temp_dir("config-write");
+    std::fs::write(dir.join("y.json"), "{\"a\":1}").unwrap();
+    for setting in [
+        "write = true",
+        "output = \"out.json\"",
+        "files_from = \"list\"",
+        "redact = [\"pii\"]",
+        "m = true",
+    ] {
+        std::fs::write(dir.join(".jsonxf.toml"), setting).unwrap();
+        let (ok, _, errors) = jsonxf_in(&dir, "", &["y.json"], "");
+        assert!(!ok, "{}", setting);
+        assert!(errors.contains("is not a formatting option"), "{}", errors);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("y.json")).unwrap(),
+            "{\"a\":1}"
+        );
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_files_are_checked() {
+    let dir = temp_dir("config-errors");
+    for (text, error) in [
+        ("[jsonxf]\nindent = 4", "line 1: tables are not supported"),
+        ("indent 4", "line 1: expected `key = value`"),
+        (
+            "# two\nindent = 4 spaces",
+            "line 2: unexpected text after value",
+        ),
+        ("indent = four", "line 1: invalid value"),
+        ("indent = 'four", "line 1: invalid value"),
+        ("no_such_option = true", "no-such-option"),
+    ] {
+        std::fs::write(dir.join(".jsonxf.toml"), text).unwrap();
+        let (ok, _, errors) = jsonxf_in(&dir, "", &[], "{}");
+        assert!(!ok, "{}", text);
+        assert!(errors.contains(".jsonxf.toml: "), "{}", errors);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn jsonxf_environment_variable_sets_formatting_defaults() {
+    let dir = temp_dir("env");
+    let input = "{\"b\": [1]}";
+    let (ok, output, _) = jsonxf_in(&dir, "-m --indent=4", &[], input);
+    assert!(ok);
+    assert_eq!(output, "{\"b\":[1]}");
+    let (_, output, _) = jsonxf_in(&dir, "--indent-string '   '", &[], input);
+    assert_eq!(output, "{\n   \"b\": [\n      1\n   ]\n}\n");
+
+    for (env, error) in [
+        ("-w", "`-w` is not a formatting option"),
+        ("--output out.json", "`--output` is not a formatting option"),
+        ("data.json", "`data.json` is not an option"),
+        ("--indent '4", "unterminated ' quote"),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, env, &[], input);
+        assert!(!ok, "{}", env);
+        assert!(errors.contains(&format!("JSONXF: {}", error)), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn interactive_reports_errors_as_jsonl() {
+    let (ok, output, errors) = jsonxf(
+        &["--interactive", "--strict", "--errors=jsonl"],
+        "[1 2]\n\n{\"a\":1}\n\n",
+    );
+    assert!(ok);
+    assert_eq!(output, "{\n  \"a\": 1\n}\n");
+    assert_eq!(
+        errors,
+        "{\"kind\":\"error\",\"file\":null,\"offset\":3,\"line\":1,\"column\":4,\
+         \"message\":\"expected `,` or `]`, found `2`\"}\n"
+    );
+}
+
+// Formats a snippet in a terminal, through `script`, where there is one.
+#[cfg(unix)]
+#[test]
+fn interactive_writes_each_snippet_at_once_to_a_terminal() {
+    use std::io::Read;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let command = format!(
+        "stty rows 40 cols 80; {} --interactive --no-config",
+        env!("CARGO_BIN_EXE_jsonxf")
+    );
+    let child = Command::new("script")
+        .args(["-qec", &command, "/dev/null"])
+        .env_remove("JSONXF")
+        .env("NO_COLOR", "1")
+        .env("PAGER", "cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return, // no `script` here
+    };
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0_u8; 1024];
+        while let Ok(n) = stdout.read(&mut buf) {
+            if n == 0 || sender.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    stdin.write_all(b"{\"a\":1}\n\n").unwrap();
+
+    // The snippet is formatted before the session ends
+    let mut output = vec![];
+    while !String::from_utf8_lossy(&output).contains("\"a\": 1") {
+        match receiver.recv_timeout(Duration::from_secs(10)) {
+            Ok(bytes) => output.extend(bytes),
+            Err(_) => break,
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    assert!(
+        String::from_utf8_lossy(&output).contains("\"a\": 1"),
+        "{}",
+        String::from_utf8_lossy(&output)
+    );
+}
+
+#[test]
+fn get_writes_one_value_to_a_line() {
+    let input = "{\"s\": \"a\", \"n\": 1}\n{\"s\": \"b\", \"n\": [2]}";
+    let get = |args: &[&str]| {
+        let mut args = args.to_vec();
+        args.insert(0, "get");
+        jsonxf(&args, input).1
+    };
+    assert_eq!(get(&["-m", "--raw-output", "/n", "/s"]), "1\na\n[2]\nb\n");
+    assert_eq!(get(&["-m", "/s"]), "\"a\"\n\"b\"\n");
+    assert_eq!(get(&["--raw-output", "/n"]), "1\n[\n  2\n]\n");
+    assert_eq!(get(&["--ndjson", "/n"]), "1\n[2]\n");
+    assert_eq!(get(&["--eol", "crlf", "/n"]), "1\r\n[\r\n  2\r\n]\r\n");
+    assert_eq!(get(&["--canonical", "/n"]), "1\n[2]\n");
+    let (ok, _, errors) = jsonxf(&["get", "/s", "/x"], input);
+    assert!(!ok);
+    assert!(errors.contains("no value at /x"), "{}", errors);
+}
+
+#[test]
+fn list_shows_the_files_which_would_change() {
+    let dir = temp_dir("list");
+    let pretty = dir.join("pretty.json");
+    let minimized = dir.join("minimized.json");
+    std::fs::write(&pretty, "{\n  \"a\": 1\n}\n").unwrap();
+    std::fs::write(&minimized, "{\"a\":1}").unwrap();
+    let files = [pretty.to_str().unwrap(), minimized.to_str().unwrap()];
+
+    for list in ["--list", "--dry-run"] {
+        let (ok, output, _) = jsonxf(&["-w", list, files[0], files[1]], "");
+        assert!(ok);
+        assert_eq!(output, format!("{}\n", files[1]));
+    }
+    // With --check, listing a file is a failure
+    let (ok, output, errors) = jsonxf(&["check", "--list", files[0], files[1]], "");
+    assert!(!ok);
+    assert_eq!(output, format!("{}\n", files[1]));
+    assert!(errors.contains("1 file would change"), "{}", errors);
+    let (ok, output, _) = jsonxf(&["--check", "--list", "-m", files[1]], "");
+    assert!(ok);
+    assert_eq!(output, "");
+    // Nothing was changed
+    assert_eq!(std::fs::read_to_string(&minimized).unwrap(), "{\"a\":1}");
+
+    for (args, error) in [
+        (&["--list", files[0]][..], "--list requires -w or --check"),
+        (&["-w", "--list", "--watch", files[0]], "without --watch"),
+        (&["check", "--list"], "--list requires one or more files"),
+    ] {
+        let (ok, _, errors) = jsonxf(args, "{}");
+        assert!(!ok, "{:?}", args);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directories_and_globs_expand_to_files() {
+    let dir = temp_dir("expand");
+    let tree = dir.join("tree");
+    for file in [
+        "a.json",
+        "b.txt",
+        "x1.json",
+        "x2.json",
+        "xa.json",
+        "sub/c.json",
+        "sub/deep/d.json",
+        ".hidden.json",
+        ".git/e.json",
+    ] {
+        let path = tree.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "{\"a\":1}").unwrap();
+    }
+    // A loop, which isn't followed
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("..", tree.join("sub/loop")).unwrap();
+
+    // Every file given is minimized, so -w --list lists each one
+    let expand = |args: &[&str]| {
+        let args: Vec<&str> = ["-w", "--list"].iter().chain(args).copied().collect();
+        let (ok, output, errors) = jsonxf_in(&dir, "", &args, "");
+        assert!(ok, "{:?}: {}", args, errors);
+        output
+    };
+    let all = "tree/a.json\ntree/sub/c.json\ntree/sub/deep/d.json\n\
+               tree/x1.json\ntree/x2.json\ntree/xa.json\n";
+    assert_eq!(expand(&["-r", "tree"]), all);
+    assert_eq!(expand(&["tree/**/*.json"]), all);
+    assert_eq!(
+        expand(&["tree/*.json"]),
+        "tree/a.json\ntree/x1.json\ntree/x2.json\ntree/xa.json\n"
+    );
+    assert_eq!(expand(&["tree/*/c.json"]), "tree/sub/c.json\n");
+    assert_eq!(
+        expand(&["tree/x?.json"]),
+        "tree/x1.json\ntree/x2.json\ntree/xa.json\n"
+    );
+    assert_eq!(
+        expand(&["tree/x[0-9].json"]),
+        "tree/x1.json\ntree/x2.json\n"
+    );
+    assert_eq!(expand(&["tree/x[!0-9].json"]), "tree/xa.json\n");
+    assert_eq!(expand(&["tree/x[a1].json"]), "tree/x1.json\ntree/xa.json\n");
+    assert_eq!(
+        expand(&["-r", "--include", "*.txt", "tree"]),
+        "tree/b.txt\n"
+    );
+    assert_eq!(
+        expand(&["-r", "--include", "sub/**", "tree"]),
+        "tree/sub/c.json\ntree/sub/deep/d.json\n"
+    );
+    assert_eq!(
+        expand(&["-r", "--exclude", "sub/**", "--exclude", "x?.json", "tree"]),
+        "tree/a.json\n"
+    );
+    // Hidden files are formatted only when named
+    assert_eq!(expand(&["tree/.hidden.json"]), "tree/.hidden.json\n");
+
+    for (args, error) in [
+        (&["tree"][..], "tree: is a directory (use -r to format it)"),
+        (&["tree/*.yaml"], "tree/*.yaml: no matching files"),
+        (&["tree/.*.json"], "tree/.*.json: no matching files"),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", args, "");
+        assert!(!ok, "{:?}", args);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn jsonxf_environment_variable_is_split_as_a_shell_would() {
+    let dir = temp_dir("words");
+    let input = "{\"b\": [1]}";
+    let indented = |indent: &str| format!("{{\n{0}\"b\": [\n{0}{0}1\n{0}]\n}}\n", indent);
+    for (env, indent) in [
+        ("", "  "),
+        (" \t\n ", "  "),
+        ("-t '| '", "| "),
+        ("-t '|'\" \"", "| "),
+        ("-t \\|\\ ", "| "),
+        ("-t \"\\\"> \"", "\"> "),
+        ("-t '\\t'", "\\t"),
+        ("-t ''", ""),
+        ("--indent=\"4\"", "    "),
+        ("  --indent   3  ", "   "),
+    ] {
+        let (ok, output, errors) = jsonxf_in(&dir, env, &[], input);
+        assert!(ok, "{}: {}", env, errors);
+        assert_eq!(output, indented(indent), "{}", env);
+    }
+
+    for (env, error) in [
+        ("-t \"| ", "JSONXF: unterminated \" quote"),
+        ("-t '| ", "JSONXF: unterminated ' quote"),
+        ("-m \\", "JSONXF: unfinished escape at end"),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, env, &[], input);
+        assert!(!ok, "{}", env);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn output_template_names_files_by_their_parts() {
+    let dir = temp_dir("template");
+    for file in ["tree/a.json", "tree/b.tar.json", "tree/noext", "top.json"] {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "{\"a\":1}").unwrap();
+    }
+    for (template, file, output) in [
+        ("{dir}/{stem}.min.{ext}", "tree/a.json", "tree/a.min.json"),
+        (
+            "{dir}/{stem}.min.{ext}",
+            "tree/b.tar.json",
+            "tree/b.tar.min.json",
+        ),
+        ("{dir}/{stem}-{ext}", "tree/noext", "tree/noext-"),
+        ("{dir}/out-{name}", "top.json", "./out-top.json"),
+        ("{name}.pretty", "tree/a.json", "a.json.pretty"),
+        ("{dir}/{{{stem}}}.json", "tree/a.json", "tree/{a}.json"),
+        ("{dir}/}}{stem}}.json", "tree/a.json", "tree/}a}.json"),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", &["--output-template", template, file], "");
+        assert!(ok, "{}: {}", template, errors);
+        let written = std::fs::read_to_string(dir.join(output)).unwrap();
+        assert_eq!(written, "{\n  \"a\": 1\n}\n", "{}", template);
+    }
+
+    for (template, error) in [
+        (
+            "{dir}/{base}.json",
+            "unknown placeholder `{base}` in output template",
+        ),
+        (
+            "{dir}/{}.json",
+            "unknown placeholder `{}` in output template",
+        ),
+        (
+            "{dir}/{stem",
+            "unclosed `{` in output template `{dir}/{stem`",
+        ),
+        (
+            "{name}",
+            "a.json: output template would overwrite an input file",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", &["--output-template", template, "a.json"], "");
+        assert!(!ok, "{}", template);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn jobs_report_in_order_and_match_one_job() {
+    let dir = temp_dir("jobs");
+    let files: Vec<String> = (0..24).map(|i| format!("{:02}.json", i)).collect();
+    // Files numbered ..3 are missing, and fail
+    let (failing, passing): (Vec<String>, Vec<String>) = files
+        .iter()
+        .cloned()
+        .partition(|file| file.ends_with("3.json"));
+    let write_files = || {
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::create_dir(&dir).unwrap();
+        for file in &passing {
+            std::fs::write(dir.join(file), "{\"a\":[1,2]}").unwrap();
+        }
+    };
+    let read_files = || {
+        let mut files: Vec<(PathBuf, String)> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let json = std::fs::read_to_string(&path).unwrap();
+                (path, json)
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let run_with = |jobs: &'static str, extra: &[&'static str]| {
+        let mut args = vec!["-v", "-j", jobs];
+        args.extend_from_slice(extra);
+        args.extend(files.iter().map(String::as_str));
+        write_files();
+        jsonxf_in(&dir, "", &args, "")
+    };
+    let reported = |errors: &str| -> Vec<String> {
+        let name = |line: &str| line.split_once(':').map(|(name, _)| name.to_string());
+        errors.lines().filter_map(name).collect()
+    };
+
+    for extra in [
+        &["-w", "--keep-going"][..],
+        &["--output-template", "{dir}/{stem}.out", "--keep-going"],
+    ] {
+        let one = run_with("1", extra);
+        let written = read_files();
+        let four = run_with("4", extra);
+        assert!(!one.0 && !four.0);
+        assert_eq!(four.2, one.2);
+        assert_eq!(read_files(), written);
+
+        // Every file is reported in the order given, failures last
+        assert_eq!(reported(&four.2), [&passing[..], &failing[..]].concat());
+        assert!(four.2.ends_with("3 of 24 files failed\n"), "{}", four.2);
+    }
+
+    // Without --keep-going, the first error stops the run
+    let (ok, _, errors) = run_with("4", &["-w"]);
+    assert!(!ok);
+    assert_eq!(
+        reported(&errors),
+        ["00.json", "01.json", "02.json", "03.json"]
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn write_formats_each_file_in_place() {
+    let dir = temp_dir("write");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}").unwrap();
+    std::fs::write(dir.join("b.json"), "[\n  1\n]\n").unwrap();
+    let (ok, output, errors) =
+        jsonxf_in(&dir, "", &["-w", "--backup=.orig", "a.json", "b.json"], "");
+    assert!(ok, "{}", errors);
+    assert_eq!(output, "");
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap();
+    assert_eq!(read("a.json"), "{\n  \"a\": 1\n}\n");
+    assert_eq!(read("a.json.orig"), "{\"a\":1}");
+    // An unchanged file gets no backup
+    assert!(!dir.join("b.json.orig").exists());
+
+    let (ok, _, _) = jsonxf_in(&dir, "", &["-w", "-m", "a.json"], "");
+    assert!(ok);
+    assert_eq!(read("a.json"), "{\"a\":1}");
+
+    for (args, error) in [
+        (&["-w"][..], "-w requires one or more files"),
+        (
+            &["-w", "-o", "out.json", "a.json"],
+            "-w cannot be combined with -i, -o, or -s",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", args, "");
+        assert!(!ok);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn input_files_are_formatted_one_after_another() {
+    let dir = temp_dir("files");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}").unwrap();
+    std::fs::write(dir.join("b.json"), "[1]").unwrap();
+    let (ok, output, _) = jsonxf_in(&dir, "", &["a.json", "b.json"], "");
+    assert!(ok);
+    assert_eq!(output, "{\n  \"a\": 1\n}\n[\n  1\n]\n");
+
+    // The first file which can't be read stops the run
+    let (ok, output, errors) = jsonxf_in(&dir, "", &["a.json", "missing.json", "b.json"], "");
+    assert!(!ok);
+    assert_eq!(output, "{\n  \"a\": 1\n}\n");
+    assert!(errors.starts_with("missing.json: "), "{}", errors);
+
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["-i", "a.json", "b.json"], "");
+    assert!(!ok);
+    assert!(
+        errors.contains("-i and -s cannot be combined with input files"),
+        "{}",
+        errors
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn watch_reformats_its_input_when_it_changes() {
+    use std::time::{Duration, Instant};
+
+    let dir = temp_dir("watch");
+    std::fs::write(dir.join("status.json"), "{\"a\":1}").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsonxf"))
+        .args(["--watch", "status.json", "-o", "pretty.json", "--no-config"])
+        .current_dir(&dir)
+        .env_remove("JSONXF")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Waits for pretty.json to hold `expected`, or gives up after a
+    // while.  Writing `input` again each time keeps the change from
+    // being missed if made before jsonxf began waiting for one.
+    let wait_for = |input: Option<&str>, expected: &str| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(input) = input {
+                std::fs::write(dir.join("status.json"), input).unwrap();
+            }
+            let json = std::fs::read_to_string(dir.join("pretty.json")).unwrap_or_default();
+            if json == expected || Instant::now() > deadline {
+                return json;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    };
+    let first = wait_for(None, "{\n  \"a\": 1\n}\n");
+    let second = wait_for(
+        Some("{\"a\":[1,2]}"),
+        "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n",
+    );
+    let _ = child.kill();
+    let _ = child.wait();
+    assert_eq!(first, "{\n  \"a\": 1\n}\n");
+    assert_eq!(second, "{\n  \"a\": [\n    1,\n    2\n  ]\n}\n");
+
+    for (args, error) in [
+        (
+            &["--watch", "status.json", "pretty.json"][..],
+            "--watch requires one input file, or -w",
+        ),
+        (
+            &["--watch", "status.json", "-o", "status.json"],
+            "--watch cannot write to its input file; use -w instead",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", args, "");
+        assert!(!ok);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+// Runs jsonxf with no input in a terminal, through `script`, where there
+// is one.
+#[cfg(unix)]
+#[test]
+fn no_input_from_a_terminal_prints_usage() {
+    use std::io::Read;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let command = format!("{} --no-config", env!("CARGO_BIN_EXE_jsonxf"));
+    let child = Command::new("script")
+        .args(["-qec", &command, "/dev/null"])
+        .env_remove("JSONXF")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return, // no `script` here
+    };
+    // Typing nothing, rather than waiting for JSON to be typed
+    let _stdin = child.stdin.take();
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        let _ = sender.send(output);
+    });
+    let output = receiver.recv_timeout(Duration::from_secs(10));
+    let _ = child.kill();
+    let status = child.wait().unwrap();
+    let output = output.expect("jsonxf waited for input from the terminal");
+    assert!(!status.success());
+    assert!(
+        output.contains("No input was given, and stdin is a terminal."),
+        "{}",
+        output
+    );
+    assert!(output.contains("--interactive"), "{}", output);
+}
+
+#[test]
+fn eol_sets_the_line_ending_of_every_line() {
+    let input = "{\"a\":[1]}\r\n{\"b\":2}\n";
+    let (ok, output, _) = jsonxf(&["--eol", "crlf"], input);
+    assert!(ok);
+    assert_eq!(
+        output,
+        "{\r\n  \"a\": [\r\n    1\r\n  ]\r\n}\r\n{\r\n  \"b\": 2\r\n}\r\n"
+    );
+    let (_, output, _) = jsonxf(&["-m", "--eol=crlf"], input);
+    assert_eq!(output, "{\"a\":[1]}\r\n{\"b\":2}");
+    let (_, output, _) = jsonxf(&["--eol", "lf"], "{\"a\":\r\n1}\r\n");
+    assert_eq!(output, "{\n  \"a\": 1\n}\n");
+
+    let (ok, _, errors) = jsonxf(&["--eol", "cr"], input);
+    assert!(!ok);
+    assert!(errors.contains("invalid line ending `cr`"), "{}", errors);
+}
+
+#[test]
+fn quiet_and_verbose_control_what_goes_to_stderr() {
+    let input = "{\"a\":1}\nnope\n";
+    let (ok, output, errors) = jsonxf(&["--on-error=skip"], input);
+    assert!(ok);
+    assert_eq!(output, "{\n  \"a\": 1\n}\n");
+    assert!(
+        errors.contains("skipped 1 line which could not be formatted"),
+        "{}",
+        errors
+    );
+    let (ok, quiet_output, errors) = jsonxf(&["--on-error=skip", "-q"], input);
+    assert!(ok);
+    assert_eq!(quiet_output, output);
+    assert_eq!(errors, "");
+
+    let dir = temp_dir("verbose");
+    std::fs::write(dir.join("a.json"), "{}").unwrap();
+    std::fs::write(dir.join("b.json"), "[]").unwrap();
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["-v", "a.json", "b.json"], "");
+    assert!(ok);
+    let lines: Vec<&str> = errors.lines().collect();
+    assert_eq!(lines[..2], ["a.json: formatted", "b.json: formatted"]);
+    assert!(lines[2].starts_with("formatted 2 files in "), "{}", errors);
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let (ok, _, errors) = jsonxf(&["-q", "-v"], "{}");
+    assert!(!ok);
+    assert!(errors.contains("use only one of -q and -v"), "{}", errors);
+}
+
+#[test]
+fn files_from_reads_a_list_of_input_files() {
+    let dir = temp_dir("files-from");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}").unwrap();
+    std::fs::write(dir.join("b.json"), "[1]").unwrap();
+    std::fs::write(dir.join("list"), "a.json\n\nb.json\n").unwrap();
+    let expected = "{\"a\":1}\n[1]";
+    for (args, list) in [
+        (&["--files-from", "list"][..], ""),
+        (&["--files-from", "-"], "a.json\nb.json"),
+        (&["--files-from=-"], "a.json\0b.json\0"),
+    ] {
+        let args = [args, &["-m"]].concat();
+        let (ok, output, errors) = jsonxf_in(&dir, "", &args, list);
+        assert!(ok, "{}", errors);
+        assert_eq!(output, expected);
+    }
+    // Files given as arguments come first
+    let (_, output, _) = jsonxf_in(&dir, "", &["-m", "--files-from", "list", "b.json"], "");
+    assert_eq!(output, "[1]\n{\"a\":1}\n[1]");
+
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--files-from", "missing"], "");
+    assert!(!ok);
+    assert!(errors.starts_with("missing: "), "{}", errors);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn smart_goes_the_other_way_from_its_input() {
+    let pretty = "{\n  \"a\": 1\n}\n";
+    assert_eq!(jsonxf(&["--smart"], "{\"a\":1}\n").1, pretty);
+    assert_eq!(jsonxf(&["--smart"], pretty).1, "{\"a\":1}");
+
+    let dir = temp_dir("smart");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}").unwrap();
+    std::fs::write(dir.join("b.json"), pretty).unwrap();
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--smart", "-w", "-v", "a.json", "b.json"], "");
+    assert!(ok);
+    assert!(
+        errors.starts_with("a.json: pretty-printed in place\nb.json: minimized in place\n"),
+        "{}",
+        errors
+    );
+    assert_eq!(std::fs::read_to_string(dir.join("a.json")).unwrap(), pretty);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("b.json")).unwrap(),
+        "{\"a\":1}"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let (ok, _, errors) = jsonxf(&["--smart", "-m"], "{}");
+    assert!(!ok);
+    assert!(
+        errors.contains("--smart cannot be combined with -m or --ndjson"),
+        "{}",
+        errors
+    );
+}
+
+#[test]
+fn write_leaves_no_temporary_files_and_keeps_permissions() {
+    let dir = temp_dir("write-safely");
+    let bad = "{\"a\":1}\nnope\n";
+    std::fs::write(dir.join("bad.json"), bad).unwrap();
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["-w", "--strict", "bad.json"], "");
+    assert!(!ok);
+    assert!(errors.starts_with("bad.json: "), "{}", errors);
+    // The original is untouched, and the temporary file is gone
+    assert_eq!(std::fs::read_to_string(dir.join("bad.json")).unwrap(), bad);
+    let files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["bad.json"]);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("a.json");
+        std::fs::write(&path, "{\"a\":1}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let (ok, _, _) = jsonxf_in(&dir, "", &["-w", "a.json"], "");
+        assert!(ok);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\n  \"a\": 1\n}\n"
+        );
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stats_summarizes_the_run_on_stderr() {
+    let dir = temp_dir("stats");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}").unwrap();
+    std::fs::write(dir.join("b.json"), "[1]").unwrap();
+    let (ok, output, errors) = jsonxf_in(&dir, "", &["--stats", "a.json", "b.json"], "");
+    assert!(ok);
+    assert_eq!(output.len(), 21);
+    assert!(
+        errors.starts_with("2 files, 2 records, 10 B in, 21 B out (210.0%), "),
+        "{}",
+        errors
+    );
+    assert_eq!(errors.lines().count(), 1);
+
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--stats=json", "a.json", "b.json"], "");
+    assert!(ok);
+    for field in [
+        "\"records\":2,",
+        "\"files\":2,",
+        "\"bytes\":10,",
+        "\"bytes_out\":21,",
+        "\"ratio\":2.100,",
+    ] {
+        assert!(errors.contains(field), "{}: {}", field, errors);
+    }
+
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--stats=xml", "a.json"], "");
+    assert!(!ok);
+    assert!(errors.contains("invalid stats format `xml`"), "{}", errors);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keep_going_reports_every_failure_at_the_end() {
+    let dir = temp_dir("keep-going");
+    std::fs::write(dir.join("a.json"), "{\"a\":1}\n").unwrap();
+    std::fs::write(dir.join("b.json"), "[1]\n").unwrap();
+    let files = ["a.json", "missing.json", "b.json", "gone.json"];
+    for fail_fast in [&[][..], &["--fail-fast"]] {
+        let args = [fail_fast, &files].concat();
+        let (ok, output, errors) = jsonxf_in(&dir, "", &args, "");
+        assert!(!ok);
+        assert_eq!(output, "{\n  \"a\": 1\n}\n");
+        assert!(errors.starts_with("missing.json: "), "{}", errors);
+        assert_eq!(errors.lines().count(), 1);
+    }
+
+    let args = [&["--keep-going"][..], &files].concat();
+    let (ok, output, errors) = jsonxf_in(&dir, "", &args, "");
+    assert!(!ok);
+    assert_eq!(output, "{\n  \"a\": 1\n}\n[\n  1\n]\n");
+    let lines: Vec<&str> = errors.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", errors);
+    assert!(lines[0].starts_with("missing.json: "));
+    assert!(lines[1].starts_with("gone.json: "));
+    assert_eq!(lines[2], "2 of 4 files failed");
+
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--fail-fast", "--keep-going", "a.json"], "");
+    assert!(!ok);
+    assert!(
+        errors.contains("use only one of --fail-fast and --keep-going"),
+        "{}",
+        errors
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn split_divides_input_into_parts_without_breaking_records() {
+    let dir = temp_dir("split");
+    let read_parts = || {
+        let mut parts: Vec<(String, String)> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap() != "big.json")
+            .map(|path| {
+                let json = std::fs::read_to_string(&path).unwrap();
+                let _ = std::fs::remove_file(&path);
+                (
+                    path.file_name().unwrap().to_string_lossy().into_owned(),
+                    json,
+                )
+            })
+            .collect();
+        parts.sort();
+        parts
+    };
+    let part = |name: &str, json: &str| (name.to_string(), json.to_string());
+
+    let input = "{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n";
+    let args = [
+        "split",
+        "-m",
+        "--records",
+        "2",
+        "--out",
+        "part-{seq}.ndjson",
+    ];
+    let (ok, _, errors) = jsonxf_in(&dir, "", &args, input);
+    assert!(ok, "{}", errors);
+    assert_eq!(
+        read_parts(),
+        [
+            part("part-0000.ndjson", "{\"a\":1}\n{\"b\":2}\n"),
+            part("part-0001.ndjson", "{\"c\":3}\n"),
+        ]
+    );
+
+    // A part may be larger than --bytes, rather than break a record
+    let args = ["split", "-m", "--bytes", "10", "--out", "b{seq}"];
+    let (ok, _, _) = jsonxf_in(&dir, "", &args, "{\"a\":1}\n{\"b\":[1,2,3,4]}\n");
+    assert!(ok);
+    assert_eq!(
+        read_parts(),
+        [
+            part("b0000", "{\"a\":1}\n"),
+            part("b0001", "{\"b\":[1,2,3,4]}\n")
+        ]
+    );
+
+    std::fs::write(dir.join("big.json"), "[1,2,3,4,5]").unwrap();
+    let args = [
+        "split",
+        "-m",
+        "--array-elements",
+        "2",
+        "--out",
+        "p{seq}.json",
+        "big.json",
+    ];
+    let (ok, _, _) = jsonxf_in(&dir, "", &args, "");
+    assert!(ok);
+    assert_eq!(
+        read_parts(),
+        [
+            part("p0000.json", "[1,2]\n"),
+            part("p0001.json", "[3,4]\n"),
+            part("p0002.json", "[5]\n"),
+        ]
+    );
+
+    for (args, error) in [
+        (
+            &["split", "--records", "1", "big.json"][..],
+            "--split requires --out",
+        ),
+        (
+            &["split", "--out", "x{seq}", "big.json"],
+            "--split needs --records, --bytes, or --array-elements",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", args, "");
+        assert!(!ok);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+// Pages output to a terminal, through `script`, where there is one.
+#[cfg(unix)]
+#[test]
+fn output_longer_than_the_terminal_goes_through_the_pager() {
+    let dir = temp_dir("pager");
+    let long: Vec<String> = (0..30).map(|i| i.to_string()).collect();
+    std::fs::write(dir.join("long.json"), format!("[{}]", long.join(","))).unwrap();
+    std::fs::write(dir.join("short.json"), "[1]").unwrap();
+    // Runs jsonxf in a terminal 10 rows high, with a pager which marks
+    // each line it shows
+    let in_terminal = |args: &str| {
+        let command = format!(
+            "stty rows 10 cols 80; {} --no-config {}",
+            env!("CARGO_BIN_EXE_jsonxf"),
+            args
+        );
+        Command::new("script")
+            .args(["-qec", &command, "/dev/null"])
+            .current_dir(&dir)
+            .env_remove("JSONXF")
+            .env_remove("NO_COLOR")
+            .env("PAGER", "sed s/^/paged:/")
+            .stdin(Stdio::null())
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let output = match in_terminal("long.json") {
+        Some(output) => output,
+        None => return, // no `script` here
+    };
+    // Every line is paged, in color
+    assert_eq!(output.lines().count(), 32, "{}", output);
+    assert!(
+        output.lines().all(|line| line.starts_with("paged:")),
+        "{}",
+        output
+    );
+    assert!(output.contains("\x1b[36m29\x1b[0m"), "{}", output);
+
+    for args in ["--no-pager long.json", "short.json"] {
+        let output = in_terminal(args).unwrap();
+        assert!(!output.contains("paged:"), "{}: {}", args, output);
+        assert!(output.contains('1'), "{}: {}", args, output);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn errors_jsonl_writes_each_diagnostic_as_json() {
+    let dir = temp_dir("errors");
+    std::fs::write(dir.join("bad.json"), "{\"a\":1}\nnope\n").unwrap();
+    let (ok, _, errors) = jsonxf_in(&dir, "", &["--errors=jsonl", "--strict", "bad.json"], "");
+    assert!(!ok);
+    assert_eq!(
+        errors,
+        "{\"kind\":\"error\",\"file\":\"bad.json\",\"offset\":8,\"line\":2,\"column\":1,\
+         \"message\":\"invalid number or literal `nope`\"}\n"
+    );
+
+    let args = [
+        "--errors",
+        "jsonl",
+        "--keep-going",
+        "missing.json",
+        "bad.json",
+    ];
+    let (ok, _, errors) = jsonxf_in(&dir, "", &args, "");
+    assert!(!ok);
+    let lines: Vec<&str> = errors.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", errors);
+    assert!(lines[0].starts_with("{\"kind\":\"error\",\"file\":\"missing.json\",\"offset\":null,"));
+    assert!(lines[1].starts_with("{\"kind\":\"error\",\"file\":null,"));
+    assert!(
+        lines[1].ends_with("\"message\":\"1 of 2 files failed\"}"),
+        "{}",
+        errors
+    );
+
+    let (ok, _, errors) = jsonxf(&["--errors=jsonl", "--on-error=skip"], "{}\nnope\n");
+    assert!(ok);
+    assert_eq!(
+        errors,
+        "{\"kind\":\"warning\",\"file\":null,\"offset\":null,\"line\":null,\"column\":null,\
+         \"message\":\"skipped 1 line which could not be formatted\"}\n"
+    );
+
+    let (ok, _, errors) = jsonxf(&["--errors=xml"], "{}");
+    assert!(!ok);
+    assert!(errors.contains("invalid errors format `xml`"), "{}", errors);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn color_honours_no_color_unless_forced() {
+    // `jsonxf` runs with NO_COLOR set, and output to a pipe
+    let (ok, output, _) = jsonxf(&["--color=always", "-m"], "[1]");
+    assert!(ok);
+    assert!(output.contains("\x1b[36m1\x1b[0m"), "{:?}", output);
+    assert_eq!(jsonxf(&["-m"], "[1]").1, "[1]");
+    assert_eq!(jsonxf(&["--color", "never", "-m"], "[1]").1, "[1]");
+
+    let (ok, _, errors) = jsonxf(&["--color=sometimes"], "[1]");
+    assert!(!ok);
+    assert!(
+        errors.contains("invalid color option `sometimes`"),
+        "{}",
+        errors
+    );
+
+    // In a terminal, through `script`, where there is one
+    #[cfg(unix)]
+    {
+        let in_terminal = |no_color: &str| {
+            let command = format!("echo [1] | {} -m --no-config", env!("CARGO_BIN_EXE_jsonxf"));
+            Command::new("script")
+                .args(["-qec", &command, "/dev/null"])
+                .env_remove("JSONXF")
+                .env("NO_COLOR", no_color)
+                .stdin(Stdio::null())
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+        if let Some(output) = in_terminal("1") {
+            assert_eq!(output, "[1]");
+            // An empty NO_COLOR doesn't count
+            let output = in_terminal("").unwrap();
+            assert!(output.contains("\x1b[36m1\x1b[0m"), "{:?}", output);
+        }
+    }
+}
+
+#[test]
+fn get_takes_files_after_a_separator() {
+    let dir = temp_dir("get");
+    std::fs::write(dir.join("a.json"), "{\"a\": 1}").unwrap();
+    std::fs::write(dir.join("b"), "{\"b\": 2}").unwrap();
+    let (ok, output, _) = jsonxf_in(&dir, "", &["get", "/a", "-m", "--", "a.json"], "");
+    assert!(ok);
+    assert_eq!(output, "1\n");
+    // A pointer is a pointer even where it names a file, as /tmp does
+    let (_, output, _) = jsonxf_in(&dir, "", &["get", "/tmp"], "{\"tmp\": [3]}");
+    assert_eq!(output, "[\n  3\n]\n");
+    let (_, output, _) = jsonxf_in(&dir, "", &["get", "", "-m", "--", "b"], "");
+    assert_eq!(output, "{\"b\":2}\n");
+
+    for (args, error) in [
+        (
+            &["get", "/a", "a.json"][..],
+            "`a.json` is not a JSON Pointer; give files after `--`",
+        ),
+        (
+            &["get", "--", "a.json"],
+            "get needs one or more JSON Pointers",
+        ),
+    ] {
+        let (ok, _, errors) = jsonxf_in(&dir, "", args, "");
+        assert!(!ok);
+        assert!(errors.contains(error), "{}", errors);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
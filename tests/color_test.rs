@@ -0,0 +1,87 @@
+extern crate jsonxf;
+use jsonxf::color::{colorize, ColorWriter, Palette};
+use jsonxf::Formatter;
+use std::io::Write;
+
+#[test]
+fn color_distinguishes_keys_from_string_values() {
+    let mut xf = Formatter::minimizer();
+    let colored = colorize("{\"k\": [\"v\"], \"k2\": \"v2\"}", &mut xf).unwrap();
+    assert_eq!(colored.matches("\x1b[1;34m\"k").count(), 2);
+    assert_eq!(colored.matches("\x1b[32m\"v").count(), 2);
+}
+
+#[test]
+fn color_uses_the_given_palette() {
+    let palette = Palette {
+        number: String::from("31"),
+        punct: String::from("2"),
+        ..Palette::default()
+    };
+    let mut output: Vec<u8> = vec![];
+    {
+        let mut writer = ColorWriter::with_palette(&mut output, palette);
+        writer.write_all(b"[1.5e3]").unwrap();
+        writer.flush().unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\x1b[2m[\x1b[0m\x1b[31m1.5e3\x1b[0m\x1b[2m]\x1b[0m"
+    );
+}
+
+#[test]
+fn color_resets_on_flush_inside_a_token() {
+    let mut output: Vec<u8> = vec![];
+    {
+        let mut writer = ColorWriter::new(&mut output);
+        writer.write_all(b"\"ab").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"c\"").unwrap();
+        writer.flush().unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\x1b[32m\"ab\x1b[0m\x1b[32mc\"\x1b[0m"
+    );
+}
+
+#[test]
+fn color_ends_a_trailing_literal_on_flush() {
+    let mut xf = Formatter::minimizer();
+    assert_eq!(colorize("false", &mut xf).unwrap(), "\x1b[33mfalse\x1b[0m");
+}
+
+#[test]
+fn color_tints_records_by_level_across_writes() {
+    let mut output: Vec<u8> = vec![];
+    {
+        let mut writer = ColorWriter::new(&mut output);
+        writer.tint_by_level("/log/level");
+        for b in b"{\"log\":{\"level\":\"ERROR\"}}\n[1]\n" {
+            writer.write_all(&[*b]).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0].matches("\x1b[31m").count(), 9);
+    assert_eq!(lines[0].matches("\x1b[").count(), 18);
+    assert_eq!(lines[1], "\x1b[1m[\x1b[0m\x1b[36m1\x1b[0m\x1b[1m]\x1b[0m");
+}
+
+#[test]
+fn color_holds_incomplete_tinted_records_across_flushes() {
+    let mut output: Vec<u8> = vec![];
+    {
+        let mut writer = ColorWriter::new(&mut output);
+        writer.tint_by_level("/level");
+        writer.write_all(b"{\"level\":").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.write(b"\"error\"}\n").unwrap(), 9);
+        writer.flush().unwrap();
+    }
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("\x1b[31m").count(), 5);
+    assert!(output.ends_with("\x1b[0m\n"));
+}
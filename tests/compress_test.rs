@@ -0,0 +1,64 @@
+#![cfg(feature = "compression")]
+
+extern crate bzip2;
+extern crate flate2;
+extern crate jsonxf;
+extern crate zstd;
+use jsonxf::compress::{decoder, detect, Compression, Encoder};
+use std::io::{Read, Write};
+
+fn decompress(compressed: &[u8]) -> String {
+    let mut input = compressed;
+    let compression = detect(&mut input).unwrap().unwrap();
+    let mut output = String::new();
+    decoder(input, compression)
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    output
+}
+
+#[test]
+fn compress_reads_concatenated_gzip_members() {
+    let mut compressed = vec![];
+    for json in &["{\"a\":1}\n", "{\"b\":2}\n"] {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(json.as_bytes()).unwrap();
+        compressed.extend(encoder.finish().unwrap());
+    }
+    assert_eq!(decompress(&compressed), "{\"a\":1}\n{\"b\":2}\n");
+}
+
+#[test]
+fn compress_reads_zstd() {
+    let compressed = zstd::encode_all(&b"[1,2,3]"[..], 0).unwrap();
+    assert_eq!(decompress(&compressed), "[1,2,3]");
+}
+
+#[test]
+fn compress_reads_bzip2() {
+    let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+    encoder.write_all(b"null").unwrap();
+    assert_eq!(decompress(&encoder.finish().unwrap()), "null");
+}
+
+#[test]
+fn compress_detection_does_not_consume_input() {
+    let mut input = &b"{\"a\":1}"[..];
+    assert_eq!(detect(&mut input).unwrap(), None);
+    assert_eq!(input, b"{\"a\":1}");
+    assert_eq!(Compression::from_filename("logs.tar"), None);
+}
+
+#[test]
+fn compress_encoder_round_trips_each_format() {
+    for compression in &[Compression::Gzip, Compression::Zstd, Compression::Bzip2] {
+        let mut encoder = Encoder::new(vec![], *compression).unwrap();
+        encoder.write_all(b"{\"a\":").unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(b"[true]}\n").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(Compression::from_magic(&compressed), Some(*compression));
+        assert_eq!(decompress(&compressed), "{\"a\":[true]}\n");
+    }
+}
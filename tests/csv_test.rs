@@ -0,0 +1,56 @@
+extern crate jsonxf;
+use jsonxf::csv::CsvOptions;
+
+#[test]
+fn csv_rows_become_objects() {
+    let csv = "id,name\r\n1,ana\r\n2,\"bo, \"\"the\"\" second\"\r\n";
+    assert_eq!(
+        jsonxf::csv_to_json(csv, CsvOptions::default()).unwrap(),
+        "{\"id\":\"1\",\"name\":\"ana\"}\n{\"id\":\"2\",\"name\":\"bo, \\\"the\\\" second\"}\n"
+    );
+}
+
+#[test]
+fn csv_quoted_fields_may_span_lines() {
+    let csv = "note,n\n\"line one\nline two\",1\n\n";
+    assert_eq!(
+        jsonxf::csv_to_json(csv, CsvOptions::default()).unwrap(),
+        "{\"note\":\"line one\\nline two\",\"n\":\"1\"}\n"
+    );
+}
+
+#[test]
+fn csv_infers_types_on_request() {
+    let options = CsvOptions {
+        infer_types: true,
+        ..CsvOptions::default()
+    };
+    let csv = "a,b,c,d,e,f\n-1.5e3,true,null,,007,1.\n";
+    assert_eq!(
+        jsonxf::csv_to_json(csv, options).unwrap(),
+        "{\"a\":-1.5e3,\"b\":true,\"c\":null,\"d\":null,\"e\":\"007\",\"f\":\"1.\"}\n"
+    );
+}
+
+#[test]
+fn tsv_and_array_output() {
+    let options = CsvOptions {
+        delimiter: b'\t',
+        array: true,
+        ..CsvOptions::default()
+    };
+    assert_eq!(
+        jsonxf::csv_to_json("x\ty\n1\t2\n3\n", options).unwrap(),
+        "[{\"x\":\"1\",\"y\":\"2\"},{\"x\":\"3\",\"y\":null}]"
+    );
+    assert_eq!(jsonxf::csv_to_json("x\ty\n", options).unwrap(), "[]");
+}
+
+#[test]
+fn csv_rejects_bad_rows() {
+    assert_eq!(
+        jsonxf::csv_to_json("a\n1\n2,3\n", CsvOptions::default()).unwrap_err(),
+        "line 3: 2 fields, but the header has only 1"
+    );
+    assert!(jsonxf::csv_to_json("a\n\"open", CsvOptions::default()).is_err());
+}
@@ -0,0 +1,112 @@
+extern crate jsonxf;
+use jsonxf::diff::{Change, Differ};
+use jsonxf::value::Value;
+
+fn num(n: &str) -> Value {
+    Value::Number(String::from(n))
+}
+
+#[test]
+fn diff_ignores_formatting() {
+    let a = "{\"a\": [1, {\"b\": 10}], \"c\": \"x\"}";
+    let b = "{\n  \"c\": \"x\",\n  \"a\": [1.0, {\"b\": 1e1}]\n}";
+    assert_eq!(jsonxf::diff(a, b).unwrap(), vec![]);
+}
+
+#[test]
+fn diff_reports_added_removed_and_changed() {
+    let a = "{\"a\": [1, 2, 3], \"b\": {\"c\": true}, \"d\": null}";
+    let b = "{\"a\": [1, 5], \"b\": {\"c\": true, \"e/f\": []}, \"d\": {}}";
+    assert_eq!(
+        jsonxf::diff(a, b).unwrap(),
+        vec![
+            Change::Changed {
+                path: String::from("/a/1"),
+                old: num("2"),
+                new: num("5"),
+            },
+            Change::Removed {
+                path: String::from("/a/2"),
+                value: num("3"),
+            },
+            Change::Added {
+                path: String::from("/b/e~1f"),
+                value: Value::Array(vec![]),
+            },
+            Change::Changed {
+                path: String::from("/d"),
+                old: Value::Null,
+                new: Value::Object(vec![]),
+            },
+        ]
+    );
+}
+
+#[test]
+fn diff_can_consider_key_order() {
+    let mut differ = Differ::new();
+    differ.ignore_key_order = false;
+    let changes = differ
+        .diff(
+            "{\"x\": {\"a\": 1, \"b\": 2}}",
+            "{\"x\": {\"b\": 2, \"a\": 1}}",
+        )
+        .unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path(), "/x");
+}
+
+#[test]
+fn diff_considering_key_order_reports_added_and_removed_keys() {
+    let mut differ = Differ::new();
+    differ.ignore_key_order = false;
+    let changes = differ
+        .diff(
+            "{\"x\": {\"k\": [1, 2, 3], \"old\": true}, \"y\": 1}",
+            "{\"x\": {\"new\": false, \"k\": [1, 2, 4]}}",
+        )
+        .unwrap();
+    let paths: Vec<&str> = changes.iter().map(|c| c.path()).collect();
+    assert_eq!(paths, vec!["/x/k/2", "/x/old", "/x/new", "/y"]);
+
+    // A reordering is reported after the differences in the members
+    let changes = differ
+        .diff(
+            "{\"a\": 1, \"b\": {\"c\": 2}, \"d\": 3}",
+            "{\"b\": {\"c\": 5}, \"a\": 1}",
+        )
+        .unwrap();
+    let paths: Vec<&str> = changes.iter().map(|c| c.path()).collect();
+    assert_eq!(paths, vec!["/b/c", "/d", ""]);
+}
+
+#[test]
+fn diff_rejects_invalid_input() {
+    assert!(jsonxf::diff("{\"a\": 1", "{}").is_err());
+    assert!(jsonxf::diff("1 2", "1").is_err());
+}
+
+#[test]
+fn diff_can_ignore_array_order() {
+    let mut differ = Differ::new();
+    differ.ignore_array_order = true;
+    assert_eq!(
+        differ
+            .diff("[3, {\"a\": [1, 2]}, 1]", "[1, {\"a\": [2, 1.0]}, 3]")
+            .unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        differ.diff("[1, 2, 2]", "[2, 4, 1]").unwrap(),
+        vec![
+            Change::Removed {
+                path: String::from("/2"),
+                value: num("2"),
+            },
+            Change::Added {
+                path: String::from("/1"),
+                value: num("4"),
+            },
+        ]
+    );
+}
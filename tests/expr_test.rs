@@ -0,0 +1,94 @@
+extern crate jsonxf;
+use jsonxf::expr::{transform, Expr};
+
+#[test]
+fn expr_selects_paths_and_iterates() {
+    let json = "{\"a\": {\"b c\": [1, 2]}, \"items\": [{\"id\": 1}, {\"id\": 2}, {}]}";
+    assert_eq!(transform(".", "[1]").unwrap(), "[1]");
+    assert_eq!(transform(".a.\"b c\"[1]", json).unwrap(), "2");
+    assert_eq!(transform(".[\"a\"].missing", json).unwrap(), "null");
+    assert_eq!(transform(".items[].id", json).unwrap(), "1\n2\nnull");
+    assert_eq!(transform(".a[]", json).unwrap(), "[1,2]");
+}
+
+#[test]
+fn expr_deletes_and_sets() {
+    let json = "{\"a\": 1, \"debug\": {\"x\": 1}, \"items\": [{\"id\": 1, \"tmp\": 0}]}";
+    assert_eq!(
+        transform(
+            "del(.debug) | del(.items[].tmp) | set(.a, {\"n\": \")\"})",
+            json
+        )
+        .unwrap(),
+        "{\"a\":{\"n\":\")\"},\"items\":[{\"id\":1}]}"
+    );
+    assert_eq!(
+        transform("set(.new.list[1], true) | del(.nothing.here)", "{}").unwrap(),
+        "{\"new\":{\"list\":[null,true]}}"
+    );
+    assert_eq!(transform("del(.[0])", "[1, 2]").unwrap(), "[2]");
+}
+
+#[test]
+fn expr_reports_errors() {
+    assert!(transform(".a.b", "{\"a\": 1}")
+        .unwrap_err()
+        .contains("cannot get key `b` of number"));
+    assert!(transform("del(.)", "{}").is_err());
+    assert!(transform("set(.a, nope)", "{}")
+        .unwrap_err()
+        .contains("invalid JSON value"));
+    assert!(transform("map(.a)", "{}")
+        .unwrap_err()
+        .contains("unknown function `map`"));
+    assert!(transform(".a | ", "{}").is_err());
+}
+
+// Reads one byte at a time, so tokens are split across chunks.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> std::io::Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((&b, rest)) if !buf.is_empty() => {
+                buf[0] = b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn expr_applies_to_a_stream() {
+    let expr: Expr = "del(.tmp) | set(.tags[1], \"new\") | .tags[]"
+        .parse()
+        .unwrap();
+    let input = b"{\"tmp\": 1, \"tags\": [\"a\\\"b\"]} {\"tags\": null}\n{\"t\": 1.50}";
+    let mut output = vec![];
+    expr.apply_stream(&mut Trickle(input), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\"a\\\"b\"\n\"new\"\nnull\n\"new\"\nnull\n\"new\"\n"
+    );
+
+    let expr: Expr = ".a".parse().unwrap();
+    let mut output = vec![];
+    expr.apply_stream(&mut Trickle(b"{\"a\": 1.50e3} 7"), &mut output)
+        .unwrap_err();
+    assert_eq!(output, b"1.50e3\n");
+}
+
+#[test]
+fn expr_rejects_invalid_input() {
+    for input in ["{\"a\": 1 \"b\": 2}", "[1, nope]", "{\"a\": [1}", "\"\\q\""] {
+        let error = transform(".", input).unwrap_err();
+        assert!(
+            error.contains(" at line 1, column "),
+            "{}: {}",
+            input,
+            error
+        );
+    }
+}
@@ -0,0 +1,80 @@
+extern crate jsonxf;
+use jsonxf::fixed::FixedFormatter;
+use jsonxf::Formatter;
+
+// Formats `input`, fed `chunk` bytes at a time, through an output
+// buffer of `room` bytes.
+fn format_fixed(xf: &mut FixedFormatter, input: &str, chunk: usize, room: usize) -> String {
+    let mut output = vec![0_u8; room];
+    let mut formatted: Vec<u8> = vec![];
+    for piece in input.as_bytes().chunks(chunk) {
+        let mut rest = piece;
+        loop {
+            let progress = xf.format(rest, &mut output);
+            formatted.extend_from_slice(&output[..progress.written]);
+            rest = &rest[progress.read..];
+            if !progress.output_full {
+                assert!(rest.is_empty());
+                break;
+            }
+        }
+    }
+    loop {
+        let progress = xf.finish(&mut output);
+        formatted.extend_from_slice(&output[..progress.written]);
+        if !progress.output_full {
+            break;
+        }
+    }
+    String::from_utf8(formatted).unwrap()
+}
+
+const INPUT: &str = "{\"a\": [1, 2.5, {\"b\": \"c\\\"d\\\\\", \"e\": []}], \"f\": {}}\n\
+                     \"s\" 7 true [[[\"deep\"]]] {\"é\": \"ü\"}";
+
+#[test]
+fn fixed_formatter_matches_formatter_at_any_buffer_size() {
+    for &(chunk, room) in &[(1, 1), (1, 64), (3, 2), (7, 5), (1000, 1), (1000, 1000)] {
+        assert_eq!(
+            format_fixed(&mut FixedFormatter::pretty_printer(), INPUT, chunk, room),
+            Formatter::pretty_printer().format(INPUT).unwrap()
+        );
+        assert_eq!(
+            format_fixed(&mut FixedFormatter::minimizer(), INPUT, chunk, room),
+            Formatter::minimizer().format(INPUT).unwrap()
+        );
+    }
+}
+
+#[test]
+fn fixed_formatter_settings() {
+    let mut xf = FixedFormatter::pretty_printer();
+    xf.indent = "\t\t";
+    xf.line_separator = "\r\n";
+    xf.record_separator = "\r\n";
+    xf.after_colon = "  ";
+    xf.trailing_output = "\r\n";
+    xf.eager_record_separators = true;
+    let mut expected = Formatter::pretty_printer();
+    expected.indent = String::from("\t\t");
+    expected.line_separator = String::from("\r\n");
+    expected.record_separator = String::from("\r\n");
+    expected.after_colon = String::from("  ");
+    expected.trailing_output = String::from("\r\n");
+    expected.eager_record_separators = true;
+    assert_eq!(
+        format_fixed(&mut xf, INPUT, 4, 3),
+        expected.format(INPUT).unwrap()
+    );
+}
+
+#[test]
+fn fixed_formatter_reset() {
+    let mut xf = FixedFormatter::minimizer();
+    let mut output = [0_u8; 4];
+    let progress = xf.format(b"[1, 2, 3]", &mut output);
+    assert!(progress.output_full);
+    assert_eq!(&output[..progress.written], b"[1,2");
+    xf.reset();
+    assert_eq!(format_fixed(&mut xf, "[4]", 1, 1), "[4]");
+}
@@ -1,5 +1,5 @@
 extern crate jsonxf;
-use jsonxf::Formatter;
+use jsonxf::{FoldStyle, Formatter, FormatterState};
 
 #[test]
 fn indent() {
@@ -51,3 +51,367 @@ fn trailing_output() {
         xf.format("{\"a\":{\"b\":{\"c\":3}}}").unwrap()
     );
 }
+
+#[test]
+fn record_separator_between_scalars() {
+    let mut xf = Formatter::minimizer();
+    xf.record_separator = String::from("X");
+    assert_eq!(
+        "1X-2.5X\"a b\"X[true]XnullX{}",
+        xf.format("1 -2.5\"a b\" [true]null{}").unwrap()
+    );
+}
+
+#[test]
+fn eager_record_separators_after_scalars() {
+    let mut xf = Formatter::minimizer();
+    xf.eager_record_separators = true;
+    let mut output: Vec<u8> = vec![];
+    xf.format_buf(b"{\"a\":\n1}\n\n12", &mut output).unwrap();
+    assert_eq!(output, b"{\"a\":1}\n12");
+    xf.format_buf(b"3 \"x\"", &mut output).unwrap();
+    assert_eq!(output, b"{\"a\":1}\n123\n\"x\"\n");
+    assert_eq!(xf.format("true [] false").unwrap(), "true\n[]\nfalse\n");
+}
+
+#[test]
+fn escape_non_ascii() {
+    let mut xf = Formatter::minimizer();
+    xf.escape_non_ascii = true;
+    assert_eq!(
+        "{\"caf\\u00e9\":[\"\\ud83d\\ude00\\\"\",\"\\u00e9\"]}",
+        xf.format("{\"café\": [\"😀\\\"\", \"\\u00e9\"]}").unwrap()
+    );
+}
+
+#[test]
+fn escape_non_ascii_across_chunks() {
+    let mut xf = Formatter::minimizer();
+    xf.escape_non_ascii = true;
+    let mut output: Vec<u8> = vec![];
+    for b in "[\"→😀\"]".as_bytes() {
+        xf.format_buf(&[*b], &mut output).unwrap();
+    }
+    assert_eq!(output, b"[\"\\u2192\\ud83d\\ude00\"]");
+}
+
+#[test]
+fn max_depth_minifies_deep_containers() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_depth = Some(1);
+    assert_eq!(
+        "[\n  {\"a\":[\"]\",{}]},\n  [],\n  1\n]",
+        xf.format("[{\"a\": [\"]\", {}]}, [], 1]").unwrap()
+    );
+}
+
+#[test]
+fn max_depth_ellipsis_across_chunks() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_depth = Some(0);
+    xf.fold_style = FoldStyle::Ellipsis;
+    let mut output: Vec<u8> = vec![];
+    for b in b"{\"a\": [\"}\\\"\"]} [] \"s\"" {
+        xf.format_buf(&[*b], &mut output).unwrap();
+    }
+    assert_eq!(output, b"{...}\n[]\n\"s\"");
+}
+
+#[test]
+fn state_resumes_formatting_at_any_point() {
+    let input = "{\"a\": [\"x\\\"y\", {}], \"é\": \"ü\"} 12 \"s\" [[]]";
+    let mut expected = Formatter::pretty_printer();
+    expected.escape_non_ascii = true;
+    let expected = expected.format(input).unwrap();
+    for split in 0..=input.len() {
+        let mut output: Vec<u8> = vec![];
+        let mut xf = Formatter::pretty_printer();
+        xf.escape_non_ascii = true;
+        xf.format_buf(&input.as_bytes()[..split], &mut output)
+            .unwrap();
+        let saved = xf.state().unwrap().to_bytes();
+
+        let mut xf = Formatter::pretty_printer();
+        xf.escape_non_ascii = true;
+        xf.restore(&FormatterState::from_bytes(&saved).unwrap());
+        xf.format_stream(&mut &input.as_bytes()[split..], &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            expected,
+            "split at {}",
+            split
+        );
+    }
+}
+
+#[test]
+fn state_bytes() {
+    let mut xf = Formatter::minimizer();
+    xf.format_buf(b"[[[\"abc", &mut vec![]).unwrap();
+    let state = xf.state().unwrap();
+    assert_eq!(state.depth(), 3);
+    assert!(state.in_string());
+    assert_eq!(FormatterState::from_bytes(&state.to_bytes()), Ok(state));
+    assert!(FormatterState::from_bytes(b"").is_err());
+    assert!(FormatterState::from_bytes(&[2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    assert!(FormatterState::from_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]).is_err());
+}
+
+#[test]
+fn set_line_ending_keeps_minimized_lines_unbroken() {
+    let mut xf = Formatter::minimizer();
+    xf.set_line_ending("\r\n");
+    assert_eq!(xf.format("[1, 2]\n[3]").unwrap(), "[1,2]\r\n[3]");
+}
+
+#[test]
+fn detect_line_ending_uses_the_first_line_feed() {
+    assert_eq!(jsonxf::detect_line_ending(b"\n{\r\n}"), Some("\n"));
+    assert_eq!(jsonxf::detect_line_ending(b"[1,\r2]\r\n"), Some("\r\n"));
+    assert_eq!(jsonxf::detect_line_ending(b"\r"), None);
+    assert_eq!(jsonxf::detect_line_ending(b""), None);
+}
+
+#[test]
+fn detect_indent_judges_by_nesting() {
+    // The first indented line closes a level, and is indented one less
+    assert_eq!(
+        jsonxf::detect_indent(b"[[\n   1\n   ],\n   2\n]"),
+        Some(String::from("   "))
+    );
+    // Newlines in strings and blank lines don't count
+    assert_eq!(
+        jsonxf::detect_indent(b"{\"a\n b\":\r\n\r\n    1\r\n}"),
+        Some(String::from("    "))
+    );
+    // Nor do lines indented unevenly for their depth
+    assert_eq!(jsonxf::detect_indent(b"[[\n   1]]"), None);
+}
+
+#[test]
+fn sort_keys_sorts_each_object() {
+    let mut xf = Formatter::pretty_printer();
+    xf.sort_keys = true;
+    assert_eq!(
+        xf.format("{\"b\": 1, \"a\": [{\"d\": 2, \"c\": \"}\"}]} 3 \"x\" [{\"z\": 0, \"y\": 0}]")
+            .unwrap(),
+        "{\n  \"a\": [\n    {\n      \"c\": \"}\",\n      \"d\": 2\n    }\n  ],\n  \"b\": 1\n}\n3\n\"x\"\n[\n  {\n    \"y\": 0,\n    \"z\": 0\n  }\n]"
+    );
+}
+
+#[test]
+fn sort_keys_holds_records_across_chunks() {
+    let input = b"{\"b\": \"{[\\\"\", \"a\": null}\n{\"d\": 1, \"c\": 2}";
+    for split in 0..input.len() {
+        let mut output: Vec<u8> = vec![];
+        let mut xf = Formatter::minimizer();
+        xf.sort_keys = true;
+        xf.format_buf(&input[..split], &mut output).unwrap();
+        xf.format_buf(&input[split..], &mut output).unwrap();
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"a\":null,\"b\":\"{[\\\"\"}\n{\"c\":2,\"d\":1}"
+        );
+    }
+}
+
+#[test]
+fn sort_keys_formats_invalid_records_as_they_were() {
+    let mut xf = Formatter::minimizer();
+    xf.sort_keys = true;
+    assert_eq!(
+        xf.format("{\"b\": 1, \"a\"} {\"b\": 01, \"a\": 2} [1, {\"b\"")
+            .unwrap(),
+        "{\"b\":1,\"a\"}\n{\"b\":01,\"a\":2}\n[1,{\"b\""
+    );
+}
+
+#[test]
+fn max_inline_array_width_keeps_short_arrays_on_one_line() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_inline_array_width = Some(15);
+    assert_eq!(
+        xf.format("{\"a\": [1,2,  3], \"b\": [\"x, ]\", null], \"c\": [10, 20, 30, 40], \"d\": [[1], {}], \"e\": [ ]}")
+            .unwrap(),
+        "{\n  \"a\": [1, 2, 3],\n  \"b\": [\"x, ]\", null],\n  \"c\": [\n    10,\n    20,\n    30,\n    40\n  ],\n  \"d\": [\n    [1],\n    {}\n  ],\n  \"e\": []\n}"
+    );
+}
+
+#[test]
+fn max_inline_array_width_holds_arrays_across_chunks() {
+    let input = b"[[1, \"\\\"]\"], [true, false, null]] [2]";
+    for split in 0..input.len() {
+        let mut output: Vec<u8> = vec![];
+        let mut xf = Formatter::pretty_printer();
+        xf.max_inline_array_width = Some(10);
+        xf.eager_record_separators = true;
+        xf.format_buf(&input[..split], &mut output).unwrap();
+        xf.format_buf(&input[split..], &mut output).unwrap();
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[\n  [1, \"\\\"]\"],\n  [\n    true,\n    false,\n    null\n  ]\n]\n[2]\n"
+        );
+    }
+}
+
+#[test]
+fn max_inline_array_width_counts_characters() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_inline_array_width = Some(9);
+    assert_eq!(xf.format("[\"éé\", 1]").unwrap(), "[\"éé\", 1]");
+    // An array cut off by the end of input is formatted as usual
+    let mut xf = Formatter::pretty_printer();
+    xf.max_inline_array_width = Some(9);
+    assert_eq!(xf.format("[1, 2").unwrap(), "[\n  1,\n  2");
+}
+
+#[test]
+fn max_inline_object_width_keeps_short_objects_on_one_line() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_inline_object_width = Some(16);
+    assert_eq!(
+        xf.format("[{\"x\":1,\"y\":2}, {\"x\": 10, \"y\": 20}, {\"p\": {\"x\": 1}}, {\"q\": [1]}, {}]")
+            .unwrap(),
+        "[\n  {\"x\": 1, \"y\": 2},\n  {\n    \"x\": 10,\n    \"y\": 20\n  },\n  {\n    \"p\": {\"x\": 1}\n  },\n  {\n    \"q\": [\n      1\n    ]\n  },\n  {}\n]"
+    );
+}
+
+#[test]
+fn max_inline_object_width_with_arrays() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_inline_array_width = Some(20);
+    xf.max_inline_object_width = Some(20);
+    xf.after_colon = String::from("");
+    assert_eq!(
+        xf.format("{\"points\": [{\"x\": 1, \"y\": 2}], \"tags\": [\"a}\", \"b]\"]}")
+            .unwrap(),
+        "{\n  \"points\":[\n    {\"x\":1, \"y\":2}\n  ],\n  \"tags\":[\"a}\", \"b]\"]\n}"
+    );
+}
+
+#[test]
+fn require_complete_rejects_input_cut_off() {
+    let cases = [
+        (
+            "[1, {\"a\": 2}",
+            Some("input ended inside an array or object"),
+        ),
+        ("{\"a\": \"b", Some("input ended inside a string")),
+        ("\"a\\", Some("input ended inside a string")),
+        ("[1] {\"a\": [2]} 3", None),
+    ];
+    for (input, error) in &cases {
+        for setup in 0..3 {
+            let mut xf = Formatter::pretty_printer();
+            xf.require_complete = true;
+            xf.sort_keys = setup == 1;
+            xf.max_inline_array_width = if setup == 2 { Some(20) } else { None };
+            let mut output: Vec<u8> = vec![];
+            let result = xf
+                .format_buf(input.as_bytes(), &mut output)
+                .and_then(|_| xf.finish_buf(&mut output));
+            match error {
+                None => result.unwrap(),
+                Some(message) => {
+                    let e = result.unwrap_err();
+                    assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+                    assert_eq!(&e.to_string(), message, "{}", input);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn format_buf_rejects_extra_closing_brackets() {
+    for setup in 0..3 {
+        let mut xf = Formatter::minimizer();
+        xf.sort_keys = setup == 1;
+        xf.max_depth = if setup == 2 { Some(0) } else { None };
+        let mut output: Vec<u8> = vec![];
+        let error = xf.format_buf(b"{\"a\": [1]}}", &mut output).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "unexpected closing bracket");
+        assert_eq!(output, b"{\"a\":[1]}");
+    }
+}
+
+// Keeps what had been written at each flush.
+#[derive(Default)]
+struct Flushes {
+    written: Vec<u8>,
+    flushed: Vec<String>,
+}
+
+impl std::io::Write for Flushes {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flushed
+            .push(String::from_utf8(self.written.clone()).unwrap());
+        Ok(())
+    }
+}
+
+#[test]
+fn ndjson_writes_and_flushes_each_record_on_its_own_line() {
+    let mut xf = Formatter::ndjson();
+    let mut output = Flushes::default();
+    xf.format_buf(b"{\n  \"a\": [1,\n 2]\n}\"x\" 3", &mut output)
+        .unwrap();
+    assert_eq!(
+        output.flushed,
+        vec!["{\"a\":[1,2]}\n", "{\"a\":[1,2]}\n\"x\"\n"]
+    );
+    xf.format_buf(b"4 [\n", &mut output).unwrap();
+    assert_eq!(output.flushed.len(), 3);
+    assert_eq!(output.flushed[2], "{\"a\":[1,2]}\n\"x\"\n34\n");
+    xf.format_buf(b"]", &mut output).unwrap();
+    assert_eq!(output.flushed[3], "{\"a\":[1,2]}\n\"x\"\n34\n[]\n");
+}
+
+#[test]
+fn flush_records_flushes_through_format_stream() {
+    let mut xf = Formatter::pretty_printer();
+    xf.flush_records = true;
+    let mut output = Flushes::default();
+    let mut input = &b"{\"a\": 1} {\"b\": 2}"[..];
+    xf.format_stream(&mut input, &mut output).unwrap();
+    assert_eq!(
+        output.flushed[..2],
+        ["{\n  \"a\": 1\n}", "{\n  \"a\": 1\n}\n{\n  \"b\": 2\n}"]
+    );
+}
+
+#[test]
+fn state_is_none_while_input_is_held() {
+    let inputs = [
+        ("[[1,", "2]]"),
+        ("{\"b\": 1, ", "\"a\": 2}"),
+        ("[1, ", "2]"),
+        ("[1, /* a, b", " */ 2]"),
+        ("[1, 2,", "]"),
+        ("[1, // one", "\n2]"),
+    ];
+    for (setup, (start, rest)) in inputs.iter().enumerate() {
+        let mut xf = Formatter::pretty_printer();
+        match setup {
+            0 => xf.max_inline_array_width = Some(20),
+            1 => xf.sort_keys = true,
+            2 => xf.validate = true,
+            3 => xf.strip_comments = true,
+            4 => xf.strip_trailing_commas = true,
+            _ => xf.keep_comments = true,
+        }
+        xf.format_buf(start.as_bytes(), &mut vec![]).unwrap();
+        assert!(xf.state().is_none(), "{}", start);
+        xf.format_buf(rest.as_bytes(), &mut vec![]).unwrap();
+        assert!(xf.state().is_some(), "{}", start);
+    }
+}
@@ -0,0 +1,73 @@
+extern crate jsonxf;
+use jsonxf::patch::ArrayStrategy;
+
+const STRATEGIES: [ArrayStrategy; 3] = [
+    ArrayStrategy::Replace,
+    ArrayStrategy::ByIndex,
+    ArrayStrategy::Lcs,
+];
+
+#[test]
+fn generated_patches_transform_a_into_b() {
+    let test_cases = vec![
+        ("{}", "{}"),
+        ("{\"a\":1}", "{\"a\":2,\"b\":[]}"),
+        ("[1,2,3,4,5]", "[0,1,3,5,6]"),
+        ("[1,2,3]", "[]"),
+        ("[]", "[{\"a\":[1]}]"),
+        (
+            "{\"a\":[{\"id\":1,\"v\":\"x\"},{\"id\":2}],\"b~/c\":true}",
+            "{\"a\":[{\"id\":0},{\"id\":1,\"v\":\"y\"},{\"id\":2}]}",
+        ),
+        ("[[1,2],[3]]", "[[1],[3,4],[5]]"),
+        ("{\"a\":1}", "[\"a\",1]"),
+        ("null", "{\"a\":null}"),
+    ];
+
+    for (a, b) in test_cases {
+        for strategy in STRATEGIES.iter() {
+            let patch = jsonxf::generate_patch(a, b, *strategy).unwrap();
+            let patched = jsonxf::apply_patch(a, &patch).unwrap();
+            assert!(
+                jsonxf::diff(&patched, b).unwrap().is_empty(),
+                "{:?} {} -> {}: {} gave {}",
+                strategy,
+                a,
+                b,
+                patch,
+                patched
+            );
+        }
+    }
+}
+
+#[test]
+fn generated_patches_for_equal_documents_are_empty() {
+    for strategy in STRATEGIES.iter() {
+        assert_eq!(
+            jsonxf::generate_patch("{\"a\": [1.0]}", "{\"a\":[1]}", *strategy).unwrap(),
+            "[]"
+        );
+    }
+}
+
+#[test]
+fn array_strategies_produce_different_patches() {
+    let a = "[1,2,3]";
+    let b = "[0,1,2,3]";
+    assert_eq!(
+        jsonxf::generate_patch(a, b, ArrayStrategy::Replace).unwrap(),
+        "[{\"op\":\"replace\",\"path\":\"\",\"value\":[0,1,2,3]}]"
+    );
+    assert_eq!(
+        jsonxf::generate_patch(a, b, ArrayStrategy::ByIndex).unwrap(),
+        "[{\"op\":\"replace\",\"path\":\"/0\",\"value\":0},\
+          {\"op\":\"replace\",\"path\":\"/1\",\"value\":1},\
+          {\"op\":\"replace\",\"path\":\"/2\",\"value\":2},\
+          {\"op\":\"add\",\"path\":\"/3\",\"value\":3}]"
+    );
+    assert_eq!(
+        jsonxf::generate_patch(a, b, ArrayStrategy::Lcs).unwrap(),
+        "[{\"op\":\"add\",\"path\":\"/0\",\"value\":0}]"
+    );
+}
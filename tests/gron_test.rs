@@ -0,0 +1,55 @@
+extern crate jsonxf;
+
+#[test]
+fn gron_passes_test_cases() {
+    let test_cases = vec![
+        ("null", "json = null;\n"),
+        ("[]", "json = [];\n"),
+        (
+            "{\"a b\": {\"c\": [1, \"two\"]}}",
+            "json = {};\njson[\"a b\"] = {};\njson[\"a b\"].c = [];\njson[\"a b\"].c[0] = 1;\njson[\"a b\"].c[1] = \"two\";\n",
+        ),
+        (
+            // escapes are preserved in values and keys
+            "{\"q\\\"\": \"\\u00e9\\n\"}",
+            "json = {};\njson[\"q\\\"\"] = \"\\u00e9\\n\";\n",
+        ),
+        (
+            // each record starts again at the root
+            "{\"a\":1}\n[true]",
+            "json = {};\njson.a = 1;\njson = [];\njson[0] = true;\n",
+        ),
+    ];
+
+    for (input, output) in test_cases {
+        assert_eq!(jsonxf::gron(input).unwrap(), output);
+    }
+}
+
+#[test]
+fn ungron_reverses_gron() {
+    let test_cases = vec![
+        "{\"users\":[{\"name\":\"ana\",\"tags\":[]},{\"name\":\"bo\"}],\"x\":{\"$y_1\":null}}",
+        "[[1,[2,[3]]],{\"a b\":\"c\\\\d\\\"\"}]",
+        "{\"a\":1}\n{\"a\":2}\n3",
+    ];
+
+    for input in test_cases {
+        let statements = jsonxf::gron(input).unwrap();
+        assert_eq!(jsonxf::ungron(&statements).unwrap(), input);
+    }
+}
+
+#[test]
+fn ungron_fills_in_missing_containers() {
+    assert_eq!(
+        jsonxf::ungron("json.a[2].b = \"x\";").unwrap(),
+        "{\"a\":[null,null,{\"b\":\"x\"}]}"
+    );
+}
+
+#[test]
+fn ungron_reports_bad_lines() {
+    let err = jsonxf::ungron("json = {};\n\njson.a 1;\n").unwrap_err();
+    assert!(err.starts_with("line 3:"), "{}", err);
+}
@@ -0,0 +1,111 @@
+extern crate jsonxf;
+use jsonxf::hash::Algorithm;
+
+#[test]
+fn canonical_hash_matches_sha256_of_canonical_form() {
+    // sha256 of `{"a":1,"b":"c"}`
+    assert_eq!(
+        jsonxf::canonical_hash("{ \"b\" : \"c\",\n \"a\" : 1 }", Algorithm::Sha256).unwrap(),
+        "1aac99d7067b5cda38322c82194676b29b993fdd2f1e93f4af3f2b2ec43212bb"
+    );
+}
+
+#[test]
+fn canonical_hash_ignores_formatting_and_key_order() {
+    let a = "{\"x\": {\"b\": [1, {\"d\": null, \"c\": \"\\u0041\"}], \"a\": true}}";
+    let b = "{\"x\":{\"a\":true,\"b\":[1,{\"c\":\"A\",\"d\":null}]}}";
+    for algorithm in [Algorithm::Sha256, Algorithm::Sha512] {
+        assert_eq!(
+            jsonxf::canonical_hash(a, algorithm).unwrap(),
+            jsonxf::canonical_hash(b, algorithm).unwrap()
+        );
+    }
+}
+
+#[test]
+fn canonical_hash_distinguishes_content() {
+    let hash = |s| jsonxf::canonical_hash(s, Algorithm::Sha256).unwrap();
+    assert_ne!(hash("[1, 2]"), hash("[2, 1]"));
+    assert_ne!(hash("{\"a\": 1}"), hash("{\"a\": \"1\"}"));
+    assert_ne!(hash("[1] [2]"), hash("[1, 2]"));
+}
+
+#[test]
+fn canonical_hash_canonicalizes_numbers() {
+    let hash = |s| jsonxf::canonical_hash(s, Algorithm::Sha256).unwrap();
+    assert_eq!(hash("1.0"), hash("1"));
+    assert_eq!(
+        hash("{\"a\": [1e3, -0.0, 0.10]}"),
+        hash("{\"a\":[1000,0,0.1]}")
+    );
+    assert!(jsonxf::canonical_hash("1e400", Algorithm::Sha256).is_err());
+}
+
+#[test]
+fn hash_records_passes_the_same_canonical_form_as_jcs() {
+    let input = "{\"b\": 1.0, \"a\": \"\\u00e9\"}";
+    let mut seen = vec![];
+    jsonxf::hash_records_stream(
+        &mut input.as_bytes(),
+        Algorithm::Sha256,
+        &mut |_, record| {
+            seen.push(String::from(record));
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(seen, vec![jsonxf::jcs::canonicalize(input).unwrap()]);
+    assert_eq!(seen[0], "{\"a\":\"\u{e9}\",\"b\":1}");
+}
+
+#[test]
+fn canonical_hash_sorts_keys_by_utf16() {
+    // U+FF61 sorts after U+1F600 by code point, but before it in UTF-16.
+    let value = jsonxf::value::parse("{\"\u{1F600}\": 1, \"\u{FF61}\": 2}").unwrap();
+    assert_eq!(
+        jsonxf::hash::canonical_string(&value).unwrap(),
+        "{\"\u{1F600}\":1,\"\u{FF61}\":2}"
+    );
+}
+
+#[test]
+fn algorithms_parse_from_names() {
+    assert_eq!("sha256".parse::<Algorithm>(), Ok(Algorithm::Sha256));
+    assert_eq!("SHA-512".parse::<Algorithm>(), Ok(Algorithm::Sha512));
+    assert!("md5".parse::<Algorithm>().is_err());
+}
+
+#[test]
+fn hash_records_hashes_each_record_alone() {
+    let digests =
+        jsonxf::hash_records("{ \"b\" : \"c\",\n \"a\" : 1 }\n[1]", Algorithm::Sha256).unwrap();
+    assert_eq!(
+        digests[0],
+        "1aac99d7067b5cda38322c82194676b29b993fdd2f1e93f4af3f2b2ec43212bb"
+    );
+    assert_eq!(
+        digests[1],
+        jsonxf::canonical_hash("[1]", Algorithm::Sha256).unwrap()
+    );
+}
+
+#[test]
+fn hash_records_passes_canonical_forms() {
+    let mut seen = vec![];
+    jsonxf::hash_records_stream(
+        &mut "{\"b\": 2, \"a\": 1} 7".as_bytes(),
+        Algorithm::Xxh3,
+        &mut |digest, record| {
+            seen.push((digest.len(), String::from(record)));
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        seen,
+        vec![
+            (16, String::from("{\"a\":1,\"b\":2}")),
+            (16, String::from("7"))
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+extern crate jsonxf;
+use jsonxf::html::HtmlWriter;
+use jsonxf::{to_html, Formatter};
+use std::io::Write;
+
+#[test]
+fn html_distinguishes_keys_from_string_values() {
+    let mut xf = Formatter::minimizer();
+    let html = to_html(
+        "{\"k\": [\"v\", {\"k2\": \"v2\"}], \"k3\": \"v3\"}",
+        &mut xf,
+    )
+    .unwrap();
+    let keys: Vec<&str> = html
+        .split("<span class=\"json-key\">")
+        .skip(1)
+        .map(|s| &s[..s.find('<').unwrap()])
+        .collect();
+    assert_eq!(keys, vec!["\"k\"", "\"k2\"", "\"k3\""]);
+    assert_eq!(html.matches("json-string").count(), 3);
+}
+
+#[test]
+fn html_escapes_entities_in_tokens() {
+    let mut xf = Formatter::minimizer();
+    assert_eq!(
+        to_html("\"<a href=\\\"x\\\">&amp;</a>\"", &mut xf).unwrap(),
+        "<pre class=\"json\"><span class=\"json-string\">\
+         \"&lt;a href=\\\"x\\\"&gt;&amp;amp;&lt;/a&gt;\"</span></pre>\n"
+    );
+}
+
+#[test]
+fn html_handles_tokens_split_across_writes() {
+    let formatted = "{\"key\\\"\": [12345, true, \"s\"]}";
+    let mut whole: Vec<u8> = vec![];
+    let mut writer = HtmlWriter::new(&mut whole);
+    writer.write_all(formatted.as_bytes()).unwrap();
+    writer.finish().unwrap();
+
+    let mut pieces: Vec<u8> = vec![];
+    let mut writer = HtmlWriter::new(&mut pieces);
+    for b in formatted.as_bytes() {
+        writer.write_all(&[*b]).unwrap();
+    }
+    writer.finish().unwrap();
+
+    assert_eq!(
+        String::from_utf8(pieces).unwrap(),
+        String::from_utf8(whole).unwrap()
+    );
+}
+
+#[test]
+fn html_keeps_formatter_whitespace() {
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(
+        to_html("[null]", &mut xf).unwrap(),
+        "<pre class=\"json\"><span class=\"json-punct\">[</span>\n  \
+         <span class=\"json-null\">null</span>\n<span class=\"json-punct\">]</span></pre>\n"
+    );
+}
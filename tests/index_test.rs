@@ -0,0 +1,119 @@
+extern crate jsonxf;
+use jsonxf::index::IndexOf;
+
+fn format_indexed(xf: &mut jsonxf::Formatter, input: &str, of: IndexOf) -> (String, String) {
+    let mut output: Vec<u8> = vec![];
+    let mut index: Vec<u8> = vec![];
+    jsonxf::index::format_indexed(xf, &mut input.as_bytes(), &mut output, &mut index, of).unwrap();
+    (
+        String::from_utf8(output).unwrap(),
+        String::from_utf8(index).unwrap(),
+    )
+}
+
+#[test]
+fn index_records_finds_each_record() {
+    let input = "{\"a\": \"}\"}\n[[], {}] 42\n\"str\" null";
+    let spans = jsonxf::index_records(input).unwrap();
+    let records: Vec<&str> = spans
+        .iter()
+        .map(|s| &input[s.start as usize..s.end as usize])
+        .collect();
+    assert_eq!(
+        records,
+        vec!["{\"a\": \"}\"}", "[[], {}]", "42", "\"str\"", "null"]
+    );
+}
+
+#[test]
+fn index_records_of_empty_input_is_empty() {
+    assert_eq!(jsonxf::index_records(" \n ").unwrap(), vec![]);
+}
+
+#[test]
+fn output_index_points_at_formatted_records() {
+    let mut xf = jsonxf::Formatter::pretty_printer();
+    xf.eager_record_separators = true;
+    let (output, index) = format_indexed(&mut xf, "{\"a\":[1]}{\"b\":2}", IndexOf::Output);
+    for line in index.lines() {
+        let fields: Vec<usize> = line.split('\t').map(|f| f.parse().unwrap()).collect();
+        let record = &output[fields[0]..fields[0] + fields[1]];
+        assert!(record.starts_with('{') && record.ends_with('}'));
+    }
+    assert_eq!(index, "0\t22\n23\t12\n");
+}
+
+#[test]
+fn input_index_ignores_formatting() {
+    let input = "  [1,\n 2]\n\n{ }";
+    let (_, index) = format_indexed(&mut jsonxf::Formatter::minimizer(), input, IndexOf::Input);
+    assert_eq!(index, "2\t7\n11\t3\n");
+}
+
+#[test]
+fn index_of_parses() {
+    assert_eq!("input".parse::<IndexOf>(), Ok(IndexOf::Input));
+    assert_eq!("both".parse::<IndexOf>(), Ok(IndexOf::Both));
+    assert!("neither".parse::<IndexOf>().is_err());
+}
+
+#[test]
+fn index_paths_lists_values_down_to_depth() {
+    let input = "{\"a\": {\"b\": [1]}, \"c\": \"x\"}\n[true]";
+    let entries = jsonxf::index_paths(input, 1).unwrap();
+    let found: Vec<(u64, &str, &str)> = entries
+        .iter()
+        .map(|e| {
+            let raw = &input[e.span.start as usize..e.span.end as usize];
+            (e.record, e.pointer.as_str(), raw)
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (0, "/a", "{\"b\": [1]}"),
+            (0, "/c", "\"x\""),
+            (0, "", "{\"a\": {\"b\": [1]}, \"c\": \"x\"}"),
+            (1, "/0", "true"),
+            (1, "", "[true]"),
+        ]
+    );
+}
+
+#[test]
+fn path_entries_round_trip_through_index_lines() {
+    for entry in jsonxf::index_paths("{\"a\\tb\": {\"c/d\": 1}}", 5).unwrap() {
+        assert_eq!(entry.to_string().parse(), Ok(entry));
+    }
+    assert!("0\t1\t".parse::<jsonxf::index::PathEntry>().is_err());
+}
+
+#[test]
+fn extract_indexed_reads_values_within_indexed_containers() {
+    let data = "{\"results\": [{\"id\": 1}, {\"id\": 2}]}\n{\"results\": [{\"id\": 3}]}";
+    let mut index: Vec<u8> = vec![];
+    jsonxf::index::index_paths_stream(&mut data.as_bytes(), 1, &mut |entry| {
+        use std::io::Write;
+        writeln!(index, "{}", entry)
+    })
+    .unwrap();
+
+    let extract = |record, pointer| {
+        jsonxf::index::extract_indexed(
+            &mut std::io::Cursor::new(data),
+            &mut index.as_slice(),
+            record,
+            pointer,
+        )
+        .unwrap()
+        .map(|v| v.to_string())
+    };
+    assert_eq!(extract(0, "/results/1/id"), Some(String::from("2")));
+    assert_eq!(extract(1, "/results/0"), Some(String::from("{\"id\":3}")));
+    assert_eq!(
+        extract(1, ""),
+        Some(String::from("{\"results\":[{\"id\":3}]}"))
+    );
+    assert_eq!(extract(1, "/results/1"), None);
+    assert_eq!(extract(2, ""), None);
+}
@@ -0,0 +1,113 @@
+extern crate jsonxf;
+use jsonxf::jcs::{canonical_number, canonicalize};
+use jsonxf::Formatter;
+
+#[test]
+fn canonical_numbers_match_rfc_8785() {
+    // From RFC 8785, Appendix B, and ECMAScript's boundaries
+    for (input, expected) in &[
+        ("0", "0"),
+        ("-0", "0"),
+        ("5e-324", "5e-324"),
+        ("-5e-324", "-5e-324"),
+        ("1.7976931348623157e308", "1.7976931348623157e+308"),
+        ("9007199254740992", "9007199254740992"),
+        ("-9007199254740992", "-9007199254740992"),
+        ("295147905179352830000", "295147905179352830000"),
+        ("9.999999999999997e22", "9.999999999999997e+22"),
+        ("1e23", "1e+23"),
+        ("1e21", "1e+21"),
+        ("1e20", "100000000000000000000"),
+        ("333333333.33333329", "333333333.3333333"),
+        ("1E30", "1e+30"),
+        ("4.50", "4.5"),
+        ("2e-3", "0.002"),
+        ("0.000001", "0.000001"),
+        ("0.0000001", "1e-7"),
+        ("-1.5e-7", "-1.5e-7"),
+        ("123456789012345680000", "123456789012345680000"),
+        ("1.25", "1.25"),
+    ] {
+        assert_eq!(canonical_number(input).unwrap(), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn canonical_numbers_reject_what_json_does() {
+    assert_eq!(
+        canonical_number("1e309").unwrap_err(),
+        "number `1e309` is out of range for canonical JSON"
+    );
+    for input in &["NaN", "inf", "01", "1.", "+1", ""] {
+        assert!(canonical_number(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn canonicalize_sorts_by_utf16_and_escapes_minimally() {
+    // From RFC 8785, section 3.2.3: U+1F600 sorts before U+FB33 in UTF-16
+    assert_eq!(
+        canonicalize("{\"\\ufb33\": 1, \"\\ud83d\\ude00\": 2, \"\\u0080\": 3, \"1\": 4}").unwrap(),
+        "{\"1\":4,\"\u{80}\":3,\"\u{1f600}\":2,\"\u{fb33}\":1}"
+    );
+    assert_eq!(
+        canonicalize("[\"\\u20ac\\u0001\\/\\t\\\"\", 56, {\"x\": null}]").unwrap(),
+        "[\"€\\u0001/\\t\\\"\",56,{\"x\":null}]"
+    );
+}
+
+#[test]
+fn canonicalize_rejects_duplicate_member_names() {
+    assert_eq!(
+        canonicalize("{\"a\": 1, \"b\": 2, \"a\": 3}").unwrap_err(),
+        "duplicate member name \"a\""
+    );
+    // However deeply nested, and however the name is escaped
+    assert!(canonicalize("[{\"x\": {\"\\u0061\": 1, \"a\": 1}}]").is_err());
+    // Equal names in different objects are fine
+    assert_eq!(
+        canonicalize("{\"a\": {\"a\": 1}}").unwrap(),
+        "{\"a\":{\"a\":1}}"
+    );
+
+    let mut xf = Formatter::canonicalizer();
+    assert_eq!(
+        xf.format("[1] {\"a\": 1, \"a\": 1}").unwrap_err(),
+        "duplicate member name \"a\""
+    );
+}
+
+#[test]
+fn canonicalizer_streams_records() {
+    let input = b"{\"b\": 2.0, \"a\": [1e1, \"\\u0041\"]} 1.50 \"\\u00e9\" true\n[ 1E2 ]";
+    for split in 0..input.len() {
+        let mut output: Vec<u8> = vec![];
+        let mut xf = Formatter::canonicalizer();
+        xf.format_buf(&input[..split], &mut output).unwrap();
+        xf.format_buf(&input[split..], &mut output).unwrap();
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"a\":[10,\"A\"],\"b\":2}\n1.5\n\"é\"\ntrue\n[100]"
+        );
+    }
+}
+
+#[test]
+fn canonicalizer_rejects_invalid_records() {
+    let mut xf = Formatter::canonicalizer();
+    assert_eq!(
+        xf.format("[1] {\"a\": tru}").unwrap_err(),
+        "cannot canonicalize record 2: not valid JSON"
+    );
+    let mut xf = Formatter::canonicalizer();
+    assert_eq!(
+        xf.format("[1e999]").unwrap_err(),
+        "number `1e999` is out of range for canonical JSON"
+    );
+    let mut xf = Formatter::canonicalizer();
+    assert_eq!(
+        xf.format("{\"a\": [1]").unwrap_err(),
+        "cannot canonicalize a record cut off by the end of input"
+    );
+}
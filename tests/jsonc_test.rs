@@ -0,0 +1,147 @@
+extern crate jsonxf;
+
+use jsonxf::jsonc::{
+    strip_comments, strip_trailing_commas, CommentStripper, TrailingCommaStripper,
+};
+use jsonxf::Formatter;
+
+const SETTINGS: &str = "// Settings\n{\n  /* the font, and its\n     size */\n  \"editor.font\": \"Fira // Code\", // no comment\n  \"glob\": \"**/*.json\",\n  \"ratio\": 1/**/,\n  \"esc\": \"\\\"/*\"\n}\n";
+
+#[test]
+fn strip_comments_keeps_strings() {
+    assert_eq!(
+        strip_comments(SETTINGS),
+        "\n{\n   \n  \"editor.font\": \"Fira // Code\", \n  \"glob\": \"**/*.json\",\n  \"ratio\": 1 ,\n  \"esc\": \"\\\"/*\"\n}\n"
+    );
+    // A lone slash is not a comment
+    assert_eq!(strip_comments("[1 / 2]/"), "[1 / 2]/");
+    // A comment separates what's on either side of it
+    assert_eq!(strip_comments("1/* */2"), "1 2");
+    // An unterminated comment is dropped
+    assert_eq!(strip_comments("[1] /* [2]"), "[1] ");
+}
+
+#[test]
+fn comment_stripper_handles_chunks() {
+    let expected = strip_comments(SETTINGS);
+    let input = SETTINGS.as_bytes();
+    for size in 1..input.len() {
+        let mut stripper = CommentStripper::new();
+        let mut stripped: Vec<u8> = vec![];
+        for chunk in input.chunks(size) {
+            stripper.strip(chunk, &mut stripped);
+        }
+        stripper.finish(&mut stripped);
+        assert_eq!(String::from_utf8(stripped).unwrap(), expected);
+    }
+}
+
+#[test]
+fn formatter_strips_comments() {
+    let mut xf = Formatter::pretty_printer();
+    xf.strip_comments = true;
+    xf.validate = true;
+    assert_eq!(
+        xf.format("{\"a\": 1, // the last\n}").unwrap_err(),
+        "expected a key, found `}` at line 2, column 1"
+    );
+
+    let input = SETTINGS.as_bytes();
+    for size in 1..input.len() {
+        let mut xf = Formatter::pretty_printer();
+        xf.strip_comments = true;
+        xf.validate = true;
+        let mut output: Vec<u8> = vec![];
+        for chunk in input.chunks(size) {
+            xf.format_buf(chunk, &mut output).unwrap();
+        }
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\n  \"editor.font\": \"Fira // Code\",\n  \"glob\": \"**/*.json\",\n  \"ratio\": 1,\n  \"esc\": \"\\\"/*\"\n}"
+        );
+    }
+}
+
+#[test]
+fn require_complete_rejects_an_unterminated_comment() {
+    let mut xf = Formatter::minimizer();
+    xf.strip_comments = true;
+    xf.require_complete = true;
+    assert_eq!(
+        xf.format("[1] /* [2]").unwrap_err(),
+        "input ended inside a comment"
+    );
+}
+
+#[test]
+fn trailing_comma_stripper_handles_chunks() {
+    let input = "{\"a\": [1, 2 ,\n ], \"b\": [\",]\", \"\\\",}\",\t],\r\n \"c\": {},}\n[,] [1,,]";
+    let expected = "{\"a\": [1, 2 \n ], \"b\": [\",]\", \"\\\",}\"\t],\r\n \"c\": {}}\n[] [1,]";
+    assert_eq!(strip_trailing_commas(input), expected);
+    let input = input.as_bytes();
+    for size in 1..input.len() {
+        let mut stripper = TrailingCommaStripper::new();
+        let mut stripped: Vec<u8> = vec![];
+        for chunk in input.chunks(size) {
+            stripper.strip(chunk, &mut stripped);
+        }
+        stripper.finish(&mut stripped);
+        assert_eq!(String::from_utf8(stripped).unwrap(), expected);
+    }
+    // A comma at the end of input is kept
+    assert_eq!(strip_trailing_commas("[1] ,  "), "[1] ,  ");
+}
+
+#[test]
+fn formatter_strips_comments_and_trailing_commas() {
+    let input = b"{\n  \"a\": [1, 2, /* 3, */],\n  // \"b\": 4,\n}\n";
+    for size in 1..input.len() {
+        let mut xf = Formatter::minimizer();
+        xf.strip_comments = true;
+        xf.strip_trailing_commas = true;
+        xf.validate = true;
+        let mut output: Vec<u8> = vec![];
+        for chunk in input.chunks(size) {
+            xf.format_buf(chunk, &mut output).unwrap();
+        }
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(output, b"{\"a\":[1,2]}");
+    }
+}
+
+const COMMENTED: &str = "// Editor settings\n{\n  \"editor.tabSize\": 4, // spaces\n  /* Rulers,\n     in columns */\n  \"rulers\": [80, /* wide */ 120],\n  \"empty\": [ // nothing yet\r\n  ],\n  \"key\" /* before colon */ : // after colon\n    true,\n  \"last\": null\n  // the end\n}\n// trailer";
+
+#[test]
+fn formatter_keeps_comments() {
+    let expected = "// Editor settings\n{\n  \"editor.tabSize\": 4,\n  // spaces\n  /* Rulers,\n     in columns */\n  \"rulers\": [\n    80,\n    /* wide */\n    120\n  ],\n  \"empty\": [\n    // nothing yet\n  ],\n  \"key\": /* before colon */ // after colon\n    true,\n  \"last\": null\n  // the end\n}\n// trailer\n";
+    let input = COMMENTED.as_bytes();
+    for size in 1..input.len() {
+        let mut xf = Formatter::pretty_printer();
+        xf.keep_comments = true;
+        xf.max_inline_array_width = Some(40);
+        let mut output: Vec<u8> = vec![];
+        for chunk in input.chunks(size) {
+            xf.format_buf(chunk, &mut output).unwrap();
+        }
+        xf.finish_buf(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
+
+#[test]
+fn formatter_keeps_comments_between_records() {
+    let mut xf = Formatter::pretty_printer();
+    xf.keep_comments = true;
+    assert_eq!(
+        xf.format("1 // one\n[] /* none */ 2").unwrap(),
+        "1\n// one\n[]\n/* none */\n2"
+    );
+    // Comments are dropped when minimizing
+    let mut xf = Formatter::minimizer();
+    xf.keep_comments = true;
+    assert_eq!(
+        xf.format("{\"a\": 1, // one\n\"b\": [/* two */]}").unwrap(),
+        "{\"a\":1,\"b\":[]}"
+    );
+}
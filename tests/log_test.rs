@@ -0,0 +1,81 @@
+#![cfg(feature = "log")]
+
+extern crate jsonxf;
+extern crate log;
+
+use log::kv::Key;
+use log::{Level, Log, Metadata, Record};
+use std::sync::{Mutex, Once};
+
+// Keeps each record's level, message, and `offset` field.
+struct Recorder {
+    records: Mutex<Vec<(Level, String, Option<u64>)>>,
+}
+
+impl Log for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let offset = record
+            .key_values()
+            .get(Key::from("offset"))
+            .and_then(|v| v.to_u64());
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string(), offset));
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder {
+    records: Mutex::new(vec![]),
+};
+static INIT: Once = Once::new();
+
+// Returns the records logged so far which mention `needle`.
+fn logged(needle: &str) -> Vec<(Level, String, Option<u64>)> {
+    INIT.call_once(|| {
+        log::set_logger(&RECORDER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+    let records = RECORDER.records.lock().unwrap();
+    records
+        .iter()
+        .filter(|(_, message, _)| message.contains(needle))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn log_warns_of_duplicate_keys() {
+    logged("");
+    let input = "{\"dup\": 1, \"ok\": {\"dup\": 2}}\n{\"dup\": 3, \"dup\": 4}";
+    jsonxf::value::parse_records(input).unwrap();
+    assert_eq!(
+        logged("\"dup\""),
+        vec![(
+            Level::Warn,
+            String::from("duplicate object key \"dup\" at line 2, column 12"),
+            Some(40)
+        )]
+    );
+}
+
+#[test]
+fn log_reports_json_copied_as_text() {
+    logged("");
+    let mut xf = jsonxf::Formatter::minimizer();
+    jsonxf::mixed::format_mixed("ok {\"a\": 1}; not [1, 2,]", &mut xf).unwrap();
+    assert_eq!(
+        logged("looks like JSON"),
+        vec![(
+            Level::Debug,
+            String::from("text at byte 17 looks like JSON but isn't valid; copied as text"),
+            Some(17)
+        )]
+    );
+}
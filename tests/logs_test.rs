@@ -0,0 +1,47 @@
+extern crate jsonxf;
+use jsonxf::logs::Unwrapper;
+
+#[test]
+fn logs_rejoin_docker_lines_split_by_length() {
+    let mut unwrapper = Unwrapper::new();
+    assert_eq!(
+        unwrapper.unwrap("{\"log\":\"{\\\"msg\\\":\",\"stream\":\"stdout\"}\n"),
+        None
+    );
+    assert_eq!(
+        unwrapper.unwrap("{\"log\":\"\\\"hi\\\"}\\r\\n\",\"stream\":\"stdout\"}\n"),
+        Some(String::from("{\"msg\":\"hi\"}"))
+    );
+    assert_eq!(unwrapper.finish(), None);
+}
+
+#[test]
+fn logs_pass_through_lines_in_neither_format() {
+    let mut unwrapper = Unwrapper::new();
+    for line in &[
+        "{\"level\":\"info\"}",
+        "2024-05-01 stdout F message",
+        "2024-05-01T12:00:00Z stdin F message",
+        "",
+    ] {
+        assert_eq!(unwrapper.unwrap(line), Some(String::from(*line)));
+    }
+    assert_eq!(
+        unwrapper.unwrap("2024-05-01T12:00:00.000000001Z stdout F"),
+        Some(String::new())
+    );
+}
+
+#[test]
+fn logs_return_an_unfinished_cri_line_at_the_end() {
+    let mut unwrapper = Unwrapper::new();
+    assert_eq!(
+        unwrapper.unwrap("2024-05-01T12:00:00Z stdout P part 1, "),
+        None
+    );
+    assert_eq!(
+        unwrapper.unwrap("2024-05-01T12:00:00Z stdout P part 2"),
+        None
+    );
+    assert_eq!(unwrapper.finish(), Some(String::from("part 1, part 2")));
+}
@@ -0,0 +1,33 @@
+extern crate jsonxf;
+use jsonxf::{to_markdown, Formatter};
+
+#[test]
+fn markdown_fences_the_formatted_stream() {
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(
+        to_markdown("{\"a\":1}", &mut xf, None).unwrap(),
+        "```json\n{\n  \"a\": 1\n}\n```\n"
+    );
+}
+
+#[test]
+fn markdown_fence_outgrows_backticks_in_strings() {
+    let mut xf = Formatter::minimizer();
+    assert_eq!(
+        to_markdown("[\"````\"]", &mut xf, None).unwrap(),
+        "`````json\n[\"````\"]\n`````\n"
+    );
+}
+
+#[test]
+fn markdown_details_collapse_only_deep_members() {
+    let mut xf = Formatter::minimizer();
+    let json = "{\"a<b\": {\"c\": {}}, \"d\": [1]}\n[[[1]]]";
+    assert_eq!(
+        to_markdown(json, &mut xf, Some(1)).unwrap(),
+        "```json\n{\"d\":[1]}\n```\n\
+         <details><summary><code>a&lt;b</code></summary>\n\n\
+         ```json\n{\"c\":{}}\n```\n\n</details>\n\
+         ```json\n[[[1]]]\n```\n"
+    );
+}
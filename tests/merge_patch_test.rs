@@ -0,0 +1,38 @@
+extern crate jsonxf;
+
+#[test]
+fn apply_merge_patch_passes_rfc_7386_examples() {
+    let test_cases = vec![
+        ("{\"a\":\"b\"}", "{\"a\":\"c\"}", "{\"a\":\"c\"}"),
+        (
+            "{\"a\":\"b\"}",
+            "{\"b\":\"c\"}",
+            "{\"a\":\"b\",\"b\":\"c\"}",
+        ),
+        ("{\"a\":\"b\"}", "{\"a\":null}", "{}"),
+        ("{\"a\":\"b\",\"b\":\"c\"}", "{\"a\":null}", "{\"b\":\"c\"}"),
+        ("{\"a\":[\"b\"]}", "{\"a\":\"c\"}", "{\"a\":\"c\"}"),
+        ("{\"a\":\"c\"}", "{\"a\":[\"b\"]}", "{\"a\":[\"b\"]}"),
+        (
+            "{\"a\":{\"b\":\"c\"}}",
+            "{\"a\":{\"b\":\"d\",\"c\":null}}",
+            "{\"a\":{\"b\":\"d\"}}",
+        ),
+        ("{\"a\":[{\"b\":\"c\"}]}", "{\"a\":[1]}", "{\"a\":[1]}"),
+        ("[\"a\",\"b\"]", "[\"c\",\"d\"]", "[\"c\",\"d\"]"),
+        ("{\"a\":\"b\"}", "[\"c\"]", "[\"c\"]"),
+        ("{\"a\":\"foo\"}", "null", "null"),
+        ("{\"a\":\"foo\"}", "\"bar\"", "\"bar\""),
+        ("{\"e\":null}", "{\"a\":1}", "{\"e\":null,\"a\":1}"),
+        ("[1,2]", "{\"a\":\"b\",\"c\":null}", "{\"a\":\"b\"}"),
+        (
+            "{}",
+            "{\"a\":{\"bb\":{\"ccc\":null}}}",
+            "{\"a\":{\"bb\":{}}}",
+        ),
+    ];
+
+    for (doc, patch, output) in test_cases {
+        assert_eq!(jsonxf::apply_merge_patch(doc, patch).unwrap(), output);
+    }
+}
@@ -0,0 +1,70 @@
+extern crate jsonxf;
+use jsonxf::merge::Strategy;
+
+#[test]
+fn merge_layers_objects_recursively() {
+    let base = "{\"a\": {\"b\": 1, \"c\": {\"d\": 2}}, \"e\": 3}";
+    let env = "{\"a\": {\"c\": {\"d\": 20, \"f\": 4}}}";
+    let secrets = "{\"a\": {\"b\": \"s3cret\"}, \"g\": null}";
+    assert_eq!(
+        jsonxf::merge(&[base, env, secrets], Strategy::LastWins).unwrap(),
+        "{\"a\":{\"b\":\"s3cret\",\"c\":{\"d\":20,\"f\":4}},\"e\":3,\"g\":null}"
+    );
+}
+
+#[test]
+fn merge_replaces_non_objects() {
+    assert_eq!(
+        jsonxf::merge(&["{\"a\": [1]}", "{\"a\": {\"b\": 2}}"], Strategy::LastWins).unwrap(),
+        "{\"a\":{\"b\":2}}"
+    );
+    assert_eq!(
+        jsonxf::merge(&["{\"a\": 1}", "[2]"], Strategy::ConcatArrays).unwrap(),
+        "[2]"
+    );
+}
+
+#[test]
+fn merge_concatenates_nested_arrays() {
+    assert_eq!(
+        jsonxf::merge(
+            &["{\"a\": {\"b\": [1]}}", "{\"a\": {\"b\": [2, 3]}}", "[]"],
+            Strategy::ConcatArrays
+        )
+        .unwrap(),
+        "[]"
+    );
+    assert_eq!(
+        jsonxf::merge(&["[1]", "[2]", "[3]"], Strategy::ConcatArrays).unwrap(),
+        "[1,2,3]"
+    );
+}
+
+#[test]
+fn merge_with_error_strategy() {
+    // equal values are not a conflict
+    assert_eq!(
+        jsonxf::merge(&["{\"a\": 1.0}", "{\"a\": 1, \"b\": 2}"], Strategy::Error).unwrap(),
+        "{\"a\":1,\"b\":2}"
+    );
+    assert_eq!(
+        jsonxf::merge(&["{\"a\": [1]}", "{\"a\": [2]}"], Strategy::Error).unwrap_err(),
+        "conflicting values at /a"
+    );
+}
+
+#[test]
+fn merge_requires_input() {
+    assert!(jsonxf::merge(&[], Strategy::LastWins).is_err());
+    assert!(jsonxf::merge(&["{}", "{"], Strategy::LastWins).is_err());
+}
+
+#[test]
+fn merge_strategies_parse_by_name() {
+    assert_eq!("last-wins".parse::<Strategy>(), Ok(Strategy::LastWins));
+    assert_eq!(
+        "concat-arrays".parse::<Strategy>(),
+        Ok(Strategy::ConcatArrays)
+    );
+    assert!("first-wins".parse::<Strategy>().is_err());
+}
@@ -0,0 +1,42 @@
+extern crate jsonxf;
+use jsonxf::metrics::{format_stream_with_metrics, Metrics};
+use jsonxf::Formatter;
+use std::time::Duration;
+
+#[test]
+fn metrics_report_periodically_and_at_the_end() {
+    let input = "{\"n\": 1}\n".repeat(10_000);
+    let mut reports: Vec<Metrics> = vec![];
+    let mut output: Vec<u8> = vec![];
+    let totals = format_stream_with_metrics(
+        &mut Formatter::pretty_printer(),
+        &mut input.as_bytes(),
+        &mut output,
+        Duration::from_secs(0),
+        &mut |metrics| reports.push(*metrics),
+    )
+    .unwrap();
+    assert_eq!(totals.bytes_in, input.len() as u64);
+    assert_eq!(totals.bytes_out, output.len() as u64);
+    assert_eq!(totals.records, 10_000);
+    assert!(reports.len() > 2);
+    assert_eq!(reports.last(), Some(&totals));
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[0].bytes_in <= pair[1].bytes_in && pair[0].records <= pair[1].records));
+}
+
+#[test]
+fn metrics_count_only_this_stream() {
+    let mut xf = Formatter::minimizer();
+    xf.format("[1] [2]").unwrap();
+    let totals = format_stream_with_metrics(
+        &mut xf,
+        &mut "3 \"4\" {}".as_bytes(),
+        &mut vec![],
+        Duration::from_secs(60),
+        &mut |_| {},
+    )
+    .unwrap();
+    assert_eq!(totals.records, 3);
+}
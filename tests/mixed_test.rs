@@ -0,0 +1,65 @@
+extern crate jsonxf;
+use jsonxf::mixed::{format_mixed, format_mixed_stream, MAX_VALUE_LEN};
+use jsonxf::Formatter;
+use std::io::Read;
+
+// Reads one byte at a time, to split values across reads.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((&b, rest)) if !buf.is_empty() => {
+                buf[0] = b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn mixed_formats_only_valid_json() {
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(
+        format_mixed("[INFO] got {\"a\": [1, 2]}\n", &mut xf).unwrap(),
+        "[INFO] got {\n  \"a\": [\n    1,\n    2\n  ]\n}\n"
+    );
+    let mut xf = Formatter::minimizer();
+    for text in &[
+        "[see above] {not json} {\"a\": } [1 2] [\"tab\there\"]",
+        "{\"a\": 1]} ]]] {{{",
+        "[tru] [01] [1,] {\"unterminated\": \"x\n",
+    ] {
+        assert_eq!(format_mixed(text, &mut xf).unwrap(), *text);
+    }
+}
+
+#[test]
+fn mixed_finds_values_inside_rejected_brackets() {
+    let mut xf = Formatter::minimizer();
+    assert_eq!(
+        format_mixed("[ok: { \"a\" : 1 }] {x [ 2 ]", &mut xf).unwrap(),
+        "[ok: {\"a\":1}] {x [2]"
+    );
+}
+
+#[test]
+fn mixed_reads_values_split_across_reads() {
+    let text = "> {\"b\": \"}\", \"c\": [true, null]} and [3]\n";
+    let mut output: Vec<u8> = vec![];
+    let mut xf = Formatter::minimizer();
+    format_mixed_stream(&mut Trickle(text.as_bytes()), &mut output, &mut xf).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "> {\"b\":\"}\",\"c\":[true,null]} and [3]\n"
+    );
+}
+
+#[test]
+fn mixed_copies_values_longer_than_the_limit() {
+    let text = format!("[\"{}\"]", "x".repeat(MAX_VALUE_LEN));
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(format_mixed(&text, &mut xf).unwrap(), text);
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "msgpack")]
+
+extern crate jsonxf;
+use jsonxf::msgpack::{from_msgpack, to_msgpack};
+
+#[test]
+fn msgpack_round_trips_records() {
+    let json =
+        "{\"s\":\"caf\\u00e9 \\\"x\\\"\",\"n\":[0,-1,127,-33,300,-70000,18446744073709551615],\
+                \"f\":0.5,\"t\":true,\"z\":null,\"o\":{}}\n[]\n\"x\"\n";
+    let packed = to_msgpack(json).unwrap();
+    assert_eq!(
+        from_msgpack(&packed).unwrap(),
+        "{\"s\":\"café \\\"x\\\"\",\"n\":[0,-1,127,-33,300,-70000,18446744073709551615],\
+         \"f\":0.5,\"t\":true,\"z\":null,\"o\":{}}\n[]\n\"x\"\n"
+    );
+}
+
+#[test]
+fn msgpack_uses_compact_integer_encodings() {
+    assert_eq!(
+        to_msgpack("[1, -1, 200, -200]").unwrap(),
+        vec![0x94, 0x01, 0xff, 0xcc, 0xc8, 0xd1, 0xff, 0x38]
+    );
+}
+
+#[test]
+fn msgpack_non_integers_become_floats() {
+    let packed = to_msgpack("[1.0, 1e400]").unwrap_err();
+    assert_eq!(packed, "invalid number `1e400`");
+    assert_eq!(
+        from_msgpack(&to_msgpack("[1.0, 2e-7]").unwrap()).unwrap(),
+        "[1.0,2e-7]\n"
+    );
+}
+
+#[test]
+fn msgpack_binary_becomes_base64() {
+    let packed = [
+        0x81, 0xa3, b'b', b'i', b'n', 0xc4, 0x04, b'j', b's', b'o', b'n',
+    ];
+    assert_eq!(from_msgpack(&packed).unwrap(), "{\"bin\":\"anNvbg==\"}\n");
+}
+
+#[test]
+fn msgpack_scalar_keys_are_stringified() {
+    let packed = [0x82, 0x01, 0xc3, 0xc0, 0xca, 0x3f, 0xc0, 0x00, 0x00];
+    assert_eq!(
+        from_msgpack(&packed).unwrap(),
+        "{\"1\":true,\"null\":1.5}\n"
+    );
+}
+
+#[test]
+fn msgpack_extensions_and_truncation_are_errors() {
+    assert_eq!(
+        from_msgpack(&[0xd4, 0x01, 0x00]).unwrap_err(),
+        "MessagePack extension types cannot be represented in JSON"
+    );
+    assert!(from_msgpack(&[0x92, 0x01]).is_err());
+    assert!(from_msgpack(&[0xa5, b'a', b'b']).is_err());
+}
@@ -0,0 +1,116 @@
+extern crate jsonxf;
+
+#[test]
+fn apply_patch_passes_rfc_6902_examples() {
+    let test_cases = vec![
+        // A.1. Adding an Object Member
+        (
+            "{\"foo\":\"bar\"}",
+            "[{\"op\":\"add\",\"path\":\"/baz\",\"value\":\"qux\"}]",
+            "{\"foo\":\"bar\",\"baz\":\"qux\"}",
+        ),
+        // A.2. Adding an Array Element
+        (
+            "{\"foo\":[\"bar\",\"baz\"]}",
+            "[{\"op\":\"add\",\"path\":\"/foo/1\",\"value\":\"qux\"}]",
+            "{\"foo\":[\"bar\",\"qux\",\"baz\"]}",
+        ),
+        // A.3. Removing an Object Member
+        (
+            "{\"baz\":\"qux\",\"foo\":\"bar\"}",
+            "[{\"op\":\"remove\",\"path\":\"/baz\"}]",
+            "{\"foo\":\"bar\"}",
+        ),
+        // A.4. Removing an Array Element
+        (
+            "{\"foo\":[\"bar\",\"qux\",\"baz\"]}",
+            "[{\"op\":\"remove\",\"path\":\"/foo/1\"}]",
+            "{\"foo\":[\"bar\",\"baz\"]}",
+        ),
+        // A.5. Replacing a Value
+        (
+            "{\"baz\":\"qux\",\"foo\":\"bar\"}",
+            "[{\"op\":\"replace\",\"path\":\"/baz\",\"value\":\"boo\"}]",
+            "{\"baz\":\"boo\",\"foo\":\"bar\"}",
+        ),
+        // A.6. Moving a Value
+        (
+            "{\"foo\":{\"bar\":\"baz\",\"waldo\":\"fred\"},\"qux\":{\"corge\":\"grault\"}}",
+            "[{\"op\":\"move\",\"from\":\"/foo/waldo\",\"path\":\"/qux/thud\"}]",
+            "{\"foo\":{\"bar\":\"baz\"},\"qux\":{\"corge\":\"grault\",\"thud\":\"fred\"}}",
+        ),
+        // A.7. Moving an Array Element
+        (
+            "{\"foo\":[\"all\",\"grass\",\"cows\",\"eat\"]}",
+            "[{\"op\":\"move\",\"from\":\"/foo/1\",\"path\":\"/foo/3\"}]",
+            "{\"foo\":[\"all\",\"cows\",\"eat\",\"grass\"]}",
+        ),
+        // A.8. Testing a Value: Success
+        (
+            "{\"baz\":\"qux\",\"foo\":[\"a\",2,\"c\"]}",
+            "[{\"op\":\"test\",\"path\":\"/baz\",\"value\":\"qux\"},{\"op\":\"test\",\"path\":\"/foo/1\",\"value\":2}]",
+            "{\"baz\":\"qux\",\"foo\":[\"a\",2,\"c\"]}",
+        ),
+        // A.10. Adding a Nested Member Object
+        (
+            "{\"foo\":\"bar\"}",
+            "[{\"op\":\"add\",\"path\":\"/child\",\"value\":{\"grandchild\":{}}}]",
+            "{\"foo\":\"bar\",\"child\":{\"grandchild\":{}}}",
+        ),
+        // A.16. Adding an Array Value
+        (
+            "{\"foo\":[\"bar\"]}",
+            "[{\"op\":\"add\",\"path\":\"/foo/-\",\"value\":[\"abc\",\"def\"]}]",
+            "{\"foo\":[\"bar\",[\"abc\",\"def\"]]}",
+        ),
+        // copy, and replacing the root
+        (
+            "{\"a\":{\"b\":1}}",
+            "[{\"op\":\"copy\",\"from\":\"/a\",\"path\":\"/c\"},{\"op\":\"replace\",\"path\":\"\",\"value\":[]}]",
+            "[]",
+        ),
+    ];
+
+    for (doc, patch, output) in test_cases {
+        assert_eq!(jsonxf::apply_patch(doc, patch).unwrap(), output);
+    }
+}
+
+#[test]
+fn apply_patch_reports_failures() {
+    let test_cases =
+        vec![
+        // A.9. Testing a Value: Error
+        (
+            "{\"baz\":\"qux\"}",
+            "[{\"op\":\"test\",\"path\":\"/baz\",\"value\":\"bar\"}]",
+            "operation 0: test failed at /baz",
+        ),
+        // A.12. Adding to a Nonexistent Target
+        (
+            "{\"foo\":\"bar\"}",
+            "[{\"op\":\"add\",\"path\":\"/baz/bat\",\"value\":\"qux\"}]",
+            "operation 0: path not found: /baz",
+        ),
+        (
+            "[1]",
+            "[{\"op\":\"add\",\"path\":\"/-\",\"value\":2},{\"op\":\"remove\",\"path\":\"/5\"}]",
+            "operation 1: path not found: /5",
+        ),
+        (
+            "{\"a\":{}}",
+            "[{\"op\":\"move\",\"from\":\"/a\",\"path\":\"/a/b\"}]",
+            "operation 0: cannot move /a into itself",
+        ),
+        (
+            "{}",
+            "[{\"op\":\"frobnicate\",\"path\":\"\"}]",
+            "operation 0: unknown op `frobnicate`",
+        ),
+        ("{}", "{\"op\":\"remove\"}", "JSON Patch must be an array of operations"),
+    ];
+
+    for (doc, patch, error) in test_cases {
+        assert_eq!(jsonxf::apply_patch(doc, patch).unwrap_err(), error);
+    }
+}
@@ -0,0 +1,30 @@
+extern crate jsonxf;
+use jsonxf::paths::PathInfo;
+
+#[test]
+fn list_paths_generalizes_array_indices() {
+    let paths = jsonxf::list_paths("[{\"a\": 1}, {\"a\": 2, \"b\": [[true]]}]").unwrap();
+    let names: Vec<&str> = paths.iter().map(|p| p.path.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![".", ".[]", ".[].a", ".[].b", ".[].b[]", ".[].b[][]"]
+    );
+}
+
+#[test]
+fn list_paths_counts_values_and_types() {
+    let paths = jsonxf::list_paths("{\"a b\": 1}\n{\"a b\": \"x\"}\n{\"a b\": 2}").unwrap();
+    assert_eq!(
+        paths[1],
+        PathInfo {
+            path: String::from(".[\"a b\"]"),
+            count: 3,
+            types: vec!["number", "string"],
+        }
+    );
+}
+
+#[test]
+fn list_paths_of_empty_input() {
+    assert_eq!(jsonxf::list_paths("  \n").unwrap(), vec![]);
+}
@@ -0,0 +1,45 @@
+extern crate jsonxf;
+use jsonxf::{to_json_macro, to_rust_string, Formatter};
+
+#[test]
+fn json_macro_follows_formatter_settings() {
+    let mut xf = Formatter::pretty_printer();
+    xf.indent = String::from("    ");
+    assert_eq!(
+        to_json_macro("{\"ok\": true, \"ids\": [1]}", &mut xf).unwrap(),
+        "json!({\n    \"ok\": true,\n    \"ids\": [\n        1\n    ]\n})"
+    );
+    let mut xf = Formatter::minimizer();
+    assert_eq!(
+        to_json_macro("{\"a\": 1}\n[2]", &mut xf).unwrap(),
+        "json!({\"a\":1})\njson!([2])"
+    );
+}
+
+#[test]
+fn json_macro_uses_rust_escapes() {
+    let mut xf = Formatter::minimizer();
+    assert_eq!(
+        to_json_macro(
+            "{\"k\\\"ey\": \"\\u00e9\\/\\b\\u0000\\\\\", \"n\": -1.5e3}",
+            &mut xf
+        )
+        .unwrap(),
+        "json!({\"k\\\"ey\":\"é/\\u{8}\\u{0}\\\\\",\"n\":-1.5e3})"
+    );
+}
+
+#[test]
+fn rust_string_keeps_layout_and_escapes_quotes() {
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(
+        to_rust_string("{\"a\": \"x\\ty\"}", &mut xf).unwrap(),
+        "\"{\n  \\\"a\\\": \\\"x\\\\ty\\\"\n}\""
+    );
+}
+
+#[test]
+fn rust_output_rejects_invalid_json() {
+    let mut xf = Formatter::minimizer();
+    assert!(to_json_macro("{\"a\": ", &mut xf).is_err());
+}
@@ -0,0 +1,81 @@
+extern crate jsonxf;
+use jsonxf::schema::PathSchema;
+
+#[test]
+fn infer_schema_describes_each_path() {
+    let input = "{\"users\": [{\"name\": \"ana\", \"age\": 30}, {\"name\": \"bo\"}]}\n\
+                 {\"users\": [{\"name\": \"cy\", \"age\": null}], \"next\": \"abc\"}";
+    let schema = jsonxf::infer_schema(input).unwrap();
+    let paths: Vec<&str> = schema.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            ".",
+            ".users",
+            ".users[]",
+            ".users[].name",
+            ".users[].age",
+            ".next"
+        ]
+    );
+
+    assert_eq!(
+        schema[3],
+        PathSchema {
+            path: String::from(".users[].name"),
+            count: 3,
+            types: vec![("string", 3)],
+            nullable: false,
+            required: Some(true),
+            examples: vec![
+                String::from("\"ana\""),
+                String::from("\"bo\""),
+                String::from("\"cy\"")
+            ],
+        }
+    );
+    assert_eq!(
+        schema[4],
+        PathSchema {
+            path: String::from(".users[].age"),
+            count: 2,
+            types: vec![("number", 1), ("null", 1)],
+            nullable: true,
+            required: Some(false),
+            examples: vec![String::from("30")],
+        }
+    );
+    assert_eq!(schema[5].required, Some(false));
+    assert_eq!(schema[0].required, None);
+    assert_eq!(schema[2].required, None);
+}
+
+#[test]
+fn infer_schema_keeps_distinct_examples() {
+    let schema = jsonxf::infer_schema("[1, 1, 2, 3, 4, true]").unwrap();
+    assert_eq!(schema[1].examples, vec!["1", "2", "3"]);
+    assert_eq!(schema[1].types, vec![("number", 5), ("boolean", 1)]);
+}
+
+#[test]
+fn json_schema_nests_properties_and_items() {
+    let schema = jsonxf::schema::json_schema(
+        "{\"a\": {\"b\": [1, null]}, \"c\": \"x\"}\n{\"a\": {\"b\": []}}\n{\"a\": null}",
+    )
+    .unwrap();
+    assert_eq!(
+        schema,
+        "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"type\":\"object\",\
+         \"properties\":{\"a\":{\"type\":[\"object\",\"null\"],\"properties\":{\"b\":\
+         {\"type\":\"array\",\"items\":{\"type\":[\"number\",\"null\"]}}},\"required\":[\"b\"]},\
+         \"c\":{\"type\":\"string\"}},\"required\":[\"a\"]}"
+    );
+}
+
+#[test]
+fn json_schema_of_nothing_is_just_the_dialect() {
+    assert_eq!(
+        jsonxf::schema::json_schema("").unwrap(),
+        "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\"}"
+    );
+}
@@ -0,0 +1,208 @@
+extern crate jsonxf;
+use jsonxf::split::RecordSplitter;
+
+// Splits `input`, feeding it `chunk_size` bytes at a time.
+fn split(input: &str, chunk_size: usize) -> Vec<String> {
+    let mut records: Vec<String> = vec![];
+    let mut splitter = RecordSplitter::new();
+    let mut f = |record: u64, bytes: &[u8], _end: bool| {
+        if records.len() as u64 == record {
+            records.push(String::new());
+        }
+        records[record as usize].push_str(std::str::from_utf8(bytes).unwrap());
+        Ok(())
+    };
+    for chunk in input.as_bytes().chunks(chunk_size) {
+        splitter.feed(chunk, &mut f).unwrap();
+    }
+    splitter.finish(&mut f).unwrap();
+    records
+}
+
+#[test]
+fn splitter_finds_records_across_chunk_boundaries() {
+    let input = "{\"a\": \"]\\\"}\"}\n[[1], {\"b\": null}]  \"str\\\\\" -12.5e3,true\r\nnull";
+    let expected = vec![
+        "{\"a\": \"]\\\"}\"}",
+        "[[1], {\"b\": null}]",
+        "\"str\\\\\"",
+        "-12.5e3",
+        "true",
+        "null",
+    ];
+    for chunk_size in 1..input.len() + 1 {
+        assert_eq!(
+            split(input, chunk_size),
+            expected,
+            "chunk size {}",
+            chunk_size
+        );
+    }
+}
+
+#[test]
+fn splitter_ends_unbalanced_records_at_eof() {
+    assert_eq!(split("[1] {\"a\": [", 4), vec!["[1]", "{\"a\": ["]);
+}
+
+#[test]
+fn select_records_formats_only_the_chosen_records() {
+    let input = "[0]\n[1]\n{\"two\": 2}\n[3]\n";
+    let select = |records| {
+        let mut output: Vec<u8> = vec![];
+        jsonxf::split::select_records(
+            &mut jsonxf::Formatter::pretty_printer(),
+            &mut input.as_bytes(),
+            &mut output,
+            records,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(select(2..=2), "{\n  \"two\": 2\n}");
+    assert_eq!(select(2..=u64::MAX), "{\n  \"two\": 2\n}\n[\n  3\n]");
+    assert_eq!(select(0..=1), "[\n  0\n]\n[\n  1\n]");
+    assert_eq!(select(9..=9), "");
+}
+
+// Splits `input` with a minimizer, returning the contents of each output.
+// Splits array elements into arrays of `rechunk` elements, if given.
+fn split_into_outputs(
+    input: &str,
+    rechunk: Option<u64>,
+    limits: jsonxf::split::SplitLimits,
+) -> Vec<String> {
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    struct Output(Rc<RefCell<Vec<String>>>);
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut outputs = self.0.borrow_mut();
+            outputs
+                .last_mut()
+                .unwrap()
+                .push_str(std::str::from_utf8(buf).unwrap());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let outputs = Rc::new(RefCell::new(vec![]));
+    let mut open = |n| {
+        assert_eq!(n as usize, outputs.borrow().len());
+        outputs.borrow_mut().push(String::new());
+        Ok(Output(outputs.clone()))
+    };
+    let mut xf = jsonxf::Formatter::minimizer();
+    let count = match rechunk {
+        None => jsonxf::split::split_records(&mut xf, &mut input.as_bytes(), limits, &mut open),
+        Some(max_elements) => jsonxf::split::rechunk_array(
+            &mut xf,
+            &mut input.as_bytes(),
+            max_elements,
+            limits,
+            &mut open,
+        ),
+    }
+    .unwrap();
+    let outputs = outputs.borrow().clone();
+    assert_eq!(count as usize, outputs.len());
+    outputs
+}
+
+#[test]
+fn split_records_rotates_by_record_count() {
+    let limits = jsonxf::split::SplitLimits {
+        records: Some(2),
+        bytes: None,
+    };
+    assert_eq!(
+        split_into_outputs("[1] [2] [3] [4] [5]", None, limits),
+        vec!["[1]\n[2]\n", "[3]\n[4]\n", "[5]\n"]
+    );
+    assert_eq!(split_into_outputs("", None, limits), Vec::<String>::new());
+}
+
+#[test]
+fn split_records_rotates_by_size_without_breaking_records() {
+    let limits = jsonxf::split::SplitLimits {
+        records: None,
+        bytes: Some(10),
+    };
+    assert_eq!(
+        split_into_outputs("[1] [2] {\"long\": \"record\"} [3, 4] 5", None, limits),
+        vec!["[1]\n[2]\n", "{\"long\":\"record\"}\n", "[3,4]\n5\n"]
+    );
+}
+
+#[test]
+fn array_element_splitter_unwraps_top_level_arrays() {
+    let mut elements: Vec<String> = vec![];
+    let mut splitter = RecordSplitter::array_elements();
+    let mut f = |record: u64, bytes: &[u8], _end: bool| {
+        if elements.len() as u64 == record {
+            elements.push(String::new());
+        }
+        elements[record as usize].push_str(std::str::from_utf8(bytes).unwrap());
+        Ok(())
+    };
+    let input = "[[1, [2]], \"]\", {\"a\": []}] [] {\"b\": 3} [true]";
+    for chunk in input.as_bytes().chunks(3) {
+        splitter.feed(chunk, &mut f).unwrap();
+    }
+    splitter.finish(&mut f).unwrap();
+    assert_eq!(
+        elements,
+        vec!["[1, [2]]", "\"]\"", "{\"a\": []}", "{\"b\": 3}", "true"]
+    );
+}
+
+#[test]
+fn rechunk_array_writes_bounded_arrays() {
+    let input = "[1, 2, 3, 4, 5, 6, 7]";
+    let limits = jsonxf::split::SplitLimits::default();
+    assert_eq!(
+        split_into_outputs(input, Some(3), limits),
+        vec!["[1,2,3]\n[4,5,6]\n[7]\n"]
+    );
+    assert_eq!(
+        split_into_outputs(input, Some(7), limits),
+        vec!["[1,2,3,4,5,6,7]\n"]
+    );
+    assert_eq!(
+        split_into_outputs(input, Some(100), limits),
+        vec!["[1,2,3,4,5,6,7]\n"]
+    );
+    assert_eq!(
+        split_into_outputs("[]", Some(2), limits),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn rechunk_array_can_write_each_chunk_to_its_own_output() {
+    let limits = jsonxf::split::SplitLimits {
+        records: Some(1),
+        bytes: None,
+    };
+    assert_eq!(
+        split_into_outputs("[{\"a\": 1}, {\"b\": 2}, {\"c\": 3}]", Some(2), limits),
+        vec!["[{\"a\":1},{\"b\":2}]\n", "[{\"c\":3}]\n"]
+    );
+}
+
+#[test]
+fn rechunk_array_rejects_empty_chunks() {
+    assert!(jsonxf::split::rechunk_array(
+        &mut jsonxf::Formatter::minimizer(),
+        &mut "[1]".as_bytes(),
+        0,
+        jsonxf::split::SplitLimits::default(),
+        &mut |_| Ok(std::io::sink()),
+    )
+    .is_err());
+}
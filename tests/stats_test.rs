@@ -0,0 +1,87 @@
+extern crate jsonxf;
+
+#[test]
+fn key_stats_summarizes_numbers_and_strings() {
+    let input = "{\"n\": -2, \"s\": \"\\u00e9t\\u00e9\"}\n{\"n\": 10, \"s\": \"\"}\n{\"n\": 1e1}";
+    let stats = jsonxf::key_stats(input).unwrap();
+    let paths: Vec<&str> = stats.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(paths, vec![".", ".n", ".s"]);
+
+    let n = &stats[1];
+    assert_eq!(n.count, 3);
+    assert_eq!(n.types, vec![("number", 3)]);
+    assert_eq!(n.min, Some(-2.0));
+    assert_eq!(n.max, Some(10.0));
+    assert_eq!(n.mean, Some(6.0));
+    assert_eq!(n.min_length, None);
+
+    let s = &stats[2];
+    assert_eq!(s.count, 2);
+    assert_eq!(s.min_length, Some(0));
+    assert_eq!(s.max_length, Some(3));
+    assert_eq!(s.min, None);
+    assert_eq!(s.mean, None);
+}
+
+#[test]
+fn key_stats_counts_each_type() {
+    let stats = jsonxf::key_stats("[1, \"ab\", null, 3, true]").unwrap();
+    let items = &stats[1];
+    assert_eq!(items.path, ".[]");
+    assert_eq!(items.count, 5);
+    assert_eq!(
+        items.types,
+        vec![("number", 2), ("string", 1), ("null", 1), ("boolean", 1)]
+    );
+    assert_eq!(items.mean, Some(2.0));
+    assert_eq!(items.max_length, Some(2));
+}
+
+#[test]
+fn key_stats_of_empty_input_is_empty() {
+    assert_eq!(jsonxf::key_stats("").unwrap(), vec![]);
+}
+
+#[test]
+fn key_stats_rejects_unterminated_strings() {
+    assert!(jsonxf::key_stats("{\"a\": \"b").is_err());
+}
+
+#[test]
+fn stream_stats_counts_records_and_values() {
+    let input = "{\"a\": [1, 2.5, \"x\"], \"b\": {\"c\": null}}\n\n[true, false]\n\"s\"\n";
+    let stats = jsonxf::stream_stats(input).unwrap();
+    assert_eq!(stats.records, 3);
+    assert_eq!(stats.bytes, input.len() as u64);
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(stats.max_record_bytes, 38);
+    assert_eq!(stats.keys, 3);
+    assert_eq!(stats.strings, 2);
+    assert_eq!(stats.numbers, 2);
+    assert_eq!(stats.bools, 2);
+    assert_eq!(stats.nulls, 1);
+}
+
+#[test]
+fn stream_stats_counts_top_level_scalars() {
+    let stats = jsonxf::stream_stats("1 22 333").unwrap();
+    assert_eq!(stats.records, 3);
+    assert_eq!(stats.max_record_bytes, 3);
+    assert_eq!(stats.max_depth, 0);
+}
+
+#[test]
+fn stats_collector_observes_formatting() {
+    use jsonxf::stats::{StatsCollector, StatsReader};
+
+    let input = "{\"a\":[1,2]}";
+    let mut collector = StatsCollector::new();
+    let output = jsonxf::Formatter::pretty_printer()
+        .format_stream(
+            &mut StatsReader::new(input.as_bytes(), &mut collector),
+            &mut std::io::sink(),
+        )
+        .map(|_| collector.finish().unwrap())
+        .unwrap();
+    assert_eq!(output, jsonxf::stream_stats(input).unwrap());
+}
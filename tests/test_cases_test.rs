@@ -62,5 +62,19 @@ fn simple_object_test() {
 
 #[test]
 fn loose_bracket() {
-    test("loose-bracket");
+    let mut input = String::new();
+    File::open("./tests/test_cases/loose-bracket.json")
+        .unwrap()
+        .read_to_string(&mut input)
+        .unwrap();
+
+    // A closing bracket with nothing to close is an error
+    assert_eq!(
+        jsonxf::pretty_print(&input).unwrap_err(),
+        "unexpected closing bracket"
+    );
+    assert_eq!(
+        jsonxf::minimize(&input).unwrap_err(),
+        "unexpected closing bracket"
+    );
 }
@@ -0,0 +1,112 @@
+extern crate jsonxf;
+use jsonxf::testkit::{assert_generated, assert_round_trip, assert_stable, check_generated};
+use jsonxf::testkit::{
+    check_invariants, check_round_trip, check_same_values, check_stable, Generator,
+};
+use jsonxf::Formatter;
+
+const INPUTS: &[&str] = &[
+    "{\"a\": [1, 2.5, -3e10], \"b\": {\"c\": null, \"d\": [true, false]}}",
+    "[[], {}, [[]], \"\\\"quoted\\\" \\\\ \\u00e9\"]",
+    "1 \"two\" [3] {\"four\": 4}",
+    "\n\n   {\"indented\":\n\t\"input\"}   \n",
+];
+
+#[test]
+fn builtin_formatters_are_stable_and_round_trip() {
+    let mut tabbed = Formatter::pretty_printer();
+    tabbed.indent = String::from("\t");
+    tabbed.line_separator = String::from("\r\n");
+    tabbed.record_separator = String::from("\r\n");
+    let mut eager = Formatter::minimizer();
+    eager.eager_record_separators = true;
+    for xf in &mut [
+        Formatter::pretty_printer(),
+        Formatter::minimizer(),
+        tabbed,
+        eager,
+    ] {
+        for input in INPUTS {
+            assert_stable(xf, input);
+            assert_round_trip(xf, input);
+        }
+    }
+}
+
+#[test]
+fn check_stable_describes_the_difference() {
+    let mut xf = Formatter::minimizer();
+    xf.trailing_output = String::from("[]");
+    assert_eq!(
+        check_stable(&mut xf, "[1]").unwrap_err(),
+        "formatting is not stable (first difference at byte 3):\n  \
+         once: \"[1][]\"\n  twice: \"[1]\\n[][]\""
+    );
+}
+
+#[test]
+fn check_round_trip_catches_folding_with_ellipses() {
+    let mut xf = Formatter::pretty_printer();
+    xf.max_depth = Some(0);
+    assert_eq!(check_round_trip(&mut xf, "[1, [2]]"), Ok(()));
+    xf.fold_style = jsonxf::FoldStyle::Ellipsis;
+    assert!(check_round_trip(&mut xf, "[1, [2]]").is_err());
+}
+
+#[test]
+#[should_panic(expected = "formatting changed more than whitespace")]
+fn assert_round_trip_panics() {
+    let mut xf = Formatter::minimizer();
+    xf.escape_non_ascii = true;
+    assert_round_trip(&mut xf, "\"ü\"");
+}
+
+#[test]
+fn generated_documents_are_valid_and_reproducible() {
+    let mut generator = Generator::new(1);
+    generator.max_depth = 8;
+    for _ in 0..200 {
+        let json = generator.records(3);
+        assert_eq!(jsonxf::value::parse_records(&json).unwrap().len(), 3);
+    }
+    assert_eq!(Generator::new(5).records(4), Generator::new(5).records(4));
+    assert_ne!(Generator::new(5).document(), Generator::new(6).document());
+}
+
+#[test]
+fn builtin_formatters_hold_invariants_on_generated_input() {
+    let mut ascii = Formatter::pretty_printer();
+    ascii.escape_non_ascii = true;
+    let mut folded = Formatter::pretty_printer();
+    folded.max_depth = Some(1);
+    for xf in &mut [
+        Formatter::pretty_printer(),
+        Formatter::minimizer(),
+        ascii,
+        folded,
+    ] {
+        for seed in 0..5 {
+            assert_generated(xf, seed, 200);
+        }
+    }
+}
+
+#[test]
+fn check_same_values_catches_changed_records() {
+    let mut xf = Formatter::minimizer();
+    xf.record_separator = String::from("\n0\n");
+    assert_eq!(
+        check_same_values(&mut xf, "1 2").unwrap_err(),
+        "formatting changed the number of records from 2 to 3\n  output: \"1\\n0\\n2\""
+    );
+    assert!(check_invariants(&mut Formatter::minimizer(), "[1] {").is_err());
+}
+
+#[test]
+fn check_generated_names_the_failing_case() {
+    let mut xf = Formatter::minimizer();
+    xf.record_separator = String::from("\n[]");
+    let error = check_generated(&mut xf, 3, 10).unwrap_err();
+    assert!(error.starts_with("seed 3, case "), "{}", error);
+    assert!(error.contains("\n  input: "), "{}", error);
+}
@@ -0,0 +1,55 @@
+extern crate jsonxf;
+
+#[test]
+fn toml_writes_keys_before_tables() {
+    let json =
+        "{\"server\": {\"port\": 8080, \"tls\": {\"on\": false}}, \"name\": \"x\", \"ratio\": 0.5}";
+    assert_eq!(
+        jsonxf::to_toml(json).unwrap(),
+        "name = \"x\"\nratio = 0.5\n\n[server]\nport = 8080\n\n[server.tls]\non = false\n"
+    );
+}
+
+#[test]
+fn toml_quotes_keys_and_escapes_strings() {
+    let json = "{\"a.b\": \"tab\\there \\\"quoted\\\" \\u0001\", \"ok-key_1\": \"\u{e9}\"}";
+    assert_eq!(
+        jsonxf::to_toml(json).unwrap(),
+        "\"a.b\" = \"tab\\there \\\"quoted\\\" \\u0001\"\nok-key_1 = \"\u{e9}\"\n"
+    );
+}
+
+#[test]
+fn toml_uses_inline_forms_inside_mixed_arrays() {
+    let json = "{\"xs\": [1, [2, 3], {\"k\": \"v\"}, {}], \"none\": []}";
+    assert_eq!(
+        jsonxf::to_toml(json).unwrap(),
+        "xs = [1, [2, 3], { k = \"v\" }, {}]\nnone = []\n"
+    );
+}
+
+#[test]
+fn toml_arrays_of_objects_become_arrays_of_tables() {
+    let json = "{\"fruit\": [{\"name\": \"apple\", \"variety\": [{\"name\": \"red\"}]}, {\"name\": \"plum\"}]}";
+    assert_eq!(
+        jsonxf::to_toml(json).unwrap(),
+        "[[fruit]]\nname = \"apple\"\n\n[[fruit.variety]]\nname = \"red\"\n\n[[fruit]]\nname = \"plum\"\n"
+    );
+}
+
+#[test]
+fn toml_rejects_incompatible_documents() {
+    assert_eq!(
+        jsonxf::to_toml("[1, 2]").unwrap_err(),
+        "TOML documents must be objects, not array"
+    );
+    assert_eq!(
+        jsonxf::to_toml("{\"a\": [1, null]}").unwrap_err(),
+        "null at /a/1 cannot be represented in TOML"
+    );
+    assert_eq!(
+        jsonxf::to_toml("{\"big\": 18446744073709551616}").unwrap_err(),
+        "18446744073709551616 at /big is out of range for a TOML integer"
+    );
+    assert!(jsonxf::to_toml("{} {}").is_err());
+}
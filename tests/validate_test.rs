@@ -0,0 +1,170 @@
+extern crate jsonxf;
+
+use jsonxf::scanner::SyntaxError;
+use jsonxf::validate::Validator;
+use jsonxf::Formatter;
+use std::io::ErrorKind;
+
+// Returns the message and offset of the error from validating `input`
+// in chunks of every size.
+fn invalid(input: &str) -> (String, u64) {
+    let input = input.as_bytes();
+    let mut errors = vec![];
+    for size in 1..=input.len() {
+        let mut validator = Validator::new();
+        let result = input
+            .chunks(size)
+            .try_for_each(|chunk| validator.feed(chunk))
+            .and_then(|_| validator.finish());
+        let error = result.unwrap_err();
+        let syntax_error = SyntaxError::of(&error).unwrap();
+        errors.push((error.to_string(), syntax_error.position.offset));
+    }
+    errors.dedup();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    errors.pop().unwrap()
+}
+
+#[test]
+fn validator_accepts_json() {
+    let inputs = [
+        "",
+        "  \n",
+        "{\"a\": [1, -2.5e+3, 0.0, true, false, null, \"\\\"\\\\\\/\\b\\f\\n\\r\\t\\u00e9\"], \"b\": {}}",
+        "[] [[]] {\"\": {\"\": []}}",
+        "1 \"a\"\"b\"{}[]null\ntrue\r\n",
+        "\"é \u{1F600}\"",
+    ];
+    for input in &inputs {
+        let mut validator = Validator::new();
+        validator.feed(input.as_bytes()).unwrap();
+        validator.finish().unwrap();
+    }
+}
+
+#[test]
+fn validator_rejects_bad_literals() {
+    assert_eq!(
+        invalid("[true, tru]"),
+        (
+            String::from("invalid number or literal `tru` at line 1, column 8"),
+            7
+        )
+    );
+    assert_eq!(
+        invalid("{\"a\": 01}").0,
+        "invalid number or literal `01` at line 1, column 7"
+    );
+    assert_eq!(
+        invalid("[1.]").0,
+        "invalid number or literal `1.` at line 1, column 2"
+    );
+    assert_eq!(
+        invalid("nul").0,
+        "invalid number or literal `nul` at line 1, column 1"
+    );
+}
+
+#[test]
+fn validator_rejects_missing_punctuation() {
+    assert_eq!(
+        invalid("[1\n 2]").0,
+        "expected `,` or `]`, found `2` at line 2, column 2"
+    );
+    assert_eq!(
+        invalid("{\"a\" 1}").0,
+        "expected `:`, found `1` at line 1, column 6"
+    );
+    assert_eq!(
+        invalid("{\"a\": 1 \"b\": 2}").0,
+        "expected `,` or `}`, found `\"` at line 1, column 9"
+    );
+    assert_eq!(
+        invalid("[1,]").0,
+        "expected a value, found `]` at line 1, column 4"
+    );
+    assert_eq!(
+        invalid("{\"a\": 1,}").0,
+        "expected a key, found `}` at line 1, column 9"
+    );
+    assert_eq!(
+        invalid("{1: 2}").0,
+        "expected a key or `}`, found `1` at line 1, column 2"
+    );
+    assert_eq!(
+        invalid("[1}").0,
+        "expected `,` or `]`, found `}` at line 1, column 3"
+    );
+    assert_eq!(
+        invalid("{}}").0,
+        "expected a value, found `}` at line 1, column 3"
+    );
+}
+
+#[test]
+fn validator_rejects_bad_strings() {
+    assert_eq!(
+        invalid("[\"a\\q\"]").0,
+        "invalid escape in string at line 1, column 5"
+    );
+    assert_eq!(
+        invalid("[\"\\u00g0\"]").0,
+        "invalid escape in string at line 1, column 7"
+    );
+    assert_eq!(
+        invalid("[\"a\tb\"]").0,
+        "unescaped control character in string at line 1, column 4"
+    );
+}
+
+#[test]
+fn validator_rejects_input_cut_off() {
+    assert_eq!(
+        invalid("{\"a\": \"b").0,
+        "unterminated string at line 1, column 7"
+    );
+    assert_eq!(
+        invalid("{\"a\": [1,\n  {}").0,
+        "unclosed `[` at line 1, column 7"
+    );
+    let mut validator = Validator::new();
+    validator.feed(b"[").unwrap();
+    assert_eq!(
+        validator.finish().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+    // and is ready for new input
+    validator.feed(b"1").unwrap();
+    validator.finish().unwrap();
+}
+
+#[test]
+fn formatter_validate_stops_at_the_first_mistake() {
+    let mut xf = Formatter::minimizer();
+    xf.validate = true;
+    let mut output: Vec<u8> = vec![];
+    xf.format_buf(b"[1, 2]\n", &mut output).unwrap();
+    let error = xf.format_buf(b"[3, 4 5]\n[6]", &mut output).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(SyntaxError::of(&error).unwrap().position.offset, 13);
+    assert_eq!(output, b"[1,2]");
+
+    let mut xf = Formatter::pretty_printer();
+    xf.validate = true;
+    assert_eq!(
+        xf.format("[1, [2").unwrap_err(),
+        "unclosed `[` at line 1, column 5"
+    );
+    let mut xf = Formatter::pretty_printer();
+    assert_eq!(xf.format("[1, [2").unwrap(), "[\n  1,\n  [\n    2");
+}
+
+#[test]
+fn validate_stream_checks_every_record() {
+    let mut input = &b"{\"a\": [1, 2]}\n\"ok\" 3\n"[..];
+    Validator::new().validate_stream(&mut input).unwrap();
+    let mut input = &b"[1, 2]\n{\"a\": [1, 2]"[..];
+    let error = Validator::new().validate_stream(&mut input).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(error.to_string(), "unclosed `{` at line 2, column 1");
+}
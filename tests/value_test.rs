@@ -0,0 +1,91 @@
+extern crate jsonxf;
+use jsonxf::hash::Algorithm;
+use jsonxf::merge::Strategy;
+use jsonxf::value::Value;
+
+#[test]
+fn parse_keeps_order_and_number_text() {
+    let value = jsonxf::value::parse("{\"b\": [1.0, \"x\\u0041\"], \"a\": null}").unwrap();
+    assert_eq!(
+        value,
+        Value::Object(vec![
+            (
+                String::from("b"),
+                Value::Array(vec![
+                    Value::Number(String::from("1.0")),
+                    Value::String(String::from("xA"))
+                ])
+            ),
+            (String::from("a"), Value::Null),
+        ])
+    );
+    assert_eq!(value.to_string(), "{\"b\":[1.0,\"xA\"],\"a\":null}");
+}
+
+#[test]
+fn parse_rejects_invalid_json() {
+    for (json, error) in [
+        (
+            "[1 2]",
+            "expected `,` or `]`, found `2` at line 1, column 4",
+        ),
+        (
+            "[,1,,2,]",
+            "expected a value or `]`, found `,` at line 1, column 2",
+        ),
+        ("[1,,2]", "expected a value, found `,` at line 1, column 4"),
+        ("[1,2,]", "expected a value, found `]` at line 1, column 6"),
+        ("{\"a\" 1}", "expected `:`, found `1` at line 1, column 6"),
+        (
+            "{\"a\": 1,}",
+            "expected a key, found `}` at line 1, column 9",
+        ),
+        ("\"\\x\"", "invalid escape in string at line 1, column 3"),
+        ("tru", "invalid number or literal `tru` at line 1, column 1"),
+        (
+            "[nul]",
+            "invalid number or literal `nul` at line 1, column 2",
+        ),
+        (
+            "{\"a\": 01}",
+            "invalid number or literal `01` at line 1, column 7",
+        ),
+        ("[1]\n]", "unexpected closing bracket at line 2, column 1"),
+    ] {
+        assert_eq!(jsonxf::value::parse(json).unwrap_err(), error, "{}", json);
+    }
+}
+
+#[test]
+fn read_records_passes_on_records_before_an_error() {
+    let mut input = &b"{\"a\": 1}\n[1 2]"[..];
+    let mut records = vec![];
+    let error = jsonxf::value::read_records(&mut input, &mut |record| {
+        records.push(record.to_string());
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(records, vec!["{\"a\":1}"]);
+    assert_eq!(
+        error.to_string(),
+        "expected `,` or `]`, found `2` at line 2, column 4"
+    );
+}
+
+#[test]
+fn features_built_on_parsing_reject_invalid_json() {
+    for json in ["[1 2]", "[,1,,2,]", "{\"a\" 1}", "\"\\x\"", "tru", "nul"] {
+        assert!(jsonxf::diff(json, "1").is_err(), "{}", json);
+        assert!(
+            jsonxf::merge(&["{}", json], Strategy::LastWins).is_err(),
+            "{}",
+            json
+        );
+        assert!(
+            jsonxf::hash::canonical_hash(json, Algorithm::Sha256).is_err(),
+            "{}",
+            json
+        );
+        assert!(jsonxf::jcs::canonicalize(json).is_err(), "{}", json);
+    }
+}
@@ -0,0 +1,128 @@
+extern crate jsonxf;
+use jsonxf::verify::format_verified;
+use jsonxf::Formatter;
+
+fn verified(mut xf: Formatter, input: &str) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    format_verified(&mut xf, &mut input.as_bytes(), &mut output).map_err(|e| e.to_string())?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn verify_passes_whitespace_changes() {
+    let input = "{\"a\": [1, 2.5e3, true, null], \"b c\": \"x  y\\\" ]\"}\n[] 7 \"z\"";
+    assert_eq!(
+        verified(Formatter::minimizer(), input).unwrap(),
+        "{\"a\":[1,2.5e3,true,null],\"b c\":\"x  y\\\" ]\"}\n[]\n7\n\"z\""
+    );
+    let mut xf = Formatter::pretty_printer();
+    xf.indent = String::from("\t");
+    xf.line_separator = String::from("\r\n");
+    assert!(verified(xf, input).is_ok());
+}
+
+#[test]
+fn verify_catches_joined_tokens() {
+    assert_eq!(
+        verified(Formatter::pretty_printer(), "{\"n\": [10, 20 30]}").unwrap_err(),
+        "verification failed: output differs from input at byte 11 of their minimized forms"
+    );
+}
+
+#[test]
+fn verify_rejects_formatters_which_change_more_than_whitespace() {
+    let mut xf = Formatter::pretty_printer();
+    xf.escape_non_ascii = true;
+    assert!(verified(xf, "\"é\"").is_err());
+}
+
+#[cfg(feature = "verify")]
+fn cross_checked(mut xf: Formatter, input: &str) -> Result<String, String> {
+    let mut output: Vec<u8> = vec![];
+    jsonxf::verify::format_cross_checked(&mut xf, &mut input.as_bytes(), &mut output)
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn cross_check_passes_equal_values() {
+    let input = "{\"a\": [1, 2.5e3, true, null], \"b\": \"ü\\n\"}\n[] 7";
+    assert_eq!(
+        cross_checked(Formatter::minimizer(), input).unwrap(),
+        "{\"a\":[1,2.5e3,true,null],\"b\":\"ü\\n\"}\n[]\n7"
+    );
+    let mut xf = Formatter::pretty_printer();
+    xf.escape_non_ascii = true;
+    assert!(cross_checked(xf, input).is_ok());
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn cross_check_rejects_invalid_json() {
+    assert_eq!(
+        cross_checked(Formatter::minimizer(), "[1 2]").unwrap_err(),
+        "cross-check failed: input is not valid JSON: expected `,` or `]` at line 1 column 4"
+    );
+    let mut xf = Formatter::minimizer();
+    xf.after_colon = String::from("?");
+    assert_eq!(
+        cross_checked(xf, "{\"a\": 1}").unwrap_err(),
+        "cross-check failed: output is not valid JSON: expected value at line 1 column 6"
+    );
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn cross_check_catches_changed_values() {
+    let mut xf = Formatter::minimizer();
+    xf.record_separator = String::from("\n0\n");
+    assert_eq!(
+        cross_checked(xf, "{\"a\": 1} {\"b\": 2}").unwrap_err(),
+        "cross-check failed: record 2 of the output parses differently from the input"
+    );
+}
+
+fn audited(mut xf: Formatter, input: &str) -> Result<String, std::io::Error> {
+    let mut output: Vec<u8> = vec![];
+    jsonxf::verify::format_audited(&mut xf, &mut input.as_bytes(), &mut output)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn audit_passes_whitespace_changes() {
+    let input = "{\"a\": [1, 2.5e3, true], \"b c\": \"x  y\"}\r\n\r\n[] 7";
+    assert_eq!(
+        audited(Formatter::minimizer(), input).unwrap(),
+        "{\"a\":[1,2.5e3,true],\"b c\":\"x  y\"}\n[]\n7"
+    );
+}
+
+#[test]
+fn audit_reports_the_input_position() {
+    use jsonxf::scanner::{Position, SyntaxError};
+    let input = "[\"ok\",\n 10 20,\n 30]";
+    let error = audited(Formatter::pretty_printer(), input).unwrap_err();
+    assert_eq!(
+        SyntaxError::of(&error).unwrap().position,
+        Position {
+            offset: 11,
+            line: 2,
+            column: 5
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "audit failed: output diverges from input byte 11 at line 2, column 5"
+    );
+}
+
+#[test]
+fn audit_reports_output_added_at_the_end() {
+    let mut xf = Formatter::minimizer();
+    xf.trailing_output = String::from("null");
+    assert_eq!(
+        audited(xf, "[1]\n").unwrap_err().to_string(),
+        "audit failed: output diverges from input byte 4 at line 2, column 1"
+    );
+}
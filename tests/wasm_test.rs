@@ -0,0 +1,200 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+// Run with `wasm-pack test --node -- --features wasm`.
+
+extern crate js_sys;
+extern crate jsonxf;
+extern crate wasm_bindgen;
+extern crate wasm_bindgen_futures;
+extern crate wasm_bindgen_test;
+extern crate web_sys;
+
+use jsonxf::wasm::{format_stream, minimize, pretty_print, WasmFormatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::wasm_bindgen_test;
+use web_sys::{ReadableStream, WritableStream};
+
+#[wasm_bindgen(inline_js = "
+    export function streamOf(chunks) {
+        const encoder = new TextEncoder();
+        return new ReadableStream({
+            start(controller) {
+                for (const chunk of chunks) {
+                    controller.enqueue(encoder.encode(chunk));
+                }
+                controller.close();
+            },
+        });
+    }
+
+    export function failingStream() {
+        return new ReadableStream({
+            pull(controller) {
+                controller.error(new Error('read failed'));
+            },
+        });
+    }
+
+    export function collector(failing) {
+        const decoder = new TextDecoder();
+        const sink = { text: '', end: 'open' };
+        const stream = new WritableStream({
+            write(chunk) {
+                if (failing) {
+                    throw new Error('write failed');
+                }
+                sink.text += decoder.decode(chunk, { stream: true });
+            },
+            close() {
+                sink.end = 'closed';
+            },
+            abort(reason) {
+                sink.end = 'aborted: ' + reason.message;
+            },
+        });
+        stream.sink = sink;
+        return stream;
+    }
+
+    export function written(stream) {
+        return stream.sink.text;
+    }
+
+    export function ending(stream) {
+        return stream.sink.end;
+    }
+")]
+extern "C" {
+    // A stream of the given strings, as bytes.
+    #[wasm_bindgen(js_name = streamOf)]
+    fn stream_of(chunks: js_sys::Array) -> ReadableStream;
+
+    // A stream which fails at its first read.
+    #[wasm_bindgen(js_name = failingStream)]
+    fn failing_stream() -> ReadableStream;
+
+    // A stream which keeps what is written to it, or fails to write.
+    fn collector(failing: bool) -> WritableStream;
+
+    // What was written to a collector.
+    fn written(stream: &WritableStream) -> String;
+
+    // How a collector ended: `open`, `closed`, or `aborted: ` and why.
+    fn ending(stream: &WritableStream) -> String;
+}
+
+// Runs `check` on what a promise resolves or rejects with.
+struct Then<F> {
+    future: JsFuture,
+    check: Option<F>,
+}
+
+fn then<F>(promise: js_sys::Promise, check: F) -> Then<F>
+where
+    F: FnOnce(Result<JsValue, JsValue>),
+{
+    Then {
+        future: JsFuture::from(promise),
+        check: Some(check),
+    }
+}
+
+impl<F> Future for Then<F>
+where
+    F: FnOnce(Result<JsValue, JsValue>) + Unpin,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let result = match Pin::new(&mut self.future).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        let check = self.check.take().unwrap();
+        check(result);
+        Poll::Ready(())
+    }
+}
+
+fn chunks(strings: &[&str]) -> js_sys::Array {
+    strings.iter().map(|s| JsValue::from_str(s)).collect()
+}
+
+fn message(error: JsValue) -> String {
+    error.unchecked_into::<js_sys::Error>().message().into()
+}
+
+#[wasm_bindgen_test]
+fn strings_are_pretty_printed_and_minimized() {
+    assert_eq!(
+        pretty_print("{\"a\":[1]}").unwrap(),
+        "{\n  \"a\": [\n    1\n  ]\n}"
+    );
+    assert_eq!(minimize("{ \"a\": [ 1 ] }").unwrap(), "{\"a\":[1]}");
+}
+
+#[wasm_bindgen_test]
+fn formatter_formats_chunks_as_they_arrive() {
+    let mut xf = WasmFormatter::pretty_printer(Some(String::from("\t")));
+    let mut output = xf.push(b"{\"a\":").unwrap();
+    output.extend(xf.push(b"1}{\"b\"").unwrap());
+    output.extend(xf.push(b":2}").unwrap());
+    output.extend(xf.finish().unwrap());
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\n\t\"a\": 1\n}\n{\n\t\"b\": 2\n}\n"
+    );
+
+    // Finishing readies the formatter for new input
+    let mut xf = WasmFormatter::minimizer();
+    let mut output = xf.push(b"[1, 2]").unwrap();
+    output.extend(xf.finish().unwrap());
+    output.extend(xf.push(b"[3]").unwrap());
+    output.extend(xf.finish().unwrap());
+    assert_eq!(String::from_utf8(output).unwrap(), "[1,2]\n[3]\n");
+}
+
+#[wasm_bindgen_test(async)]
+fn format_stream_formats_and_closes_the_output() -> impl Future<Output = ()> {
+    let input = stream_of(chunks(&["{\"a\":", "[1,2]}\n", "[]"]));
+    let output = collector(false);
+    let promise = format_stream(WasmFormatter::minimizer(), input.clone(), output.clone());
+    then(promise.unwrap(), move |result| {
+        assert!(result.is_ok());
+        assert_eq!(written(&output), "{\"a\":[1,2]}\n[]\n");
+        assert_eq!(ending(&output), "closed");
+        assert!(!input.locked());
+    })
+}
+
+#[wasm_bindgen_test(async)]
+fn format_stream_aborts_the_output_when_the_input_fails() -> impl Future<Output = ()> {
+    let input = failing_stream();
+    let output = collector(false);
+    let promise = format_stream(WasmFormatter::minimizer(), input.clone(), output.clone());
+    then(promise.unwrap(), move |result| {
+        assert_eq!(message(result.unwrap_err()), "read failed");
+        assert_eq!(ending(&output), "aborted: read failed");
+        // Both streams are free to be used again, as by the caller to
+        // report the error
+        assert!(!output.locked());
+        assert!(!input.locked());
+    })
+}
+
+#[wasm_bindgen_test(async)]
+fn format_stream_releases_the_streams_when_the_output_fails() -> impl Future<Output = ()> {
+    let input = stream_of(chunks(&["{\"a\":1}", "{\"b\":2}"]));
+    let output = collector(true);
+    let promise = format_stream(WasmFormatter::minimizer(), input.clone(), output.clone());
+    then(promise.unwrap(), move |result| {
+        assert_eq!(message(result.unwrap_err()), "write failed");
+        assert!(!output.locked());
+        assert!(!input.locked());
+    })
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "yaml")]
+
+extern crate jsonxf;
+use jsonxf::yaml::yaml_to_json;
+
+#[test]
+fn yaml_scalars_convert_to_json_types() {
+    let yaml = "s: text\nq: \"1\"\ni: -12\nf: 2.50\ng: 1e3\nh: +1.5\nt: true\nn: ~\n";
+    assert_eq!(
+        yaml_to_json(yaml).unwrap(),
+        "{\"s\":\"text\",\"q\":\"1\",\"i\":-12,\"f\":2.50,\"g\":1e3,\"h\":1.5,\"t\":true,\"n\":null}"
+    );
+}
+
+#[test]
+fn yaml_collections_keep_their_order() {
+    let yaml = "z:\n  - 1\n  - {b: 2, a: 3}\na: []\n";
+    assert_eq!(
+        yaml_to_json(yaml).unwrap(),
+        "{\"z\":[1,{\"b\":2,\"a\":3}],\"a\":[]}"
+    );
+}
+
+#[test]
+fn yaml_aliases_are_expanded() {
+    let yaml = "base: &base {x: 1}\ncopy: *base\n";
+    assert_eq!(
+        yaml_to_json(yaml).unwrap(),
+        "{\"base\":{\"x\":1},\"copy\":{\"x\":1}}"
+    );
+}
+
+#[test]
+fn each_yaml_document_is_a_record() {
+    assert_eq!(yaml_to_json("1\n---\n[2]\n").unwrap(), "1\n[2]");
+}
+
+#[test]
+fn yaml_without_json_equivalent_is_rejected() {
+    assert!(yaml_to_json("x: .inf\n").is_err());
+    assert!(yaml_to_json("? [a, b]\n: c\n").is_err());
+    assert!(yaml_to_json("a: [\n").is_err());
+}